@@ -5,11 +5,12 @@
 CREATE TABLE IF NOT EXISTS environmental_data (
     id BIGINT AUTO_INCREMENT PRIMARY KEY,
     timestamp DATETIME(3) NOT NULL COMMENT 'UTC timezone',
-    metric_id TINYINT UNSIGNED NOT NULL COMMENT 'Metric identifier: 1=pressure, 2=cabin_temp, 3=water_temp, 4=humidity, 5=wind_speed, 6=wind_dir, 7=roll',
+    metric_id TINYINT UNSIGNED NOT NULL COMMENT 'Metric identifier: 1=pressure, 2=cabin_temp, 3=water_temp, 4=humidity, 5=wind_speed, 6=wind_dir, 7=roll, 8=depth, 9=depth_corrected, 10=rudder_activity, 11=gust_peak_kn, 12=rate_of_turn',
     value_avg FLOAT COMMENT 'Average value over the 1-minute interval',
     value_max FLOAT COMMENT 'Maximum value over the 1-minute interval',
     value_min FLOAT COMMENT 'Minimum value over the 1-minute interval',
-    unit CHAR(3) COMMENT 'Unit of measurement: Pa, C, %, Kn, deg',
+    value_count INT UNSIGNED COMMENT 'Number of samples over the 1-minute interval',
+    unit CHAR(10) COMMENT 'Unit of measurement: Pa, C, %, Kn, deg, deg/s',
     
     INDEX idx_timestamp (timestamp),
     INDEX idx_metric_timestamp (metric_id, timestamp),
@@ -25,6 +26,11 @@ CREATE TABLE IF NOT EXISTS environmental_data (
 -- 5 = 'wind_speed'  - Wind speed in knots (kn)
 -- 6 = 'wind_dir'    - Wind direction in degrees (°)
 -- 7 = 'roll'        - Roll angle in degrees (°)
+-- 8 = 'depth'       - Depth below transducer in meters (m)
+-- 9 = 'depth_corrected' - Depth with transducer offset applied in meters (m)
+-- 10 = 'rudder_activity' - Rudder position standard deviation in degrees (°)
+-- 11 = 'gust_peak_kn' - Peak wind speed of the interval in knots (kn), timestamped at the peak
+-- 12 = 'rate_of_turn' - Rate of turn in degrees per second (deg/s), positive to starboard
 
 -- Example query to retrieve last 24 hours of data (pivot format)
 -- SELECT 
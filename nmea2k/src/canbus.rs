@@ -1,9 +1,25 @@
-use socketcan::{CanSocket, EmbeddedFrame, ExtendedId, Frame, Socket};
-use std::{error::Error, time::Duration};
+use socketcan::{CanFrame, CanSocket, EmbeddedFrame, ExtendedId, Frame, Socket};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::{error::Error, io, time::Duration};
 use tracing::{info, warn};
 
 pub use crate::stream_reader::N2kFrame;
 
+/// Largest payload of a fast packet: 6 bytes in the first frame and 7 in each of the 31 following
+pub const MAX_FAST_PACKET_LEN: usize = 223;
+
+/// ISO Request, asking a device to transmit a PGN
+pub const ISO_REQUEST_PGN: u32 = 59904;
+
+/// Default priority of the ISO Request
+const ISO_REQUEST_PRIORITY: u8 = 6;
+
+/// Default read timeout of the CAN socket
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Sequence counter of the fast packets sent, 3 bits
+static FAST_PACKET_SEQUENCE: AtomicU8 = AtomicU8::new(0);
+
 /// Opens a CAN socket with automatic retry on failure
 /// 
 /// # Arguments
@@ -31,16 +47,23 @@ pub fn open_can_socket_with_retry(interface: &str) -> CanSocket {
 /// 
 /// # Arguments
 /// * `socket` - The CAN socket to configure
+/// * `read_timeout` - Longest wait for a frame, see `is_read_timeout`
 /// 
 /// # Returns
 /// Result indicating success or failure
-pub fn configure_nmea2k_socket(socket: &mut CanSocket) -> Result<(), Box<dyn Error>> {
+pub fn configure_nmea2k_socket(socket: &mut CanSocket, read_timeout: Duration) -> Result<(), Box<dyn Error>> {
     // Set read timeout to prevent blocking indefinitely
     // This allows metrics logging and health checks to run even with no CAN activity
-    socket.set_read_timeout(Duration::from_millis(500))?;
+    socket.set_read_timeout(read_timeout)?;
     Ok(())
 }
 
+/// Whether a read error is the expiry of the read timeout (no frame on the bus) rather than
+/// a failure of the socket
+pub fn is_read_timeout(error: &io::Error) -> bool {
+    matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
 /// Reads a CAN frame and converts it to NMEA2000 extended ID format
 /// 
 /// # Arguments
@@ -64,17 +87,171 @@ pub fn read_nmea2k_frame(socket: &CanSocket) -> Result<(ExtendedId, Vec<u8>), st
     Ok((extended_id, data))
 }
 
+/// Sends a single-frame NMEA2000 message
+/// 
+/// # Arguments
+/// * `socket` - The CAN socket to write to
+/// * `id` - The 29-bit identifier (priority, PGN, destination, source)
+/// * `data` - Up to 8 data bytes
+pub fn send_nmea2k_frame(socket: &CanSocket, id: ExtendedId, data: &[u8]) -> io::Result<()> {
+    let frame = CanFrame::new(id, data).ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("CAN frame data too long ({} bytes)", data.len())
+    ))?;
+    socket.write_frame(&frame)
+}
+
+/// Sends a message as a fast packet, split into as many frames as needed
+/// 
+/// # Arguments
+/// * `socket` - The CAN socket to write to
+/// * `identifier` - The 29-bit identifier of every frame
+/// * `payload` - Up to 223 bytes
+pub fn send_fast_packet(socket: &CanSocket, identifier: ExtendedId, payload: &[u8]) -> io::Result<()> {
+    let sequence = FAST_PACKET_SEQUENCE.fetch_add(1, Ordering::Relaxed) & 0x07;
+    for frame in fast_packet_frames(payload, sequence)? {
+        send_nmea2k_frame(socket, identifier, &frame)?;
+    }
+    Ok(())
+}
+
+/// Asks a device to transmit a PGN, e.g. Product Information (126996)
+/// 
+/// # Arguments
+/// * `socket` - The CAN socket to write to
+/// * `destination` - Address of the device queried, 255 for all the devices
+/// * `requested_pgn` - The PGN the device should transmit
+/// * `source` - Our own source address
+pub fn request_pgn(socket: &CanSocket, destination: u8, requested_pgn: u32, source: u8) -> io::Result<()> {
+    let id = nmea2k_id(ISO_REQUEST_PRIORITY, ISO_REQUEST_PGN, destination, source);
+    send_nmea2k_frame(socket, id, &iso_request_payload(requested_pgn))
+}
+
+/// Builds the 29-bit identifier of a message
+/// 
+/// For PDU1 PGNs (PDU format below 240) the destination goes in the PDU specific byte,
+/// PDU2 PGNs are broadcast and the destination is ignored.
+pub fn nmea2k_id(priority: u8, pgn: u32, destination: u8, source: u8) -> ExtendedId {
+    let pdu_format = (pgn >> 8) & 0xFF;
+    let pgn_bits = if pdu_format < 240 {
+        (pgn & 0x3FF00) | destination as u32
+    } else {
+        pgn & 0x3FFFF
+    };
+    let raw = ((priority as u32 & 0x07) << 26) | (pgn_bits << 8) | source as u32;
+    // Fits in 29 bits by construction
+    ExtendedId::new(raw).expect("29-bit NMEA2000 identifier")
+}
+
+/// Payload of an ISO Request: the requested PGN, 3 bytes little endian
+pub fn iso_request_payload(requested_pgn: u32) -> [u8; 3] {
+    let bytes = requested_pgn.to_le_bytes();
+    [bytes[0], bytes[1], bytes[2]]
+}
+
+/// Splits a payload into the frames of a fast packet
+/// 
+/// The first byte of each frame holds the sequence counter (3 high bits) and the frame
+/// counter (5 low bits). The first frame carries the payload length and 6 bytes, the
+/// following frames 7 bytes each; the last frame is padded with 0xFF.
+pub fn fast_packet_frames(payload: &[u8], sequence: u8) -> io::Result<Vec<[u8; 8]>> {
+    if payload.len() > MAX_FAST_PACKET_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Fast packet payload too long ({} bytes, max {})", payload.len(), MAX_FAST_PACKET_LEN)
+        ));
+    }
+
+    let sequence_bits = (sequence & 0x07) << 5;
+    let mut frames = Vec::new();
+
+    let (head, tail) = payload.split_at(payload.len().min(6));
+    let mut first = [0xFF; 8];
+    first[0] = sequence_bits;
+    first[1] = payload.len() as u8;
+    first[2..2 + head.len()].copy_from_slice(head);
+    frames.push(first);
+
+    for (index, chunk) in tail.chunks(7).enumerate() {
+        let mut frame = [0xFF; 8];
+        frame[0] = sequence_bits | (index as u8 + 1);
+        frame[1..1 + chunk.len()].copy_from_slice(chunk);
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
 #[cfg(test)]
 mod tests {
-    
+    use super::*;
+
+    #[test]
+    fn test_iso_request_for_product_information() {
+        let id = nmea2k_id(ISO_REQUEST_PRIORITY, ISO_REQUEST_PGN, 0x23, 0x0F);
+        assert_eq!(id.as_raw(), 0x18EA230F);
+        assert_eq!(iso_request_payload(126996), [0x14, 0xF0, 0x01]);
+
+        let frame = crate::N2kStreamReader::new().process_frame(id, &iso_request_payload(126996)).unwrap();
+        assert_eq!(frame.pgn(), ISO_REQUEST_PGN);
+        // No dedicated decoder: kept raw under the ISO Request PGN, not the addressed one
+        assert!(matches!(frame.message, crate::N2kMessage::Unknown(ISO_REQUEST_PGN, ref data) if data[..] == [0x14, 0xF0, 0x01]));
+        assert_eq!(frame.destination(), 0x23);
+        assert_eq!(frame.source(), 0x0F);
+        assert_eq!(frame.priority(), 6);
+    }
+
+    #[test]
+    fn test_nmea2k_id_broadcast_pgn_ignores_destination() {
+        // PDU2 PGN 129025, priority 2, source 0x16
+        let id = nmea2k_id(2, 129025, 0x23, 0x16);
+        assert_eq!(id.as_raw(), 0x09F80116);
+        // Global ISO Request
+        assert_eq!(nmea2k_id(6, ISO_REQUEST_PGN, 0xFF, 0x00).as_raw(), 0x18EAFF00);
+    }
+
+    #[test]
+    fn test_fast_packet_frames_split() {
+        let payload: Vec<u8> = (1..=20).collect();
+        let frames = fast_packet_frames(&payload, 3).unwrap();
+
+        // 6 + 7 + 7 bytes
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0], [0x60, 20, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(frames[1], [0x61, 7, 8, 9, 10, 11, 12, 13]);
+        assert_eq!(frames[2], [0x62, 14, 15, 16, 17, 18, 19, 20]);
+    }
+
+    #[test]
+    fn test_fast_packet_frames_padding() {
+        let payload: Vec<u8> = (1..=9).collect();
+        let frames = fast_packet_frames(&payload, 7).unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], [0xE0, 9, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(frames[1], [0xE1, 7, 8, 9, 0xFF, 0xFF, 0xFF, 0xFF]);
+
+        // Short payloads fit the first frame
+        assert_eq!(fast_packet_frames(&[0xAA, 0xBB], 0).unwrap(), [[0x00, 2, 0xAA, 0xBB, 0xFF, 0xFF, 0xFF, 0xFF]]);
+    }
+
+    #[test]
+    fn test_fast_packet_frames_max_length() {
+        let frames = fast_packet_frames(&[0x55; MAX_FAST_PACKET_LEN], 1).unwrap();
+        assert_eq!(frames.len(), 32);
+        assert_eq!(frames[31][0], 0x20 | 31);
+        assert_eq!(frames[31][1..], [0x55; 7]);
+
+        let err = fast_packet_frames(&[0x55; MAX_FAST_PACKET_LEN + 1], 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
     #[test]
-    fn test_configure_socket_sets_timeout() {
-        // Note: This test requires a virtual CAN interface
-        // Run: sudo modprobe vcan && sudo ip link add dev vcan0 type vcan && sudo ip link set up vcan0
-        // For CI/CD, this test should be conditional or mocked
-        
-        // We can't easily test this without a real/virtual CAN interface
-        // but we can at least verify the function exists and has the right signature
-        assert!(true);
+    fn test_read_timeout_classification() {
+        assert!(is_read_timeout(&io::Error::from(io::ErrorKind::WouldBlock)));
+        assert!(is_read_timeout(&io::Error::from(io::ErrorKind::TimedOut)));
+        assert!(!is_read_timeout(&io::Error::from(io::ErrorKind::NotConnected)));
+        assert!(!is_read_timeout(&io::Error::new(io::ErrorKind::InvalidData, "Invalid CAN ID for NMEA2000")));
+        // The network interface went down
+        assert!(!is_read_timeout(&io::Error::from_raw_os_error(100)));
     }
 }
@@ -20,7 +20,7 @@
 //!
 //! // Open CAN interface
 //! let mut socket = CanBus::open_can_socket_with_retry("can0");
-//! CanBus::configure_nmea2k_socket(&mut socket).unwrap();
+//! CanBus::configure_nmea2k_socket(&mut socket, CanBus::DEFAULT_READ_TIMEOUT).unwrap();
 //!
 //! // Create stream reader
 //! let mut reader = N2kStreamReader::new();
@@ -0,0 +1,34 @@
+use std::error::Error;
+use std::fmt;
+
+/// Why the payload of a known PGN could not be decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The payload is shorter than the fields of the PGN
+    TooShort { needed: usize, got: usize },
+    /// A field holds a value outside of its definition
+    BadField { field: &'static str, value: u64 },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::TooShort { needed, got } => {
+                write!(f, "payload too short: {} bytes, at least {} needed", got, needed)
+            }
+            DecodeError::BadField { field, value } => {
+                write!(f, "invalid value {} for field '{}'", value, field)
+            }
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Check the payload holds at least `needed` bytes
+pub(crate) fn check_len(data: &[u8], needed: usize) -> Result<(), DecodeError> {
+    if data.len() < needed {
+        return Err(DecodeError::TooShort { needed, got: data.len() });
+    }
+    Ok(())
+}
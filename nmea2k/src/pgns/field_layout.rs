@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+/// Layout of a byte-aligned numeric field of a PGN
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldLayout {
+    pub name: String,
+    /// Offset of the first byte in the message data
+    pub offset: usize,
+    /// Width in bytes: 1, 2 or 4 (little endian)
+    pub width: usize,
+    pub signed: bool,
+    /// Resolution: the decoded value is the raw value times the scale
+    pub scale: f64,
+}
+
+impl FieldLayout {
+    pub fn unsigned(name: &str, offset: usize, width: usize, scale: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            offset,
+            width,
+            signed: false,
+            scale,
+        }
+    }
+
+    pub fn signed(name: &str, offset: usize, width: usize, scale: f64) -> Self {
+        Self {
+            signed: true,
+            ..Self::unsigned(name, offset, width, scale)
+        }
+    }
+
+    /// Decode the field, None when the message is too short or the value is "not available"
+    /// (all bits set for unsigned fields, the maximum positive value for signed ones)
+    pub fn decode(&self, data: &[u8]) -> Option<f64> {
+        let bytes = data.get(self.offset..self.offset + self.width)?;
+        let raw = match (self.width, self.signed) {
+            (1, false) => Some(bytes[0] as i64).filter(|v| *v != u8::MAX as i64),
+            (1, true) => Some(bytes[0] as i8 as i64).filter(|v| *v != i8::MAX as i64),
+            (2, false) => Some(u16::from_le_bytes([bytes[0], bytes[1]]) as i64).filter(|v| *v != u16::MAX as i64),
+            (2, true) => Some(i16::from_le_bytes([bytes[0], bytes[1]]) as i64).filter(|v| *v != i16::MAX as i64),
+            (4, false) => Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64)
+                .filter(|v| *v != u32::MAX as i64),
+            (4, true) => Some(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64)
+                .filter(|v| *v != i32::MAX as i64),
+            _ => None,
+        }?;
+        Some(raw as f64 * self.scale)
+    }
+}
+
+/// Field layouts of the PGNs without a dedicated decoder
+///
+/// Messages of these PGNs are decoded as `N2kMessage::Fields`, a map from field name
+/// to scaled value, instead of `N2kMessage::Unknown`.
+#[derive(Debug, Clone, Default)]
+pub struct PgnLayouts {
+    layouts: HashMap<u32, Vec<FieldLayout>>,
+}
+
+impl PgnLayouts {
+    /// Empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starter table of common PGNs
+    pub fn builtin() -> Self {
+        let mut layouts = Self::new();
+        // Engine Parameters, Dynamic
+        layouts.insert(127489, vec![
+            FieldLayout::unsigned("instance", 0, 1, 1.0),
+            FieldLayout::unsigned("oil_pressure", 1, 2, 100.0), // Pa
+            FieldLayout::unsigned("oil_temperature", 3, 2, 0.1), // K
+            FieldLayout::unsigned("temperature", 5, 2, 0.01), // K
+            FieldLayout::signed("alternator_potential", 7, 2, 0.01), // V
+            FieldLayout::signed("fuel_rate", 9, 2, 0.1), // L/h
+            FieldLayout::unsigned("total_engine_hours", 11, 4, 1.0), // s
+            FieldLayout::unsigned("coolant_pressure", 15, 2, 100.0), // Pa
+            FieldLayout::unsigned("fuel_pressure", 17, 2, 1000.0), // Pa
+        ]);
+        // Fluid Level
+        layouts.insert(127505, vec![
+            FieldLayout::signed("level", 1, 2, 0.004), // %
+            FieldLayout::unsigned("capacity", 3, 4, 0.1), // L
+        ]);
+        // Distance Log
+        layouts.insert(128275, vec![
+            FieldLayout::unsigned("log", 6, 4, 1.0), // m
+            FieldLayout::unsigned("trip_log", 10, 4, 1.0), // m
+        ]);
+        layouts
+    }
+
+    /// Add or replace the layout of a PGN
+    pub fn insert(&mut self, pgn: u32, fields: Vec<FieldLayout>) {
+        self.layouts.insert(pgn, fields);
+    }
+
+    pub fn contains(&self, pgn: u32) -> bool {
+        self.layouts.contains_key(&pgn)
+    }
+
+    /// Decode a message with the layout of its PGN
+    /// Returns None if the PGN has no layout; fields not available are left out of the map
+    pub fn decode(&self, pgn: u32, data: &[u8]) -> Option<HashMap<String, f64>> {
+        let fields = self.layouts.get(&pgn)?;
+        Some(
+            fields
+                .iter()
+                .filter_map(|field| field.decode(data).map(|value| (field.name.clone(), value)))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_fluid_level() {
+        // Fuel tank 0, 62.5 %, 180.0 L
+        let data = [0x00, 0x09, 0x3D, 0x08, 0x07, 0x00, 0x00, 0xFF];
+        let fields = PgnLayouts::builtin().decode(127505, &data).unwrap();
+
+        assert_eq!(fields.len(), 2);
+        assert!((fields["level"] - 62.5).abs() < 1e-9);
+        assert!((fields["capacity"] - 180.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_decode_not_available_and_short_data() {
+        let mut layouts = PgnLayouts::new();
+        layouts.insert(65000, vec![
+            FieldLayout::unsigned("a", 0, 2, 0.1),
+            FieldLayout::signed("b", 2, 2, 0.01),
+            FieldLayout::signed("c", 4, 1, 1.0),
+            FieldLayout::unsigned("d", 6, 4, 1.0),
+        ]);
+        let fields = layouts.decode(65000, &[0xFF, 0xFF, 0x9C, 0xFF, 0xFE, 0x00]).unwrap();
+
+        assert!(!fields.contains_key("a")); // Not available
+        assert!((fields["b"] + 1.0).abs() < 1e-9);
+        assert_eq!(fields["c"], -2.0);
+        assert!(!fields.contains_key("d")); // Beyond the data
+        assert!(layouts.decode(65001, &[0x00]).is_none());
+    }
+}
@@ -1,19 +1,53 @@
+use std::collections::HashMap;
 use std::fmt;
 
+use serde_json::{json, Value};
+use tracing::debug;
+
+use super::decode_error::DecodeError;
+
 use super::pgn126992::NMEASystemTime;
+use super::pgn126996::ProductInformation;
+use super::pgn127237::HeadingTrackControl;
+use super::pgn127245::Rudder;
 use super::pgn127250::VesselHeading;
 use super::pgn127251::RateOfTurn;
 use super::pgn127257::Attitude;
 use super::pgn127488::EngineRapidUpdate;
+use super::pgn127493::TransmissionParameters;
+use super::pgn127506::DcDetailedStatus;
+use super::pgn127508::BatteryStatus;
 use super::pgn128259::SpeedWaterReferenced;
 use super::pgn128267::WaterDepth;
 use super::pgn129025::PositionRapidUpdate;
 use super::pgn129026::CogSogRapidUpdate;
 use super::pgn129029::GnssPositionData;
+use super::pgn129283::CrossTrackError;
+use super::pgn129284::NavigationData;
+use super::pgn129291::SetDrift;
+use super::pgn129540::GnssSatsInView;
 use super::pgn130306::WindData;
+use super::pgn130311::EnvironmentalParameters;
 use super::pgn130312::Temperature;
 use super::pgn130313::Humidity;
 use super::pgn130314::ActualPressure;
+use super::pgn130316::TemperatureExtended;
+
+/// Wrap a decoded message, or fall back to the raw bytes logging why the decoder failed
+fn decoded<T>(
+    pgn: u32,
+    data: &[u8],
+    result: Result<T, DecodeError>,
+    variant: fn(T) -> N2kMessage,
+) -> N2kMessage {
+    match result {
+        Ok(msg) => variant(msg),
+        Err(e) => {
+            debug!("Failed to decode PGN {}: {}", pgn, e);
+            N2kMessage::Unknown(pgn, data.to_vec())
+        }
+    }
+}
 
 fn format_data_bytes(data: &[u8]) -> String {
     data.iter()
@@ -26,92 +60,385 @@ fn format_data_bytes(data: &[u8]) -> String {
 #[derive(Debug, Clone)]
 pub enum N2kMessage {
     NMEASystemTime(NMEASystemTime),
+    ProductInformation(ProductInformation),
+    HeadingTrackControl(HeadingTrackControl),
+    Rudder(Rudder),
     VesselHeading(VesselHeading),
     RateOfTurn(RateOfTurn),
     Attitude(Attitude),
     EngineRapidUpdate(EngineRapidUpdate),
+    TransmissionParameters(TransmissionParameters),
+    DcDetailedStatus(DcDetailedStatus),
+    BatteryStatus(BatteryStatus),
     SpeedWaterReferenced(SpeedWaterReferenced),
     WaterDepth(WaterDepth),
     PositionRapidUpdate(PositionRapidUpdate),
     CogSogRapidUpdate(CogSogRapidUpdate),
     GnssPositionData(GnssPositionData),
+    CrossTrackError(CrossTrackError),
+    NavigationData(NavigationData),
+    SetDrift(SetDrift),
+    GnssSatsInView(GnssSatsInView),
     WindData(WindData),
+    EnvironmentalParameters(EnvironmentalParameters),
     Temperature(Temperature),
     Humidity(Humidity),
     ActualPressure(ActualPressure),
+    TemperatureExtended(TemperatureExtended),
+    /// PGN without a dedicated decoder, decoded with its field layout (see `PgnLayouts`)
+    Fields(u32, HashMap<String, f64>),
     Unknown(u32, Vec<u8>),
 }
 
 impl N2kMessage {
     pub fn from_pgn(pgn: u32, data: &[u8]) -> Self {
         match pgn {
-            126992 => NMEASystemTime::from_bytes(data)
-                .map(N2kMessage::NMEASystemTime)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
-            127250 => VesselHeading::from_bytes(data)
-                .map(N2kMessage::VesselHeading)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
-            127251 => RateOfTurn::from_bytes(data)
-                .map(N2kMessage::RateOfTurn)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
-            127257 => Attitude::from_bytes(data)
-                .map(N2kMessage::Attitude)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
-            127488 => EngineRapidUpdate::from_bytes(data)
-                .map(N2kMessage::EngineRapidUpdate)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
-            128259 => SpeedWaterReferenced::from_bytes(data)
-                .map(N2kMessage::SpeedWaterReferenced)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
-            128267 => WaterDepth::from_bytes(data)
-                .map(N2kMessage::WaterDepth)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
-            129025 => PositionRapidUpdate::from_bytes(data)
-                .map(N2kMessage::PositionRapidUpdate)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
-            129026 => CogSogRapidUpdate::from_bytes(data)
-                .map(N2kMessage::CogSogRapidUpdate)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
-            129029 => GnssPositionData::from_bytes(data)
-                .map(N2kMessage::GnssPositionData)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
-            130306 => WindData::from_bytes(data)
-                .map(N2kMessage::WindData)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
-            130312 => Temperature::from_bytes(data)
-                .map(N2kMessage::Temperature)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
-            130313 => Humidity::from_bytes(data)
-                .map(N2kMessage::Humidity)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
-            130314 => ActualPressure::from_bytes(data)
-                .map(N2kMessage::ActualPressure)
-                .unwrap_or(N2kMessage::Unknown(pgn, data.to_vec())),
+            126992 => decoded(pgn, data, NMEASystemTime::decode(data), N2kMessage::NMEASystemTime),
+            126996 => decoded(pgn, data, ProductInformation::decode(data), N2kMessage::ProductInformation),
+            127237 => decoded(pgn, data, HeadingTrackControl::decode(data), N2kMessage::HeadingTrackControl),
+            127245 => decoded(pgn, data, Rudder::decode(data), N2kMessage::Rudder),
+            127250 => decoded(pgn, data, VesselHeading::decode(data), N2kMessage::VesselHeading),
+            127251 => decoded(pgn, data, RateOfTurn::decode(data), N2kMessage::RateOfTurn),
+            127257 => decoded(pgn, data, Attitude::decode(data), N2kMessage::Attitude),
+            127488 => decoded(pgn, data, EngineRapidUpdate::decode(data), N2kMessage::EngineRapidUpdate),
+            127493 => decoded(pgn, data, TransmissionParameters::decode(data), N2kMessage::TransmissionParameters),
+            127506 => decoded(pgn, data, DcDetailedStatus::decode(data), N2kMessage::DcDetailedStatus),
+            127508 => decoded(pgn, data, BatteryStatus::decode(data), N2kMessage::BatteryStatus),
+            128259 => decoded(pgn, data, SpeedWaterReferenced::decode(data), N2kMessage::SpeedWaterReferenced),
+            128267 => decoded(pgn, data, WaterDepth::decode(data), N2kMessage::WaterDepth),
+            129025 => decoded(pgn, data, PositionRapidUpdate::decode(data), N2kMessage::PositionRapidUpdate),
+            129026 => decoded(pgn, data, CogSogRapidUpdate::decode(data), N2kMessage::CogSogRapidUpdate),
+            129029 => decoded(pgn, data, GnssPositionData::decode(data), N2kMessage::GnssPositionData),
+            129283 => decoded(pgn, data, CrossTrackError::decode(data), N2kMessage::CrossTrackError),
+            129284 => decoded(pgn, data, NavigationData::decode(data), N2kMessage::NavigationData),
+            129291 => decoded(pgn, data, SetDrift::decode(data), N2kMessage::SetDrift),
+            129540 => decoded(pgn, data, GnssSatsInView::decode(data), N2kMessage::GnssSatsInView),
+            130306 => decoded(pgn, data, WindData::decode(data), N2kMessage::WindData),
+            130310 => decoded(pgn, data, EnvironmentalParameters::decode_130310(data), N2kMessage::EnvironmentalParameters),
+            130311 => decoded(pgn, data, EnvironmentalParameters::decode(data), N2kMessage::EnvironmentalParameters),
+            130312 => decoded(pgn, data, Temperature::decode(data), N2kMessage::Temperature),
+            130313 => decoded(pgn, data, Humidity::decode(data), N2kMessage::Humidity),
+            130314 => decoded(pgn, data, ActualPressure::decode(data), N2kMessage::ActualPressure),
+            130316 => decoded(pgn, data, TemperatureExtended::decode(data), N2kMessage::TemperatureExtended),
             _ => N2kMessage::Unknown(pgn, data.to_vec()),
         }
     }
+
+    /// Name of the message type, as used in the JSON outputs
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            N2kMessage::NMEASystemTime(_) => "NMEASystemTime",
+            N2kMessage::ProductInformation(_) => "ProductInformation",
+            N2kMessage::HeadingTrackControl(_) => "HeadingTrackControl",
+            N2kMessage::Rudder(_) => "Rudder",
+            N2kMessage::VesselHeading(_) => "VesselHeading",
+            N2kMessage::RateOfTurn(_) => "RateOfTurn",
+            N2kMessage::Attitude(_) => "Attitude",
+            N2kMessage::EngineRapidUpdate(_) => "EngineRapidUpdate",
+            N2kMessage::TransmissionParameters(_) => "TransmissionParameters",
+            N2kMessage::DcDetailedStatus(_) => "DcDetailedStatus",
+            N2kMessage::BatteryStatus(_) => "BatteryStatus",
+            N2kMessage::SpeedWaterReferenced(_) => "SpeedWaterReferenced",
+            N2kMessage::WaterDepth(_) => "WaterDepth",
+            N2kMessage::PositionRapidUpdate(_) => "PositionRapidUpdate",
+            N2kMessage::CogSogRapidUpdate(_) => "CogSogRapidUpdate",
+            N2kMessage::GnssPositionData(_) => "GnssPositionData",
+            N2kMessage::CrossTrackError(_) => "CrossTrackError",
+            N2kMessage::NavigationData(_) => "NavigationData",
+            N2kMessage::SetDrift(_) => "SetDrift",
+            N2kMessage::GnssSatsInView(_) => "GnssSatsInView",
+            N2kMessage::WindData(_) => "WindData",
+            N2kMessage::EnvironmentalParameters(_) => "EnvironmentalParameters",
+            N2kMessage::Temperature(_) => "Temperature",
+            N2kMessage::Humidity(_) => "Humidity",
+            N2kMessage::ActualPressure(_) => "ActualPressure",
+            N2kMessage::TemperatureExtended(_) => "TemperatureExtended",
+            N2kMessage::Fields(..) => "Fields",
+            N2kMessage::Unknown(..) => "Unknown",
+        }
+    }
+
+    pub fn pgn(&self) -> u32 {
+        match self {
+            N2kMessage::NMEASystemTime(msg) => msg.pgn,
+            N2kMessage::ProductInformation(msg) => msg.pgn,
+            N2kMessage::HeadingTrackControl(msg) => msg.pgn,
+            N2kMessage::Rudder(msg) => msg.pgn,
+            N2kMessage::VesselHeading(msg) => msg.pgn,
+            N2kMessage::RateOfTurn(msg) => msg.pgn,
+            N2kMessage::Attitude(msg) => msg.pgn,
+            N2kMessage::EngineRapidUpdate(msg) => msg.pgn,
+            N2kMessage::TransmissionParameters(msg) => msg.pgn,
+            N2kMessage::DcDetailedStatus(msg) => msg.pgn,
+            N2kMessage::BatteryStatus(msg) => msg.pgn,
+            N2kMessage::SpeedWaterReferenced(msg) => msg.pgn,
+            N2kMessage::WaterDepth(msg) => msg.pgn,
+            N2kMessage::PositionRapidUpdate(msg) => msg.pgn,
+            N2kMessage::CogSogRapidUpdate(msg) => msg.pgn,
+            N2kMessage::GnssPositionData(msg) => msg.pgn,
+            N2kMessage::CrossTrackError(msg) => msg.pgn,
+            N2kMessage::NavigationData(msg) => msg.pgn,
+            N2kMessage::SetDrift(msg) => msg.pgn,
+            N2kMessage::GnssSatsInView(msg) => msg.pgn,
+            N2kMessage::WindData(msg) => msg.pgn,
+            N2kMessage::EnvironmentalParameters(msg) => msg.pgn,
+            N2kMessage::Temperature(msg) => msg.pgn,
+            N2kMessage::Humidity(msg) => msg.pgn,
+            N2kMessage::ActualPressure(msg) => msg.pgn,
+            N2kMessage::TemperatureExtended(msg) => msg.pgn,
+            N2kMessage::Fields(pgn, _) => *pgn,
+            N2kMessage::Unknown(pgn, _) => *pgn,
+        }
+    }
+
+    /// Decoded fields of the message as a JSON object
+    /// Missing values (`None`) are serialized as `null`
+    pub fn to_json(&self) -> Value {
+        match self {
+            N2kMessage::NMEASystemTime(msg) => json!({
+                "date": format!("{:?}", msg.date_time.date),
+                "time": format!("{:?}", msg.date_time.time)
+            }),
+            N2kMessage::ProductInformation(msg) => json!({
+                "product_code": msg.product_code,
+                "model_id": msg.model_id,
+                "software_version": msg.software_version,
+                "model_version": msg.model_version,
+                "model_serial_code": msg.model_serial_code,
+            }),
+            N2kMessage::PositionRapidUpdate(msg) => json!({
+                "latitude": msg.latitude,
+                "longitude": msg.longitude,
+            }),
+            N2kMessage::CogSogRapidUpdate(msg) => json!({
+                "sog": msg.sog,
+                "cog": msg.cog,
+                "cog_reference": msg.cog_reference
+            }),
+            N2kMessage::GnssPositionData(msg) => json!({
+                "date": format!("{:?}", msg.date_time.date),
+                "time": format!("{:?}", msg.date_time.time),
+                "latitude": msg.latitude,
+                "longitude": msg.longitude,
+                "altitude": msg.altitude,
+            }),
+            N2kMessage::CrossTrackError(msg) => json!({
+                "xte": msg.xte,
+                "navigation_terminated": msg.navigation_terminated,
+            }),
+            N2kMessage::NavigationData(msg) => json!({
+                "distance_to_waypoint": msg.distance_to_waypoint,
+                "bearing_to_waypoint": msg.bearing_to_waypoint,
+                "bearing_reference": msg.bearing_reference,
+                "waypoint_closing_velocity": msg.waypoint_closing_velocity,
+            }),
+            N2kMessage::SetDrift(msg) => json!({
+                "set": msg.set,
+                "drift": msg.drift,
+                "set_reference": msg.set_reference,
+            }),
+            N2kMessage::GnssSatsInView(msg) => {
+                let satellites: Vec<Value> = msg.satellites.iter().map(|sat| json!({
+                    "prn": sat.prn,
+                    "elevation": sat.elevation,
+                    "azimuth": sat.azimuth,
+                    "snr": sat.snr,
+                    "status": sat.status,
+                })).collect();
+                json!({
+                    "sats_in_view": msg.sats_in_view,
+                    "satellites": satellites,
+                })
+            }
+            N2kMessage::WindData(msg) => json!({
+                "speed": msg.speed,
+                "angle": msg.angle,
+                "reference": format!("{:?}", msg.reference)
+            }),
+            N2kMessage::EnvironmentalParameters(msg) => json!({
+                "temperature_source": msg.temperature_source,
+                "temperature": msg.temperature,
+                "humidity_source": msg.humidity_source,
+                "humidity": msg.humidity,
+                "atmospheric_pressure": msg.atmospheric_pressure,
+            }),
+            N2kMessage::Temperature(msg) => json!({
+                "instance": msg.instance,
+                "source": msg.source,
+                "temperature": msg.temperature,
+                "set_temperature": msg.set_temperature,
+            }),
+            N2kMessage::Humidity(msg) => json!({
+                "instance": msg.instance,
+                "source": msg.source,
+                "actual_humidity": msg.actual_humidity,
+                "set_humidity": msg.set_humidity,
+            }),
+            N2kMessage::ActualPressure(msg) => json!({
+                "instance": msg.instance,
+                "source": msg.source,
+                "pressure": msg.pressure,
+            }),
+            N2kMessage::TemperatureExtended(msg) => json!({
+                "instance": msg.instance,
+                "source": msg.source,
+                "temperature": msg.temperature,
+                "set_temperature": msg.set_temperature,
+            }),
+            N2kMessage::EngineRapidUpdate(msg) => json!({
+                "engine_instance": msg.engine_instance,
+                "engine_speed": msg.engine_speed,
+                "engine_boost_pressure": msg.engine_boost_pressure,
+                "engine_tilt_trim": msg.engine_tilt_trim,
+            }),
+            N2kMessage::HeadingTrackControl(msg) => json!({
+                "rudder_limit_exceeded": msg.rudder_limit_exceeded,
+                "steering_mode": msg.steering_mode,
+                "autopilot_engaged": msg.is_autopilot_engaged(),
+                "commanded_heading": msg.commanded_heading,
+                "heading_reference": msg.heading_reference,
+            }),
+            N2kMessage::TransmissionParameters(msg) => json!({
+                "instance": msg.instance,
+                "gear": format!("{:?}", msg.gear),
+                "oil_pressure": msg.oil_pressure,
+                "oil_temperature": msg.oil_temperature,
+            }),
+            N2kMessage::DcDetailedStatus(msg) => json!({
+                "instance": msg.instance,
+                "dc_type": msg.dc_type,
+                "state_of_charge": msg.state_of_charge,
+                "state_of_health": msg.state_of_health,
+                "time_remaining_min": msg.time_remaining_min,
+                "ripple_voltage": msg.ripple_voltage,
+            }),
+            N2kMessage::BatteryStatus(msg) => json!({
+                "instance": msg.instance,
+                "voltage": msg.voltage,
+                "current": msg.current,
+                "temperature": msg.temperature,
+            }),
+            N2kMessage::Attitude(msg) => json!({
+                "yaw": msg.yaw,
+                "pitch": msg.pitch,
+                "roll": msg.roll,
+            }),
+            N2kMessage::Rudder(msg) => json!({
+                "instance": msg.instance,
+                "position": msg.position,
+                "angle_order": msg.angle_order,
+            }),
+            N2kMessage::VesselHeading(msg) => json!({
+                "heading": msg.heading,
+                "reference": format!("{:?}", msg.reference),
+            }),
+            N2kMessage::RateOfTurn(msg) => json!({
+                "rate": msg.rate,
+            }),
+            N2kMessage::SpeedWaterReferenced(msg) => json!({
+                "speed": msg.speed,
+            }),
+            N2kMessage::WaterDepth(msg) => json!({
+                "depth": msg.depth,
+                "offset": msg.offset,
+            }),
+            N2kMessage::Fields(_, fields) => json!(fields),
+            N2kMessage::Unknown(_, raw_data) => json!({
+                "raw": raw_data
+            }),
+        }
+    }
 }
 
 impl fmt::Display for N2kMessage {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             N2kMessage::NMEASystemTime(msg) => write!(f, "{}", msg),
+            N2kMessage::ProductInformation(msg) => write!(f, "{}", msg),
+            N2kMessage::HeadingTrackControl(msg) => write!(f, "{}", msg),
+            N2kMessage::Rudder(msg) => write!(f, "{}", msg),
             N2kMessage::VesselHeading(msg) => write!(f, "{}", msg),
             N2kMessage::RateOfTurn(msg) => write!(f, "{}", msg),
             N2kMessage::Attitude(msg) => write!(f, "{}", msg),
             N2kMessage::EngineRapidUpdate(msg) => write!(f, "{}", msg),
+            N2kMessage::TransmissionParameters(msg) => write!(f, "{}", msg),
+            N2kMessage::DcDetailedStatus(msg) => write!(f, "{}", msg),
+            N2kMessage::BatteryStatus(msg) => write!(f, "{}", msg),
             N2kMessage::SpeedWaterReferenced(msg) => write!(f, "{}", msg),
             N2kMessage::WaterDepth(msg) => write!(f, "{}", msg),
             N2kMessage::PositionRapidUpdate(msg) => write!(f, "{}", msg),
             N2kMessage::CogSogRapidUpdate(msg) => write!(f, "{}", msg),
             N2kMessage::GnssPositionData(msg) => write!(f, "{}", msg),
+            N2kMessage::CrossTrackError(msg) => write!(f, "{}", msg),
+            N2kMessage::NavigationData(msg) => write!(f, "{}", msg),
+            N2kMessage::SetDrift(msg) => write!(f, "{}", msg),
+            N2kMessage::GnssSatsInView(msg) => write!(f, "{}", msg),
             N2kMessage::WindData(msg) => write!(f, "{}", msg),
+            N2kMessage::EnvironmentalParameters(msg) => write!(f, "{}", msg),
             N2kMessage::Temperature(msg) => write!(f, "{}", msg),
             N2kMessage::Humidity(msg) => write!(f, "{}", msg),
             N2kMessage::ActualPressure(msg) => write!(f, "{}", msg),
+            N2kMessage::TemperatureExtended(msg) => write!(f, "{}", msg),
+            N2kMessage::Fields(_pgn, fields) => {
+                let mut names: Vec<&String> = fields.keys().collect();
+                names.sort();
+                write!(f, "      Fields:")?;
+                for name in names {
+                    write!(f, " {}={}", name, fields[name])?;
+                }
+                Ok(())
+            }
             N2kMessage::Unknown(_pgn, data) => {
                 write!(f, "      Raw data: [{}]", format_data_bytes(data))
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wind_data_to_json() {
+        let message = N2kMessage::WindData(WindData::new_apparent(5.0, 0.5));
+        assert_eq!(message.message_type(), "WindData");
+        assert_eq!(message.pgn(), 130306);
+        assert_eq!(message.to_json(), json!({
+            "speed": 5.0,
+            "angle": 0.5,
+            "reference": "Apparent",
+        }));
+    }
+
+    #[test]
+    fn test_position_rapid_update_to_json() {
+        let message = N2kMessage::PositionRapidUpdate(PositionRapidUpdate::new(43.5, 10.25));
+        assert_eq!(message.message_type(), "PositionRapidUpdate");
+        assert_eq!(message.pgn(), 129025);
+        assert_eq!(message.to_json(), json!({
+            "latitude": 43.5,
+            "longitude": 10.25,
+        }));
+    }
+
+    #[test]
+    fn test_unknown_to_json() {
+        let message = N2kMessage::Unknown(65280, vec![0x01, 0xFF]);
+        assert_eq!(message.message_type(), "Unknown");
+        assert_eq!(message.pgn(), 65280);
+        assert_eq!(message.to_json(), json!({ "raw": [1, 255] }));
+    }
+
+    #[test]
+    fn test_truncated_frame_decoded_as_unknown() {
+        let message = N2kMessage::from_pgn(127488, &[0x00, 0x01]);
+        assert!(matches!(message, N2kMessage::Unknown(127488, ref data) if data.len() == 2));
+    }
+
+    #[test]
+    fn test_missing_values_to_json_null() {
+        let message = N2kMessage::HeadingTrackControl(HeadingTrackControl::new(0, None));
+        assert_eq!(message.to_json()["commanded_heading"], Value::Null);
+    }
+}
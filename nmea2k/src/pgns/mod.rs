@@ -1,30 +1,59 @@
 pub mod pgn126992;
+pub mod pgn126996;
+pub mod pgn127237;
+pub mod pgn127245;
 pub mod pgn127250;
 pub mod pgn127251;
 pub mod pgn127257;
 pub mod pgn127488;
+pub mod pgn127493;
+pub mod pgn127506;
+pub mod pgn127508;
 pub mod pgn128259;
 pub mod pgn128267;
 pub mod pgn129025;
 pub mod pgn129026;
 pub mod pgn129029;
+pub mod pgn129283;
+pub mod pgn129284;
+pub mod pgn129291;
+pub mod pgn129540;
 pub mod pgn130306;
+pub mod pgn130311;
 pub mod pgn130312;
 pub mod pgn130313;
 pub mod pgn130314;
+pub mod pgn130316;
 pub mod message;
+pub mod decode_error;
+pub mod field_layout;
 pub mod nmea2000_date_time;
 
 // Re-export commonly used types
 pub use message::N2kMessage;
+pub use decode_error::DecodeError;
+pub use field_layout::{FieldLayout, PgnLayouts};
 pub use pgn126992::NMEASystemTime;
+pub use pgn126996::ProductInformation;
+pub use pgn127237::HeadingTrackControl;
+pub use pgn127245::Rudder;
 pub use pgn127257::Attitude;
 pub use pgn127488::EngineRapidUpdate;
+pub use pgn127493::{Gear, TransmissionParameters};
+pub use pgn127506::DcDetailedStatus;
+pub use pgn127508::BatteryStatus;
 pub use pgn129025::PositionRapidUpdate;
 pub use pgn129026::CogSogRapidUpdate;
+pub use pgn129283::CrossTrackError;
+pub use pgn129284::NavigationData;
+pub use pgn129291::SetDrift;
+pub use pgn129540::{GnssSatsInView, SatelliteInfo};
 pub use pgn130306::WindData;
+pub use pgn130311::EnvironmentalParameters;
 pub use pgn130312::Temperature;
 pub use pgn130313::Humidity;
 pub use pgn130314::ActualPressure;
+pub use pgn130316::TemperatureExtended;
+pub use pgn128267::WaterDepth;
 pub use pgn127250::VesselHeading;
 pub use pgn127250::HeadingReference;
@@ -42,7 +42,7 @@ impl N2kDateTime {
         let seconds_from_date = days_since_epoch * 86400;
         
         // NMEA2000 time is in units of 0.0001 seconds since midnight
-        let seconds_since_midnight = (self.time as f64 * 0.0001) as i64;
+        let seconds_since_midnight = (self.time * 0.0001) as i64;
         
         seconds_from_date + seconds_since_midnight
     }
@@ -57,7 +57,7 @@ impl N2kDateTime {
     /// Get milliseconds component
     pub fn milliseconds(&self) -> u32 {
         // Time is in units of 0.0001 seconds (100 microseconds)
-        let total_ms = (self.time as f64 * 0.0001 * 1000.0) as u32;
+        let total_ms = (self.time * 0.0001 * 1000.0) as u32;
         total_ms % 1000
     }
 
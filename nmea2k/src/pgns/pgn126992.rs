@@ -1,5 +1,6 @@
 use std::fmt;
 
+use super::decode_error::{check_len, DecodeError};
 use super::nmea2000_date_time::N2kDateTime;
 
 #[derive(Debug, Clone)]
@@ -28,16 +29,18 @@ impl NMEASystemTime {
     }
     
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 8 {
-            return None;
-        }
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 8)?;
 
         let sid = data[0];
         let source = data[1];
         let date = u16::from_le_bytes([data[2], data[3]]);
         let time = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as f64;
 
-        Some(NMEASystemTime {
+        Ok(NMEASystemTime {
             pgn: 126992,
             sid,
             source,
@@ -56,7 +59,7 @@ impl fmt::Display for NMEASystemTime {
         
         // Convert to date/time components
         let days_since_epoch = self.date_time.date as i64;
-        let seconds_since_midnight = (self.date_time.time as f64 * 0.0001) as i64;
+        let seconds_since_midnight = (self.date_time.time * 0.0001) as i64;
         
         let hours = seconds_since_midnight / 3600;
         let minutes = (seconds_since_midnight % 3600) / 60;
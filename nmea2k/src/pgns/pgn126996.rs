@@ -0,0 +1,133 @@
+use std::fmt;
+
+use super::decode_error::{check_len, DecodeError};
+
+/// Length of the fixed-size string fields
+const STRING_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductInformation {
+    #[allow(dead_code)]
+    pub pgn: u32,
+    pub nmea2000_version: u16,
+    pub product_code: u16,
+    pub model_id: String,
+    pub software_version: String,
+    pub model_version: String,
+    pub model_serial_code: String,
+    pub certification_level: u8,
+    pub load_equivalency: u8, // multiples of 50 mA
+}
+
+impl ProductInformation {
+    // Constructor
+    // model_id: model name
+    // software_version: software version code
+    // model_serial_code: serial number
+    pub fn new(model_id: &str, software_version: &str, model_serial_code: &str) -> Self {
+        Self {
+            pgn: 126996,
+            nmea2000_version: 0,
+            product_code: 0,
+            model_id: model_id.to_string(),
+            software_version: software_version.to_string(),
+            model_version: String::new(),
+            model_serial_code: model_serial_code.to_string(),
+            certification_level: 0,
+            load_equivalency: 0,
+        }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 4 + 4 * STRING_LENGTH + 2)?;
+
+        // String fields (bytes 4-131): 4 x 32 characters
+        let field = |index: usize| {
+            let start = 4 + index * STRING_LENGTH;
+            fixed_string(&data[start..start + STRING_LENGTH])
+        };
+
+        Ok(Self {
+            pgn: 126996,
+            nmea2000_version: u16::from_le_bytes([data[0], data[1]]),
+            product_code: u16::from_le_bytes([data[2], data[3]]),
+            model_id: field(0),
+            software_version: field(1),
+            model_version: field(2),
+            model_serial_code: field(3),
+            certification_level: data[132],
+            load_equivalency: data[133],
+        })
+    }
+}
+
+// Fixed-size string, padded with 0x00, 0xFF, spaces or '@' depending on the manufacturer
+fn fixed_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0x00 || *b == 0xFF).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end])
+        .trim_end_matches([' ', '@'])
+        .to_string()
+}
+
+impl fmt::Display for ProductInformation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "      Model: {} | Software: {} | Serial: {}",
+            self.model_id, self.software_version, self.model_serial_code
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product_information(model_id: &str, software_version: &str, serial: &str, padding: u8) -> Vec<u8> {
+        let mut data = vec![padding; 134];
+        data[0..2].copy_from_slice(&2100u16.to_le_bytes());
+        data[2..4].copy_from_slice(&1234u16.to_le_bytes());
+        data[4..4 + model_id.len()].copy_from_slice(model_id.as_bytes());
+        data[36..36 + software_version.len()].copy_from_slice(software_version.as_bytes());
+        data[100..100 + serial.len()].copy_from_slice(serial.as_bytes());
+        data[132] = 1;
+        data[133] = 2;
+        data
+    }
+
+    #[test]
+    fn test_product_information_from_bytes() {
+        let data = product_information("GPS 19x NMEA 2000", "4.10", "3G1234567", 0xFF);
+        let info = ProductInformation::from_bytes(&data).unwrap();
+
+        assert_eq!(info.pgn, 126996);
+        assert_eq!(info.nmea2000_version, 2100);
+        assert_eq!(info.product_code, 1234);
+        assert_eq!(info.model_id, "GPS 19x NMEA 2000");
+        assert_eq!(info.software_version, "4.10");
+        assert_eq!(info.model_version, "");
+        assert_eq!(info.model_serial_code, "3G1234567");
+        assert_eq!(info.certification_level, 1);
+        assert_eq!(info.load_equivalency, 2);
+    }
+
+    #[test]
+    fn test_product_information_padding() {
+        for padding in [0x00, b' ', b'@'] {
+            let data = product_information("i70s", "1.08", "1100123", padding);
+            let info = ProductInformation::from_bytes(&data).unwrap();
+            assert_eq!(info.model_id, "i70s");
+            assert_eq!(info.model_serial_code, "1100123");
+        }
+    }
+
+    #[test]
+    fn test_product_information_short_data() {
+        // A single CAN frame is not enough, the message is a fast packet
+        assert!(ProductInformation::from_bytes(&[0x00; 8]).is_none());
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+
+use super::decode_error::{check_len, DecodeError};
+
+/// Steering modes reported by the autopilot
+pub const STEERING_MODE_MAIN: u8 = 0;
+pub const STEERING_MODE_HEADING_CONTROL_STANDALONE: u8 = 3;
+pub const STEERING_MODE_HEADING_CONTROL: u8 = 4;
+pub const STEERING_MODE_TRACK_CONTROL: u8 = 5;
+
+#[derive(Debug, Clone)]
+pub struct HeadingTrackControl {
+    #[allow(dead_code)]
+    pub pgn: u32,
+    pub rudder_limit_exceeded: u8, // 0 = No, 1 = Yes, 2 = Error, 3 = Unavailable
+    pub steering_mode: u8, // 0 = Main steering, 1 = Non-follow-up, 2 = Follow-up, 3-5 = autopilot modes
+    pub commanded_heading: Option<f64>, // radians, heading to steer
+    pub heading_reference: u8, // 0 = True, 1 = Magnetic, 2 = Error, 3 = Null
+}
+
+impl HeadingTrackControl {
+    // Constructor
+    // steering_mode: steering mode
+    // commanded_heading: radians (optional)
+    pub fn new(steering_mode: u8, commanded_heading: Option<f64>) -> Self {
+        Self {
+            pgn: 127237,
+            rudder_limit_exceeded: 0,
+            steering_mode,
+            commanded_heading,
+            heading_reference: 0,
+        }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 21)?;
+
+        // Heading-to-steer (bytes 5-6): uint16, 0.0001 radians
+        let heading_raw = u16::from_le_bytes([data[5], data[6]]);
+        let commanded_heading = if heading_raw == u16::MAX {
+            None
+        } else {
+            Some(heading_raw as f64 * 0.0001)
+        };
+
+        Ok(Self {
+            pgn: 127237,
+            // Rudder limit exceeded (byte 0, bits 0-1)
+            rudder_limit_exceeded: data[0] & 0x03,
+            // Steering mode (byte 1, bits 0-2), heading reference (byte 1, bits 6-7)
+            steering_mode: data[1] & 0x07,
+            commanded_heading,
+            heading_reference: (data[1] >> 6) & 0x03,
+        })
+    }
+
+    /// The autopilot is steering: heading or track control
+    pub fn is_autopilot_engaged(&self) -> bool {
+        matches!(
+            self.steering_mode,
+            STEERING_MODE_HEADING_CONTROL_STANDALONE | STEERING_MODE_HEADING_CONTROL | STEERING_MODE_TRACK_CONTROL
+        )
+    }
+}
+
+impl fmt::Display for HeadingTrackControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "      Steering mode: {}", self.steering_mode)?;
+        if self.is_autopilot_engaged() {
+            write!(f, " (autopilot)")?;
+        }
+        if let Some(heading) = self.commanded_heading {
+            let reference = if self.heading_reference == 1 { "M" } else { "T" };
+            write!(f, " | Commanded heading: {:.1}°{}", heading.to_degrees(), reference)?;
+        }
+        if self.rudder_limit_exceeded == 1 {
+            write!(f, " | Rudder limit exceeded")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heading_track_control_from_bytes() {
+        // Heading control, magnetic, heading to steer 90° (15708 * 0.0001 rad)
+        let mut data = [0xFF; 21];
+        data[0] = 0xFC; // Rudder limit not exceeded, other limits unavailable
+        data[1] = 0x7C; // Steering mode 4, turn mode unavailable, magnetic
+        data[5] = 0x5C;
+        data[6] = 0x3D;
+        let control = HeadingTrackControl::from_bytes(&data).unwrap();
+
+        assert_eq!(control.pgn, 127237);
+        assert_eq!(control.rudder_limit_exceeded, 0);
+        assert_eq!(control.steering_mode, STEERING_MODE_HEADING_CONTROL);
+        assert_eq!(control.heading_reference, 1);
+        assert!((control.commanded_heading.unwrap().to_degrees() - 90.0).abs() < 0.01);
+        assert!(control.is_autopilot_engaged());
+    }
+
+    #[test]
+    fn test_heading_track_control_standby() {
+        // Main steering, heading to steer unavailable
+        let mut data = [0xFF; 21];
+        data[1] = 0xF8;
+        let control = HeadingTrackControl::from_bytes(&data).unwrap();
+        assert_eq!(control.steering_mode, STEERING_MODE_MAIN);
+        assert!(control.commanded_heading.is_none());
+        assert!(!control.is_autopilot_engaged());
+    }
+
+    #[test]
+    fn test_heading_track_control_short_data() {
+        assert!(HeadingTrackControl::from_bytes(&[0xFC, 0x7C, 0xFF, 0xFF, 0xFF, 0x5C, 0x3D, 0xFF]).is_none());
+    }
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+
+use super::decode_error::{check_len, DecodeError};
+
+#[derive(Debug, Clone)]
+pub struct Rudder {
+    #[allow(dead_code)]
+    pub pgn: u32,
+    pub instance: u8,
+    pub position: Option<f64>,    // radians
+    pub angle_order: Option<f64>, // radians
+}
+
+impl Rudder {
+    // Constructor
+    // instance: rudder instance
+    // position: radians (optional)
+    // angle_order: radians (optional)
+    pub fn new(instance: u8, position: Option<f64>, angle_order: Option<f64>) -> Self {
+        Self {
+            pgn: 127245,
+            instance,
+            position,
+            angle_order,
+        }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 6)?;
+        let instance = data[0];
+        // Byte 1: direction order (2 bits) + reserved, not used
+
+        // Angle order (bytes 2-3): int16, 0.0001 radians
+        let angle_order_raw = i16::from_le_bytes([data[2], data[3]]);
+        let angle_order = if angle_order_raw == i16::MAX {
+            None
+        } else {
+            Some(angle_order_raw as f64 * 0.0001)
+        };
+
+        // Position (bytes 4-5): int16, 0.0001 radians
+        let position_raw = i16::from_le_bytes([data[4], data[5]]);
+        let position = if position_raw == i16::MAX {
+            None
+        } else {
+            Some(position_raw as f64 * 0.0001)
+        };
+
+        Ok(Self {
+            pgn: 127245,
+            instance,
+            position,
+            angle_order,
+        })
+    }
+
+    pub fn position_degrees(&self) -> Option<f64> {
+        self.position.map(|p| p.to_degrees())
+    }
+}
+
+impl fmt::Display for Rudder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "      Rudder {}: ", self.instance)?;
+        if let Some(position) = self.position {
+            write!(f, "{:.2}°", position.to_degrees())?;
+        } else {
+            write!(f, "N/A")?;
+        }
+
+        write!(f, ", Order: ")?;
+        if let Some(order) = self.angle_order {
+            write!(f, "{:.2}°", order.to_degrees())?;
+        } else {
+            write!(f, "N/A")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rudder_starboard_15_degrees() {
+        // 15° = 0.2618 rad = 2618 * 0.0001
+        let data = [
+            0x00, // Instance
+            0xFF, // Direction order + reserved
+            0xFF, 0x7F, // Angle order not available
+            0x3A, 0x0A, // Position = 2618 * 0.0001 rad
+            0xFF, 0xFF, // Reserved
+        ];
+
+        let rudder = Rudder::from_bytes(&data).unwrap();
+        assert_eq!(rudder.instance, 0);
+        assert!(rudder.angle_order.is_none());
+        assert!((rudder.position_degrees().unwrap() - 15.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_rudder_port_20_degrees() {
+        // -20° = -0.3491 rad = -3491 * 0.0001
+        let data = [
+            0x01, // Instance
+            0xFF, // Direction order + reserved
+            0x00, 0x00, // Angle order = 0
+            0x5D, 0xF2, // Position = -3491 * 0.0001 rad
+            0xFF, 0xFF, // Reserved
+        ];
+
+        let rudder = Rudder::from_bytes(&data).unwrap();
+        assert_eq!(rudder.instance, 1);
+        assert_eq!(rudder.angle_order, Some(0.0));
+        assert!((rudder.position_degrees().unwrap() + 20.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_rudder_position_not_available() {
+        let data = [0x00, 0xFF, 0xFF, 0x7F, 0xFF, 0x7F, 0xFF, 0xFF];
+
+        let rudder = Rudder::from_bytes(&data).unwrap();
+        assert!(rudder.position.is_none());
+        assert!(rudder.position_degrees().is_none());
+    }
+
+    #[test]
+    fn test_rudder_insufficient_data() {
+        assert!(Rudder::from_bytes(&[0x00, 0xFF, 0x00]).is_none());
+    }
+}
@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::decode_error::{check_len, DecodeError};
+
 #[derive(Debug, Clone)]
 pub struct VesselHeading {
     #[allow(dead_code)]
@@ -34,10 +36,12 @@ impl VesselHeading {
     }
 
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 8 {
-            return None;
-        }
-        Some(Self {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 8)?;
+        Ok(Self {
             pgn: 127250,
             sid: data[0],
             heading: u16::from_le_bytes([data[1], data[2]]) as f64 * 0.0001,
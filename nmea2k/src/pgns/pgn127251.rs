@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::decode_error::{check_len, DecodeError};
+
 #[derive(Debug, Clone)]
 pub struct RateOfTurn {
     #[allow(dead_code)]
@@ -11,10 +13,12 @@ pub struct RateOfTurn {
 
 impl RateOfTurn {
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 5 {
-            return None;
-        }
-        Some(Self {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 5)?;
+        Ok(Self {
             pgn: 127251,
             sid: data[0],
             rate: i32::from_le_bytes([data[1], data[2], data[3], data[4]]) as f64 * 1e-6,
@@ -1,3 +1,5 @@
+use super::decode_error::{check_len, DecodeError};
+
 #[derive(Debug, Clone)]
 pub struct Attitude {
     #[allow(dead_code)]
@@ -27,42 +29,44 @@ impl Attitude {
 
 
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() >= 7 {
-            let sid = data[0];
-            
-            // Yaw (bytes 1-2): int16, 0.0001 radians
-            let yaw_raw = i16::from_le_bytes([data[1], data[2]]);
-            let yaw = if yaw_raw == i16::MAX {
-                None
-            } else {
-                Some(yaw_raw as f64 * 0.0001)
-            };
-            
-            // Pitch (bytes 3-4): int16, 0.0001 radians
-            let pitch_raw = i16::from_le_bytes([data[3], data[4]]);
-            let pitch = if pitch_raw == i16::MAX {
-                None
-            } else {
-                Some(pitch_raw as f64 * 0.0001)
-            };
-            
-            // Roll (bytes 5-6): int16, 0.0001 radians
-            let roll_raw = i16::from_le_bytes([data[5], data[6]]);
-            let roll = if roll_raw == i16::MAX {
-                None
-            } else {
-                Some(roll_raw as f64 * 0.0001)
-            };
-            
-            return Some(Attitude {
-                pgn: 127257,
-                sid,
-                yaw,
-                pitch,
-                roll,
-            });
-        }
-        None
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 7)?;
+        let sid = data[0];
+        
+        // Yaw (bytes 1-2): int16, 0.0001 radians
+        let yaw_raw = i16::from_le_bytes([data[1], data[2]]);
+        let yaw = if yaw_raw == i16::MAX {
+            None
+        } else {
+            Some(yaw_raw as f64 * 0.0001)
+        };
+        
+        // Pitch (bytes 3-4): int16, 0.0001 radians
+        let pitch_raw = i16::from_le_bytes([data[3], data[4]]);
+        let pitch = if pitch_raw == i16::MAX {
+            None
+        } else {
+            Some(pitch_raw as f64 * 0.0001)
+        };
+        
+        // Roll (bytes 5-6): int16, 0.0001 radians
+        let roll_raw = i16::from_le_bytes([data[5], data[6]]);
+        let roll = if roll_raw == i16::MAX {
+            None
+        } else {
+            Some(roll_raw as f64 * 0.0001)
+        };
+        
+        Ok(Attitude {
+            pgn: 127257,
+            sid,
+            yaw,
+            pitch,
+            roll,
+        })
     }
     
     pub fn roll_degrees(&self) -> Option<f64> {
@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::decode_error::{check_len, DecodeError};
+
 #[derive(Debug, Clone)]
 pub struct EngineRapidUpdate {
     #[allow(dead_code)]
@@ -31,9 +33,11 @@ impl EngineRapidUpdate {
 
 
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 8 {
-            return None;
-        }
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 8)?;
 
         let engine_instance = data[0];
         
@@ -61,7 +65,7 @@ impl EngineRapidUpdate {
             Some(tilt_trim)
         };
 
-        Some(EngineRapidUpdate {
+        Ok(EngineRapidUpdate {
             pgn: 127488,
             engine_instance,
             engine_speed,
@@ -149,5 +153,9 @@ mod tests {
     fn test_engine_rapid_update_short_data() {
         let data = [0x00, 0x01];
         assert!(EngineRapidUpdate::from_bytes(&data).is_none());
+        assert_eq!(
+            EngineRapidUpdate::decode(&data).unwrap_err(),
+            DecodeError::TooShort { needed: 8, got: 2 }
+        );
     }
 }
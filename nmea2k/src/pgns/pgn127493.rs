@@ -0,0 +1,133 @@
+use std::fmt;
+
+use super::decode_error::{check_len, DecodeError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gear {
+    Forward,
+    Neutral,
+    Reverse,
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct TransmissionParameters {
+    #[allow(dead_code)]
+    pub pgn: u32,
+    pub instance: u8,
+    pub gear: Gear,
+    pub oil_pressure: Option<f64>, // Pa
+    pub oil_temperature: Option<f64>, // Kelvin
+}
+
+impl TransmissionParameters {
+    // Constructor
+    // instance: transmission instance
+    // gear: transmission gear
+    pub fn new(instance: u8, gear: Gear) -> Self {
+        Self {
+            pgn: 127493,
+            instance,
+            gear,
+            oil_pressure: None,
+            oil_temperature: None,
+        }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 8)?;
+
+        // Transmission gear (byte 1, bits 0-1)
+        let gear = match data[1] & 0x03 {
+            0 => Gear::Forward,
+            1 => Gear::Neutral,
+            2 => Gear::Reverse,
+            _ => Gear::Unknown,
+        };
+
+        // Oil pressure (bytes 2-3): uint16, 100 Pa
+        let pressure_raw = u16::from_le_bytes([data[2], data[3]]);
+        let oil_pressure = if pressure_raw == u16::MAX {
+            None
+        } else {
+            Some(pressure_raw as f64 * 100.0)
+        };
+
+        // Oil temperature (bytes 4-5): uint16, 0.1 K
+        let temperature_raw = u16::from_le_bytes([data[4], data[5]]);
+        let oil_temperature = if temperature_raw == u16::MAX {
+            None
+        } else {
+            Some(temperature_raw as f64 * 0.1)
+        };
+
+        Ok(Self {
+            pgn: 127493,
+            instance: data[0],
+            gear,
+            oil_pressure,
+            oil_temperature,
+        })
+    }
+}
+
+impl fmt::Display for TransmissionParameters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "      Transmission #{}: Gear {:?}", self.instance, self.gear)?;
+        if let Some(pressure) = self.oil_pressure {
+            write!(f, " | Oil pressure: {:.0} Pa", pressure)?;
+        }
+        if let Some(temperature) = self.oil_temperature {
+            write!(f, " | Oil temperature: {:.1}°C", temperature - 273.15)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transmission_parameters_from_bytes() {
+        // Instance 0, neutral, oil pressure 250000 Pa (2500 * 100), oil temperature 353.1 K (3531 * 0.1)
+        let data = [0x00, 0xFD, 0xC4, 0x09, 0xCB, 0x0D, 0x00, 0xFF];
+        let transmission = TransmissionParameters::from_bytes(&data).unwrap();
+
+        assert_eq!(transmission.pgn, 127493);
+        assert_eq!(transmission.instance, 0);
+        assert_eq!(transmission.gear, Gear::Neutral);
+        assert_eq!(transmission.oil_pressure, Some(2500.0 * 100.0));
+        assert!((transmission.oil_temperature.unwrap() - 353.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_transmission_gears() {
+        let gear = |bits: u8| {
+            let data = [0x01, 0xFC | bits, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0xFF];
+            TransmissionParameters::from_bytes(&data).unwrap().gear
+        };
+        assert_eq!(gear(0), Gear::Forward);
+        assert_eq!(gear(1), Gear::Neutral);
+        assert_eq!(gear(2), Gear::Reverse);
+        assert_eq!(gear(3), Gear::Unknown);
+    }
+
+    #[test]
+    fn test_transmission_parameters_invalid_values() {
+        let data = [0x00, 0xFC, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0xFF];
+        let transmission = TransmissionParameters::from_bytes(&data).unwrap();
+        assert_eq!(transmission.gear, Gear::Forward);
+        assert_eq!(transmission.oil_pressure, None);
+        assert_eq!(transmission.oil_temperature, None);
+    }
+
+    #[test]
+    fn test_transmission_parameters_short_data() {
+        assert!(TransmissionParameters::from_bytes(&[0x00, 0x01, 0x00]).is_none());
+    }
+}
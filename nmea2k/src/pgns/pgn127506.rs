@@ -0,0 +1,134 @@
+use std::fmt;
+
+use super::decode_error::{check_len, DecodeError};
+
+#[derive(Debug, Clone)]
+pub struct DcDetailedStatus {
+    #[allow(dead_code)]
+    pub pgn: u32,
+    #[allow(dead_code)]
+    sid: u8,
+    pub instance: u8,
+    pub dc_type: u8, // 0 = Battery, 1 = Alternator, 2 = Converter, 3 = Solar Cell, 4 = Wind Generator
+    pub state_of_charge: Option<u8>, // %
+    pub state_of_health: Option<u8>, // %
+    pub time_remaining_min: Option<u16>, // minutes
+    pub ripple_voltage: Option<f64>, // V
+}
+
+impl DcDetailedStatus {
+    // Constructor
+    // instance: DC instance
+    // dc_type: DC source type
+    // state_of_charge: % (optional)
+    // time_remaining_min: minutes (optional)
+    pub fn new(instance: u8, dc_type: u8, state_of_charge: Option<u8>, time_remaining_min: Option<u16>) -> Self {
+        Self {
+            pgn: 127506,
+            sid: 0,
+            instance,
+            dc_type,
+            state_of_charge,
+            state_of_health: None,
+            time_remaining_min,
+            ripple_voltage: None,
+        }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        // Fast packet, the remaining capacity (bytes 9-10) is not decoded
+        check_len(data, 9)?;
+
+        // State of charge and state of health (bytes 3 and 4): uint8, 1 %
+        let percent = |raw: u8| if raw == u8::MAX { None } else { Some(raw) };
+
+        // Time remaining (bytes 5-6): uint16, 1 minute
+        let time_remaining_raw = u16::from_le_bytes([data[5], data[6]]);
+        let time_remaining_min = if time_remaining_raw == u16::MAX {
+            None
+        } else {
+            Some(time_remaining_raw)
+        };
+
+        // Ripple voltage (bytes 7-8): uint16, 0.001 V
+        let ripple_raw = u16::from_le_bytes([data[7], data[8]]);
+        let ripple_voltage = if ripple_raw == u16::MAX {
+            None
+        } else {
+            Some(ripple_raw as f64 * 0.001)
+        };
+
+        Ok(Self {
+            pgn: 127506,
+            sid: data[0],
+            instance: data[1],
+            dc_type: data[2],
+            state_of_charge: percent(data[3]),
+            state_of_health: percent(data[4]),
+            time_remaining_min,
+            ripple_voltage,
+        })
+    }
+}
+
+impl fmt::Display for DcDetailedStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "      DC #{} (type {}): ", self.instance, self.dc_type)?;
+        if let Some(soc) = self.state_of_charge {
+            write!(f, "SOC {}%", soc)?;
+        } else {
+            write!(f, "SOC N/A")?;
+        }
+        if let Some(soh) = self.state_of_health {
+            write!(f, " | SOH {}%", soh)?;
+        }
+        if let Some(minutes) = self.time_remaining_min {
+            write!(f, " | {}h{:02}m remaining", minutes / 60, minutes % 60)?;
+        }
+        if let Some(ripple) = self.ripple_voltage {
+            write!(f, " | ripple {:.3} V", ripple)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dc_detailed_status_from_bytes() {
+        // SID 3, instance 1, battery, 95% SOC, 98% SOH, 600 min, 0.025 V ripple, capacity n/a
+        let data = [0x03, 0x01, 0x00, 0x5F, 0x62, 0x58, 0x02, 0x19, 0x00, 0xFF, 0xFF];
+        let status = DcDetailedStatus::from_bytes(&data).unwrap();
+
+        assert_eq!(status.pgn, 127506);
+        assert_eq!(status.instance, 1);
+        assert_eq!(status.dc_type, 0);
+        assert_eq!(status.state_of_charge, Some(95));
+        assert_eq!(status.state_of_health, Some(98));
+        assert_eq!(status.time_remaining_min, Some(600));
+        assert!((status.ripple_voltage.unwrap() - 0.025).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_dc_detailed_status_not_available() {
+        let data = [0x00, 0x02, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        let status = DcDetailedStatus::from_bytes(&data).unwrap();
+
+        assert_eq!(status.instance, 2);
+        assert!(status.state_of_charge.is_none());
+        assert!(status.state_of_health.is_none());
+        assert!(status.time_remaining_min.is_none());
+        assert!(status.ripple_voltage.is_none());
+    }
+
+    #[test]
+    fn test_dc_detailed_status_short_data() {
+        assert!(DcDetailedStatus::from_bytes(&[0x00, 0x01, 0x00, 0x5F]).is_none());
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+
+use super::decode_error::{check_len, DecodeError};
+
+#[derive(Debug, Clone)]
+pub struct BatteryStatus {
+    #[allow(dead_code)]
+    pub pgn: u32,
+    #[allow(dead_code)]
+    sid: u8,
+    pub instance: u8,
+    pub voltage: Option<f64>, // V
+    pub current: Option<f64>, // A, sign as reported by the device
+    pub temperature: Option<f64>, // Kelvin
+}
+
+impl BatteryStatus {
+    // Constructor
+    // instance: battery instance
+    // voltage: V (optional)
+    // current: A (optional)
+    pub fn new(instance: u8, voltage: Option<f64>, current: Option<f64>) -> Self {
+        Self {
+            pgn: 127508,
+            sid: 0,
+            instance,
+            voltage,
+            current,
+            temperature: None,
+        }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 8)?;
+
+        // Voltage (bytes 1-2): uint16, 0.01 V
+        let voltage_raw = u16::from_le_bytes([data[1], data[2]]);
+        let voltage = if voltage_raw == u16::MAX {
+            None
+        } else {
+            Some(voltage_raw as f64 * 0.01)
+        };
+
+        // Current (bytes 3-4): int16, 0.1 A
+        let current_raw = i16::from_le_bytes([data[3], data[4]]);
+        let current = if current_raw == i16::MAX {
+            None
+        } else {
+            Some(current_raw as f64 * 0.1)
+        };
+
+        // Temperature (bytes 5-6): uint16, 0.01 K
+        let temperature_raw = u16::from_le_bytes([data[5], data[6]]);
+        let temperature = if temperature_raw == u16::MAX {
+            None
+        } else {
+            Some(temperature_raw as f64 * 0.01)
+        };
+
+        Ok(Self {
+            pgn: 127508,
+            sid: data[7],
+            instance: data[0],
+            voltage,
+            current,
+            temperature,
+        })
+    }
+}
+
+impl fmt::Display for BatteryStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "      Battery #{}: ", self.instance)?;
+        if let Some(voltage) = self.voltage {
+            write!(f, "{:.2} V", voltage)?;
+        } else {
+            write!(f, "N/A V")?;
+        }
+        if let Some(current) = self.current {
+            write!(f, " | {:.1} A", current)?;
+        }
+        if let Some(temperature) = self.temperature {
+            write!(f, " | {:.1}°C", temperature - 273.15)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_battery_status_from_bytes() {
+        // Instance 1, 12.80 V, -15.3 A, 298.15 K
+        let data = [0x01, 0x00, 0x05, 0x67, 0xFF, 0x77, 0x74, 0x02];
+        let battery = BatteryStatus::from_bytes(&data).unwrap();
+
+        assert_eq!(battery.pgn, 127508);
+        assert_eq!(battery.instance, 1);
+        assert!((battery.voltage.unwrap() - 12.8).abs() < 1e-9);
+        assert!((battery.current.unwrap() + 15.3).abs() < 1e-9);
+        assert!((battery.temperature.unwrap() - 298.15).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_battery_status_not_available() {
+        let data = [0x00, 0xFF, 0xFF, 0xFF, 0x7F, 0xFF, 0xFF, 0x00];
+        let battery = BatteryStatus::from_bytes(&data).unwrap();
+
+        assert!(battery.voltage.is_none());
+        assert!(battery.current.is_none());
+        assert!(battery.temperature.is_none());
+    }
+
+    #[test]
+    fn test_battery_status_short_data() {
+        assert!(BatteryStatus::from_bytes(&[0x00, 0x01]).is_none());
+    }
+}
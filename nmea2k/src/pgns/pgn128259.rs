@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::decode_error::{check_len, DecodeError};
+
 #[derive(Debug, Clone)]
 pub struct SpeedWaterReferenced {
     #[allow(dead_code)]
@@ -11,10 +13,12 @@ pub struct SpeedWaterReferenced {
 
 impl SpeedWaterReferenced {
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 3 {
-            return None;
-        }
-        Some(Self {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 3)?;
+        Ok(Self {
             pgn: 128259,
             sid: data[0],
             speed: u16::from_le_bytes([data[1], data[2]]) as f64 * 0.01,
@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::decode_error::{check_len, DecodeError};
+
 #[derive(Debug, Clone)]
 pub struct WaterDepth {
     #[allow(dead_code)]
@@ -12,10 +14,12 @@ pub struct WaterDepth {
 
 impl WaterDepth {
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 7 {
-            return None;
-        }
-        Some(Self {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 7)?;
+        Ok(Self {
             pgn: 128267,
             sid: data[0],
             depth: u32::from_le_bytes([data[1], data[2], data[3], data[4]]) as f64 * 0.01,
@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::decode_error::{check_len, DecodeError};
+
 #[derive(Debug, Clone)]
 pub struct PositionRapidUpdate {
     #[allow(dead_code)]
@@ -18,14 +20,28 @@ impl PositionRapidUpdate {
     }
 
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 8 {
-            return None;
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 8)?;
+        // Latitude (bytes 0-3) and longitude (bytes 4-7): int32, 1e-7 degrees
+        // 0x7FFFFFFF is "not available", the values above are reserved
+        let latitude_raw = i32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        let longitude_raw = i32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+        let position = Self::new(latitude_raw as f64 * 1e-7, longitude_raw as f64 * 1e-7);
+        if !(-90.0..=90.0).contains(&position.latitude) {
+            return Err(DecodeError::BadField { field: "latitude", value: latitude_raw as u32 as u64 });
         }
-        Some(Self {
-            pgn: 129025,
-            latitude: i32::from_le_bytes([data[0], data[1], data[2], data[3]]) as f64 * 1e-7,
-            longitude: i32::from_le_bytes([data[4], data[5], data[6], data[7]]) as f64 * 1e-7,
-        })
+        if !(-180.0..=180.0).contains(&position.longitude) {
+            return Err(DecodeError::BadField { field: "longitude", value: longitude_raw as u32 as u64 });
+        }
+        Ok(position)
+    }
+
+    /// Latitude and longitude within their ranges
+    pub fn is_valid(&self) -> bool {
+        (-90.0..=90.0).contains(&self.latitude) && (-180.0..=180.0).contains(&self.longitude)
     }
 }
 
@@ -34,3 +50,34 @@ impl fmt::Display for PositionRapidUpdate {
         write!(f, "      Position: {:.6}°, {:.6}°", self.latitude, self.longitude)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position_bytes(latitude_raw: i32, longitude_raw: i32) -> Vec<u8> {
+        [latitude_raw.to_le_bytes(), longitude_raw.to_le_bytes()].concat()
+    }
+
+    #[test]
+    fn test_position_near_pole() {
+        let position = PositionRapidUpdate::decode(&position_bytes(899_999_999, -1_795_000_000)).unwrap();
+        assert!((position.latitude - 89.9999999).abs() < 1e-9);
+        assert!((position.longitude + 179.5).abs() < 1e-9);
+        assert!(position.is_valid());
+    }
+
+    #[test]
+    fn test_position_not_available() {
+        assert_eq!(
+            PositionRapidUpdate::decode(&position_bytes(i32::MAX, 102_000_000)).unwrap_err(),
+            DecodeError::BadField { field: "latitude", value: 0x7FFF_FFFF }
+        );
+        assert_eq!(
+            PositionRapidUpdate::decode(&position_bytes(435_000_000, i32::MAX)).unwrap_err(),
+            DecodeError::BadField { field: "longitude", value: 0x7FFF_FFFF }
+        );
+        assert!(PositionRapidUpdate::from_bytes(&position_bytes(i32::MAX, i32::MAX)).is_none());
+        assert!(!PositionRapidUpdate::new(214.7483647, 10.2).is_valid());
+    }
+}
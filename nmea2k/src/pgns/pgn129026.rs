@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::decode_error::{check_len, DecodeError};
+
 #[derive(Debug, Clone)]
 pub struct CogSogRapidUpdate {
     #[allow(dead_code)]
@@ -29,10 +31,12 @@ impl CogSogRapidUpdate {
     }
 
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 8 {
-            return None;
-        }
-        Some(Self {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 8)?;
+        Ok(Self {
             pgn: 129026,
             sid: data[0],
             cog_reference: (data[1] & 0x03) == 0,
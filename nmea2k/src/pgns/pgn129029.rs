@@ -1,5 +1,6 @@
 use std::fmt;
 
+use super::decode_error::{check_len, DecodeError};
 use super::nmea2000_date_time::N2kDateTime;
 
 #[derive(Debug, Clone)]
@@ -49,10 +50,12 @@ pub enum GnssMethod {
 
 impl GnssPositionData {
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 43 {
-            return None;
-        }
-        Some(Self {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 43)?;
+        Ok(Self {
             pgn: 129029,
             sid: data[0],
             date_time: N2kDateTime {
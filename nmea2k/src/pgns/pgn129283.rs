@@ -0,0 +1,99 @@
+use std::fmt;
+
+use super::decode_error::{check_len, DecodeError};
+
+#[derive(Debug, Clone)]
+pub struct CrossTrackError {
+    #[allow(dead_code)]
+    pub pgn: u32,
+    #[allow(dead_code)]
+    sid: u8,
+    pub xte_mode: u8, // 0 = Autonomous, 1 = Differential, 2 = Estimated, 3 = Simulator, 4 = Manual
+    pub navigation_terminated: bool,
+    pub xte: Option<f64>, // meters, positive = steer left
+}
+
+impl CrossTrackError {
+    // Constructor
+    // sid: sequence ID
+    // xte: meters (optional)
+    pub fn new(sid: u8, xte: Option<f64>) -> Self {
+        Self {
+            pgn: 129283,
+            sid,
+            xte_mode: 0,
+            navigation_terminated: false,
+            xte,
+        }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 6)?;
+
+        // XTE (bytes 2-5): int32, 0.01 meters
+        let xte_raw = i32::from_le_bytes([data[2], data[3], data[4], data[5]]);
+        let xte = if xte_raw == i32::MAX {
+            None
+        } else {
+            Some(xte_raw as f64 * 0.01)
+        };
+
+        Ok(Self {
+            pgn: 129283,
+            sid: data[0],
+            xte_mode: data[1] & 0x0F,
+            navigation_terminated: (data[1] >> 6) & 0x03 == 1,
+            xte,
+        })
+    }
+}
+
+impl fmt::Display for CrossTrackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "      XTE: ")?;
+        if let Some(xte) = self.xte {
+            write!(f, "{:.2} m", xte)?;
+        } else {
+            write!(f, "N/A")?;
+        }
+        if self.navigation_terminated {
+            write!(f, " (navigation terminated)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cross_track_error_from_bytes() {
+        // XTE = -1234 * 0.01 = -12.34 m
+        let data = [0x01, 0x00, 0x2E, 0xFB, 0xFF, 0xFF, 0xFF, 0xFF];
+        let xte = CrossTrackError::from_bytes(&data).unwrap();
+
+        assert_eq!(xte.pgn, 129283);
+        assert_eq!(xte.xte_mode, 0);
+        assert!(!xte.navigation_terminated);
+        assert!((xte.xte.unwrap() + 12.34).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cross_track_error_not_available() {
+        let data = [0x01, 0x40, 0xFF, 0xFF, 0xFF, 0x7F, 0xFF, 0xFF];
+        let xte = CrossTrackError::from_bytes(&data).unwrap();
+
+        assert!(xte.xte.is_none());
+        assert!(xte.navigation_terminated);
+    }
+
+    #[test]
+    fn test_cross_track_error_short_data() {
+        assert!(CrossTrackError::from_bytes(&[0x01, 0x00, 0x00]).is_none());
+    }
+}
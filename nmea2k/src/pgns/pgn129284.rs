@@ -0,0 +1,155 @@
+use std::fmt;
+
+use super::decode_error::{check_len, DecodeError};
+
+#[derive(Debug, Clone)]
+pub struct NavigationData {
+    #[allow(dead_code)]
+    pub pgn: u32,
+    #[allow(dead_code)]
+    sid: u8,
+    pub distance_to_waypoint: Option<f64>, // meters
+    pub bearing_reference: bool, // true = True, false = Magnetic
+    pub bearing_to_waypoint: Option<f64>, // radians, from current position
+    pub waypoint_closing_velocity: Option<f64>, // m/s
+}
+
+impl NavigationData {
+    // Constructor
+    // sid: sequence ID
+    // distance_to_waypoint: meters (optional)
+    // bearing_to_waypoint: radians (optional)
+    // waypoint_closing_velocity: m/s (optional)
+    pub fn new(
+        sid: u8,
+        distance_to_waypoint: Option<f64>,
+        bearing_to_waypoint: Option<f64>,
+        waypoint_closing_velocity: Option<f64>,
+    ) -> Self {
+        Self {
+            pgn: 129284,
+            sid,
+            distance_to_waypoint,
+            bearing_reference: true,
+            bearing_to_waypoint,
+            waypoint_closing_velocity,
+        }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 34)?;
+
+        // Distance to destination waypoint (bytes 1-4): uint32, 0.01 meters
+        let distance_raw = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
+        let distance_to_waypoint = if distance_raw == u32::MAX {
+            None
+        } else {
+            Some(distance_raw as f64 * 0.01)
+        };
+
+        // Bearing from current position to destination (bytes 14-15): uint16, 0.0001 radians
+        let bearing_raw = u16::from_le_bytes([data[14], data[15]]);
+        let bearing_to_waypoint = if bearing_raw == u16::MAX {
+            None
+        } else {
+            Some(bearing_raw as f64 * 0.0001)
+        };
+
+        // Waypoint closing velocity (bytes 32-33): int16, 0.01 m/s
+        let velocity_raw = i16::from_le_bytes([data[32], data[33]]);
+        let waypoint_closing_velocity = if velocity_raw == i16::MAX {
+            None
+        } else {
+            Some(velocity_raw as f64 * 0.01)
+        };
+
+        Ok(Self {
+            pgn: 129284,
+            sid: data[0],
+            distance_to_waypoint,
+            bearing_reference: (data[5] & 0x03) == 0,
+            bearing_to_waypoint,
+            waypoint_closing_velocity,
+        })
+    }
+}
+
+impl fmt::Display for NavigationData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "      DTW: ")?;
+        if let Some(distance) = self.distance_to_waypoint {
+            write!(f, "{:.0} m ({:.2} NM)", distance, distance / 1852.0)?;
+        } else {
+            write!(f, "N/A")?;
+        }
+
+        write!(f, " | BTW: ")?;
+        if let Some(bearing) = self.bearing_to_waypoint {
+            write!(
+                f,
+                "{:.1}° ({})",
+                bearing.to_degrees(),
+                if self.bearing_reference { "True" } else { "Mag" }
+            )?;
+        } else {
+            write!(f, "N/A")?;
+        }
+
+        write!(f, " | VMG: ")?;
+        if let Some(velocity) = self.waypoint_closing_velocity {
+            write!(f, "{:.2} m/s", velocity)?;
+        } else {
+            write!(f, "N/A")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn navigation_data(distance: u32, bearing: u16, velocity: i16) -> Vec<u8> {
+        let mut data = vec![0xFF; 34];
+        data[0] = 0x01; // SID
+        data[1..5].copy_from_slice(&distance.to_le_bytes());
+        data[5] = 0x00; // True bearing reference
+        data[14..16].copy_from_slice(&bearing.to_le_bytes());
+        data[32..34].copy_from_slice(&velocity.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_navigation_data_from_bytes() {
+        // 1852.00 m, 1.0000 rad, -2.50 m/s
+        let data = navigation_data(185_200, 10_000, -250);
+        let nav = NavigationData::from_bytes(&data).unwrap();
+
+        assert_eq!(nav.pgn, 129284);
+        assert!(nav.bearing_reference);
+        assert!((nav.distance_to_waypoint.unwrap() - 1852.0).abs() < 1e-9);
+        assert!((nav.bearing_to_waypoint.unwrap() - 1.0).abs() < 1e-9);
+        assert!((nav.waypoint_closing_velocity.unwrap() + 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_navigation_data_not_available() {
+        let data = navigation_data(u32::MAX, u16::MAX, i16::MAX);
+        let nav = NavigationData::from_bytes(&data).unwrap();
+
+        assert!(nav.distance_to_waypoint.is_none());
+        assert!(nav.bearing_to_waypoint.is_none());
+        assert!(nav.waypoint_closing_velocity.is_none());
+    }
+
+    #[test]
+    fn test_navigation_data_short_data() {
+        // A single CAN frame is not enough, the message is a fast packet
+        assert!(NavigationData::from_bytes(&[0x01; 8]).is_none());
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt;
+
+use super::decode_error::{check_len, DecodeError};
+
+#[derive(Debug, Clone)]
+pub struct SetDrift {
+    #[allow(dead_code)]
+    pub pgn: u32,
+    #[allow(dead_code)]
+    sid: u8,
+    pub set_reference: u8, // 0 = True, 1 = Magnetic, 2 = Error, 3 = Null
+    pub set: Option<f64>, // radians, direction the current flows towards
+    pub drift: Option<f64>, // m/s
+}
+
+impl SetDrift {
+    // Constructor
+    // set: radians (optional)
+    // drift: m/s (optional)
+    pub fn new(set: Option<f64>, drift: Option<f64>) -> Self {
+        Self {
+            pgn: 129291,
+            sid: 0,
+            set_reference: 0,
+            set,
+            drift,
+        }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 6)?;
+
+        // Set (bytes 2-3): uint16, 0.0001 radians
+        let set_raw = u16::from_le_bytes([data[2], data[3]]);
+        let set = if set_raw == u16::MAX {
+            None
+        } else {
+            Some(set_raw as f64 * 0.0001)
+        };
+
+        // Drift (bytes 4-5): uint16, 0.01 m/s
+        let drift_raw = u16::from_le_bytes([data[4], data[5]]);
+        let drift = if drift_raw == u16::MAX {
+            None
+        } else {
+            Some(drift_raw as f64 * 0.01)
+        };
+
+        Ok(Self {
+            pgn: 129291,
+            sid: data[0],
+            // Set reference (byte 1, bits 0-1)
+            set_reference: data[1] & 0x03,
+            set,
+            drift,
+        })
+    }
+
+    pub fn drift_knots(&self) -> Option<f64> {
+        self.drift.map(|drift| drift * 1.94384)
+    }
+}
+
+impl fmt::Display for SetDrift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.set {
+            Some(set) => {
+                let reference = if self.set_reference == 1 { "M" } else { "T" };
+                write!(f, "      Set: {:.1}°{}", set.to_degrees(), reference)?;
+            }
+            None => write!(f, "      Set: N/A")?,
+        }
+        match self.drift {
+            Some(drift) => write!(f, " | Drift: {:.2} m/s ({:.2} knots)", drift, drift * 1.94384),
+            None => write!(f, " | Drift: N/A"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_drift_from_bytes() {
+        // True reference, set 90° (15708 * 0.0001 rad), drift 1.03 m/s (103 * 0.01)
+        let data = [0x01, 0xFC, 0x5C, 0x3D, 0x67, 0x00, 0xFF, 0xFF];
+        let set_drift = SetDrift::from_bytes(&data).unwrap();
+
+        assert_eq!(set_drift.pgn, 129291);
+        assert_eq!(set_drift.set_reference, 0);
+        assert!((set_drift.set.unwrap().to_degrees() - 90.0).abs() < 0.01);
+        assert!((set_drift.drift.unwrap() - 1.03).abs() < 1e-9);
+        assert!((set_drift.drift_knots().unwrap() - 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_set_drift_not_available() {
+        let data = [0x01, 0xFD, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        let set_drift = SetDrift::from_bytes(&data).unwrap();
+        assert_eq!(set_drift.set_reference, 1);
+        assert!(set_drift.set.is_none());
+        assert!(set_drift.drift.is_none());
+        assert!(set_drift.drift_knots().is_none());
+    }
+
+    #[test]
+    fn test_set_drift_short_data() {
+        assert_eq!(
+            SetDrift::decode(&[0x01, 0xFC, 0x5C]).unwrap_err(),
+            DecodeError::TooShort { needed: 6, got: 3 }
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+
+use super::decode_error::{check_len, DecodeError};
+
+/// Size of the repeated block of each satellite
+const SATELLITE_BLOCK_LEN: usize = 12;
+
+#[derive(Debug, Clone)]
+pub struct SatelliteInfo {
+    pub prn: u8,
+    pub elevation: Option<f64>, // radians
+    pub azimuth: Option<f64>,   // radians
+    pub snr: Option<f64>,       // dB
+    pub status: u8, // 0 = Not tracked, 1 = Tracked, 2 = Used, 3 = Not tracked+Diff, 4 = Tracked+Diff, 5 = Used+Diff
+}
+
+#[derive(Debug, Clone)]
+pub struct GnssSatsInView {
+    #[allow(dead_code)]
+    pub pgn: u32,
+    #[allow(dead_code)]
+    sid: u8,
+    pub sats_in_view: u8,
+    pub satellites: Vec<SatelliteInfo>,
+}
+
+impl SatelliteInfo {
+    fn from_bytes(data: &[u8]) -> Self {
+        // Elevation (bytes 1-2): int16, 0.0001 rad
+        let elevation_raw = i16::from_le_bytes([data[1], data[2]]);
+        // Azimuth (bytes 3-4): uint16, 0.0001 rad
+        let azimuth_raw = u16::from_le_bytes([data[3], data[4]]);
+        // SNR (bytes 5-6): int16, 0.01 dB
+        let snr_raw = i16::from_le_bytes([data[5], data[6]]);
+        // Range residuals (bytes 7-10) are not decoded
+        Self {
+            prn: data[0],
+            elevation: if elevation_raw == i16::MAX { None } else { Some(elevation_raw as f64 * 0.0001) },
+            azimuth: if azimuth_raw == u16::MAX { None } else { Some(azimuth_raw as f64 * 0.0001) },
+            snr: if snr_raw == i16::MAX { None } else { Some(snr_raw as f64 * 0.01) },
+            status: data[11] & 0x0F,
+        }
+    }
+
+    /// Whether the satellite is used in the position fix
+    pub fn is_used(&self) -> bool {
+        self.status == 2 || self.status == 5
+    }
+}
+
+impl GnssSatsInView {
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        // Fast packet: SID, range residual mode, count, then one 12-byte block per satellite
+        check_len(data, 3)?;
+        let sats_in_view = data[2];
+        let sats_count = if sats_in_view == u8::MAX { 0 } else { sats_in_view as usize };
+        // A truncated payload keeps the complete blocks only
+        let satellites = data[3..]
+            .chunks_exact(SATELLITE_BLOCK_LEN)
+            .take(sats_count)
+            .map(SatelliteInfo::from_bytes)
+            .collect();
+        Ok(Self {
+            pgn: 129540,
+            sid: data[0],
+            sats_in_view,
+            satellites,
+        })
+    }
+
+    /// Number of satellites used in the position fix
+    pub fn sats_used(&self) -> usize {
+        self.satellites.iter().filter(|s| s.is_used()).count()
+    }
+}
+
+impl fmt::Display for GnssSatsInView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "      Satellites in view: {} (used: {})", self.sats_in_view, self.sats_used())?;
+        for sat in &self.satellites {
+            write!(f, " | PRN {}", sat.prn)?;
+            if let Some(snr) = sat.snr {
+                write!(f, " {:.0} dB", snr)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn satellite(prn: u8, elevation: i16, azimuth: u16, snr: i16, status: u8) -> Vec<u8> {
+        let mut block = vec![prn];
+        block.extend_from_slice(&elevation.to_le_bytes());
+        block.extend_from_slice(&azimuth.to_le_bytes());
+        block.extend_from_slice(&snr.to_le_bytes());
+        block.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0x7F]); // Range residuals n/a
+        block.push(0xF0 | status);
+        block
+    }
+
+    #[test]
+    fn test_gnss_sats_in_view_from_bytes() {
+        let mut data = vec![0x05, 0xFD, 0x03];
+        data.extend(satellite(3, 7854, 15708, 4200, 2)); // 45°, 90°, 42 dB, used
+        data.extend(satellite(17, 2618, 31416, 3150, 1)); // 15°, 180°, 31.5 dB, tracked
+        data.extend(satellite(22, i16::MAX, u16::MAX, i16::MAX, 0)); // Not tracked
+
+        let sats = GnssSatsInView::from_bytes(&data).unwrap();
+        assert_eq!(sats.pgn, 129540);
+        assert_eq!(sats.sats_in_view, 3);
+        assert_eq!(sats.satellites.len(), 3);
+        assert_eq!(sats.sats_used(), 1);
+
+        let first = &sats.satellites[0];
+        assert_eq!(first.prn, 3);
+        assert!((first.elevation.unwrap().to_degrees() - 45.0).abs() < 0.01);
+        assert!((first.azimuth.unwrap().to_degrees() - 90.0).abs() < 0.01);
+        assert!((first.snr.unwrap() - 42.0).abs() < 1e-9);
+        assert_eq!(first.status, 2);
+
+        assert_eq!(sats.satellites[1].prn, 17);
+        assert!((sats.satellites[1].snr.unwrap() - 31.5).abs() < 1e-9);
+
+        let last = &sats.satellites[2];
+        assert!(last.elevation.is_none());
+        assert!(last.azimuth.is_none());
+        assert!(last.snr.is_none());
+    }
+
+    #[test]
+    fn test_gnss_sats_in_view_truncated() {
+        let mut data = vec![0x05, 0xFD, 0x02];
+        data.extend(satellite(3, 7854, 15708, 4200, 2));
+        data.extend_from_slice(&[17, 0x00, 0x00]); // Incomplete second block
+
+        let sats = GnssSatsInView::from_bytes(&data).unwrap();
+        assert_eq!(sats.sats_in_view, 2);
+        assert_eq!(sats.satellites.len(), 1);
+        assert!(GnssSatsInView::from_bytes(&[0x05, 0xFD]).is_none());
+    }
+}
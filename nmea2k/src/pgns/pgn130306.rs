@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::decode_error::{check_len, DecodeError};
+
 #[derive(Debug, Clone)]
 pub struct WindData {
     #[allow(dead_code)]
@@ -22,33 +24,44 @@ pub enum WindReference {
 
 impl WindData {
 
-    pub fn new_apparent(speed: f64, angle: f64) -> Self {
+    // Constructor
+    // speed: m/s
+    // angle: radians
+    pub fn new(speed: f64, angle: f64, reference: WindReference) -> Self {
         Self {
             pgn: 130306,
             sid: 0,
             speed,
             angle,
-            reference: WindReference::Apparent,
+            reference,
         }
     }
 
+    pub fn new_apparent(speed: f64, angle: f64) -> Self {
+        Self::new(speed, angle, WindReference::Apparent)
+    }
+
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 6 {
-            return None;
-        }
-        Some(Self {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 6)?;
+        // Wind reference (byte 5, bits 0-2): values above 4 are not defined
+        let reference = match data[5] & 0x07 {
+            0 => WindReference::TrueGroundNorth,
+            1 => WindReference::Magnetic,
+            2 => WindReference::Apparent,
+            3 => WindReference::TrueBoat,
+            4 => WindReference::TrueWater,
+            value => return Err(DecodeError::BadField { field: "reference", value: value as u64 }),
+        };
+        Ok(Self {
             pgn: 130306,
             sid: data[0],
             speed: u16::from_le_bytes([data[1], data[2]]) as f64 * 0.01,
             angle: u16::from_le_bytes([data[3], data[4]]) as f64 * 0.0001,
-            reference: match data[5] & 0x07 {
-                0 => WindReference::TrueGroundNorth,
-                1 => WindReference::Magnetic,
-                2 => WindReference::Apparent,
-                3 => WindReference::TrueBoat,
-                4 => WindReference::TrueWater,
-                _ => WindReference::Apparent,
-            },
+            reference,
         })
     }
 
@@ -69,3 +82,25 @@ impl fmt::Display for WindData {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wind_data_decode() {
+        // 5.5 m/s, 0.5 rad, true (water referenced)
+        let wind = WindData::decode(&[0x01, 0x26, 0x02, 0x88, 0x13, 0x04]).unwrap();
+        assert!((wind.speed - 5.5).abs() < 1e-9);
+        assert!((wind.angle - 0.5).abs() < 1e-9);
+        assert!(matches!(wind.reference, WindReference::TrueWater));
+    }
+
+    #[test]
+    fn test_wind_data_undefined_reference() {
+        assert_eq!(
+            WindData::decode(&[0x01, 0x26, 0x02, 0x88, 0x13, 0xFF]).unwrap_err(),
+            DecodeError::BadField { field: "reference", value: 7 }
+        );
+    }
+}
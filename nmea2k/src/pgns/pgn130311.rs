@@ -0,0 +1,179 @@
+use std::fmt;
+
+use super::decode_error::{check_len, DecodeError};
+
+/// Temperature source of the water temperature in PGN 130310
+const SEA_TEMPERATURE: u8 = 0;
+
+/// Combined environmental parameters (PGN 130311, and the older PGN 130310)
+#[derive(Debug, Clone)]
+pub struct EnvironmentalParameters {
+    #[allow(dead_code)]
+    pub pgn: u32,
+    #[allow(dead_code)]
+    sid: u8,
+    pub temperature_source: u8, // 0 = Sea, 1 = Outside, 2 = Inside, ..., 4 = Inside Ambient
+    pub temperature: Option<f64>, // Kelvin
+    pub humidity_source: u8, // 0 = Inside, 1 = Outside
+    pub humidity: Option<f64>, // %
+    pub atmospheric_pressure: Option<f64>, // Pa
+}
+
+impl EnvironmentalParameters {
+    // Constructor
+    // temperature_source: temperature source
+    // temperature: Kelvin (optional)
+    // humidity_source: humidity source
+    // humidity: % (optional)
+    // atmospheric_pressure: Pa (optional)
+    pub fn new(
+        temperature_source: u8,
+        temperature: Option<f64>,
+        humidity_source: u8,
+        humidity: Option<f64>,
+        atmospheric_pressure: Option<f64>,
+    ) -> Self {
+        Self {
+            pgn: 130311,
+            sid: 0,
+            temperature_source,
+            temperature,
+            humidity_source,
+            humidity,
+            atmospheric_pressure,
+        }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        Self::decode(data).ok()
+    }
+
+    /// Decode PGN 130311
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 8)?;
+
+        // Humidity (bytes 4-5): int16, 0.004 %
+        let humidity_raw = i16::from_le_bytes([data[4], data[5]]);
+        let humidity = if humidity_raw == i16::MAX {
+            None
+        } else {
+            Some(humidity_raw as f64 * 0.004)
+        };
+
+        Ok(Self {
+            pgn: 130311,
+            sid: data[0],
+            temperature_source: data[1] & 0x3F,
+            temperature: temperature([data[2], data[3]]),
+            humidity_source: (data[1] >> 6) & 0x03,
+            humidity,
+            atmospheric_pressure: pressure([data[6], data[7]]),
+        })
+    }
+
+    pub fn from_bytes_130310(data: &[u8]) -> Option<Self> {
+        Self::decode_130310(data).ok()
+    }
+
+    /// Decode PGN 130310, reported as a sea temperature and atmospheric pressure
+    /// The outside air temperature of the message is not decoded
+    pub fn decode_130310(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 7)?;
+
+        Ok(Self {
+            pgn: 130310,
+            sid: data[0],
+            temperature_source: SEA_TEMPERATURE,
+            temperature: temperature([data[1], data[2]]),
+            humidity_source: 0,
+            humidity: None,
+            atmospheric_pressure: pressure([data[5], data[6]]),
+        })
+    }
+}
+
+// Temperature: uint16, 0.01 K
+fn temperature(bytes: [u8; 2]) -> Option<f64> {
+    let raw = u16::from_le_bytes(bytes);
+    if raw == u16::MAX {
+        None
+    } else {
+        Some(raw as f64 * 0.01)
+    }
+}
+
+// Atmospheric pressure: uint16, 100 Pa
+fn pressure(bytes: [u8; 2]) -> Option<f64> {
+    let raw = u16::from_le_bytes(bytes);
+    if raw == u16::MAX {
+        None
+    } else {
+        Some(raw as f64 * 100.0)
+    }
+}
+
+impl fmt::Display for EnvironmentalParameters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "      Temperature: ")?;
+        if let Some(temperature) = self.temperature {
+            write!(f, "{:.2}°C (Source: {})", temperature - 273.15, self.temperature_source)?;
+        } else {
+            write!(f, "N/A")?;
+        }
+        if let Some(humidity) = self.humidity {
+            write!(f, " | Humidity: {:.1}% (Source: {})", humidity, self.humidity_source)?;
+        }
+        if let Some(pressure) = self.atmospheric_pressure {
+            write!(f, " | Pressure: {:.0} Pa", pressure)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_environmental_parameters_from_bytes() {
+        // Inside Ambient (4), outside humidity (1), 293.15 K, 65.0 %, 101300 Pa
+        let data = [0x01, 0x44, 0x83, 0x72, 0x7A, 0x3F, 0xF5, 0x03];
+        let env = EnvironmentalParameters::from_bytes(&data).unwrap();
+
+        assert_eq!(env.pgn, 130311);
+        assert_eq!(env.temperature_source, 4);
+        assert_eq!(env.humidity_source, 1);
+        assert!((env.temperature.unwrap() - 293.15).abs() < 1e-9);
+        assert!((env.humidity.unwrap() - 65.0).abs() < 1e-9);
+        assert_eq!(env.atmospheric_pressure, Some(101300.0));
+    }
+
+    #[test]
+    fn test_environmental_parameters_not_available() {
+        let data = [0x01, 0x00, 0xFF, 0xFF, 0xFF, 0x7F, 0xFF, 0xFF];
+        let env = EnvironmentalParameters::from_bytes(&data).unwrap();
+
+        assert!(env.temperature.is_none());
+        assert!(env.humidity.is_none());
+        assert!(env.atmospheric_pressure.is_none());
+    }
+
+    #[test]
+    fn test_environmental_parameters_130310() {
+        // Water 288.15 K, outside air 293.15 K, 101300 Pa
+        let data = [0x01, 0x8F, 0x70, 0x83, 0x72, 0xF5, 0x03, 0xFF];
+        let env = EnvironmentalParameters::from_bytes_130310(&data).unwrap();
+
+        assert_eq!(env.pgn, 130310);
+        assert_eq!(env.temperature_source, 0);
+        assert!((env.temperature.unwrap() - 288.15).abs() < 1e-9);
+        assert!(env.humidity.is_none());
+        assert_eq!(env.atmospheric_pressure, Some(101300.0));
+    }
+
+    #[test]
+    fn test_environmental_parameters_short_data() {
+        assert!(EnvironmentalParameters::from_bytes(&[0x01, 0x00, 0x00]).is_none());
+        assert!(EnvironmentalParameters::from_bytes_130310(&[0x01, 0x00, 0x00]).is_none());
+    }
+}
@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::decode_error::{check_len, DecodeError};
+
 #[derive(Debug, Clone)]
 pub struct Temperature {
     #[allow(dead_code)]
@@ -30,15 +32,17 @@ impl Temperature {
     }
 
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 6 {
-            return None;
-        }
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 6)?;
         let set_temp = if data.len() >= 8 {
             Some(u16::from_le_bytes([data[6], data[7]]) as f64 * 0.01)
         } else {
             None
         };
-        Some(Self {
+        Ok(Self {
             pgn: 130312,
             sid: data[0],
             instance: data[1],
@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::decode_error::{check_len, DecodeError};
+
 #[derive(Debug, Clone)]
 pub struct Humidity {
     #[allow(dead_code)]
@@ -30,9 +32,11 @@ impl Humidity {
     }
 
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 6 {
-            return None;
-        }
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 6)?;
         
         // Parse actual humidity (bytes 3-4)
         let actual = u16::from_le_bytes([data[3], data[4]]) as f64 * 0.004;
@@ -49,7 +53,7 @@ impl Humidity {
             None
         };
         
-        Some(Self {
+        Ok(Self {
             pgn: 130313,
             sid: data[0],
             instance: data[1],
@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::decode_error::{check_len, DecodeError};
+
 #[derive(Debug, Clone)]
 pub struct ActualPressure {
     #[allow(dead_code)]
@@ -27,9 +29,11 @@ impl ActualPressure {
     }
 
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 6 {
-            return None;
-        }
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 6)?;
         
         // Parse pressure (bytes 3-6) - 32-bit value
         let pressure_raw = if data.len() >= 7 {
@@ -47,7 +51,7 @@ impl ActualPressure {
         // But actually the resolution is 1 Pa based on standard
         let pressure = pressure_raw as f64;
         
-        Some(Self {
+        Ok(Self {
             pgn: 130314,
             sid: data[0],
             instance: data[1],
@@ -0,0 +1,117 @@
+use std::fmt;
+
+use super::decode_error::{check_len, DecodeError};
+
+#[derive(Debug, Clone)]
+pub struct TemperatureExtended {
+    #[allow(dead_code)]
+    pub pgn: u32,
+    #[allow(dead_code)]
+    sid: u8,
+    pub instance: u8,
+    pub source: u8,
+    pub temperature: Option<f64>, // Kelvin
+    pub set_temperature: Option<f64>, // Kelvin
+}
+
+impl TemperatureExtended {
+    // Constructor
+    // instance: sensor instance
+    // source: sensor source
+    // temperature: Kelvin (optional)
+    // set_temperature: Kelvin (optional)
+    pub fn new(instance: u8, source: u8, temperature: Option<f64>, set_temperature: Option<f64>) -> Self {
+        Self {
+            pgn: 130316,
+            sid: 0,
+            instance,
+            source,
+            temperature,
+            set_temperature,
+        }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        Self::decode(data).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        check_len(data, 6)?;
+
+        // Temperature (bytes 3-5): uint24, 0.001 K
+        let temperature_raw = u32::from_le_bytes([data[3], data[4], data[5], 0]);
+        let temperature = if temperature_raw == 0x00FF_FFFF {
+            None
+        } else {
+            Some(temperature_raw as f64 * 0.001)
+        };
+
+        // Set temperature (bytes 6-7): uint16, 0.1 K
+        let set_temperature = if data.len() >= 8 {
+            let set_raw = u16::from_le_bytes([data[6], data[7]]);
+            if set_raw == u16::MAX {
+                None
+            } else {
+                Some(set_raw as f64 * 0.1)
+            }
+        } else {
+            None
+        };
+
+        Ok(Self {
+            pgn: 130316,
+            sid: data[0],
+            instance: data[1],
+            source: data[2],
+            temperature,
+            set_temperature,
+        })
+    }
+}
+
+impl fmt::Display for TemperatureExtended {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(temperature) = self.temperature {
+            write!(f, "      Temperature: {:.3}°C", temperature - 273.15)?;
+        } else {
+            write!(f, "      Temperature: N/A")?;
+        }
+        write!(f, " (Source: {}, Instance: {})", self.source, self.instance)?;
+        if let Some(set) = self.set_temperature {
+            write!(f, " | Set: {:.1}°C", set - 273.15)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temperature_extended_from_bytes() {
+        // SID 1, instance 2, source 14 (engine room), 300.000 K, set 303.0 K
+        let data = [0x01, 0x02, 0x0E, 0xE0, 0x93, 0x04, 0xD6, 0x0B];
+        let temp = TemperatureExtended::from_bytes(&data).unwrap();
+
+        assert_eq!(temp.pgn, 130316);
+        assert_eq!(temp.instance, 2);
+        assert_eq!(temp.source, 14);
+        assert!((temp.temperature.unwrap() - 300.0).abs() < 1e-9);
+        assert!((temp.set_temperature.unwrap() - 303.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_temperature_extended_not_available() {
+        let data = [0x00, 0x00, 0x04, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        let temp = TemperatureExtended::from_bytes(&data).unwrap();
+
+        assert!(temp.temperature.is_none());
+        assert!(temp.set_temperature.is_none());
+    }
+
+    #[test]
+    fn test_temperature_extended_short_data() {
+        assert!(TemperatureExtended::from_bytes(&[0x00, 0x00, 0x04, 0xE0]).is_none());
+    }
+}
@@ -1,8 +1,9 @@
 use nmea2000::{FastPacket, Identifier};
 use socketcan::ExtendedId;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 
-use crate::pgns::N2kMessage;
+use crate::pgns::{N2kMessage, PgnLayouts};
 
 /// NMEA2000 Stream Reader
 /// 
@@ -32,14 +33,25 @@ use crate::pgns::N2kMessage;
 // Key for tracking multi-frame messages: (PGN, Source)
 type FastPacketKey = (u32, u8);
 
+/// Default maximum number of fast packets being assembled at the same time
+pub const DEFAULT_MAX_FAST_PACKET_BUFFERS: usize = 64;
+/// Default time after which an incomplete fast packet is discarded
+pub const DEFAULT_FAST_PACKET_TIMEOUT: Duration = Duration::from_millis(750);
+/// PGNs transmitted as fast packets, assembled from multiple frames
+pub const DEFAULT_FAST_PACKET_PGNS: [u32; 16] = [
+    126996, 127233, 127237, 127489, 127493, 127505, 127506, 128275, 129029,
+    129038, 129039, 129284, 129540, 129794, 129809, 129810,
+];
+
 struct FastPacketBuffer {
     frames: Vec<Vec<u8>>,
     total_len: usize,
     expected_frames: usize,
+    last_update: Instant,
 }
 
 impl FastPacketBuffer {
-    fn new(total_len: usize) -> Self {
+    fn new(total_len: usize, now: Instant) -> Self {
         // First frame has 6 bytes of data (2 bytes overhead)
         // Subsequent frames have 7 bytes of data (1 byte overhead)
         let expected_frames = if total_len <= 6 {
@@ -52,11 +64,13 @@ impl FastPacketBuffer {
             frames: Vec::new(),
             total_len,
             expected_frames,
+            last_update: now,
         }
     }
     
-    fn add_frame(&mut self, frame_data: Vec<u8>) {
+    fn add_frame(&mut self, frame_data: Vec<u8>, now: Instant) {
         self.frames.push(frame_data);
+        self.last_update = now;
     }
     
     fn is_complete(&self) -> bool {
@@ -82,21 +96,162 @@ pub struct N2kFrame {
     pub is_fast_packet: bool,
     #[allow(dead_code)]
     pub data: Vec<u8>, // Complete assembled data
+    destination: u8,
+}
+
+/// Address of global (broadcast) messages
+pub const GLOBAL_ADDRESS: u8 = 0xFF;
+
+/// Destination address of a message from its extended CAN id
+/// Only PDU1 messages (PF < 240) are addressed, PDU2 messages are always global
+fn destination_from_can_id(can_id: u32) -> u8 {
+    let pdu_format = (can_id >> 16) & 0xFF;
+    if pdu_format < 240 {
+        ((can_id >> 8) & 0xFF) as u8
+    } else {
+        GLOBAL_ADDRESS
+    }
+}
+
+/// PGN without the destination address that PDU1 messages (PF < 240) carry in the low byte
+fn pgn_without_destination(pgn: u32) -> u32 {
+    if (pgn >> 8) & 0xFF < 240 { pgn & !0xFF } else { pgn }
+}
+
+impl N2kFrame {
+    /// PGN of the message, without the destination address of PDU1 messages
+    pub fn pgn(&self) -> u32 {
+        pgn_without_destination(self.identifier.pgn())
+    }
+
+    pub fn priority(&self) -> u8 {
+        self.identifier.priority()
+    }
+
+    pub fn source(&self) -> u8 {
+        self.identifier.source()
+    }
+
+    /// Destination address, `GLOBAL_ADDRESS` for broadcast messages
+    pub fn destination(&self) -> u8 {
+        self.destination
+    }
+
+    /// Broadcast frame of a message computed by the application (e.g. true wind from apparent
+    /// wind), attributed to `source`; it has no raw data
+    pub fn derived(priority: u8, source: u8, message: N2kMessage) -> Self {
+        let can_id = ((priority as u32 & 0x07) << 26) | ((message.pgn() & 0x3FFFF) << 8) | source as u32;
+        // At most 29 bits by construction
+        let can_id = ExtendedId::new(can_id).expect("29-bit CAN identifier");
+        Self {
+            identifier: Identifier::from_can_id(can_id),
+            message,
+            is_fast_packet: false,
+            data: Vec::new(),
+            destination: GLOBAL_ADDRESS,
+        }
+    }
 }
 
 /// NMEA2000 stream reader that processes CAN frames and assembles fast packets
 pub struct N2kStreamReader {
     fast_packet_buffers: HashMap<FastPacketKey, FastPacketBuffer>,
+    max_buffers: usize,
+    buffer_timeout: Duration,
+    evicted_count: u64,
+    expired_count: u64,
+    field_layouts: Option<PgnLayouts>,
+    fast_packet_pgns: HashSet<u32>,
+    raw_passthrough_pgns: HashSet<u32>,
+    drop_unknown: bool,
 }
 
 impl N2kStreamReader {
     /// Create a new NMEA2000 stream reader
     pub fn new() -> Self {
+        Self::with_limits(DEFAULT_MAX_FAST_PACKET_BUFFERS, DEFAULT_FAST_PACKET_TIMEOUT)
+    }
+
+    /// Create a new NMEA2000 stream reader with custom fast packet limits
+    ///
+    /// # Arguments
+    /// * `max_buffers` - Maximum number of incomplete fast packets kept at the same time;
+    ///   when exceeded the least recently updated one is evicted
+    /// * `buffer_timeout` - Incomplete fast packets not updated within this time are discarded
+    pub fn with_limits(max_buffers: usize, buffer_timeout: Duration) -> Self {
         Self {
             fast_packet_buffers: HashMap::new(),
+            max_buffers: max_buffers.max(1),
+            buffer_timeout,
+            evicted_count: 0,
+            expired_count: 0,
+            field_layouts: None,
+            fast_packet_pgns: HashSet::from(DEFAULT_FAST_PACKET_PGNS),
+            raw_passthrough_pgns: HashSet::new(),
+            drop_unknown: false,
         }
     }
 
+    /// Same as `with_limits`, also assembling the given PGNs as fast packets
+    /// (e.g. proprietary PGNs), in addition to the default ones
+    pub fn with_extra_fast_packet_pgns(max_buffers: usize, buffer_timeout: Duration, extra_pgns: HashSet<u32>) -> Self {
+        let mut reader = Self::with_limits(max_buffers, buffer_timeout);
+        reader.fast_packet_pgns.extend(extra_pgns);
+        reader
+    }
+
+    /// Assemble the frames of a PGN as fast packets
+    pub fn register_fast_packet_pgn(&mut self, pgn: u32) {
+        self.fast_packet_pgns.insert(pgn);
+    }
+
+    /// Decode the PGNs without a dedicated decoder with the given field layouts,
+    /// as `N2kMessage::Fields` instead of `N2kMessage::Unknown`
+    pub fn set_field_layouts(&mut self, field_layouts: Option<PgnLayouts>) {
+        self.field_layouts = field_layouts;
+    }
+
+    /// Surface the PGN without a dedicated decoder as `N2kMessage::Unknown` with its raw bytes
+    /// (e.g. proprietary PGNs), skipping the field layouts and the unknown PGN filter
+    pub fn register_raw_passthrough(&mut self, pgn: u32) {
+        self.raw_passthrough_pgns.insert(pgn);
+    }
+
+    /// Unregister all the PGNs registered for raw passthrough
+    pub fn clear_raw_passthrough(&mut self) {
+        self.raw_passthrough_pgns.clear();
+    }
+
+    /// Drop the messages left `N2kMessage::Unknown` of the PGNs not registered for raw passthrough
+    pub fn set_drop_unknown(&mut self, drop_unknown: bool) {
+        self.drop_unknown = drop_unknown;
+    }
+
+    /// Decode a complete message, None if it is an unknown PGN to drop
+    fn decode(&self, pgn: u32, data: &[u8]) -> Option<N2kMessage> {
+        match N2kMessage::from_pgn(pgn, data) {
+            N2kMessage::Unknown(pgn, data) if self.raw_passthrough_pgns.contains(&pgn) => Some(N2kMessage::Unknown(pgn, data)),
+            N2kMessage::Unknown(pgn, data) => match self.field_layouts.as_ref().and_then(|l| l.decode(pgn, &data)) {
+                Some(fields) => Some(N2kMessage::Fields(pgn, fields)),
+                None if self.drop_unknown => None,
+                None => Some(N2kMessage::Unknown(pgn, data)),
+            },
+            message => Some(message),
+        }
+    }
+
+    /// Number of incomplete fast packets evicted because the buffer cap was reached
+    /// since the last call
+    pub fn take_evicted_count(&mut self) -> u64 {
+        std::mem::take(&mut self.evicted_count)
+    }
+
+    /// Number of incomplete fast packets discarded because they went stale
+    /// since the last call
+    pub fn take_expired_count(&mut self) -> u64 {
+        std::mem::take(&mut self.expired_count)
+    }
+
     /// Process a CAN frame and return a complete message if available
     /// 
     /// # Arguments
@@ -106,67 +261,82 @@ impl N2kStreamReader {
     /// # Returns
     /// `Some(N2kFrame)` if a complete message is ready, `None` otherwise
     pub fn process_frame(&mut self, can_id: ExtendedId, data: &[u8]) -> Option<N2kFrame> {
+        self.process_frame_at(can_id, data, Instant::now())
+    }
+
+    /// Same as `process_frame`, with an explicit reception time used for fast packet expiry
+    pub fn process_frame_at(&mut self, can_id: ExtendedId, data: &[u8], now: Instant) -> Option<N2kFrame> {
         let identifier = Identifier::from_can_id(can_id);
-        let pgn = identifier.pgn();
+        let destination = destination_from_can_id(can_id.as_raw());
+        let pgn = pgn_without_destination(identifier.pgn());
         
         // Check if this is a fast packet PGN
         if self.is_fast_packet_pgn(pgn) && data.len() == 8 {
-            self.process_fast_packet(identifier, data)
+            self.process_fast_packet(identifier, pgn, destination, data, now)
         } else {
             // Regular single-frame message
-            let message = N2kMessage::from_pgn(pgn, data);
+            let message = self.decode(pgn, data)?;
             Some(N2kFrame {
                 identifier,
                 message,
                 is_fast_packet: false,
                 data: data.to_vec(),
+                destination,
             })
         }
     }
 
-    fn process_fast_packet(&mut self, identifier: Identifier, data: &[u8]) -> Option<N2kFrame> {
+    fn process_fast_packet(&mut self, identifier: Identifier, pgn: u32, destination: u8, data: &[u8], now: Instant) -> Option<N2kFrame> {
+        self.purge_stale_buffers(now);
+
         // Parse as FastPacket
         let mut packet_data = [0u8; 8];
         packet_data.copy_from_slice(data);
         let fast_packet = FastPacket(packet_data);
         
-        let pgn = identifier.pgn();
         let source = identifier.source();
         let key = (pgn, source);
         
         if fast_packet.is_first() {
             // First frame - start new buffer
             if let Some(total_len) = fast_packet.total_len() {
-                let mut buffer = FastPacketBuffer::new(total_len as usize);
-                buffer.add_frame(fast_packet.data().to_vec());
+                let mut buffer = FastPacketBuffer::new(total_len as usize, now);
+                buffer.add_frame(fast_packet.data().to_vec(), now);
                 
                 if buffer.is_complete() {
                     // Single-frame fast packet
                     let complete_data = buffer.get_complete_data();
-                    let message = N2kMessage::from_pgn(pgn, &complete_data);
+                    let message = self.decode(pgn, &complete_data)?;
                     return Some(N2kFrame {
                         identifier,
                         message,
                         is_fast_packet: true,
                         data: complete_data,
+                        destination,
                     });
                 } else {
+                    if !self.fast_packet_buffers.contains_key(&key)
+                        && self.fast_packet_buffers.len() >= self.max_buffers
+                    {
+                        self.evict_oldest_buffer();
+                    }
                     self.fast_packet_buffers.insert(key, buffer);
                 }
             }
         } else if let Some(buffer) = self.fast_packet_buffers.get_mut(&key) {
             // Subsequent frame - add to existing buffer
-            buffer.add_frame(fast_packet.data().to_vec());
+            buffer.add_frame(fast_packet.data().to_vec(), now);
             
             if buffer.is_complete() {
                 let complete_data = buffer.get_complete_data();
                 self.fast_packet_buffers.remove(&key);
-                let message = N2kMessage::from_pgn(pgn, &complete_data);
+                let message = self.decode(pgn, &complete_data)?;
                 return Some(N2kFrame {
                     identifier,
                     message,
                     is_fast_packet: true,
                     data: complete_data,
+                    destination,
                 });
             }
         }
@@ -174,12 +344,30 @@ impl N2kStreamReader {
         None
     }
 
+    /// Drop incomplete fast packets that have not received a frame within the timeout
+    fn purge_stale_buffers(&mut self, now: Instant) {
+        let timeout = self.buffer_timeout;
+        let before = self.fast_packet_buffers.len();
+        self.fast_packet_buffers
+            .retain(|_, buffer| now.saturating_duration_since(buffer.last_update) <= timeout);
+        self.expired_count += (before - self.fast_packet_buffers.len()) as u64;
+    }
+
+    /// Drop the least recently updated incomplete fast packet
+    fn evict_oldest_buffer(&mut self) {
+        let oldest = self
+            .fast_packet_buffers
+            .iter()
+            .min_by_key(|(_, buffer)| buffer.last_update)
+            .map(|(key, _)| *key);
+        if let Some(key) = oldest {
+            self.fast_packet_buffers.remove(&key);
+            self.evicted_count += 1;
+        }
+    }
+
     fn is_fast_packet_pgn(&self, pgn: u32) -> bool {
-        matches!(
-            pgn,
-            126996 | 127233 | 127237 | 127489 | 127493 | 127505 | 128275 | 129029
-                | 129038 | 129039 | 129540 | 129794 | 129809 | 129810
-        )
+        self.fast_packet_pgns.contains(&pgn)
     }
 }
 
@@ -188,3 +376,219 @@ impl Default for N2kStreamReader {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // PGN 129029 (fast packet), priority 3
+    fn gnss_can_id(source: u8) -> ExtendedId {
+        ExtendedId::new(0x0DF80500 | source as u32).unwrap()
+    }
+
+    // First frame of a 43-byte fast packet (never completed)
+    const FIRST_FRAME: [u8; 8] = [0x00, 43, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
+
+    #[test]
+    fn test_field_layout_decoding() {
+        // PGN 130576 (Small Craft Status), priority 2, from 0x05
+        let id = ExtendedId::new(0x09FE1005).unwrap();
+        let data = [0x0A, 0xF6, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+
+        let mut reader = N2kStreamReader::new();
+        assert!(matches!(reader.process_frame(id, &data).unwrap().message, N2kMessage::Unknown(130576, _)));
+
+        let mut layouts = PgnLayouts::new();
+        layouts.insert(130576, vec![
+            crate::pgns::FieldLayout::signed("port_trim_tab", 0, 1, 1.0),
+            crate::pgns::FieldLayout::signed("starboard_trim_tab", 1, 1, 1.0),
+        ]);
+        reader.set_field_layouts(Some(layouts));
+        match reader.process_frame(id, &data).unwrap().message {
+            N2kMessage::Fields(pgn, fields) => {
+                assert_eq!(pgn, 130576);
+                assert_eq!(fields["port_trim_tab"], 10.0);
+                assert_eq!(fields["starboard_trim_tab"], -10.0);
+            }
+            message => panic!("Unexpected message {:?}", message),
+        }
+
+        // PGNs with a dedicated decoder are not affected
+        let id = ExtendedId::new(0x09F80105).unwrap();
+        let frame = reader.process_frame(id, &[0; 8]).unwrap();
+        assert!(matches!(frame.message, N2kMessage::PositionRapidUpdate(_)));
+    }
+
+    #[test]
+    fn test_raw_passthrough() {
+        // PGN 65280 (proprietary single frame), priority 7, from 0x0A
+        let registered = ExtendedId::new(0x1CFF000A).unwrap();
+        // PGN 65281, priority 7, from 0x0A
+        let unregistered = ExtendedId::new(0x1CFF010A).unwrap();
+        let data = [0x3F, 0x9F, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
+
+        let mut reader = N2kStreamReader::new();
+        reader.register_raw_passthrough(65280);
+        reader.set_drop_unknown(true);
+
+        let frame = reader.process_frame(registered, &data).unwrap();
+        match frame.message {
+            N2kMessage::Unknown(pgn, raw) => {
+                assert_eq!(pgn, 65280);
+                assert_eq!(raw, data);
+            }
+            message => panic!("Unexpected message {:?}", message),
+        }
+        assert!(reader.process_frame(unregistered, &data).is_none());
+
+        // Decoded PGNs are not affected by the filter
+        let id = ExtendedId::new(0x09F80105).unwrap();
+        assert!(matches!(reader.process_frame(id, &[0; 8]).unwrap().message, N2kMessage::PositionRapidUpdate(_)));
+
+        // Once unregistered, the PGN is filtered as well
+        reader.clear_raw_passthrough();
+        assert!(reader.process_frame(registered, &data).is_none());
+
+        // Without the filter the unregistered PGN is surfaced as well
+        reader.set_drop_unknown(false);
+        assert!(matches!(reader.process_frame(unregistered, &data).unwrap().message, N2kMessage::Unknown(65281, _)));
+    }
+
+    #[test]
+    fn test_raw_passthrough_skips_field_layouts() {
+        // PGN 130576 (Small Craft Status), priority 2, from 0x05
+        let id = ExtendedId::new(0x09FE1005).unwrap();
+        let data = [0x0A, 0xF6, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+
+        let mut layouts = PgnLayouts::new();
+        layouts.insert(130576, vec![crate::pgns::FieldLayout::signed("port_trim_tab", 0, 1, 1.0)]);
+        let mut reader = N2kStreamReader::new();
+        reader.set_field_layouts(Some(layouts));
+        reader.register_raw_passthrough(130576);
+        assert!(matches!(reader.process_frame(id, &data).unwrap().message, N2kMessage::Unknown(130576, _)));
+    }
+
+    #[test]
+    fn test_frame_priority_source_destination() {
+        let mut reader = N2kStreamReader::new();
+
+        // PGN 59904 (ISO Request, PDU1) priority 6, from 0x23 to 0x42
+        let id = ExtendedId::new(0x18EA4223).unwrap();
+        let frame = reader.process_frame(id, &[0x14, 0xF0, 0x01]).unwrap();
+        assert_eq!(frame.pgn(), 59904);
+        // Decoded on the PGN, not on the PGN with the destination
+        assert!(matches!(frame.message, N2kMessage::Unknown(59904, _)));
+        assert_eq!(frame.priority(), 6);
+        assert_eq!(frame.source(), 0x23);
+        assert_eq!(frame.destination(), 0x42);
+
+        // PGN 129025 (PDU2) priority 2, from 0x05: always global
+        let id = ExtendedId::new(0x09F80105).unwrap();
+        let frame = reader.process_frame(id, &[0; 8]).unwrap();
+        assert_eq!(frame.pgn(), 129025);
+        assert_eq!(frame.priority(), 2);
+        assert_eq!(frame.source(), 0x05);
+        assert_eq!(frame.destination(), GLOBAL_ADDRESS);
+    }
+
+    #[test]
+    fn test_derived_frame() {
+        let message = N2kMessage::WindData(crate::pgns::WindData::new_apparent(5.0, 0.5));
+        let frame = N2kFrame::derived(2, 0x15, message);
+        assert_eq!(frame.pgn(), 130306);
+        assert_eq!(frame.priority(), 2);
+        assert_eq!(frame.source(), 0x15);
+        assert_eq!(frame.destination(), GLOBAL_ADDRESS);
+        assert!(frame.data.is_empty());
+    }
+
+    #[test]
+    fn test_fast_packet_assembly() {
+        let mut reader = N2kStreamReader::new();
+        let id = ExtendedId::new(0x0DF80500 | 0x10).unwrap();
+        let now = Instant::now();
+
+        // 10-byte payload: first frame carries 6 bytes, second frame 4 (+3 padding)
+        assert!(reader.process_frame_at(id, &[0x20, 10, 1, 2, 3, 4, 5, 6], now).is_none());
+        let frame = reader.process_frame_at(id, &[0x21, 7, 8, 9, 10, 0xFF, 0xFF, 0xFF], now).unwrap();
+        assert!(frame.is_fast_packet);
+        assert_eq!(frame.data, vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert!(reader.fast_packet_buffers.is_empty());
+    }
+
+    #[test]
+    fn test_registered_fast_packet_pgn() {
+        // PGN 130817 (proprietary), priority 7, from 0x0A
+        let id = ExtendedId::new(0x1DFF010A).unwrap();
+        let first = [0x40, 9, 0x87, 0x98, 1, 2, 3, 4];
+        let second = [0x41, 5, 6, 7, 0xFF, 0xFF, 0xFF, 0xFF];
+
+        // Not a fast packet by default: each frame is a message of its own
+        let mut reader = N2kStreamReader::new();
+        assert_eq!(reader.process_frame(id, &first).unwrap().data, first);
+
+        reader.register_fast_packet_pgn(130817);
+        assert!(reader.process_frame(id, &first).is_none());
+        let frame = reader.process_frame(id, &second).unwrap();
+        assert_eq!(frame.pgn(), 130817);
+        assert!(frame.is_fast_packet);
+        assert_eq!(frame.data, vec![0x87, 0x98, 1, 2, 3, 4, 5, 6, 7]);
+
+        let mut reader = N2kStreamReader::with_extra_fast_packet_pgns(8, DEFAULT_FAST_PACKET_TIMEOUT, HashSet::from([130817]));
+        assert!(reader.process_frame(id, &first).is_none());
+        assert!(reader.process_frame(id, &second).unwrap().is_fast_packet);
+        // Default fast packet PGNs are kept
+        assert!(reader.is_fast_packet_pgn(129029));
+    }
+
+    #[test]
+    fn test_incomplete_buffers_capped_evicting_oldest() {
+        let mut reader = N2kStreamReader::with_limits(3, Duration::from_secs(10));
+        let start = Instant::now();
+
+        for source in 1..=5u8 {
+            let now = start + Duration::from_millis(source as u64);
+            assert!(reader.process_frame_at(gnss_can_id(source), &FIRST_FRAME, now).is_none());
+        }
+
+        assert_eq!(reader.fast_packet_buffers.len(), 3);
+        assert!(!reader.fast_packet_buffers.contains_key(&(129029, 1)));
+        assert!(!reader.fast_packet_buffers.contains_key(&(129029, 2)));
+        assert!(reader.fast_packet_buffers.contains_key(&(129029, 3)));
+        assert!(reader.fast_packet_buffers.contains_key(&(129029, 4)));
+        assert!(reader.fast_packet_buffers.contains_key(&(129029, 5)));
+        assert_eq!(reader.take_evicted_count(), 2);
+        assert_eq!(reader.take_evicted_count(), 0);
+    }
+
+    #[test]
+    fn test_recently_updated_buffer_not_evicted() {
+        let mut reader = N2kStreamReader::with_limits(2, Duration::from_secs(10));
+        let start = Instant::now();
+
+        reader.process_frame_at(gnss_can_id(1), &FIRST_FRAME, start);
+        reader.process_frame_at(gnss_can_id(2), &FIRST_FRAME, start + Duration::from_millis(1));
+        // Continuation for source 1 makes it the most recently updated
+        reader.process_frame_at(gnss_can_id(1), &[0x01, 7, 8, 9, 10, 11, 12, 13], start + Duration::from_millis(2));
+        reader.process_frame_at(gnss_can_id(3), &FIRST_FRAME, start + Duration::from_millis(3));
+
+        assert!(reader.fast_packet_buffers.contains_key(&(129029, 1)));
+        assert!(!reader.fast_packet_buffers.contains_key(&(129029, 2)));
+        assert!(reader.fast_packet_buffers.contains_key(&(129029, 3)));
+    }
+
+    #[test]
+    fn test_stale_buffers_purged() {
+        let mut reader = N2kStreamReader::with_limits(10, Duration::from_millis(750));
+        let start = Instant::now();
+
+        reader.process_frame_at(gnss_can_id(1), &FIRST_FRAME, start);
+        reader.process_frame_at(gnss_can_id(2), &FIRST_FRAME, start + Duration::from_millis(500));
+        reader.process_frame_at(gnss_can_id(3), &FIRST_FRAME, start + Duration::from_millis(1000));
+
+        assert_eq!(reader.fast_packet_buffers.len(), 2);
+        assert!(!reader.fast_packet_buffers.contains_key(&(129029, 1)));
+        assert_eq!(reader.take_expired_count(), 1);
+        assert_eq!(reader.take_evicted_count(), 0);
+    }
+}
@@ -28,8 +28,11 @@ CREATE TABLE IF NOT EXISTS vessel_status (
     total_time_ms BIGINT NOT NULL DEFAULT 0 COMMENT 'Time elapsed since last report in milliseconds',
     cog_deg DECIMAL(6,3) COMMENT 'Course over ground over reporting period in degrees (NULL if no position fix)',
     average_heading_deg DECIMAL(6,3) COMMENT 'Average heading over reporting period in degrees (NULL if no heading data)',
+    quality_score DECIMAL(4,3) COMMENT 'Data quality of the report, from 0 (unusable) to 1 (best)',
+    session_id BIGINT COMMENT 'Session active when the report was generated (NULL outside of sessions)',
     INDEX idx_timestamp (timestamp),
-    INDEX idx_moored (is_moored, timestamp)
+    INDEX idx_moored (is_moored, timestamp),
+    INDEX idx_session (session_id)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci
 COMMENT='Stores vessel navigation status reports';
 
@@ -41,14 +44,17 @@ COMMENT='Stores vessel navigation status reports';
 CREATE TABLE IF NOT EXISTS environmental_data (
     id BIGINT AUTO_INCREMENT PRIMARY KEY,
     timestamp DATETIME(3) NOT NULL COMMENT 'Reading timestamp in UTC with millisecond precision',
-    metric_id TINYINT UNSIGNED NOT NULL COMMENT '1=Pressure, 2=CabinTemp, 3=WaterTemp, 4=Humidity, 5=WindSpeed, 6=WindDir, 7=Roll',
+    metric_id TINYINT UNSIGNED NOT NULL COMMENT '1=Pressure, 2=CabinTemp, 3=WaterTemp, 4=Humidity, 5=WindSpeed, 6=WindDir, 7=Roll, 8=Depth, 9=DepthCorrected, 10=RudderActivity, 11=WindGust, 12=RateOfTurn',
     value_avg FLOAT COMMENT 'Average value over collection period',
     value_max FLOAT COMMENT 'Maximum value over collection period',
     value_min FLOAT COMMENT 'Minimum value over collection period',
+    value_count INT UNSIGNED COMMENT 'Number of samples over collection period',
     unit CHAR(10) COMMENT 'Unit of measurement (Pa, C, %, m/s, deg)',
+    session_id BIGINT COMMENT 'Session active at the reading time (NULL outside of sessions)',
     UNIQUE KEY unique_metric_time (timestamp, metric_id),
     INDEX idx_timestamp (timestamp),
-    INDEX idx_metric (metric_id, timestamp)
+    INDEX idx_metric (metric_id, timestamp),
+    INDEX idx_session (session_id)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci
 COMMENT='Stores environmental sensor data with min/max/avg aggregation';
 
@@ -59,7 +65,7 @@ COMMENT='Stores environmental sensor data with min/max/avg aggregation';
 -- Separates sailing, motoring, and moored time/distance
 CREATE TABLE IF NOT EXISTS trips (
     id BIGINT AUTO_INCREMENT PRIMARY KEY,
-    description VARCHAR(255) NOT NULL COMMENT 'Trip name, auto-generated as "Trip YYYY-MM-DD"',
+    description VARCHAR(255) NOT NULL COMMENT 'Trip name, auto-generated from trip_name_template (default "Trip YYYY-MM-DD", " #N" appended on same-day collisions)',
     start_timestamp DATETIME(3) NOT NULL COMMENT 'Trip start time in UTC',
     end_timestamp DATETIME(3) NOT NULL COMMENT 'Trip end time in UTC (updated with each status report)',
     total_distance_sailed DOUBLE NOT NULL DEFAULT 0 COMMENT 'Distance traveled under sail in nautical miles',
@@ -72,6 +78,31 @@ CREATE TABLE IF NOT EXISTS trips (
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci
 COMMENT='Stores vessel trips with sailing vs motoring breakdown';
 
+-- ============================================================================
+-- CAN ERROR STATISTICS TABLE
+-- ============================================================================
+-- Stores CAN bus frame and error counts per hour (diagnostics.persist_can_errors)
+CREATE TABLE IF NOT EXISTS can_error_stats (
+    hour DATETIME NOT NULL PRIMARY KEY COMMENT 'Start of the hour in UTC',
+    can_frames BIGINT UNSIGNED NOT NULL DEFAULT 0 COMMENT 'CAN frames received in the hour',
+    can_errors BIGINT UNSIGNED NOT NULL DEFAULT 0 COMMENT 'CAN bus read errors in the hour'
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci
+COMMENT='Stores hourly CAN bus error statistics for link health analysis';
+
+-- ============================================================================
+-- SESSIONS TABLE
+-- ============================================================================
+-- Stores time windows started and stopped through the web API (race legs, watches)
+-- Independent of the trips; rows written during a session carry its id in session_id
+CREATE TABLE IF NOT EXISTS sessions (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(255) NOT NULL COMMENT 'Session name',
+    start_timestamp DATETIME(3) NOT NULL COMMENT 'Session start time in UTC',
+    end_timestamp DATETIME(3) COMMENT 'Session end time in UTC (NULL while active)',
+    INDEX idx_start_timestamp (start_timestamp)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci
+COMMENT='Stores manually started sessions, coexisting with trips';
+
 -- ============================================================================
 -- EXAMPLE QUERIES
 -- ============================================================================
@@ -1,7 +1,9 @@
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tracing::info;
 
-use crate::time_monitor::TimeSyncStatus;
+use crate::time_monitor::{SkewAction, TimeSyncStatus};
 
 /// Application-level metrics for tracking CAN bus and NMEA2000 processing statistics
 /// (not to be confused with environmental metrics like wind, temperature, etc.)
@@ -18,8 +20,69 @@ pub struct AppMetrics {
     pub env_reports: u64,
     /// Number of CAN bus errors encountered
     pub can_errors: u64,
+    /// Number of incomplete fast packets evicted because too many were in flight
+    pub fast_packet_evictions: u64,
+    /// Number of incomplete fast packets discarded because they went stale
+    pub fast_packet_expired: u64,
+    /// Number of messages dropped as repetitions of the previous one
+    pub duplicate_messages: u64,
     pub gnss_time_skew: i64,
-    pub gnss_time_skew_status: TimeSyncStatus
+    pub gnss_time_skew_status: TimeSyncStatus,
+    /// Last escalation action taken on time skew in the period
+    pub skew_action: Option<SkewAction>,
+    /// Estimated CAN bus utilization in percent
+    pub bus_load_percent: f64,
+    /// Humidity set point announced by the humidity sensor
+    pub humidity_set_point: Option<f64>,
+    /// Counts of the periods already reset, never reset themselves
+    past_totals: MetricTotals,
+}
+
+/// Monotonic totals since startup, exposed to Prometheus
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricTotals {
+    pub can_frames: u64,
+    pub nmea_messages: u64,
+    pub vessel_reports: u64,
+    pub env_reports: u64,
+    pub can_errors: u64,
+    /// Last GNSS time skew, a gauge
+    pub gnss_time_skew_ms: i64,
+    /// Last humidity set point in percent, a gauge only exposed once known
+    pub humidity_set_point: Option<f64>,
+}
+
+/// Totals published by the main loop and read by the web server
+pub type SharedMetricTotals = Arc<Mutex<MetricTotals>>;
+
+impl MetricTotals {
+    /// Render the totals in the Prometheus text exposition format
+    pub fn render_prometheus(&self) -> String {
+        let mut text = String::new();
+        let counters = [
+            ("nmea_can_frames_total", "CAN frames received", self.can_frames),
+            ("nmea_messages_total", "NMEA2000 messages assembled", self.nmea_messages),
+            ("nmea_vessel_reports_total", "Vessel status reports written to the database", self.vessel_reports),
+            ("nmea_env_reports_total", "Environmental reports written to the database", self.env_reports),
+            ("nmea_can_errors_total", "CAN bus read errors", self.can_errors),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(text, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, value);
+        }
+        let _ = writeln!(
+            text,
+            "# HELP nmea_gnss_time_skew_ms Skew between the GNSS time and the system time in milliseconds\n# TYPE nmea_gnss_time_skew_ms gauge\nnmea_gnss_time_skew_ms {}",
+            self.gnss_time_skew_ms
+        );
+        if let Some(set_point) = self.humidity_set_point {
+            let _ = writeln!(
+                text,
+                "# HELP nmea_humidity_set_point_percent Humidity set point of the humidity sensor in percent\n# TYPE nmea_humidity_set_point_percent gauge\nnmea_humidity_set_point_percent {}",
+                set_point
+            );
+        }
+        text
+    }
 }
 
 impl AppMetrics {
@@ -33,13 +96,34 @@ impl AppMetrics {
             vessel_reports: 0,
             env_reports: 0,
             can_errors: 0,
+            fast_packet_evictions: 0,
+            fast_packet_expired: 0,
+            duplicate_messages: 0,
             gnss_time_skew: 0,
             gnss_time_skew_status: TimeSyncStatus::NotInitialized,
+            skew_action: None,
+            bus_load_percent: 0.0,
+            humidity_set_point: None,
+            past_totals: MetricTotals::default(),
+        }
+    }
+
+    /// Totals since startup, including the current period
+    pub fn totals(&self) -> MetricTotals {
+        MetricTotals {
+            can_frames: self.past_totals.can_frames + self.can_frames,
+            nmea_messages: self.past_totals.nmea_messages + self.nmea_messages,
+            vessel_reports: self.past_totals.vessel_reports + self.vessel_reports,
+            env_reports: self.past_totals.env_reports + self.env_reports,
+            can_errors: self.past_totals.can_errors + self.can_errors,
+            gnss_time_skew_ms: self.gnss_time_skew,
+            humidity_set_point: self.humidity_set_point,
         }
     }
     
-    /// Reset all counters to zero
+    /// Reset all counters to zero, the totals keep counting
     pub fn reset(&mut self) {
+        self.past_totals = self.totals();
         self.can_frames = 0;
         self.can_processed_frames = 0;
         self.nmea_messages = 0;
@@ -47,14 +131,18 @@ impl AppMetrics {
         self.vessel_reports = 0;
         self.env_reports = 0;
         self.can_errors = 0;
+        self.fast_packet_evictions = 0;
+        self.fast_packet_expired = 0;
+        self.duplicate_messages = 0;
         self.gnss_time_skew = 0;
-        // Note: Do not reset gnss_time_skew_status
+        self.skew_action = None;
+        // Note: Do not reset gnss_time_skew_status, bus_load_percent and humidity_set_point
     }
     
     /// Log current metrics to the info log
     pub fn log(&self) {
         info!(
-            "[Metrics] CAN frames: {}, CAN processed frames: {}, NMEA messages: {}, NMEA processed messages: {}, Vessel reports: {}, Env reports: {}, CAN errors: {}, GNSS time sync: {:?}/{} ms",
+            "[Metrics] CAN frames: {}, CAN processed frames: {}, NMEA messages: {}, NMEA processed messages: {}, Vessel reports: {}, Env reports: {}, CAN errors: {}, Fast packets evicted/expired: {}/{}, Duplicates dropped: {}, GNSS time sync: {:?}/{} ms, Skew action: {}, Bus load: {:.1}%",
             self.can_frames,
             self.can_processed_frames,
             self.nmea_messages,
@@ -62,8 +150,13 @@ impl AppMetrics {
             self.vessel_reports,
             self.env_reports,
             self.can_errors,
+            self.fast_packet_evictions,
+            self.fast_packet_expired,
+            self.duplicate_messages,
             self.gnss_time_skew_status,
-            self.gnss_time_skew
+            self.gnss_time_skew,
+            self.skew_action.map_or("none".to_string(), |a| a.to_string()),
+            self.bus_load_percent
         );
     }
 }
@@ -117,6 +210,8 @@ mod tests {
         assert_eq!(metrics.vessel_reports, 0);
         assert_eq!(metrics.env_reports, 0);
         assert_eq!(metrics.can_errors, 0);
+        assert_eq!(metrics.fast_packet_evictions, 0);
+        assert_eq!(metrics.fast_packet_expired, 0);
     }
     
     #[test]
@@ -127,6 +222,9 @@ mod tests {
         metrics.vessel_reports = 10;
         metrics.env_reports = 20;
         metrics.can_errors = 5;
+        metrics.fast_packet_evictions = 6;
+        metrics.fast_packet_expired = 7;
+        metrics.skew_action = Some(SkewAction::NtpResync);
         
         metrics.reset();
         
@@ -135,8 +233,59 @@ mod tests {
         assert_eq!(metrics.vessel_reports, 0);
         assert_eq!(metrics.env_reports, 0);
         assert_eq!(metrics.can_errors, 0);
+        assert_eq!(metrics.fast_packet_evictions, 0);
+        assert_eq!(metrics.fast_packet_expired, 0);
+        assert_eq!(metrics.skew_action, None);
     }
     
+    #[test]
+    fn test_totals_survive_reset() {
+        let mut metrics = AppMetrics::new();
+        metrics.can_frames = 100;
+        metrics.can_errors = 2;
+        metrics.reset();
+        metrics.can_frames = 30;
+        metrics.gnss_time_skew = -12;
+
+        let totals = metrics.totals();
+        assert_eq!(totals.can_frames, 130);
+        assert_eq!(totals.can_errors, 2);
+        assert_eq!(totals.gnss_time_skew_ms, -12);
+    }
+
+    #[test]
+    fn test_prometheus_exposition() {
+        let mut metrics = AppMetrics::new();
+        metrics.can_frames = 1200;
+        metrics.nmea_messages = 800;
+        metrics.vessel_reports = 3;
+        metrics.env_reports = 21;
+        metrics.can_errors = 1;
+        metrics.gnss_time_skew = 45;
+
+        let text = metrics.totals().render_prometheus();
+        assert!(text.contains("# TYPE nmea_can_frames_total counter\nnmea_can_frames_total 1200\n"));
+        assert!(text.contains("nmea_messages_total 800\n"));
+        assert!(text.contains("nmea_vessel_reports_total 3\n"));
+        assert!(text.contains("nmea_env_reports_total 21\n"));
+        assert!(text.contains("nmea_can_errors_total 1\n"));
+        assert!(text.contains("# TYPE nmea_gnss_time_skew_ms gauge\nnmea_gnss_time_skew_ms 45\n"));
+        // Every sample line is preceded by its HELP and TYPE lines
+        assert_eq!(text.lines().filter(|l| l.starts_with("# HELP ")).count(), 6);
+        assert_eq!(text.lines().filter(|l| !l.starts_with('#')).count(), 6);
+        assert!(!text.contains("nmea_humidity_set_point_percent"));
+    }
+
+    #[test]
+    fn test_prometheus_humidity_set_point() {
+        let mut metrics = AppMetrics::new();
+        metrics.humidity_set_point = Some(55.0);
+        metrics.reset();
+
+        let text = metrics.totals().render_prometheus();
+        assert!(text.contains("# TYPE nmea_humidity_set_point_percent gauge\nnmea_humidity_set_point_percent 55\n"));
+    }
+
     #[test]
     fn test_metrics_logger_interval() {
         let mut logger = MetricsLogger::new(Duration::from_millis(50));
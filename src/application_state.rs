@@ -2,7 +2,7 @@ use std::time::Instant;
 
 use chrono::{DateTime, Utc};
 
-use crate::{config::Config, vessel_monitor::Position};
+use crate::{dead_reckoning::CompositePosition, vessel_monitor::Position};
 
 #[derive(Debug)]
 pub struct ApplicationState {
@@ -12,11 +12,11 @@ pub struct ApplicationState {
     pub last_position_timestamp: Option<Instant>,
     pub last_heading_deg: Option<f64>, // in degrees
     pub last_heading_timestamp: Option<Instant>,
-    pub config: Config
+    pub composite_position: Option<CompositePosition>, // GPS or dead reckoning
 }
 
 impl ApplicationState {
-    pub fn new(config: Config) -> Self {
+    pub fn new() -> Self {
         ApplicationState {
             last_gnss_timestamp: None,
             last_position: None,
@@ -24,7 +24,7 @@ impl ApplicationState {
             last_position_timestamp: None,
             last_heading_deg: None, // in degrees
             last_heading_timestamp: None,
-            config,
+            composite_position: None,
         }
     }
 
@@ -38,6 +38,10 @@ impl ApplicationState {
         self.last_position_timestamp = Some(timestamp);
     }
 
+    pub fn update_composite_position(&mut self, position: Option<CompositePosition>) {
+        self.composite_position = position;
+    }
+
     pub fn update_heading(&mut self, heading_deg: f64, timestamp: Instant) {
         self.last_heading_deg = Some(heading_deg);
         self.last_heading_timestamp = Some(timestamp);
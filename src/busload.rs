@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Bit rate of the NMEA2000 backbone
+pub const NMEA2000_BIT_RATE: u64 = 250_000;
+
+/// Window the bus load is averaged over
+pub const BUS_LOAD_WINDOW: Duration = Duration::from_secs(5);
+
+/// Bits of an extended (29-bit identifier) CAN frame besides the data: start of frame,
+/// identifier, control, CRC, acknowledge, end of frame and interframe space
+const EXTENDED_FRAME_OVERHEAD_BITS: u64 = 67;
+
+/// Bits on the wire of an extended CAN frame with `dlc` data bytes, without bit stuffing
+pub fn frame_bits(dlc: usize) -> u64 {
+    EXTENDED_FRAME_OVERHEAD_BITS + 8 * dlc.min(8) as u64
+}
+
+/// Estimated utilization of the CAN bus, to know if the backbone is saturated
+///
+/// Accumulates the bit time of the frames received over a sliding window. Bit stuffing is not
+/// counted, so the load is slightly underestimated (up to ~20% with the worst data patterns).
+#[derive(Debug)]
+pub struct BusLoadEstimator {
+    bit_rate: u64,
+    window: Duration,
+    frames: VecDeque<(Instant, u64)>,
+    window_bits: u64,
+    first_frame: Option<Instant>,
+}
+
+impl BusLoadEstimator {
+    pub fn new(bit_rate: u64, window: Duration) -> Self {
+        Self {
+            bit_rate,
+            window,
+            frames: VecDeque::new(),
+            window_bits: 0,
+            first_frame: None,
+        }
+    }
+
+    /// Record a frame with `dlc` data bytes received now
+    pub fn record(&mut self, dlc: usize, now: Instant) {
+        let bits = frame_bits(dlc);
+        self.frames.push_back((now, bits));
+        self.window_bits += bits;
+        self.first_frame.get_or_insert(now);
+        self.evict(now);
+    }
+
+    /// Bus utilization in percent over the window ending now
+    /// Until a full window has elapsed since the first frame, the load is over the elapsed time
+    pub fn load_percent(&mut self, now: Instant) -> f64 {
+        self.evict(now);
+        let Some(first_frame) = self.first_frame else {
+            return 0.0;
+        };
+        let elapsed = now.saturating_duration_since(first_frame).min(self.window).as_secs_f64();
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        self.window_bits as f64 / (self.bit_rate as f64 * elapsed) * 100.0
+    }
+
+    /// Drop the frames out of the window
+    fn evict(&mut self, now: Instant) {
+        while let Some(&(time, bits)) = self.frames.front() {
+            if now.saturating_duration_since(time) < self.window {
+                break;
+            }
+            self.frames.pop_front();
+            self.window_bits -= bits;
+        }
+    }
+}
+
+impl Default for BusLoadEstimator {
+    fn default() -> Self {
+        Self::new(NMEA2000_BIT_RATE, BUS_LOAD_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_bits() {
+        assert_eq!(frame_bits(0), 67);
+        assert_eq!(frame_bits(8), 131);
+        // CAN 2.0 frames carry at most 8 bytes
+        assert_eq!(frame_bits(64), 131);
+    }
+
+    #[test]
+    fn test_load_at_known_frame_rate() {
+        let mut estimator = BusLoadEstimator::new(NMEA2000_BIT_RATE, Duration::from_secs(1));
+        let start = Instant::now();
+        // 100 frames of 8 bytes per second for 3 seconds: 13100 bit/s
+        for i in 0..300 {
+            estimator.record(8, start + Duration::from_millis(i * 10));
+        }
+        let load = estimator.load_percent(start + Duration::from_millis(2990));
+        assert!((load - 5.24).abs() < 1e-9, "Expected 5.24, got {}", load);
+    }
+
+    #[test]
+    fn test_load_before_a_full_window() {
+        let mut estimator = BusLoadEstimator::new(NMEA2000_BIT_RATE, Duration::from_secs(5));
+        let start = Instant::now();
+        // 1000 frames of 3 bytes per second for half a second: 91000 bit/s
+        for i in 0..500 {
+            estimator.record(3, start + Duration::from_millis(i));
+        }
+        let load = estimator.load_percent(start + Duration::from_millis(500));
+        assert!((load - 36.4).abs() < 1e-9, "Expected 36.4, got {}", load);
+    }
+
+    #[test]
+    fn test_idle_bus() {
+        let mut estimator = BusLoadEstimator::default();
+        let start = Instant::now();
+        assert_eq!(estimator.load_percent(start), 0.0);
+
+        estimator.record(8, start);
+        // The frames leave the window when the bus goes quiet
+        assert_eq!(estimator.load_percent(start + Duration::from_secs(10)), 0.0);
+    }
+}
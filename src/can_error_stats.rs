@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::db::CanErrorBucket;
+
+/// Accumulates the CAN frames and errors of the current UTC hour
+///
+/// The counts are handed over as a `CanErrorBucket` once the hour is over,
+/// to be persisted for long-term link health analysis.
+pub struct CanErrorStats {
+    /// Current hour, as hours since the Unix epoch
+    hour: Option<u64>,
+    can_frames: u64,
+    can_errors: u64,
+}
+
+fn hours_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs() / 3600).unwrap_or(0)
+}
+
+/// Start of the given hour, as stored in the database
+fn format_hour(hour: u64) -> String {
+    DateTime::<Utc>::from(UNIX_EPOCH + Duration::from_secs(hour * 3600)).format("%Y-%m-%d %H:00:00").to_string()
+}
+
+impl CanErrorStats {
+    pub fn new() -> Self {
+        Self {
+            hour: None,
+            can_frames: 0,
+            can_errors: 0,
+        }
+    }
+
+    pub fn record_frame(&mut self) {
+        self.can_frames += 1;
+    }
+
+    pub fn record_error(&mut self) {
+        self.can_errors += 1;
+    }
+
+    /// Move to the hour of `now`
+    /// Returns the bucket of the previous hour when a new hour has started
+    pub fn roll(&mut self, now: SystemTime) -> Option<CanErrorBucket> {
+        let hour = hours_since_epoch(now);
+        match self.hour.replace(hour) {
+            Some(previous) if previous != hour => {
+                let bucket = CanErrorBucket {
+                    hour: format_hour(previous),
+                    can_frames: self.can_frames,
+                    can_errors: self.can_errors,
+                };
+                self.can_frames = 0;
+                self.can_errors = 0;
+                Some(bucket)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for CanErrorStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        // 2024-06-01 10:00:00 UTC
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_717_236_000 + secs)
+    }
+
+    #[test]
+    fn test_hourly_buckets() {
+        let mut stats = CanErrorStats::new();
+        assert!(stats.roll(at(0)).is_none());
+        for _ in 0..100 {
+            stats.record_frame();
+        }
+        stats.record_error();
+        stats.record_error();
+        assert!(stats.roll(at(3599)).is_none());
+
+        let bucket = stats.roll(at(3600)).unwrap();
+        assert_eq!(bucket, CanErrorBucket { hour: "2024-06-01 10:00:00".to_string(), can_frames: 100, can_errors: 2 });
+
+        // Counters restart with the new hour
+        stats.record_frame();
+        let bucket = stats.roll(at(3 * 3600 + 10)).unwrap();
+        assert_eq!(bucket, CanErrorBucket { hour: "2024-06-01 11:00:00".to_string(), can_frames: 1, can_errors: 0 });
+    }
+}
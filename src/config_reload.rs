@@ -0,0 +1,188 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{info, warn};
+
+use crate::config::{Config, LogConfig};
+
+/// Flag raised on SIGHUP, taken by the main loop
+#[derive(Clone, Default)]
+pub struct ReloadFlag(Arc<AtomicBool>);
+
+impl ReloadFlag {
+    /// Whether a reload was requested since the last call
+    pub fn take(&self) -> bool {
+        self.0.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn request(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Raise the returned flag each time the process receives SIGHUP
+///
+/// The signal is awaited on a dedicated thread; the main loop notices the flag
+/// within the CAN read timeout.
+pub fn install_reload_handler() -> ReloadFlag {
+    let flag = ReloadFlag::default();
+    let handler_flag = flag.clone();
+    std::thread::spawn(move || {
+        let rt = match tokio::runtime::Builder::new_current_thread().enable_io().build() {
+            Ok(rt) => rt,
+            Err(e) => {
+                warn!("Failed to create the signal handler runtime, configuration reload disabled: {}", e);
+                return;
+            }
+        };
+        rt.block_on(async {
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(e) => {
+                    warn!("Failed to install the SIGHUP handler: {}", e);
+                    return;
+                }
+            };
+            while hangup.recv().await.is_some() {
+                info!("SIGHUP received, reloading the configuration");
+                handler_flag.request();
+            }
+        });
+    });
+    flag
+}
+
+/// Sections of the configuration changed between the running and the reloaded configuration
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigChanges {
+    pub source_filter: bool,
+    pub vessel_status: bool,
+    pub environmental: bool,
+    pub log_level: bool,
+    /// Destinations or JSON style of the UDP broadcaster
+    pub udp: bool,
+    /// Changed settings applied only on restart
+    pub restart_required: Vec<&'static str>,
+}
+
+impl ConfigChanges {
+    pub fn is_empty(&self) -> bool {
+        !self.source_filter
+            && !self.vessel_status
+            && !self.environmental
+            && !self.log_level
+            && !self.udp
+            && self.restart_required.is_empty()
+    }
+}
+
+/// Compare the sections through their serialized form, the config structs are not comparable
+fn changed<T: Serialize>(old: &T, new: &T) -> bool {
+    serde_json::to_value(old).ok() != serde_json::to_value(new).ok()
+}
+
+/// Find what changed between the running configuration and the reloaded one
+pub fn diff_config(old: &Config, new: &Config) -> ConfigChanges {
+    // Only the level of the logging section can be reloaded
+    let old_logging = LogConfig { level: new.logging.level.clone(), ..old.logging.clone() };
+    let restart_only = [
+        ("can_interface", changed(&old.can_interface, &new.can_interface)),
+        ("can_read_timeout_ms", changed(&old.can_read_timeout_ms, &new.can_read_timeout_ms)),
+        ("time", changed(&old.time, &new.time)),
+        ("database.connection", changed(&old.database.connection, &new.database.connection)),
+        ("database.write_buffer_path", changed(&old.database.write_buffer_path, &new.database.write_buffer_path)),
+        ("logging", changed(&old_logging, &new.logging)),
+        ("web", changed(&old.web, &new.web)),
+        ("udp.enabled", old.udp.enabled != new.udp.enabled),
+        ("tcp", changed(&old.tcp, &new.tcp)),
+        ("signalk", changed(&old.signalk, &new.signalk)),
+        ("fast_packet", changed(&old.fast_packet, &new.fast_packet)),
+        ("dead_reckoning", changed(&old.dead_reckoning, &new.dead_reckoning)),
+        ("stuck_sensor", changed(&old.stuck_sensor, &new.stuck_sensor)),
+        ("diagnostics", changed(&old.diagnostics, &new.diagnostics)),
+        ("batteries", changed(&old.batteries, &new.batteries)),
+        ("quality_score", changed(&old.quality_score, &new.quality_score)),
+        ("recorder", changed(&old.recorder, &new.recorder)),
+        ("field_decoding", changed(&old.field_decoding, &new.field_decoding)),
+        ("watchdog", changed(&old.watchdog, &new.watchdog)),
+        ("dedup", changed(&old.dedup, &new.dedup)),
+        ("derived", changed(&old.derived, &new.derived)),
+        ("timezone", changed(&old.timezone, &new.timezone)),
+    ];
+    ConfigChanges {
+        source_filter: changed(&old.source_filter, &new.source_filter),
+        vessel_status: changed(&old.database.vessel_status, &new.database.vessel_status),
+        environmental: changed(&old.database.environmental, &new.database.environmental),
+        log_level: old.logging.level != new.logging.level,
+        udp: old.udp.destinations() != new.udp.destinations() || old.udp.json_style != new.udp.json_style,
+        restart_required: restart_only
+            .into_iter()
+            .filter(|(_, changed)| *changed)
+            .map(|(name, _)| name)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::JsonStyle;
+
+    #[test]
+    fn test_same_config_has_no_changes() {
+        let changes = diff_config(&Config::default(), &Config::default());
+        assert!(changes.is_empty());
+        assert_eq!(changes, ConfigChanges::default());
+    }
+
+    #[test]
+    fn test_reloadable_changes() {
+        let old = Config::default();
+        let mut new = Config::default();
+        new.source_filter.pgn_denylist.push(130312);
+        new.database.vessel_status.interval_underway_seconds += 30;
+        new.database.environmental.pressure_seconds += 60;
+        new.logging.level = "debug".to_string();
+        new.udp.json_style = JsonStyle::Lines;
+
+        let changes = diff_config(&old, &new);
+        assert!(changes.source_filter);
+        assert!(changes.vessel_status);
+        assert!(changes.environmental);
+        assert!(changes.log_level);
+        assert!(changes.udp);
+        // The log level alone doesn't require a restart
+        assert!(changes.restart_required.is_empty());
+    }
+
+    #[test]
+    fn test_udp_destinations_change() {
+        let old = Config::default();
+        let mut new = Config::default();
+        new.udp.extra_addresses.push("10.0.0.5:2000".to_string());
+        assert_eq!(diff_config(&old, &new), ConfigChanges { udp: true, ..Default::default() });
+
+        // A duplicate destination is not a change
+        let mut new = Config::default();
+        new.udp.extra_addresses.push(old.udp.address.clone());
+        assert!(diff_config(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn test_restart_required_changes() {
+        let old = Config::default();
+        let mut new = Config::default();
+        new.can_interface = "can1".to_string();
+        new.udp.enabled = !old.udp.enabled;
+        new.logging.directory = "/var/log/nmea_router".to_string();
+        new.web.port += 1;
+
+        let changes = diff_config(&old, &new);
+        assert_eq!(changes.restart_required, ["can_interface", "logging", "web", "udp.enabled"]);
+        assert!(!changes.udp);
+        assert!(!changes.log_level);
+        assert!(!changes.is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use nmea2k::pgns::pgn129029::GnssMethod;
+
+use crate::config::QualityScoreConfig;
+
+/// HDOP at or below which the position geometry is considered ideal
+const HDOP_GOOD: f64 = 1.0;
+/// HDOP at or above which the position geometry is considered unusable
+const HDOP_POOR: f64 = 5.0;
+
+/// Quality signals collected over a vessel status reporting period
+#[derive(Debug, Clone, Default)]
+pub struct QualitySignals {
+    /// Fix method of the last GNSS position data (PGN 129029), None if not received
+    pub fix_method: Option<GnssMethod>,
+    /// HDOP of the last GNSS position data (PGN 129029), None if not received
+    pub hdop: Option<f64>,
+    /// Positions accepted in the reporting period
+    pub number_of_samples: usize,
+    /// Positions rejected by the noise filter in the reporting period
+    pub rejected_positions: usize,
+}
+
+fn fix_method_score(method: &GnssMethod) -> f64 {
+    match method {
+        GnssMethod::NoGnss => 0.0,
+        GnssMethod::GnssFix => 0.6,
+        GnssMethod::DGnss => 0.9,
+        GnssMethod::PreciseGnss | GnssMethod::RtkFixed | GnssMethod::RtkFloat => 1.0,
+    }
+}
+
+fn hdop_score(hdop: f64) -> f64 {
+    ((HDOP_POOR - hdop) / (HDOP_POOR - HDOP_GOOD)).clamp(0.0, 1.0)
+}
+
+/// Combine the quality signals into a single score, from 0 (unusable) to 1 (best)
+/// The score is the weighted average of the signals available, signals not received
+/// (no PGN 129029 on the bus) do not count.
+pub fn quality_score(signals: &QualitySignals, config: &QualityScoreConfig) -> f64 {
+    let total_positions = signals.number_of_samples + signals.rejected_positions;
+    let components = [
+        (config.fix_method_weight, signals.fix_method.as_ref().map(fix_method_score)),
+        (config.hdop_weight, signals.hdop.map(hdop_score)),
+        (
+            config.samples_weight,
+            Some((signals.number_of_samples as f64 / config.expected_samples as f64).min(1.0)),
+        ),
+        (
+            config.rejections_weight,
+            (total_positions > 0).then(|| signals.number_of_samples as f64 / total_positions as f64),
+        ),
+    ];
+
+    let (weighted_sum, total_weight) = components
+        .iter()
+        .filter_map(|(weight, score)| score.map(|score| (weight * score, *weight)))
+        .fold((0.0, 0.0), |(sum, total), (value, weight)| (sum + value, total + weight));
+    if total_weight > 0.0 {
+        weighted_sum / total_weight
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn high_quality() -> QualitySignals {
+        QualitySignals {
+            fix_method: Some(GnssMethod::DGnss),
+            hdop: Some(0.8),
+            number_of_samples: 100,
+            rejected_positions: 0,
+        }
+    }
+
+    fn degraded() -> QualitySignals {
+        QualitySignals {
+            fix_method: Some(GnssMethod::GnssFix),
+            hdop: Some(3.5),
+            number_of_samples: 8,
+            rejected_positions: 4,
+        }
+    }
+
+    #[test]
+    fn test_high_quality_fix_scores_above_degraded() {
+        let config = QualityScoreConfig::default();
+        let high = quality_score(&high_quality(), &config);
+        let low = quality_score(&degraded(), &config);
+        assert!(high > low, "{} <= {}", high, low);
+        assert!((high - 0.975).abs() < 1e-9);
+        assert!(low > 0.0 && low < 0.7);
+    }
+
+    #[test]
+    fn test_missing_signals_are_not_counted() {
+        let config = QualityScoreConfig::default();
+        let signals = QualitySignals {
+            fix_method: None,
+            hdop: None,
+            ..high_quality()
+        };
+        assert_eq!(quality_score(&signals, &config), 1.0);
+        assert_eq!(quality_score(&QualitySignals::default(), &config), 0.0);
+    }
+
+    #[test]
+    fn test_weights() {
+        // Only HDOP counts
+        let config = QualityScoreConfig {
+            fix_method_weight: 0.0,
+            hdop_weight: 1.0,
+            samples_weight: 0.0,
+            rejections_weight: 0.0,
+            ..QualityScoreConfig::default()
+        };
+        assert!((quality_score(&degraded(), &config) - 0.375).abs() < 1e-9);
+        assert_eq!(quality_score(&high_quality(), &config), 1.0);
+    }
+}
@@ -1,15 +1,19 @@
 use mysql::*;
 use mysql::prelude::*;
-use std::{error::Error, time::{Duration, Instant}};
+use std::{collections::BTreeMap, error::Error, sync::{Arc, Mutex}, time::{Duration, Instant}};
 use std::time::{SystemTime};
-use crate::{environmental_monitor::{MetricData, MetricId}, utilities::dirty_instant_to_systemtime};
-use crate::trip::Trip;
+use crate::environmental_monitor::{MetricData, MetricId};
+use crate::trip::{numbered_description_pattern, unique_trip_description, Trip};
+use crate::config::DatabaseConnectionConfig;
+use crate::sqlite_db::SqliteDatabase;
 use chrono::NaiveDateTime;
+use chrono_tz::Tz;
 use tracing::{info, warn};
 
 /// Encapsulates vessel status data for database insertion
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct VesselStatusOperation {
-    pub time: Instant,
+    pub time: SystemTime,
     pub latitude: f64,
     pub longitude: f64,
     pub average_speed_kn: f64,
@@ -26,6 +30,9 @@ pub struct VesselStatusOperation {
     pub wind_angle_variance: Option<f64>,
     pub cog_deg: Option<f64>,
     pub average_heading_deg: Option<f64>,
+    /// Data quality of the status, from 0 to 1
+    #[serde(default)]
+    pub quality_score: Option<f64>,
 }
 
 /// Represents a trip operation to be performed atomically with vessel status insert
@@ -35,9 +42,567 @@ pub enum TripOperation {
     None,
 }
 
+// Web API query structures
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TripSummary {
+    pub id: u32,
+    pub description: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub total_distance_nm: f64,
+    pub total_time_ms: i64,
+    pub sailing_time_ms: i64,
+    pub motoring_time_ms: i64,
+    pub moored_time_ms: i64,
+    pub sailing_distance_nm: f64,
+    pub motoring_distance_nm: f64,
+    /// Start and end in the configured timezone, set by `localize`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_date_local: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_date_local: Option<String>,
+}
+
+impl TripSummary {
+    /// Add the start and end in the given timezone to the UTC ones
+    pub fn localize(&mut self, timezone: Tz) {
+        self.start_date_local = local_timestamp(&self.start_date, timezone);
+        self.end_date_local = local_timestamp(&self.end_date, timezone);
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TrackPoint {
+    pub timestamp: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub avg_speed_kn: f64,
+    pub max_speed_kn: f64,
+    pub moored: bool,
+    pub engine_on: bool,
+    /// Timestamp in the configured timezone, set by `localize`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp_local: Option<String>,
+}
+
+impl TrackPoint {
+    /// Add the timestamp in the given timezone to the UTC one
+    pub fn localize(&mut self, timezone: Tz) {
+        self.timestamp_local = local_timestamp(&self.timestamp, timezone);
+    }
+}
+
+/// Convert a UTC timestamp of the database ("YYYY-MM-DD HH:MM:SS", optionally with fractional
+/// seconds) to the same format in the given timezone
+pub fn local_timestamp(utc: &str, timezone: Tz) -> Option<String> {
+    let time = NaiveDateTime::parse_from_str(utc, "%Y-%m-%d %H:%M:%S%.f").ok()?;
+    Some(time.and_utc().with_timezone(&timezone).format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+/// Paging of the track points returned by `fetch_track`, the whole track when nothing is set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrackPaging {
+    /// Maximum number of rows read
+    pub limit: Option<u32>,
+    /// Number of rows skipped
+    pub offset: Option<u32>,
+    /// Downsample the rows read to at most this number of points, keeping every Nth row
+    pub max_points: Option<u32>,
+}
+
+impl TrackPaging {
+    /// SQL LIMIT/OFFSET clause, empty when neither is set
+    /// `no_limit` is the backend's LIMIT value meaning "all rows", needed when only the offset is given
+    pub fn limit_clause(&self, no_limit: &str) -> String {
+        match (self.limit, self.offset) {
+            (None, None) => String::new(),
+            (Some(limit), None) => format!(" LIMIT {}", limit),
+            (Some(limit), Some(offset)) => format!(" LIMIT {} OFFSET {}", limit, offset),
+            (None, Some(offset)) => format!(" LIMIT {} OFFSET {}", no_limit, offset),
+        }
+    }
+
+    /// Keep every Nth point of the track when it has more than `max_points` points
+    pub fn downsample(&self, track: Vec<TrackPoint>) -> Vec<TrackPoint> {
+        match self.max_points {
+            Some(max_points) if track.len() > max_points as usize => {
+                let stride = downsample_stride(track.len(), max_points as usize);
+                track.into_iter().step_by(stride).collect()
+            }
+            _ => track,
+        }
+    }
+}
+
+/// Smallest stride so that keeping every Nth of `count` rows yields at most `max_points` rows
+pub fn downsample_stride(count: usize, max_points: usize) -> usize {
+    count.div_ceil(max_points.max(1)).max(1)
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct WebMetricData {
+    pub timestamp: String,
+    pub metric_id: String,
+    pub avg_value: Option<f64>,
+    pub max_value: Option<f64>,
+    pub min_value: Option<f64>,
+    pub count: Option<u32>,
+}
+
+/// Time bucket the environmental rows are aggregated in, one row per bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricBucket {
+    Hour,
+    Day,
+}
+
+impl MetricBucket {
+    /// Expression of the bucket start of `column` in `dialect`, as "YYYY-MM-DD HH:MM:SS"
+    pub fn expression(self, dialect: SqlDialect, column: &str) -> String {
+        match (dialect, self) {
+            (SqlDialect::MySql, MetricBucket::Hour) => format!("DATE_FORMAT({}, '%Y-%m-%d %H:00:00')", column),
+            (SqlDialect::MySql, MetricBucket::Day) => format!("DATE_FORMAT({}, '%Y-%m-%d 00:00:00')", column),
+            (SqlDialect::Sqlite, MetricBucket::Hour) => format!("substr({}, 1, 13) || ':00:00'", column),
+            (SqlDialect::Sqlite, MetricBucket::Day) => format!("substr({}, 1, 10) || ' 00:00:00'", column),
+        }
+    }
+}
+
+/// SQL dialect of the database backends sharing a query builder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlDialect {
+    MySql,
+    Sqlite,
+}
+
+impl SqlDialect {
+    /// Expression of `column` as "YYYY-MM-DD HH:MM:SS"
+    fn timestamp(self, column: &str) -> String {
+        match self {
+            SqlDialect::MySql => format!("DATE_FORMAT({}, '%Y-%m-%d %H:%i:%S')", column),
+            SqlDialect::Sqlite => format!("substr({}, 1, 19)", column),
+        }
+    }
+
+    /// Expression of the integer `expression`, unsigned in MySQL where SUM is a DECIMAL
+    fn integer(self, expression: &str) -> String {
+        match self {
+            SqlDialect::MySql => format!("CAST({} AS UNSIGNED)", expression),
+            SqlDialect::Sqlite => expression.to_string(),
+        }
+    }
+}
+
+/// Newest vessel status and newest row of each environmental metric, for the dashboards
+#[derive(Debug, Default, serde::Serialize)]
+pub struct CurrentStatus {
+    pub status: Option<TrackPoint>,
+    /// Latest row of each metric having data, by metric name
+    pub metrics: BTreeMap<String, WebMetricData>,
+}
+
+impl CurrentStatus {
+    /// Nothing was ever written to the database
+    pub fn is_empty(&self) -> bool {
+        self.status.is_none() && self.metrics.is_empty()
+    }
+}
+
+/// Aggregate figures over all the trips (or the trips of a year)
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct VesselStats {
+    pub trip_count: u32,
+    pub total_distance_nm: f64,
+    pub sailing_time_ms: i64,
+    pub motoring_time_ms: i64,
+    /// Highest max speed ever recorded in the vessel status
+    pub max_speed_kn: f64,
+    /// Number of distinct days with at least one underway (not moored) vessel status
+    pub days_underway: u32,
+}
+
+/// CAN bus frame and error counts of an hour, for link health trends
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CanErrorBucket {
+    /// Start of the hour in UTC ("YYYY-MM-DD HH:00:00")
+    pub hour: String,
+    pub can_frames: u64,
+    pub can_errors: u64,
+}
+
+/// Time window started and stopped on demand (a race leg, a watch), independent of the trips
+///
+/// Vessel status and environmental rows written while a session is active are tagged with its id.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Session {
+    pub id: i64,
+    pub name: String,
+    pub start_date: String,
+    /// None while the session is active
+    pub end_date: Option<String>,
+}
+
+/// Database connection shared with the web server, replaced by the main loop when it reconnects
+/// None while the database is unavailable
+pub type SharedDatabase = Arc<Mutex<Option<Arc<dyn Database>>>>;
+
+/// Storage backend for vessel status, trips and environmental data
+///
+/// Implemented by `VesselDatabase` (MySQL/MariaDB) and `SqliteDatabase` (embedded SQLite file),
+/// the backend is selected with `database.connection.backend` in the configuration.
+pub trait Database: Send + Sync {
+    /// Check database connection health using a simple query
+    /// Returns Ok(()) if the connection is healthy, Err otherwise
+    fn health_check(&self) -> Result<(), Box<dyn Error>>;
+
+    fn update_trip_description(&self, trip_id: i64, new_description: &str) -> Result<(), Box<dyn Error>>;
+
+    /// Delete a trip
+    /// The vessel status rows and metrics of the trip are kept: they are linked to the trip only
+    /// by its time range, so they are simply no longer part of a trip.
+    /// Returns false if the trip does not exist
+    fn delete_trip(&self, trip_id: i64) -> Result<bool, Box<dyn Error>>;
+
+    /// Merge the secondary trip into the primary one, in a single transaction
+    /// The distances and times are summed, the time range is extended to cover both trips
+    /// and the secondary trip is deleted. Returns false if either trip does not exist
+    fn merge_trips(&self, primary_id: i64, secondary_id: i64) -> Result<bool, Box<dyn Error>>;
+
+    /// Split a trip at the given time, in a single transaction
+    /// The vessel statuses from that time on make a new trip ("<description> #N"), their
+    /// distances and times are moved out of the original trip.
+    /// Returns the id of the new trip, None if the trip does not exist
+    fn split_trip(&self, trip_id: i64, at: SystemTime) -> Result<Option<i64>, Box<dyn Error>>;
+
+    /// Fetch the descriptions of trips named like the given base description
+    /// Matches the base description itself and its numbered variants ("<base> #N")
+    fn fetch_trip_descriptions(&self, base_description: &str) -> Result<Vec<String>, Box<dyn Error>>;
+
+    /// Insert vessel status and create/update trip in a single transaction
+    /// Returns the id of the vessel status row and the id of the trip, if one was created
+    fn insert_status_and_trip(
+        &self,
+        status_op: VesselStatusOperation,
+        trip_operation: TripOperation,
+    ) -> Result<(i64, Option<i64>), Box<dyn Error>>;
+
+    /// Insert a backlog of vessel status rows without trip operations, e.g. after a database outage
+    /// Writes them one by one unless the backend has a faster bulk insert
+    fn insert_status_batch(&self, ops: &[VesselStatusOperation]) -> Result<(), Box<dyn Error>> {
+        for op in ops {
+            self.insert_status_and_trip(op.clone(), TripOperation::None)?;
+        }
+        Ok(())
+    }
+
+    /// Insert or replace the row of an environmental metric at the given time
+    /// Returns the id of the written row, None if there was nothing to write
+    fn insert_environmental_metrics(
+        &self,
+        data: &MetricData,
+        metric_id: MetricId,
+        now: SystemTime,
+    ) -> Result<Option<i64>, Box<dyn Error>>;
+
+    /// Get the most recent trip
+    fn get_last_trip(&self) -> Result<Option<Trip>, Box<dyn Error>>;
+
+    fn fetch_trip(&self, trip_id: u32) -> Result<Option<TripSummary>, Box<dyn Error>>;
+
+    /// Fetch trips started in the given year or in the last months (default last 12 months)
+    /// With `since` ("YYYY-MM-DD HH:MM:SS", UTC) only the trips that ended after it, i.e. modified since
+    fn fetch_trips(&self, year: Option<i32>, last_months: Option<u32>, since: Option<&str>) -> Result<Vec<TripSummary>, Box<dyn Error>>;
+
+    /// Fetch vessel track data by trip_id or date range, optionally paged and downsampled
+    fn fetch_track(&self, trip_id: Option<u32>, start: Option<&str>, end: Option<&str>, paging: TrackPaging) -> Result<Vec<TrackPoint>, Box<dyn Error>>;
+
+    /// Fetch environmental metrics by metric_id with optional trip_id or date range
+    /// With a bucket the rows are aggregated, one row per hour or day
+    fn fetch_metrics(&self, metric: &str, trip_id: Option<u32>, start: Option<&str>, end: Option<&str>, bucket: Option<MetricBucket>) -> Result<Vec<WebMetricData>, Box<dyn Error>>;
+
+    /// Fetch the newest vessel status and the newest row of each environmental metric
+    fn fetch_latest_status(&self) -> Result<CurrentStatus, Box<dyn Error>>;
+
+    /// Compute the aggregate statistics of the given year (all time if None)
+    fn fetch_stats(&self, year: Option<i32>) -> Result<VesselStats, Box<dyn Error>>;
+
+    /// Add the counts of an hour to the CAN error statistics
+    fn insert_can_error_bucket(&self, bucket: &CanErrorBucket) -> Result<(), Box<dyn Error>>;
+
+    /// Fetch the hourly CAN error statistics, optionally within a date range
+    fn fetch_can_error_buckets(&self, start: Option<&str>, end: Option<&str>) -> Result<Vec<CanErrorBucket>, Box<dyn Error>>;
+
+    /// Start a session at the given time, stopping the active session if any
+    fn start_session(&self, name: &str, now: SystemTime) -> Result<Session, Box<dyn Error>>;
+
+    /// Stop the active session at the given time
+    /// Returns the stopped session, None if no session was active
+    fn stop_session(&self, now: SystemTime) -> Result<Option<Session>, Box<dyn Error>>;
+
+    fn fetch_session(&self, session_id: i64) -> Result<Option<Session>, Box<dyn Error>>;
+
+    /// Fetch the vessel status rows tagged with the session
+    fn fetch_session_track(&self, session_id: i64) -> Result<Vec<TrackPoint>, Box<dyn Error>>;
+
+    /// Fetch the environmental rows of a metric tagged with the session
+    fn fetch_session_metrics(&self, metric: &str, session_id: i64) -> Result<Vec<WebMetricData>, Box<dyn Error>>;
+}
+
+/// Open the database configured in the connection settings
+pub fn connect(connection: &DatabaseConnectionConfig) -> Result<Arc<dyn Database>, Box<dyn Error>> {
+    match connection.backend.as_str() {
+        "mysql" => Ok(Arc::new(VesselDatabase::new(mysql_opts(connection)?, connection.connect_timeout())?)),
+        "sqlite" => Ok(Arc::new(SqliteDatabase::new(&connection.connection_url())?)),
+        other => Err(format!("Unsupported database backend: {}", other).into()),
+    }
+}
+
+/// MySQL connection options from the connection settings: pool size and timeouts, so that an
+/// overloaded server makes the queries fail instead of blocking the pipeline
+pub fn mysql_opts(connection: &DatabaseConnectionConfig) -> Result<Opts, Box<dyn Error>> {
+    let constraints = PoolConstraints::new(connection.pool_min, connection.pool_max)
+        .ok_or_else(|| format!("Invalid database pool size {}-{}", connection.pool_min, connection.pool_max))?;
+    let opts = OptsBuilder::from_opts(Opts::from_url(&connection.connection_url())?)
+        .pool_opts(PoolOpts::default().with_constraints(constraints))
+        .tcp_connect_timeout(Some(connection.connect_timeout()))
+        .read_timeout(Some(connection.query_timeout()))
+        .write_timeout(Some(connection.query_timeout()));
+    Ok(opts.into())
+}
+
+/// Attempt to reconnect to the database with exponential backoff
+/// Returns Some(database) if successful, None if all retries fail
+pub fn reconnect_with_retry(connection: &DatabaseConnectionConfig, max_retries: u32) -> Option<Arc<dyn Database>> {
+    for attempt in 1..=max_retries {
+        warn!("Attempting to reconnect to database (attempt {}/{})...", attempt, max_retries);
+        match connect(connection) {
+            Ok(db) => {
+                info!("Database reconnection successful");
+                return Some(db);
+            }
+            Err(e) => {
+                warn!("Database reconnection attempt {} failed: {}", attempt, e);
+                if attempt < max_retries {
+                    let wait_time = std::cmp::min(2_u64.pow(attempt - 1), 30); // Exponential backoff, max 30s
+                    warn!("Waiting {} seconds before retry...", wait_time);
+                    std::thread::sleep(Duration::from_secs(wait_time));
+                }
+            }
+        }
+    }
+    warn!("Failed to reconnect to database after {} attempts", max_retries);
+    None
+}
+
+/// Maximum wait for a pooled connection in `health_check`
+const HEALTH_CHECK_CONN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Maximum number of rows written by a single multi-row INSERT in `insert_status_batch`
+const STATUS_BATCH_SIZE: usize = 500;
+
+/// Build the query of the environmental rows of a metric, by trip (`:trip_id`) or by date
+/// range (`:start`, `:end`), aggregated per bucket if any
+///
+/// The bucket average is weighted by the sample count of each row, so that rows covering few
+/// samples don't count as much as the full ones.
+pub(crate) fn build_metrics_query(dialect: SqlDialect, by_trip: bool, bucket: Option<MetricBucket>) -> String {
+    let (join, filter) = if by_trip {
+        (
+            "JOIN trips t ON e.timestamp BETWEEN t.start_timestamp AND t.end_timestamp",
+            "t.id = :trip_id AND e.metric_id = :metric_id",
+        )
+    } else {
+        ("", "e.metric_id = :metric_id AND e.timestamp BETWEEN :start AND :end")
+    };
+    match bucket {
+        None => format!(
+            "SELECT {} as timestamp,
+                    e.metric_id, e.value_avg as avg_value, e.value_max as max_value, e.value_min as min_value,
+                    e.value_count as count
+             FROM environmental_data e {}
+             WHERE {}
+             ORDER BY e.timestamp",
+            dialect.timestamp("e.timestamp"), join, filter
+        ),
+        Some(bucket) => {
+            let start = bucket.expression(dialect, "e.timestamp");
+            let count = dialect.integer("SUM(e.value_count)");
+            format!(
+                "SELECT {start} as timestamp,
+                        MIN(e.metric_id) as metric_id, SUM(e.value_avg * e.value_count) / SUM(e.value_count) as avg_value,
+                        MAX(e.value_max) as max_value, MIN(e.value_min) as min_value, {count} as count
+                 FROM environmental_data e {join}
+                 WHERE {filter}
+                 GROUP BY {start}
+                 ORDER BY MIN(e.timestamp)"
+            )
+        }
+    }
+}
+
+/// Build the MySQL query of the trips started in `year` or in the last months (default 12),
+/// restricted to the trips ended after `:since` if `since`
+fn build_trips_query(year: Option<i32>, last_months: Option<u32>, since: bool) -> String {
+    let mut query = String::from(
+        "SELECT id,
+                description,
+                DATE_FORMAT(start_timestamp, '%Y-%m-%d %H:%i:%S') as start_ts,
+                DATE_FORMAT(end_timestamp, '%Y-%m-%d %H:%i:%S') as end_ts,
+                (total_distance_sailed + total_distance_motoring) as total_distance,
+                (total_time_sailing + total_time_motoring + total_time_moored) as total_time,
+                total_time_sailing as total_time_sailing,
+                total_time_motoring as total_time_motoring,
+                total_time_moored as total_time_moored,
+                total_distance_sailed as total_distance_sailed,
+                total_distance_motoring as total_distance_motoring
+         FROM trips WHERE "
+    );
+
+    if let Some(year) = year {
+        query.push_str(&format!(" YEAR(start_timestamp) = {}", year));
+    } else {
+        // default last 12 months
+        query.push_str(&format!(" start_timestamp >= DATE_SUB(NOW(), INTERVAL {} MONTH)", last_months.unwrap_or(12)));
+    }
+
+    // The end of a trip moves forward with each status report, so it is its last modification
+    if since {
+        query.push_str(" AND end_timestamp > :since");
+    }
+
+    query.push_str(" ORDER BY start_timestamp DESC");
+    query
+}
+
+/// Number of values bound for each row in `insert_status_batch`
+const STATUS_BATCH_COLUMNS: usize = 14;
+
+/// Build a multi-row INSERT statement for `rows` vessel status rows, using positional parameters
+fn build_status_batch_insert(rows: usize) -> String {
+    let placeholders = format!("({})", vec!["?"; STATUS_BATCH_COLUMNS].join(", "));
+    let values = vec![placeholders.as_str(); rows].join(", ");
+    format!(
+        "INSERT INTO vessel_status \
+         (timestamp, latitude, longitude, average_speed_kn, max_speed_kn, is_moored, engine_on, total_distance_nm, total_time_ms, average_wind_speed_kn, average_wind_angle_deg, cog_deg, average_heading_deg, quality_score) \
+         VALUES {}",
+        values
+    )
+}
+
+/// Id of the session covering the `:timestamp` parameter, NULL outside of sessions
+/// The latest session wins at the boundary between two sessions
+const MYSQL_SESSION_AT: &str = "(SELECT MAX(id) FROM sessions \
+     WHERE start_timestamp <= :timestamp AND COALESCE(end_timestamp, :timestamp) >= :timestamp)";
+
+/// LIMIT meaning "all rows" in MySQL, used when only an offset is given
+const MYSQL_NO_LIMIT: &str = "18446744073709551615";
+
+/// Columns selected to load a trip, read by `trip_from_row`
+const MYSQL_TRIP_COLUMNS: &str = "id, description,
+    DATE_FORMAT(start_timestamp, '%Y-%m-%d %H:%i:%S.%f') as start_ts,
+    DATE_FORMAT(end_timestamp, '%Y-%m-%d %H:%i:%S.%f') as end_ts,
+    total_distance_sailed, total_distance_motoring,
+    total_time_sailing, total_time_motoring, total_time_moored";
+
+/// Format a time as a DATETIME(3) value
+fn mysql_timestamp(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time).format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+}
+
+fn trip_from_row(mut row: mysql::Row) -> Result<Trip, Box<dyn Error>> {
+    let id: i64 = row.take("id").ok_or("Missing id")?;
+    let description: String = row.take("description").ok_or("Missing description")?;
+    let start_ts: String = row.take("start_ts").ok_or("Missing start_ts")?;
+    let end_ts: String = row.take("end_ts").ok_or("Missing end_ts")?;
+    let total_distance_sailed: f64 = row.take("total_distance_sailed").ok_or("Missing total_distance_sailed")?;
+    let total_distance_motoring: f64 = row.take("total_distance_motoring").ok_or("Missing total_distance_motoring")?;
+    let total_time_sailing: u64 = row.take("total_time_sailing").ok_or("Missing total_time_sailing")?;
+    let total_time_motoring: u64 = row.take("total_time_motoring").ok_or("Missing total_time_motoring")?;
+    let total_time_moored: u64 = row.take("total_time_moored").ok_or("Missing total_time_moored")?;
+
+    // Parse timestamps
+    let start_dt = NaiveDateTime::parse_from_str(&start_ts, "%Y-%m-%d %H:%M:%S%.6f")?;
+    let end_dt = NaiveDateTime::parse_from_str(&end_ts, "%Y-%m-%d %H:%M:%S%.6f")?;
+
+    Ok(Trip {
+        id: Some(id),
+        description,
+        start_timestamp: SystemTime::from(start_dt.and_utc()),
+        end_timestamp: SystemTime::from(end_dt.and_utc()),
+        total_distance_sailed,
+        total_distance_motoring,
+        total_time_sailing,
+        total_time_motoring,
+        total_time_moored,
+    })
+}
+
+/// Write the time range and the totals of an existing trip
+fn update_trip(tx: &mut Transaction, trip: &Trip, trip_id: i64) -> Result<(), Box<dyn Error>> {
+    tx.exec_drop(
+        r"UPDATE trips
+          SET start_timestamp = :start_ts,
+              end_timestamp = :end_ts,
+              total_distance_sailed = :distance_sailed,
+              total_distance_motoring = :distance_motoring,
+              total_time_sailing = :time_sailing,
+              total_time_motoring = :time_motoring,
+              total_time_moored = :time_moored
+          WHERE id = :trip_id",
+        params! {
+            "trip_id" => trip_id,
+            "start_ts" => mysql_timestamp(trip.start_timestamp),
+            "end_ts" => mysql_timestamp(trip.end_timestamp),
+            "distance_sailed" => trip.total_distance_sailed,
+            "distance_motoring" => trip.total_distance_motoring,
+            "time_sailing" => trip.total_time_sailing,
+            "time_motoring" => trip.total_time_motoring,
+            "time_moored" => trip.total_time_moored,
+        },
+    )?;
+    Ok(())
+}
+
+fn track_point_from_row(row: mysql::Row) -> TrackPoint {
+    TrackPoint {
+        timestamp: row.get::<String, _>("timestamp").unwrap_or_default(),
+        latitude: row.get::<f64, _>("latitude").unwrap_or(0.0),
+        longitude: row.get::<f64, _>("longitude").unwrap_or(0.0),
+        avg_speed_kn: row.get::<f64, _>("average_speed_kn").unwrap_or(0.0),
+        max_speed_kn: row.get::<f64, _>("max_speed_kn").unwrap_or(0.0),
+        moored: row.get::<i32, _>("is_moored").unwrap_or(0) != 0,
+        engine_on: row.get::<i32, _>("engine_on").unwrap_or(0) != 0,
+        timestamp_local: None,
+    }
+}
+
+fn session_from_row(row: mysql::Row) -> Session {
+    Session {
+        id: row.get::<i64, _>("id").unwrap_or(0),
+        name: row.get::<String, _>("name").unwrap_or_default(),
+        start_date: row.get::<String, _>("start_ts").unwrap_or_default(),
+        end_date: row.get::<Option<String>, _>("end_ts").flatten(),
+    }
+}
+
+fn metric_data_from_row(row: mysql::Row) -> WebMetricData {
+    WebMetricData {
+        timestamp: row.get::<String, _>("timestamp").unwrap_or_default(),
+        metric_id: row.get::<u8, _>("metric_id").map(|id| id.to_string()).unwrap_or_default(),
+        avg_value: row.get::<Option<f64>, _>("avg_value").flatten(),
+        max_value: row.get::<Option<f64>, _>("max_value").flatten(),
+        min_value: row.get::<Option<f64>, _>("min_value").flatten(),
+        count: row.get::<Option<u32>, _>("count").flatten(),
+    }
+}
+
+/// MySQL/MariaDB database
 #[derive(Clone)]
 pub struct VesselDatabase {
     pub pool: Pool,
+    /// Maximum wait for a free connection of the pool
+    conn_timeout: Duration,
 }
 
 impl VesselDatabase {
@@ -62,27 +627,35 @@ impl VesselDatabase {
     ///     average_wind_angle_deg DECIMAL(6,3),
     ///     cog_deg DECIMAL(6,3),
     ///     average_heading_deg DECIMAL(6,3),
+    ///     quality_score DECIMAL(4,3),
+    ///     session_id BIGINT,
     ///     INDEX idx_timestamp (timestamp)
     /// );
     /// ```
-    pub fn new(connection_url: &str) -> Result<Self, Box<dyn Error>> {
-        let opts = Opts::from_url(connection_url)?;
+    pub fn new(opts: Opts, conn_timeout: Duration) -> Result<Self, Box<dyn Error>> {
         let pool = Pool::new(opts)?;
         
-        Ok(VesselDatabase { pool })
+        Ok(VesselDatabase { pool, conn_timeout })
     }
-    
+
+    /// Connection from the pool, failing after the connect timeout when all are in use
+    fn get_conn(&self) -> std::result::Result<PooledConn, mysql::Error> {
+        self.pool.try_get_conn(self.conn_timeout)
+    }
+}
+
+impl Database for VesselDatabase {
     /// Check database connection health using a simple query
     /// Returns Ok(()) if the connection is healthy, Err otherwise
-    pub fn health_check(&self) -> Result<(), Box<dyn Error>> {
-        let mut conn = self.pool.get_conn()?;
+    fn health_check(&self) -> Result<(), Box<dyn Error>> {
+        // Fail instead of waiting for a connection when the pool is exhausted
+        let mut conn = self.pool.try_get_conn(HEALTH_CHECK_CONN_TIMEOUT)?;
         conn.query_drop("SELECT 1")?;
         Ok(())
     }
-    
 
-    pub fn update_trip_description(&self, trip_id: i64, new_description: &str) -> Result<(), Box<dyn Error>> {
-        let mut conn = self.pool.get_conn()?;
+    fn update_trip_description(&self, trip_id: i64, new_description: &str) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
         let query = "UPDATE trips SET description = :description WHERE id = :id";
         conn.exec_drop(query, mysql::params! {
             "description" => new_description,
@@ -91,23 +664,196 @@ impl VesselDatabase {
         Ok(())
     }
 
+    /// Insert a backlog of vessel status rows in a single transaction
+    /// Rows are written with multi-row INSERT statements (up to STATUS_BATCH_SIZE rows each),
+    /// which is much faster than one INSERT per row when flushing after a database outage
+    fn insert_status_batch(&self, ops: &[VesselStatusOperation]) -> Result<(), Box<dyn Error>> {
+        if ops.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self.get_conn()?;
+        let mut tx = conn.start_transaction(TxOpts::default())?;
+
+        for chunk in ops.chunks(STATUS_BATCH_SIZE) {
+            let query = build_status_batch_insert(chunk.len());
+            let mut values: Vec<Value> = Vec::with_capacity(chunk.len() * STATUS_BATCH_COLUMNS);
+            for status_op in chunk {
+                let timestamp = chrono::DateTime::<chrono::Utc>::from(status_op.time);
+                values.push(timestamp.format("%Y-%m-%d %H:%M:%S%.3f").to_string().into());
+                values.push(status_op.latitude.into());
+                values.push(status_op.longitude.into());
+                values.push(status_op.average_speed_kn.into());
+                values.push(status_op.max_speed_kn.into());
+                values.push(status_op.is_moored.into());
+                values.push(status_op.engine_on.into());
+                values.push(status_op.total_distance_nm.into());
+                values.push(status_op.total_time_ms.into());
+                values.push(status_op.average_wind_speed_kn.into());
+                values.push(status_op.average_wind_angle_deg.into());
+                values.push(status_op.cog_deg.into());
+                values.push(status_op.average_heading_deg.into());
+                values.push(status_op.quality_score.into());
+            }
+            tx.exec_drop(query, values)?;
+        }
+
+        // Tag the rows written during a session
+        let timestamp = |time: SystemTime| chrono::DateTime::<chrono::Utc>::from(time).format("%Y-%m-%d %H:%M:%S%.3f").to_string();
+        let first = ops.iter().map(|op| op.time).min().unwrap_or(ops[0].time);
+        let last = ops.iter().map(|op| op.time).max().unwrap_or(ops[0].time);
+        tx.exec_drop(
+            r"UPDATE vessel_status SET session_id = (
+                  SELECT MAX(s.id) FROM sessions s
+                  WHERE s.start_timestamp <= vessel_status.timestamp
+                    AND COALESCE(s.end_timestamp, vessel_status.timestamp) >= vessel_status.timestamp)
+              WHERE session_id IS NULL AND timestamp BETWEEN :first AND :last",
+            params! {
+                "first" => timestamp(first),
+                "last" => timestamp(last),
+            },
+        )?;
+
+        tx.commit()?;
+        info!("Inserted {} vessel status rows in batch", ops.len());
+        Ok(())
+    }
+
+    fn delete_trip(&self, trip_id: i64) -> Result<bool, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+        conn.exec_drop("DELETE FROM trips WHERE id = :id", mysql::params! { "id" => trip_id })?;
+        Ok(conn.affected_rows() > 0)
+    }
+
+    /// Fetch the descriptions of trips named like the given base description
+    /// Matches the base description itself and its numbered variants ("<base> #N")
+    fn fetch_trip_descriptions(&self, base_description: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+        let descriptions: Vec<String> = conn.exec(
+            // '\\' is a single backslash in a MySQL string literal
+            "SELECT description FROM trips WHERE description = :base OR description LIKE :pattern ESCAPE '\\\\'",
+            params! {
+                "base" => base_description,
+                "pattern" => numbered_description_pattern(base_description),
+            },
+        )?;
+        Ok(descriptions)
+    }
+
+    fn merge_trips(&self, primary_id: i64, secondary_id: i64) -> Result<bool, Box<dyn Error>> {
+        if primary_id == secondary_id {
+            return Err("Cannot merge a trip with itself".into());
+        }
+        let mut conn = self.get_conn()?;
+        let mut tx = conn.start_transaction(TxOpts::default())?;
+        let query = format!("SELECT {} FROM trips WHERE id = :id FOR UPDATE", MYSQL_TRIP_COLUMNS);
+        let primary: Option<mysql::Row> = tx.exec_first(&query, params! { "id" => primary_id })?;
+        let secondary: Option<mysql::Row> = tx.exec_first(&query, params! { "id" => secondary_id })?;
+        let (Some(primary), Some(secondary)) = (primary, secondary) else {
+            return Ok(false);
+        };
+
+        let mut primary = trip_from_row(primary)?;
+        primary.merge(&trip_from_row(secondary)?);
+        update_trip(&mut tx, &primary, primary_id)?;
+        tx.exec_drop("DELETE FROM trips WHERE id = :id", params! { "id" => secondary_id })?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    fn split_trip(&self, trip_id: i64, at: SystemTime) -> Result<Option<i64>, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+        let mut tx = conn.start_transaction(TxOpts::default())?;
+        let query = format!("SELECT {} FROM trips WHERE id = :id FOR UPDATE", MYSQL_TRIP_COLUMNS);
+        let Some(row) = tx.exec_first::<mysql::Row, _, _>(query, params! { "id" => trip_id })? else {
+            return Ok(None);
+        };
+        let mut head = trip_from_row(row)?;
+        if at <= head.start_timestamp || at >= head.end_timestamp {
+            return Err("The split time must be within the trip".into());
+        }
+
+        // The statuses from the split time on make the new trip
+        let existing: Vec<String> = tx.exec(
+            "SELECT description FROM trips WHERE description = :base OR description LIKE :pattern",
+            params! {
+                "base" => &head.description,
+                "pattern" => format!("{} #%", head.description),
+            },
+        )?;
+        let mut tail = Trip::new(at, unique_trip_description(&head.description, &existing));
+        let statuses: Vec<(String, f64, u64, bool, bool)> = tx.exec(
+            r"SELECT DATE_FORMAT(timestamp, '%Y-%m-%d %H:%i:%S.%f'), total_distance_nm, total_time_ms, engine_on, is_moored
+              FROM vessel_status WHERE timestamp BETWEEN :start AND :end ORDER BY timestamp",
+            params! {
+                "start" => mysql_timestamp(at),
+                "end" => mysql_timestamp(head.end_timestamp),
+            },
+        )?;
+        for (timestamp, distance, time_ms, engine_on, is_moored) in statuses {
+            let time = NaiveDateTime::parse_from_str(&timestamp, "%Y-%m-%d %H:%M:%S%.6f")?;
+            tail.update(SystemTime::from(time.and_utc()), distance, time_ms, engine_on, is_moored);
+        }
+        let head_end: Option<Option<String>> = tx.exec_first(
+            r"SELECT DATE_FORMAT(MAX(timestamp), '%Y-%m-%d %H:%i:%S.%f')
+              FROM vessel_status WHERE timestamp >= :start AND timestamp < :at",
+            params! {
+                "start" => mysql_timestamp(head.start_timestamp),
+                "at" => mysql_timestamp(at),
+            },
+        )?;
+        let head_end = match head_end.flatten() {
+            Some(timestamp) => SystemTime::from(NaiveDateTime::parse_from_str(&timestamp, "%Y-%m-%d %H:%M:%S%.6f")?.and_utc()),
+            None => head.start_timestamp,
+        };
+        head.truncate(head_end, &tail);
+
+        update_trip(&mut tx, &head, trip_id)?;
+        tx.exec_drop(
+            r"INSERT INTO trips
+              (description, start_timestamp, end_timestamp,
+               total_distance_sailed, total_distance_motoring,
+               total_time_sailing, total_time_motoring, total_time_moored)
+              VALUES (:description, :start_ts, :end_ts,
+                      :distance_sailed, :distance_motoring,
+                      :time_sailing, :time_motoring, :time_moored)",
+            params! {
+                "description" => &tail.description,
+                "start_ts" => mysql_timestamp(tail.start_timestamp),
+                "end_ts" => mysql_timestamp(tail.end_timestamp),
+                "distance_sailed" => tail.total_distance_sailed,
+                "distance_motoring" => tail.total_distance_motoring,
+                "time_sailing" => tail.total_time_sailing,
+                "time_motoring" => tail.total_time_motoring,
+                "time_moored" => tail.total_time_moored,
+            },
+        )?;
+        let tail_id = tx.last_insert_id().map(|id| id as i64).ok_or("Missing trip id")?;
+        tx.commit()?;
+        Ok(Some(tail_id))
+    }
+
     /// Insert vessel status and create/update trip in a single transaction
     /// This ensures atomicity - either both operations succeed or both fail
-    pub fn insert_status_and_trip(
+    /// Returns the id of the vessel status row and the id of the trip, if one was created
+    fn insert_status_and_trip(
         &self,
         status_op: VesselStatusOperation,
         trip_operation: TripOperation,
-    ) -> Result<Option<i64>, Box<dyn Error>> {
-        let mut conn = self.pool.get_conn()?;
+    ) -> Result<(i64, Option<i64>), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
         let mut tx = conn.start_transaction(TxOpts::default())?;
         
         // Insert vessel status
-        let timestamp = chrono::DateTime::<chrono::Utc>::from(dirty_instant_to_systemtime(status_op.time));
+        let timestamp = chrono::DateTime::<chrono::Utc>::from(status_op.time);
                
                 tx.exec_drop(
-                        r"INSERT INTO vessel_status 
-                            (timestamp, latitude, longitude, average_speed_kn, max_speed_kn, is_moored, engine_on, total_distance_nm, total_time_ms, average_wind_speed_kn, average_wind_angle_deg, cog_deg, average_heading_deg)
-                            VALUES (:timestamp, :latitude, :longitude, :avg_speed, :max_speed, :is_moored, :engine_on, :total_distance, :total_time, :avg_wind_speed, :avg_wind_angle, :cog_deg, :avg_heading_deg)",
+                        format!(
+                            "INSERT INTO vessel_status 
+                            (timestamp, latitude, longitude, average_speed_kn, max_speed_kn, is_moored, engine_on, total_distance_nm, total_time_ms, average_wind_speed_kn, average_wind_angle_deg, cog_deg, average_heading_deg, quality_score, session_id)
+                            VALUES (:timestamp, :latitude, :longitude, :avg_speed, :max_speed, :is_moored, :engine_on, :total_distance, :total_time, :avg_wind_speed, :avg_wind_angle, :cog_deg, :avg_heading_deg, :quality_score, {})",
+                            MYSQL_SESSION_AT
+                        ),
                         params! {
                                 "timestamp" => timestamp.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
                                 "latitude" => status_op.latitude,
@@ -122,8 +868,10 @@ impl VesselDatabase {
                                 "avg_wind_angle" => status_op.average_wind_angle_deg,
                                 "cog_deg" => status_op.cog_deg,
                                 "avg_heading_deg" => status_op.average_heading_deg,
+                                "quality_score" => status_op.quality_score,
                         },
                 )?;
+        let status_id = tx.last_insert_id().map(|id| id as i64).ok_or("Missing vessel status id")?;
         
         // Handle trip operation
         let trip_id = match trip_operation {
@@ -184,18 +932,19 @@ impl VesselDatabase {
         };
         
         tx.commit()?;
-        Ok(trip_id)
+        Ok((status_id, trip_id))
     }
-        
+
     /// Insert only specific environmental metrics into the database
     /// This allows for adaptive persistence intervals per metric
-    pub fn insert_environmental_metrics(
+    /// Returns the id of the written row, None if there was nothing to write
+    fn insert_environmental_metrics(
         &self, 
         data: &MetricData, 
         metric_id: MetricId,
         now: std::time::SystemTime,
-    ) -> Result<(), Box<dyn Error>> {
-        let mut conn = self.pool.get_conn()?;
+    ) -> Result<Option<i64>, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
         
         // Get current system time and convert to UTC
         let timestamp = chrono::DateTime::<chrono::Utc>::from(now);
@@ -203,27 +952,35 @@ impl VesselDatabase {
         
         if data.avg.is_some() || data.max.is_some() || data.min.is_some() {
             conn.exec_drop(
-                r"INSERT INTO environmental_data 
-                    (timestamp, metric_id, value_avg, value_max, value_min, unit)
-                    VALUES (:timestamp, :metric_id, :value_avg, :value_max, :value_min, :unit)
+                format!(
+                    "INSERT INTO environmental_data 
+                    (timestamp, metric_id, value_avg, value_max, value_min, value_count, unit, session_id)
+                    VALUES (:timestamp, :metric_id, :value_avg, :value_max, :value_min, :value_count, :unit, {})
                     ON DUPLICATE KEY UPDATE
+                        id = LAST_INSERT_ID(id),
                         value_avg = VALUES(value_avg),
                         value_max = VALUES(value_max),
                         value_min = VALUES(value_min),
-                        unit = VALUES(unit)",
+                        value_count = VALUES(value_count),
+                        unit = VALUES(unit),
+                        session_id = VALUES(session_id)",
+                    MYSQL_SESSION_AT
+                ),
                 params! {
                     "timestamp" => &timestamp_str,
                     "metric_id" => metric_id.as_u8(),
                     "value_avg" => data.avg,
                     "value_max" => data.max,
                     "value_min" => data.min,
+                    "value_count" => data.count.map(|count| count as u32),
                     "unit" => metric_id.unit(),
                 },
             )?;
+            return Ok(Some(conn.last_insert_id() as i64));
         }
 
         
-        Ok(())
+        Ok(None)
     }
 
     /// Get the most recent trip from the database
@@ -242,80 +999,369 @@ impl VesselDatabase {
     ///     INDEX idx_end_timestamp (end_timestamp)
     /// );
     /// ```
-    pub fn get_last_trip(&self) -> Result<Option<Trip>, Box<dyn Error>> {
-        let mut conn = self.pool.get_conn()?;
+    fn get_last_trip(&self) -> Result<Option<Trip>, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+        let row: Option<mysql::Row> = conn.exec_first(
+            format!("SELECT {} FROM trips ORDER BY end_timestamp DESC LIMIT 1", MYSQL_TRIP_COLUMNS),
+            (),
+        )?;
+        row.map(trip_from_row).transpose()
+    }
+
+    fn fetch_trip(&self, trip_id: u32) -> Result<Option<TripSummary>, Box<dyn std::error::Error>> {
+        let mut conn = self.get_conn()
+            .map_err(|e| format!("Database connection error: {}", e))?;
         
         let row: Option<mysql::Row> = conn.exec_first(
             r"SELECT id, description, 
                      DATE_FORMAT(start_timestamp, '%Y-%m-%d %H:%i:%S.%f') as start_ts,
                      DATE_FORMAT(end_timestamp, '%Y-%m-%d %H:%i:%S.%f') as end_ts,
                      total_distance_sailed, total_distance_motoring,
+                     (total_distance_sailed + total_distance_motoring) as total_distance,
                      total_time_sailing, total_time_motoring, total_time_moored
               FROM trips
-              ORDER BY end_timestamp DESC
-              LIMIT 1",
-            (),
-        )?;
+              WHERE id = :trip_id",
+            params! {
+                "trip_id" => trip_id,
+            },
+        ).map_err(|e| format!("Database query error: {}", e))?;
         
-        if let Some(mut row) = row {
-            let id: i64 = row.take("id").ok_or("Missing id")?;
-            let description: String = row.take("description").ok_or("Missing description")?;
-            let start_ts: String = row.take("start_ts").ok_or("Missing start_ts")?;
-            let end_ts: String = row.take("end_ts").ok_or("Missing end_ts")?;
-            let total_distance_sailed: f64 = row.take("total_distance_sailed").ok_or("Missing total_distance_sailed")?;
-            let total_distance_motoring: f64 = row.take("total_distance_motoring").ok_or("Missing total_distance_motoring")?;
-            let total_time_sailing: u64 = row.take("total_time_sailing").ok_or("Missing total_time_sailing")?;
-            let total_time_motoring: u64 = row.take("total_time_motoring").ok_or("Missing total_time_motoring")?;
-            let total_time_moored: u64 = row.take("total_time_moored").ok_or("Missing total_time_moored")?;
-            
-            // Parse timestamps
-            let start_dt = NaiveDateTime::parse_from_str(&start_ts, "%Y-%m-%d %H:%M:%S%.6f")?;
-            let end_dt = NaiveDateTime::parse_from_str(&end_ts, "%Y-%m-%d %H:%M:%S%.6f")?;
-            
-            // Convert to SystemTime then to Instant (approximate)
-            let start_datetime = chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(start_dt, chrono::Utc);
-            let end_datetime = chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(end_dt, chrono::Utc);
-            let start_timestamp = SystemTime::from(start_datetime);
-            let end_timestamp = SystemTime::from(end_datetime);
-
-            Ok(Some(Trip {
-                id: Some(id),
-                description,
-                start_timestamp,
-                end_timestamp,
-                total_distance_sailed,
-                total_distance_motoring,
-                total_time_sailing,
-                total_time_motoring,
-                total_time_moored,
-            }))
-        } else {
-            Ok(None)
-        }
-    }
-    
-    /// Attempt to reconnect to the database with exponential backoff
-    /// Returns Some(VesselDatabase) if successful, None if all retries fail
-    pub fn reconnect_with_retry(db_url: &str, max_retries: u32) -> Option<Self> {
-        for attempt in 1..=max_retries {
-            warn!("Attempting to reconnect to database (attempt {}/{})...", attempt, max_retries);
-            match Self::new(db_url) {
-                Ok(db) => {
-                    info!("Database reconnection successful");
-                    return Some(db);
-                }
-                Err(e) => {
-                    warn!("Database reconnection attempt {} failed: {}", attempt, e);
-                    if attempt < max_retries {
-                        let wait_time = std::cmp::min(2_u64.pow(attempt - 1), 30); // Exponential backoff, max 30s
-                        warn!("Waiting {} seconds before retry...", wait_time);
-                        std::thread::sleep(Duration::from_secs(wait_time));
-                    }
-                }
+        if let Some(row) = row {
+            let trip = TripSummary {
+                id: row.get("id").unwrap_or(0),
+                description: row.get::<String, _>("description").unwrap_or_default(),
+                start_date: row.get::<String, _>("start_ts").unwrap_or_default(),
+                end_date: row.get::<String, _>("end_ts").unwrap_or_default(),
+                total_distance_nm: row.get::<f64, _>("total_distance").unwrap_or(0.0),
+                total_time_ms: row.get::<i64, _>("total_time").unwrap_or(0),
+                sailing_time_ms: row.get::<i64, _>("total_time_sailing").unwrap_or(0),
+                motoring_time_ms: row.get::<i64, _>("total_time_motoring").unwrap_or(0),
+                moored_time_ms: row.get::<i64, _>("total_time_moored").unwrap_or(0),
+                sailing_distance_nm: row.get::<f64, _>("total_distance_sailed").unwrap_or(0.0),
+                motoring_distance_nm: row.get::<f64, _>("total_distance_motoring").unwrap_or(0.0),
+                start_date_local: None,
+                end_date_local: None,
+            };
+            Ok(Some(trip))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Fetch trips with optional filtering
+    fn fetch_trips(&self, year: Option<i32>, last_months: Option<u32>, since: Option<&str>) -> Result<Vec<TripSummary>, Box<dyn std::error::Error>> {
+        let query = build_trips_query(year, last_months, since.is_some());
+
+        let mut conn = self.get_conn()
+            .map_err(|e| format!("Database connection error: {}", e))?;
+
+        let results: Vec<mysql::Row> = match since {
+            Some(since) => conn.exec(&query, params! { "since" => since }),
+            None => conn.query(&query),
+        }.map_err(|e| format!("Database query error: {}", e))?;
+
+        let trips = results
+            .iter()
+            .map(|row| TripSummary {
+                id: row.get("id").unwrap_or(0),
+                description: row.get::<String, _>("description").unwrap_or_default(),
+                start_date: row.get::<String, _>("start_ts").unwrap_or_default(),
+                end_date: row.get::<String, _>("end_ts").unwrap_or_default(),
+                total_distance_nm: row.get::<f64, _>("total_distance").unwrap_or(0.0),
+                total_time_ms: row.get::<i64, _>("total_time").unwrap_or(0),
+                sailing_time_ms: row.get::<i64, _>("total_time_sailing").unwrap_or(0),
+                motoring_time_ms: row.get::<i64, _>("total_time_motoring").unwrap_or(0),
+                moored_time_ms: row.get::<i64, _>("total_time_moored").unwrap_or(0),
+                sailing_distance_nm: row.get::<f64, _>("total_distance_sailed").unwrap_or(0.0),
+                motoring_distance_nm: row.get::<f64, _>("total_distance_motoring").unwrap_or(0.0),
+                start_date_local: None,
+                end_date_local: None,
+            })
+            .collect();
+
+        Ok(trips)
+    }
+
+    /// Fetch vessel track data by trip_id or date range
+    fn fetch_track(&self, trip_id: Option<u32>, start: Option<&str>, end: Option<&str>, paging: TrackPaging) -> Result<Vec<TrackPoint>, Box<dyn std::error::Error>> {
+        let mut conn = self.get_conn()
+            .map_err(|e| format!("Database connection error: {}", e))?;
+
+        let track = if let Some(trip_id) = trip_id {
+            // Get trip date range and fetch vessel_status data for that period
+            conn.exec_map(
+                format!(
+                    "SELECT DATE_FORMAT(vs.timestamp, '%Y-%m-%d %H:%i:%S') as timestamp,
+                            vs.latitude, vs.longitude, vs.average_speed_kn, vs.max_speed_kn, 
+                            vs.is_moored, vs.engine_on 
+                     FROM vessel_status vs
+                     JOIN trips t ON vs.timestamp BETWEEN t.start_timestamp AND COALESCE(t.end_timestamp, NOW())
+                     WHERE t.id = :trip_id
+                     ORDER BY vs.timestamp{}",
+                    paging.limit_clause(MYSQL_NO_LIMIT)
+                ),
+                params! {
+                    "trip_id" => trip_id,
+                },
+                track_point_from_row,
+            )
+        } else if let (Some(start), Some(end)) = (start, end) {
+            conn.exec_map(
+                format!(
+                    "SELECT DATE_FORMAT(timestamp, '%Y-%m-%d %H:%i:%S') as timestamp,
+                            latitude, longitude, average_speed_kn, max_speed_kn, is_moored, engine_on 
+                     FROM vessel_status WHERE timestamp BETWEEN :start AND :end ORDER BY timestamp{}",
+                    paging.limit_clause(MYSQL_NO_LIMIT)
+                ),
+                params! {
+                    "start" => start,
+                    "end" => end,
+                },
+                track_point_from_row,
+            )
+        } else {
+            return Err("Either trip_id or both start and end timestamps are required".into());
+        }.map_err(|e| format!("Database query error: {}", e))?;
+
+        Ok(paging.downsample(track))
+    }
+
+    /// Fetch environmental metrics by metric_id with optional trip_id or date range
+    fn fetch_metrics(&self, metric: &str, trip_id: Option<u32>, start: Option<&str>, end: Option<&str>, bucket: Option<MetricBucket>) -> Result<Vec<WebMetricData>, Box<dyn std::error::Error>> {
+        // Only known metrics reach the database, by name ("pressure") or by id ("1")
+        let metric_id = MetricId::parse(metric)
+            .ok_or_else(|| format!("Unknown metric: {}", metric))?
+            .as_u8();
+
+        let mut conn = self.get_conn()
+            .map_err(|e| format!("Database connection error: {}", e))?;
+
+        let metrics = if let Some(trip_id) = trip_id {
+            conn.exec_map(
+                build_metrics_query(SqlDialect::MySql, true, bucket),
+                params! {
+                    "trip_id" => trip_id,
+                    "metric_id" => metric_id,
+                },
+                metric_data_from_row,
+            )
+        } else if let (Some(start), Some(end)) = (start, end) {
+            conn.exec_map(
+                build_metrics_query(SqlDialect::MySql, false, bucket),
+                params! {
+                    "metric_id" => metric_id,
+                    "start" => start,
+                    "end" => end,
+                },
+                metric_data_from_row,
+            )
+        } else {
+            return Err("Either trip_id or both start and end timestamps are required".into());
+        }.map_err(|e| format!("Database query error: {}", e))?;
+
+        Ok(metrics)
+    }
+
+    fn fetch_latest_status(&self) -> Result<CurrentStatus, Box<dyn std::error::Error>> {
+        let mut conn = self.get_conn()
+            .map_err(|e| format!("Database connection error: {}", e))?;
+
+        let status: Option<mysql::Row> = conn.exec_first(
+            r"SELECT DATE_FORMAT(timestamp, '%Y-%m-%d %H:%i:%S') as timestamp,
+                     latitude, longitude, average_speed_kn, max_speed_kn, is_moored, engine_on
+              FROM vessel_status ORDER BY timestamp DESC LIMIT 1",
+            (),
+        ).map_err(|e| format!("Database query error: {}", e))?;
+
+        let mut metrics = BTreeMap::new();
+        for metric in MetricId::ALL_METRICS {
+            let row: Option<mysql::Row> = conn.exec_first(
+                r"SELECT DATE_FORMAT(timestamp, '%Y-%m-%d %H:%i:%S') as timestamp,
+                         metric_id, value_avg as avg_value, value_max as max_value, value_min as min_value,
+                         value_count as count
+                  FROM environmental_data
+                  WHERE metric_id = :metric_id
+                  ORDER BY timestamp DESC LIMIT 1",
+                params! { "metric_id" => metric.as_u8() },
+            ).map_err(|e| format!("Database query error: {}", e))?;
+            if let Some(row) = row {
+                metrics.insert(metric.name().to_string(), metric_data_from_row(row));
             }
         }
-        warn!("Failed to reconnect to database after {} attempts", max_retries);
-        None
+
+        Ok(CurrentStatus {
+            status: status.map(track_point_from_row),
+            metrics,
+        })
+    }
+
+    fn fetch_stats(&self, year: Option<i32>) -> Result<VesselStats, Box<dyn std::error::Error>> {
+        let mut conn = self.get_conn()
+            .map_err(|e| format!("Database connection error: {}", e))?;
+
+        let trips: Option<mysql::Row> = conn.exec_first(
+            r"SELECT COUNT(*) as trip_count,
+                     COALESCE(SUM(total_distance_sailed + total_distance_motoring), 0) as total_distance,
+                     CAST(COALESCE(SUM(total_time_sailing), 0) AS SIGNED) as total_time_sailing,
+                     CAST(COALESCE(SUM(total_time_motoring), 0) AS SIGNED) as total_time_motoring
+              FROM trips
+              WHERE :year IS NULL OR YEAR(start_timestamp) = :year",
+            params! {
+                "year" => year,
+            },
+        ).map_err(|e| format!("Database query error: {}", e))?;
+
+        let status: Option<mysql::Row> = conn.exec_first(
+            r"SELECT COALESCE(MAX(max_speed_kn), 0) as max_speed,
+                     COUNT(DISTINCT CASE WHEN is_moored = 0 THEN DATE(timestamp) END) as days_underway
+              FROM vessel_status
+              WHERE :year IS NULL OR YEAR(timestamp) = :year",
+            params! {
+                "year" => year,
+            },
+        ).map_err(|e| format!("Database query error: {}", e))?;
+
+        let mut stats = VesselStats::default();
+        if let Some(row) = trips {
+            stats.trip_count = row.get("trip_count").unwrap_or(0);
+            stats.total_distance_nm = row.get::<f64, _>("total_distance").unwrap_or(0.0);
+            stats.sailing_time_ms = row.get::<i64, _>("total_time_sailing").unwrap_or(0);
+            stats.motoring_time_ms = row.get::<i64, _>("total_time_motoring").unwrap_or(0);
+        }
+        if let Some(row) = status {
+            stats.max_speed_kn = row.get::<f64, _>("max_speed").unwrap_or(0.0);
+            stats.days_underway = row.get("days_underway").unwrap_or(0);
+        }
+        Ok(stats)
+    }
+
+    fn insert_can_error_bucket(&self, bucket: &CanErrorBucket) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conn = self.get_conn()
+            .map_err(|e| format!("Database connection error: {}", e))?;
+        conn.exec_drop(
+            r"INSERT INTO can_error_stats (hour, can_frames, can_errors)
+              VALUES (:hour, :can_frames, :can_errors)
+              ON DUPLICATE KEY UPDATE can_frames = can_frames + VALUES(can_frames), can_errors = can_errors + VALUES(can_errors)",
+            params! {
+                "hour" => &bucket.hour,
+                "can_frames" => bucket.can_frames,
+                "can_errors" => bucket.can_errors,
+            },
+        ).map_err(|e| format!("Database query error: {}", e))?;
+        Ok(())
+    }
+
+    fn fetch_can_error_buckets(&self, start: Option<&str>, end: Option<&str>) -> Result<Vec<CanErrorBucket>, Box<dyn std::error::Error>> {
+        let mut conn = self.get_conn()
+            .map_err(|e| format!("Database connection error: {}", e))?;
+        let buckets = conn.exec_map(
+            r"SELECT DATE_FORMAT(hour, '%Y-%m-%d %H:%i:%S') as hour, can_frames, can_errors
+              FROM can_error_stats
+              WHERE (:start IS NULL OR hour >= :start) AND (:end IS NULL OR hour <= :end)
+              ORDER BY hour",
+            params! {
+                "start" => start,
+                "end" => end,
+            },
+            |(hour, can_frames, can_errors)| CanErrorBucket { hour, can_frames, can_errors },
+        ).map_err(|e| format!("Database query error: {}", e))?;
+        Ok(buckets)
+    }
+
+    fn start_session(&self, name: &str, now: SystemTime) -> Result<Session, Box<dyn std::error::Error>> {
+        let timestamp = chrono::DateTime::<chrono::Utc>::from(now);
+        let mut conn = self.get_conn()
+            .map_err(|e| format!("Database connection error: {}", e))?;
+        let mut tx = conn.start_transaction(TxOpts::default())?;
+        tx.exec_drop(
+            "UPDATE sessions SET end_timestamp = :now WHERE end_timestamp IS NULL",
+            params! { "now" => timestamp.format("%Y-%m-%d %H:%M:%S%.3f").to_string() },
+        )?;
+        tx.exec_drop(
+            "INSERT INTO sessions (name, start_timestamp) VALUES (:name, :now)",
+            params! {
+                "name" => name,
+                "now" => timestamp.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+            },
+        )?;
+        let id = tx.last_insert_id().map(|id| id as i64).ok_or("Missing session id")?;
+        tx.commit()?;
+        Ok(Session {
+            id,
+            name: name.to_string(),
+            start_date: timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+            end_date: None,
+        })
+    }
+
+    fn stop_session(&self, now: SystemTime) -> Result<Option<Session>, Box<dyn std::error::Error>> {
+        let timestamp = chrono::DateTime::<chrono::Utc>::from(now);
+        let mut conn = self.get_conn()
+            .map_err(|e| format!("Database connection error: {}", e))?;
+        let mut tx = conn.start_transaction(TxOpts::default())?;
+        let active: Option<mysql::Row> = tx.exec_first(
+            r"SELECT id, name, DATE_FORMAT(start_timestamp, '%Y-%m-%d %H:%i:%S') as start_ts, NULL as end_ts
+              FROM sessions WHERE end_timestamp IS NULL ORDER BY id DESC LIMIT 1",
+            (),
+        )?;
+        let Some(mut session) = active.map(session_from_row) else {
+            return Ok(None);
+        };
+        tx.exec_drop(
+            "UPDATE sessions SET end_timestamp = :now WHERE end_timestamp IS NULL",
+            params! { "now" => timestamp.format("%Y-%m-%d %H:%M:%S%.3f").to_string() },
+        )?;
+        tx.commit()?;
+        session.end_date = Some(timestamp.format("%Y-%m-%d %H:%M:%S").to_string());
+        Ok(Some(session))
+    }
+
+    fn fetch_session(&self, session_id: i64) -> Result<Option<Session>, Box<dyn std::error::Error>> {
+        let mut conn = self.get_conn()
+            .map_err(|e| format!("Database connection error: {}", e))?;
+        let row: Option<mysql::Row> = conn.exec_first(
+            r"SELECT id, name,
+                     DATE_FORMAT(start_timestamp, '%Y-%m-%d %H:%i:%S') as start_ts,
+                     DATE_FORMAT(end_timestamp, '%Y-%m-%d %H:%i:%S') as end_ts
+              FROM sessions WHERE id = :session_id",
+            params! { "session_id" => session_id },
+        ).map_err(|e| format!("Database query error: {}", e))?;
+        Ok(row.map(session_from_row))
+    }
+
+    fn fetch_session_track(&self, session_id: i64) -> Result<Vec<TrackPoint>, Box<dyn std::error::Error>> {
+        let mut conn = self.get_conn()
+            .map_err(|e| format!("Database connection error: {}", e))?;
+        let track = conn.exec_map(
+            r"SELECT DATE_FORMAT(timestamp, '%Y-%m-%d %H:%i:%S') as timestamp,
+                     latitude, longitude, average_speed_kn, max_speed_kn, is_moored, engine_on
+              FROM vessel_status WHERE session_id = :session_id ORDER BY timestamp",
+            params! { "session_id" => session_id },
+            track_point_from_row,
+        ).map_err(|e| format!("Database query error: {}", e))?;
+        Ok(track)
+    }
+
+    fn fetch_session_metrics(&self, metric: &str, session_id: i64) -> Result<Vec<WebMetricData>, Box<dyn std::error::Error>> {
+        let metric_id = MetricId::parse(metric)
+            .ok_or_else(|| format!("Unknown metric: {}", metric))?
+            .as_u8();
+        let mut conn = self.get_conn()
+            .map_err(|e| format!("Database connection error: {}", e))?;
+        let metrics = conn.exec_map(
+            r"SELECT DATE_FORMAT(timestamp, '%Y-%m-%d %H:%i:%S') as timestamp,
+                     metric_id, value_avg as avg_value, value_max as max_value, value_min as min_value,
+                     value_count as count
+              FROM environmental_data
+              WHERE session_id = :session_id AND metric_id = :metric_id
+              ORDER BY timestamp",
+            params! {
+                "session_id" => session_id,
+                "metric_id" => metric_id,
+            },
+            metric_data_from_row,
+        ).map_err(|e| format!("Database query error: {}", e))?;
+        Ok(metrics)
     }
 }
 
@@ -348,8 +1394,8 @@ impl HealthCheckManager {
     /// Returns the updated database connection (may be None if reconnection fails)
     pub fn check_and_reconnect(
         &mut self,
-        db: &mut Option<VesselDatabase>,
-        db_url: &str,
+        db: &mut Option<Arc<dyn Database>>,
+        connection: &DatabaseConnectionConfig,
     ) -> bool {
         if !self.should_check() {
             return false;
@@ -364,10 +1410,22 @@ impl HealthCheckManager {
                 Err(e) => {
                     warn!("[DB Health] Connection check failed: {}", e);
                     warn!("Attempting to reconnect to database...");
-                    *db = VesselDatabase::reconnect_with_retry(db_url, 3);
+                    *db = reconnect_with_retry(connection, 3);
                 }
             }
             did_check = true;
+        } else {
+            // Not connected (e.g. database down at startup or after failed retries), try once per check
+            match connect(connection) {
+                Ok(database) => {
+                    info!("[DB Health] Database connection established");
+                    *db = Some(database);
+                    did_check = true;
+                }
+                Err(e) => {
+                    warn!("[DB Health] Database still unavailable: {}", e);
+                }
+            }
         }
         
         self.reset();
@@ -375,226 +1433,187 @@ impl HealthCheckManager {
     }
 }
 
-// Web API query structures
-#[derive(Debug, serde::Serialize)]
-pub struct TripSummary {
-    pub id: u32,
-    pub description: String,
-    pub start_date: String,
-    pub end_date: String,
-    pub total_distance_nm: f64,
-    pub total_time_ms: i64,
-    pub sailing_time_ms: i64,
-    pub motoring_time_ms: i64,
-    pub moored_time_ms: i64,
-    pub sailing_distance_nm: f64,
-    pub motoring_distance_nm: f64,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-#[derive(Debug, serde::Serialize)]
-pub struct TrackPoint {
-    pub timestamp: String,
-    pub latitude: f64,
-    pub longitude: f64,
-    pub avg_speed_kn: f64,
-    pub max_speed_kn: f64,
-    pub moored: bool,
-    pub engine_on: bool,
-}
+    fn sample_status(time: SystemTime) -> VesselStatusOperation {
+        VesselStatusOperation {
+            time,
+            latitude: 43.5,
+            longitude: 10.2,
+            average_speed_kn: 5.5,
+            max_speed_kn: 6.5,
+            is_moored: false,
+            engine_on: false,
+            total_distance_nm: 0.1,
+            total_time_ms: 30000,
+            average_wind_speed_kn: Some(12.0),
+            wind_speed_variance: None,
+            average_wind_angle_deg: Some(45.0),
+            wind_angle_variance: None,
+            cog_deg: Some(180.0),
+            average_heading_deg: None,
+            quality_score: None,
+        }
+    }
 
-#[derive(Debug, serde::Serialize)]
-pub struct WebMetricData {
-    pub timestamp: String,
-    pub metric_id: String,
-    pub avg_value: Option<f64>,
-    pub max_value: Option<f64>,
-    pub min_value: Option<f64>,
-    pub count: Option<u32>,
-}
+    #[test]
+    fn test_mysql_opts_from_config() {
+        let connection = DatabaseConnectionConfig {
+            pool_min: 1,
+            pool_max: 3,
+            connect_timeout_ms: 2000,
+            ..DatabaseConnectionConfig::default()
+        };
+        let opts = mysql_opts(&connection).unwrap();
+        let constraints = opts.get_pool_opts().constraints();
+        assert_eq!(constraints.min(), 1);
+        assert_eq!(constraints.max(), 3);
+        assert_eq!(opts.get_tcp_connect_timeout().map(|t| t.as_millis()), Some(2000));
+        assert_eq!(opts.get_read_timeout().map(|t| t.as_millis()), Some(30000));
+        assert_eq!(opts.get_db_name(), Some("nmea_router"));
 
-impl VesselDatabase {
+        let invalid = DatabaseConnectionConfig { pool_min: 4, pool_max: 2, ..DatabaseConnectionConfig::default() };
+        assert!(mysql_opts(&invalid).is_err());
+    }
 
-    pub fn fetch_trip(&self, trip_id: u32) -> Result<Option<TripSummary>, Box<dyn std::error::Error>> {
-        let mut conn = self.pool.get_conn()
-            .map_err(|e| format!("Database connection error: {}", e))?;
-        
-        let row: Option<mysql::Row> = conn.exec_first(
-            r"SELECT id, description, 
-                     DATE_FORMAT(start_timestamp, '%Y-%m-%d %H:%i:%S.%f') as start_ts,
-                     DATE_FORMAT(end_timestamp, '%Y-%m-%d %H:%i:%S.%f') as end_ts,
-                     total_distance_sailed, total_distance_motoring,
-                     (total_distance_sailed + total_distance_motoring) as total_distance,
-                     total_time_sailing, total_time_motoring, total_time_moored
-              FROM trips
-              WHERE id = :trip_id",
-            params! {
-                "trip_id" => trip_id,
-            },
-        ).map_err(|e| format!("Database query error: {}", e))?;
-        
-        if let Some(row) = row {
-            let trip = TripSummary {
-                id: row.get("id").unwrap_or(0),
-                description: row.get::<String, _>("description").unwrap_or_default(),
-                start_date: row.get::<String, _>("start_ts").unwrap_or_default(),
-                end_date: row.get::<String, _>("end_ts").unwrap_or_default(),
-                total_distance_nm: row.get::<f64, _>("total_distance").unwrap_or(0.0),
-                total_time_ms: row.get::<i64, _>("total_time").unwrap_or(0),
-                sailing_time_ms: row.get::<i64, _>("total_time_sailing").unwrap_or(0),
-                motoring_time_ms: row.get::<i64, _>("total_time_motoring").unwrap_or(0),
-                moored_time_ms: row.get::<i64, _>("total_time_moored").unwrap_or(0),
-                sailing_distance_nm: row.get::<f64, _>("total_distance_sailed").unwrap_or(0.0),
-                motoring_distance_nm: row.get::<f64, _>("total_distance_motoring").unwrap_or(0.0),
-            };
-            Ok(Some(trip))
-        } else {
-            Ok(None)
-        }
+    #[test]
+    fn test_build_trips_query() {
+        let query = build_trips_query(Some(2024), None, false);
+        assert!(query.contains("YEAR(start_timestamp) = 2024"));
+        assert!(!query.contains(":since"));
+
+        let query = build_trips_query(None, Some(3), true);
+        assert!(query.contains("INTERVAL 3 MONTH"));
+        assert!(query.contains("AND end_timestamp > :since ORDER BY start_timestamp DESC"));
+        assert!(build_trips_query(None, None, true).contains("INTERVAL 12 MONTH"));
     }
 
-    /// Fetch trips with optional filtering
-    pub fn fetch_trips(&self, year: Option<i32>, last_months: Option<u32>) -> Result<Vec<TripSummary>, Box<dyn std::error::Error>> {
-        let mut query = String::from(
-            "SELECT id, 
-                    description,
-                    DATE_FORMAT(start_timestamp, '%Y-%m-%d %H:%i:%S') as start_ts,
-                    DATE_FORMAT(end_timestamp, '%Y-%m-%d %H:%i:%S') as end_ts,
-                    (total_distance_sailed + total_distance_motoring) as total_distance,
-                    (total_time_sailing + total_time_motoring + total_time_moored) as total_time,
-                    total_time_sailing as total_time_sailing,
-                    total_time_motoring as total_time_motoring,
-                    total_time_moored as total_time_moored,
-                    total_distance_sailed as total_distance_sailed,
-                    total_distance_motoring as total_distance_motoring
-             FROM trips WHERE "
-        );
-
-        if let Some(year) = year {
-            query.push_str(&format!(" YEAR(start_timestamp) = {}", year));
-        } else if let Some(months) = last_months {
-            query.push_str(&format!(" start_timestamp >= DATE_SUB(NOW(), INTERVAL {} MONTH)", months));
-        } else {
-            query.push_str(&format!(" start_timestamp >= DATE_SUB(NOW(), INTERVAL {} MONTH)", 12)); // default last 12 months
-        }
+    #[test]
+    fn test_build_metrics_query_raw() {
+        let query = build_metrics_query(SqlDialect::MySql, false, None);
+        assert!(!query.contains("GROUP BY"));
+        assert!(query.contains("e.timestamp BETWEEN :start AND :end"));
+    }
 
-        query.push_str(" ORDER BY start_timestamp DESC");
+    #[test]
+    fn test_build_metrics_query_day_bucket() {
+        let query = build_metrics_query(SqlDialect::MySql, true, Some(MetricBucket::Day));
+        assert!(query.contains("GROUP BY DATE_FORMAT(e.timestamp, '%Y-%m-%d 00:00:00')"));
+        assert!(query.contains("SUM(e.value_avg * e.value_count) / SUM(e.value_count) as avg_value"));
+        assert!(query.contains("MAX(e.value_max) as max_value"));
+        assert!(query.contains("MIN(e.value_min) as min_value"));
+        assert!(query.contains("t.id = :trip_id"));
+    }
 
-        let mut conn = self.pool.get_conn()
-            .map_err(|e| format!("Database connection error: {}", e))?;
-        
-        let results: Vec<mysql::Row> = conn.query(&query)
-            .map_err(|e| format!("Database query error: {}", e))?;
+    #[test]
+    fn test_build_status_batch_insert() {
+        let query = build_status_batch_insert(3);
+        assert!(query.starts_with("INSERT INTO vessel_status"));
+        assert_eq!(query.matches('?').count(), 3 * STATUS_BATCH_COLUMNS);
+        assert_eq!(query.matches("(?,").count(), 3);
 
-        let trips = results
-            .iter()
-            .map(|row| TripSummary {
-                id: row.get("id").unwrap_or(0),
-                description: row.get::<String, _>("description").unwrap_or_default(),
-                start_date: row.get::<String, _>("start_ts").unwrap_or_default(),
-                end_date: row.get::<String, _>("end_ts").unwrap_or_default(),
-                total_distance_nm: row.get::<f64, _>("total_distance").unwrap_or(0.0),
-                total_time_ms: row.get::<i64, _>("total_time").unwrap_or(0),
-                sailing_time_ms: row.get::<i64, _>("total_time_sailing").unwrap_or(0),
-                motoring_time_ms: row.get::<i64, _>("total_time_motoring").unwrap_or(0),
-                moored_time_ms: row.get::<i64, _>("total_time_moored").unwrap_or(0),
-                sailing_distance_nm: row.get::<f64, _>("total_distance_sailed").unwrap_or(0.0),
-                motoring_distance_nm: row.get::<f64, _>("total_distance_motoring").unwrap_or(0.0),
-            })
-            .collect();
+        // Column list matches the number of bound values
+        let columns = query.split('(').nth(1).unwrap().split(')').next().unwrap();
+        assert_eq!(columns.split(',').count(), STATUS_BATCH_COLUMNS);
+    }
 
-        Ok(trips)
+    #[test]
+    fn test_track_limit_clause() {
+        let paging = |limit, offset| TrackPaging { limit, offset, max_points: None };
+        assert_eq!(TrackPaging::default().limit_clause(MYSQL_NO_LIMIT), "");
+        assert_eq!(paging(Some(100), None).limit_clause(MYSQL_NO_LIMIT), " LIMIT 100");
+        assert_eq!(paging(Some(100), Some(200)).limit_clause(MYSQL_NO_LIMIT), " LIMIT 100 OFFSET 200");
+        assert_eq!(paging(None, Some(200)).limit_clause(MYSQL_NO_LIMIT), " LIMIT 18446744073709551615 OFFSET 200");
+        assert_eq!(paging(None, Some(200)).limit_clause("-1"), " LIMIT -1 OFFSET 200");
     }
 
-    /// Fetch vessel track data by trip_id or date range
-    pub fn fetch_track(&self, trip_id: Option<u32>, start: Option<&str>, end: Option<&str>) -> Result<Vec<TrackPoint>, Box<dyn std::error::Error>> {
-        let query = if let Some(trip_id) = trip_id {
-            // Get trip date range and fetch vessel_status data for that period
-            format!(
-                "SELECT DATE_FORMAT(vs.timestamp, '%Y-%m-%d %H:%i:%S') as timestamp,
-                        vs.latitude, vs.longitude, vs.average_speed_kn, vs.max_speed_kn, 
-                        vs.is_moored, vs.engine_on 
-                 FROM vessel_status vs
-                 JOIN trips t ON vs.timestamp BETWEEN t.start_timestamp AND COALESCE(t.end_timestamp, NOW())
-                 WHERE t.id = {}
-                 ORDER BY vs.timestamp",
-                trip_id
-            )
-        } else if let (Some(start), Some(end)) = (start, end) {
-            format!(
-                "SELECT DATE_FORMAT(timestamp, '%Y-%m-%d %H:%i:%S') as timestamp,
-                        latitude, longitude, average_speed_kn, max_speed_kn, is_moored, engine_on 
-                 FROM vessel_status WHERE timestamp BETWEEN '{}' AND '{}' ORDER BY timestamp",
-                start, end
-            )
-        } else {
-            return Err("Either trip_id or both start and end timestamps are required".into());
+    #[test]
+    fn test_local_timestamp_fixed_offset() {
+        // IANA "Etc/GMT-2" is UTC+2 all year round
+        let plus_two: Tz = "Etc/GMT-2".parse().unwrap();
+        assert_eq!(local_timestamp("2024-06-01 10:00:00", plus_two).as_deref(), Some("2024-06-01 12:00:00"));
+        assert_eq!(local_timestamp("2024-06-01 23:30:00.250", plus_two).as_deref(), Some("2024-06-02 01:30:00"));
+        let minus_five: Tz = "Etc/GMT+5".parse().unwrap();
+        assert_eq!(local_timestamp("2024-01-01 03:00:00", minus_five).as_deref(), Some("2023-12-31 22:00:00"));
+        assert_eq!(local_timestamp("2024-06-01 10:00:00", Tz::UTC).as_deref(), Some("2024-06-01 10:00:00"));
+        assert!(local_timestamp("not a timestamp", plus_two).is_none());
+
+        let mut point = TrackPoint {
+            timestamp: "2024-06-01 10:00:00".to_string(),
+            latitude: 0.0,
+            longitude: 0.0,
+            avg_speed_kn: 0.0,
+            max_speed_kn: 0.0,
+            moored: false,
+            engine_on: false,
+            timestamp_local: None,
         };
+        point.localize(plus_two);
+        assert_eq!(point.timestamp_local.as_deref(), Some("2024-06-01 12:00:00"));
+    }
 
-        let mut conn = self.pool.get_conn()
-            .map_err(|e| format!("Database connection error: {}", e))?;
-        
-        let results: Vec<mysql::Row> = conn.query(&query)
-            .map_err(|e| format!("Database query error: {}", e))?;
+    #[test]
+    fn test_downsample_stride() {
+        assert_eq!(downsample_stride(1000, 1000), 1);
+        assert_eq!(downsample_stride(1001, 1000), 2);
+        assert_eq!(downsample_stride(3000, 1000), 3);
+        assert_eq!(downsample_stride(10, 4), 3);
+        assert_eq!(downsample_stride(10, 0), 10);
+        assert_eq!(downsample_stride(0, 5), 1);
 
-        let track = results
-            .iter()
-            .map(|row| TrackPoint {
-                timestamp: row.get::<String, _>("timestamp").unwrap_or_default(),
-                latitude: row.get::<f64, _>("latitude").unwrap_or(0.0),
-                longitude: row.get::<f64, _>("longitude").unwrap_or(0.0),
-                avg_speed_kn: row.get::<f64, _>("average_speed_kn").unwrap_or(0.0),
-                max_speed_kn: row.get::<f64, _>("max_speed_kn").unwrap_or(0.0),
-                moored: row.get::<i32, _>("is_moored").unwrap_or(0) != 0,
-                engine_on: row.get::<i32, _>("engine_on").unwrap_or(0) != 0,
+        let track: Vec<TrackPoint> = (0..10)
+            .map(|i| TrackPoint {
+                timestamp: i.to_string(),
+                latitude: 0.0,
+                longitude: 0.0,
+                avg_speed_kn: 0.0,
+                max_speed_kn: 0.0,
+                moored: false,
+                engine_on: false,
+                timestamp_local: None,
             })
             .collect();
+        let paging = |max_points| TrackPaging { limit: None, offset: None, max_points };
+        assert_eq!(paging(None).downsample(track.clone()).len(), 10);
+        assert_eq!(paging(Some(20)).downsample(track.clone()).len(), 10);
+        let sampled: Vec<String> = paging(Some(4)).downsample(track).into_iter().map(|p| p.timestamp).collect();
+        assert_eq!(sampled, ["0", "3", "6", "9"]);
+    }
 
-        Ok(track)
+    /// Requires a live database: set NMEA_ROUTER_TEST_DB_URL and run with `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_injection_in_range_is_bound_live() {
+        let url = std::env::var("NMEA_ROUTER_TEST_DB_URL").expect("NMEA_ROUTER_TEST_DB_URL not set");
+        let db = VesselDatabase::new(Opts::from_url(&url).unwrap(), Duration::from_secs(5)).unwrap();
+
+        let injection = "' OR '1'='1";
+        let track = db.fetch_track(None, Some(injection), Some("2000-01-01 00:00:00"), TrackPaging::default()).unwrap();
+        assert!(track.is_empty());
+        let metrics = db.fetch_metrics("pressure", None, Some(injection), Some("2000-01-01 00:00:00"), None).unwrap();
+        assert!(metrics.is_empty());
+        assert!(db.fetch_metrics(injection, None, Some("2024-01-01 00:00:00"), Some("2024-01-02 00:00:00"), None).is_err());
     }
 
-    /// Fetch environmental metrics by metric_id with optional trip_id or date range
-    pub fn fetch_metrics(&self, metric: &str, trip_id: Option<u32>, start: Option<&str>, end: Option<&str>) -> Result<Vec<WebMetricData>, Box<dyn std::error::Error>> {
-        let query = if let Some(trip_id) = trip_id {
-            format!(
-                "SELECT DATE_FORMAT(e.timestamp, '%Y-%m-%d %H:%i:%S') as timestamp,
-                        e.metric_id, e.avg_value, e.max_value, e.min_value, e.count 
-                 FROM environmental_data e 
-                 JOIN vessel_status v ON DATE(e.timestamp) = DATE(v.timestamp) 
-                 WHERE v.trip_id = {} AND e.metric_id = '{}' 
-                 ORDER BY e.timestamp",
-                trip_id, metric
-            )
-        } else if let (Some(start), Some(end)) = (start, end) {
-            format!(
-                "SELECT DATE_FORMAT(timestamp, '%Y-%m-%d %H:%i:%S') as timestamp,
-                        metric_id, avg_value, max_value, min_value, count 
-                 FROM environmental_data 
-                 WHERE metric_id = '{}' AND timestamp BETWEEN '{}' AND '{}' 
-                 ORDER BY timestamp",
-                metric, start, end
-            )
-        } else {
-            return Err("Either trip_id or both start and end timestamps are required".into());
+    /// Requires a live database: set NMEA_ROUTER_TEST_DB_URL and run with `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_insert_status_batch_live() {
+        let url = std::env::var("NMEA_ROUTER_TEST_DB_URL").expect("NMEA_ROUTER_TEST_DB_URL not set");
+        let db = VesselDatabase::new(Opts::from_url(&url).unwrap(), Duration::from_secs(5)).unwrap();
+        let count = |db: &VesselDatabase| -> u64 {
+            db.pool.get_conn().unwrap().query_first("SELECT COUNT(*) FROM vessel_status").unwrap().unwrap()
         };
 
-        let mut conn = self.pool.get_conn()
-            .map_err(|e| format!("Database connection error: {}", e))?;
-        
-        let results: Vec<mysql::Row> = conn.query(&query)
-            .map_err(|e| format!("Database query error: {}", e))?;
-
-        let metrics = results
-            .iter()
-            .map(|row| WebMetricData {
-                timestamp: row.get::<String, _>("timestamp").unwrap_or_default(),
-                metric_id: row.get::<String, _>("metric_id").unwrap_or_default(),
-                avg_value: row.get("avg_value"),
-                max_value: row.get("max_value"),
-                min_value: row.get("min_value"),
-                count: row.get("count"),
-            })
+        let before = count(&db);
+        let now = SystemTime::now();
+        let ops: Vec<VesselStatusOperation> = (0..100)
+            .map(|i| sample_status(now - Duration::from_secs(30 * (100 - i))))
             .collect();
+        db.insert_status_batch(&ops).unwrap();
 
-        Ok(metrics)
+        assert_eq!(count(&db) - before, 100);
     }
 }
@@ -0,0 +1,355 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TrySendError};
+use std::thread::JoinHandle;
+use std::time::SystemTime;
+
+use tracing::{debug, info, warn};
+
+use crate::db::{Database, TripOperation, VesselStatusOperation};
+use crate::environmental_monitor::{MetricData, MetricId};
+use crate::trip::unique_trip_description;
+use crate::write_buffer::{BufferedWrite, WriteBuffer};
+
+/// Writes waiting for the database, further writes are dropped
+pub const DB_WRITER_QUEUE_CAPACITY: usize = 256;
+
+/// Database write handed over to the writer thread
+pub enum DbWrite {
+    /// Vessel status and trip operation, written in a single transaction
+    StatusAndTrip(Box<VesselStatusOperation>, TripOperation),
+    Metric {
+        metric_id: MetricId,
+        timestamp: SystemTime,
+        data: MetricData,
+    },
+}
+
+/// Outcome of a write the main loop needs to know about
+#[derive(Debug, Clone, PartialEq)]
+pub enum DbWriteEvent {
+    /// A trip was created, identified by its start time
+    TripCreated { start: SystemTime, id: i64, description: String },
+}
+
+enum Command {
+    Write(DbWrite),
+    SetDatabase(Option<Arc<dyn Database>>),
+    /// Acknowledged once all the previous commands are processed
+    Sync(Sender<()>),
+    Stop,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueueError {
+    /// The queue is full, the write was dropped
+    Full,
+    /// The writer thread is not running
+    Stopped,
+}
+
+impl fmt::Display for QueueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueueError::Full => write!(f, "database write queue full, write dropped"),
+            QueueError::Stopped => write!(f, "database writer stopped"),
+        }
+    }
+}
+
+impl Error for QueueError {}
+
+/// Sending side of the write queue, shared by the status handlers
+#[derive(Clone)]
+pub struct DbQueue {
+    sender: SyncSender<Command>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl DbQueue {
+    fn bounded(capacity: usize) -> (Self, Receiver<Command>) {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        (Self { sender, dropped: Arc::new(AtomicU64::new(0)) }, receiver)
+    }
+
+    /// Queue a write without blocking, the write is dropped when the queue is full
+    pub fn send(&self, write: DbWrite) -> Result<(), QueueError> {
+        match self.sender.try_send(Command::Write(write)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                Err(QueueError::Full)
+            }
+            Err(TrySendError::Disconnected(_)) => Err(QueueError::Stopped),
+        }
+    }
+
+    /// Number of writes dropped because the queue was full
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Background thread writing vessel status and environmental metrics to the database
+///
+/// The main loop queues the writes and keeps processing CAN frames while the database is slow.
+/// Each vessel status is written with its trip operation in a single transaction, as before.
+/// Writes failing on the database are appended to the write buffer when one is configured.
+pub struct DbWriter {
+    queue: DbQueue,
+    events: Receiver<DbWriteEvent>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DbWriter {
+    pub fn spawn(db: Option<Arc<dyn Database>>, write_buffer: Option<WriteBuffer>, capacity: usize) -> Self {
+        let (queue, receiver) = DbQueue::bounded(capacity);
+        let (event_sender, events) = mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            WriterThread { db, write_buffer, events: event_sender, created_trip: None }.run(receiver);
+        });
+        Self { queue, events, handle: Some(handle) }
+    }
+
+    pub fn queue(&self) -> DbQueue {
+        self.queue.clone()
+    }
+
+    /// Replace the database written to, e.g. after a reconnection
+    pub fn set_database(&self, db: Option<Arc<dyn Database>>) {
+        let _ = self.queue.sender.send(Command::SetDatabase(db));
+    }
+
+    /// Block until all the writes queued so far are processed
+    pub fn sync(&self) {
+        let (ack, done) = mpsc::channel();
+        if self.queue.sender.send(Command::Sync(ack)).is_ok() {
+            let _ = done.recv();
+        }
+    }
+
+    /// Events of the writes processed since the last call
+    pub fn take_events(&self) -> Vec<DbWriteEvent> {
+        self.events.try_iter().collect()
+    }
+
+    /// Process the writes still queued and stop the thread
+    pub fn stop(mut self) {
+        if self.queue.sender.send(Command::Stop).is_ok()
+            && let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        let dropped = self.queue.dropped_count();
+        if dropped > 0 {
+            warn!("{} database writes were dropped because the write queue was full", dropped);
+        }
+    }
+}
+
+struct WriterThread {
+    db: Option<Arc<dyn Database>>,
+    write_buffer: Option<WriteBuffer>,
+    events: Sender<DbWriteEvent>,
+    /// Start time, id and description of the last trip created
+    created_trip: Option<(SystemTime, i64, String)>,
+}
+
+impl WriterThread {
+    fn run(mut self, receiver: Receiver<Command>) {
+        info!("Database writer started");
+        for command in receiver {
+            match command {
+                Command::Write(write) => self.write(write),
+                Command::SetDatabase(db) => self.db = db,
+                Command::Sync(ack) => {
+                    let _ = ack.send(());
+                }
+                Command::Stop => break,
+            }
+        }
+        info!("Database writer stopped");
+    }
+
+    fn write(&mut self, write: DbWrite) {
+        let result = match self.db.clone() {
+            Some(db) => self.apply(db.as_ref(), &write),
+            None => Err("Database unavailable".into()),
+        };
+        if let Err(e) = result {
+            warn!("Error writing to database: {}", e);
+            self.buffer(write);
+        }
+    }
+
+    fn apply(&mut self, db: &dyn Database, write: &DbWrite) -> Result<(), Box<dyn Error>> {
+        match write {
+            DbWrite::StatusAndTrip(status, trip_operation) => {
+                let trip_operation = self.resolve_trip(db, trip_operation);
+                let start = match &trip_operation {
+                    TripOperation::CreateTrip(trip) => Some((trip.start_timestamp, trip.description.clone())),
+                    _ => None,
+                };
+                let (_, new_trip_id) = db.insert_status_and_trip(VesselStatusOperation::clone(status), trip_operation)?;
+                if let (Some(id), Some((start, description))) = (new_trip_id, start) {
+                    info!("Created new trip: {} (ID: {})", description, id);
+                    self.created_trip = Some((start, id, description.clone()));
+                    let _ = self.events.send(DbWriteEvent::TripCreated { start, id, description });
+                }
+            }
+            DbWrite::Metric { metric_id, timestamp, data } => {
+                db.insert_environmental_metrics(data, *metric_id, *timestamp)?;
+                debug!("Environmental metric {} written to database", metric_id.name());
+            }
+        }
+        Ok(())
+    }
+
+    /// The handler learns the id of a new trip only once it is written: the updates queued
+    /// in the meantime carry no id and are matched to the trip created by its start time
+    fn resolve_trip(&self, db: &dyn Database, trip_operation: &TripOperation) -> TripOperation {
+        match trip_operation {
+            TripOperation::CreateTrip(trip) | TripOperation::UpdateTrip(trip) if trip.id.is_none() => {
+                let mut trip = trip.clone();
+                if let Some((start, id, ref description)) = self.created_trip
+                    && start == trip.start_timestamp {
+                    trip.id = Some(id);
+                    trip.description = description.clone();
+                    return TripOperation::UpdateTrip(trip);
+                }
+                // A trip started while the database was unavailable, or a new one
+                let existing = db.fetch_trip_descriptions(&trip.description).unwrap_or_else(|e| {
+                    warn!("Failed to fetch existing trip descriptions: {}", e);
+                    Vec::new()
+                });
+                trip.description = unique_trip_description(&trip.description, &existing);
+                TripOperation::CreateTrip(trip)
+            }
+            TripOperation::CreateTrip(trip) => TripOperation::CreateTrip(trip.clone()),
+            TripOperation::UpdateTrip(trip) => TripOperation::UpdateTrip(trip.clone()),
+            TripOperation::None => TripOperation::None,
+        }
+    }
+
+    /// Keep a failed write in the write buffer, replayed once the database is back
+    fn buffer(&self, write: DbWrite) {
+        let Some(ref buffer) = self.write_buffer else {
+            return;
+        };
+        let buffered = match write {
+            DbWrite::StatusAndTrip(status, _) => BufferedWrite::VesselStatus(*status),
+            DbWrite::Metric { metric_id, timestamp, data } => BufferedWrite::Metric { metric_id, timestamp, data },
+        };
+        if let Err(e) = buffer.append(&buffered) {
+            warn!("Error writing to write buffer: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite_db::SqliteDatabase;
+    use crate::trip::Trip;
+    use std::time::Duration;
+
+    fn status_at(time: SystemTime) -> VesselStatusOperation {
+        VesselStatusOperation {
+            time,
+            latitude: 43.5,
+            longitude: 10.2,
+            average_speed_kn: 5.5,
+            max_speed_kn: 6.5,
+            is_moored: false,
+            engine_on: false,
+            total_distance_nm: 0.05,
+            total_time_ms: 30000,
+            average_wind_speed_kn: None,
+            wind_speed_variance: None,
+            average_wind_angle_deg: None,
+            wind_angle_variance: None,
+            cog_deg: None,
+            average_heading_deg: None,
+            quality_score: Some(1.0),
+        }
+    }
+
+    fn trip(start: SystemTime, distance: f64) -> Trip {
+        let mut trip = Trip::new(start, "Trip 2024-06-01".to_string());
+        trip.update(start + Duration::from_secs(30), distance, 30000, false, false);
+        trip
+    }
+
+    #[test]
+    fn test_writes_applied_in_order() {
+        let db: Arc<dyn Database> = Arc::new(SqliteDatabase::new(":memory:").unwrap());
+        let writer = DbWriter::spawn(Some(db.clone()), None, DB_WRITER_QUEUE_CAPACITY);
+        let queue = writer.queue();
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_717_236_000);
+
+        // The update is queued before the handler knows the id of the trip
+        queue.send(DbWrite::StatusAndTrip(Box::new(status_at(start)), TripOperation::CreateTrip(trip(start, 0.5)))).unwrap();
+        queue.send(DbWrite::StatusAndTrip(Box::new(status_at(start + Duration::from_secs(30))), TripOperation::UpdateTrip(trip(start, 1.5)))).unwrap();
+        let pressure = MetricData { avg: Some(101300.0), max: Some(101300.0), min: Some(101300.0), count: Some(1) };
+        queue.send(DbWrite::Metric { metric_id: MetricId::Pressure, timestamp: start, data: pressure }).unwrap();
+        writer.sync();
+
+        let events = writer.take_events();
+        assert_eq!(events.len(), 1);
+        let DbWriteEvent::TripCreated { start: created_start, id, ref description } = events[0];
+        assert_eq!(created_start, start);
+        assert_eq!(description, "Trip 2024-06-01");
+
+        // A single trip, updated by the second write
+        let last = db.get_last_trip().unwrap().unwrap();
+        assert_eq!(last.id, Some(id));
+        assert!((last.total_distance() - 1.5).abs() < 1e-9);
+        assert_eq!(db.fetch_trip_descriptions("Trip 2024-06-01").unwrap().len(), 1);
+        assert!(writer.take_events().is_empty());
+        writer.stop();
+    }
+
+    #[test]
+    fn test_failed_writes_buffered() {
+        let path = std::env::temp_dir().join(format!("nmea_router_db_writer_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let writer = DbWriter::spawn(None, Some(WriteBuffer::new(&path)), DB_WRITER_QUEUE_CAPACITY);
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_717_236_000);
+
+        writer.queue().send(DbWrite::StatusAndTrip(Box::new(status_at(start)), TripOperation::CreateTrip(trip(start, 0.5)))).unwrap();
+        writer.sync();
+
+        let buffered = WriteBuffer::new(&path).read_all().unwrap();
+        assert_eq!(buffered.len(), 1);
+        assert!(matches!(buffered[0], BufferedWrite::VesselStatus(_)));
+        writer.stop();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_full_queue_drops_writes() {
+        // No writer thread consuming the queue
+        let (queue, receiver) = DbQueue::bounded(2);
+        let start = SystemTime::UNIX_EPOCH;
+        let metric = || DbWrite::Metric {
+            metric_id: MetricId::Humidity,
+            timestamp: start,
+            data: MetricData { avg: Some(60.0), max: Some(60.0), min: Some(60.0), count: Some(1) },
+        };
+
+        assert_eq!(queue.send(metric()), Ok(()));
+        assert_eq!(queue.send(metric()), Ok(()));
+        assert_eq!(queue.send(metric()), Err(QueueError::Full));
+        assert_eq!(queue.send(metric()), Err(QueueError::Full));
+        assert_eq!(queue.dropped_count(), 2);
+
+        // Room again once the writer catches up
+        receiver.recv().unwrap();
+        assert_eq!(queue.send(metric()), Ok(()));
+
+        drop(receiver);
+        assert_eq!(queue.send(metric()), Err(QueueError::Stopped));
+        assert_eq!(queue.dropped_count(), 2);
+    }
+}
@@ -0,0 +1,310 @@
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use nmea2k::pgns::{HeadingReference, N2kMessage, VesselHeading};
+
+use crate::utilities::{destination_point, get_variation_deg, normalize0_360};
+use crate::vessel_monitor::Position;
+
+/// Heading and speed older than this are not used to extrapolate the position
+const MAX_INPUT_AGE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionSource {
+    /// Recent fix from the GNSS
+    Gps,
+    /// Extrapolated from the last fix using heading and speed
+    DeadReckoning,
+}
+
+/// Best available vessel position, either from the GNSS or estimated
+#[derive(Debug, Clone, Copy)]
+pub struct CompositePosition {
+    pub position: Position,
+    pub source: PositionSource,
+    /// Time since the last GNSS fix
+    pub fix_age: Duration,
+}
+
+impl CompositePosition {
+    pub fn is_estimated(&self) -> bool {
+        self.source == PositionSource::DeadReckoning
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Reading {
+    value: f64,
+    timestamp: Instant,
+}
+
+/// Dead reckoning estimator
+///
+/// Bridges brief GPS outages: when position fixes stop arriving, the position
+/// is extrapolated from the last fix using the heading and the water speed (or
+/// the last SOG when no water speed is available). The estimate snaps back to
+/// the GPS as soon as a new fix arrives, and is given up after `max_duration`.
+pub struct DeadReckoning {
+    gps_timeout: Duration,
+    max_duration: Duration,
+    last_fix: Option<(Position, Instant)>,
+    /// Last estimated position, advanced every time heading or speed change
+    estimate: Option<(Position, Instant)>,
+    heading_deg: Option<Reading>,
+    water_speed_kn: Option<Reading>,
+    sog_kn: Option<Reading>,
+    last_source: Option<PositionSource>,
+}
+
+impl DeadReckoning {
+    /// Create a new estimator
+    ///
+    /// # Arguments
+    /// * `gps_timeout` - Time without fixes after which dead reckoning starts
+    /// * `max_duration` - Maximum time without fixes before declaring "no position"
+    pub fn new(gps_timeout: Duration, max_duration: Duration) -> Self {
+        Self {
+            gps_timeout,
+            max_duration,
+            last_fix: None,
+            estimate: None,
+            heading_deg: None,
+            water_speed_kn: None,
+            sog_kn: None,
+            last_source: None,
+        }
+    }
+
+    /// Record a GNSS position fix
+    pub fn update_fix(&mut self, position: Position, now: Instant) {
+        self.last_fix = Some((position, now));
+        self.estimate = Some((position, now));
+    }
+
+    /// Record the true heading in degrees
+    pub fn update_heading(&mut self, heading_deg: f64, now: Instant) {
+        self.advance(now);
+        self.heading_deg = Some(Reading { value: normalize0_360(heading_deg), timestamp: now });
+    }
+
+    /// Record the speed through water in knots
+    pub fn update_water_speed(&mut self, speed_kn: f64, now: Instant) {
+        self.advance(now);
+        self.water_speed_kn = Some(Reading { value: speed_kn, timestamp: now });
+    }
+
+    /// Record the speed over ground in knots
+    pub fn update_sog(&mut self, speed_kn: f64, now: Instant) {
+        self.advance(now);
+        self.sog_kn = Some(Reading { value: speed_kn, timestamp: now });
+    }
+
+    /// Speed used for the extrapolation: water speed if recent, otherwise the last SOG
+    fn speed_kn(&self, since: Instant) -> Option<f64> {
+        let recent = |r: &Option<Reading>| r.filter(|r| since.saturating_duration_since(r.timestamp) <= MAX_INPUT_AGE);
+        recent(&self.water_speed_kn)
+            .or_else(|| recent(&self.sog_kn))
+            .map(|r| r.value)
+    }
+
+    fn heading(&self, since: Instant) -> Option<f64> {
+        self.heading_deg
+            .filter(|r| since.saturating_duration_since(r.timestamp) <= MAX_INPUT_AGE)
+            .map(|r| r.value)
+    }
+
+    /// Extrapolate the estimate from its time to `now` with the current heading and speed
+    fn extrapolate(&self, now: Instant) -> Option<Position> {
+        let (position, timestamp) = self.estimate?;
+        let elapsed = now.saturating_duration_since(timestamp);
+        if elapsed.is_zero() {
+            return Some(position);
+        }
+        let heading = self.heading(timestamp)?;
+        let speed_kn = self.speed_kn(timestamp)?;
+        let distance_nm = speed_kn * elapsed.as_secs_f64() / 3600.0;
+        let (latitude, longitude) = destination_point(position.latitude, position.longitude, heading, distance_nm);
+        Some(Position { latitude, longitude })
+    }
+
+    /// Move the estimate forward to `now`, before heading or speed change
+    fn advance(&mut self, now: Instant) {
+        if let Some(position) = self.extrapolate(now) {
+            self.estimate = Some((position, now));
+        }
+    }
+
+    /// Get the best available position at `now`
+    /// Returns None when there is no fix or the last fix is older than the maximum dead reckoning duration
+    pub fn position_at(&self, now: Instant) -> Option<CompositePosition> {
+        let (fix, fix_time) = self.last_fix?;
+        let fix_age = now.saturating_duration_since(fix_time);
+        if fix_age <= self.gps_timeout {
+            return Some(CompositePosition { position: fix, source: PositionSource::Gps, fix_age });
+        }
+        if fix_age > self.max_duration {
+            return None;
+        }
+        self.extrapolate(now).map(|position| CompositePosition {
+            position,
+            source: PositionSource::DeadReckoning,
+            fix_age,
+        })
+    }
+
+    /// Get the best available position at `now`, logging the transitions between sources
+    pub fn update(&mut self, now: Instant) -> Option<CompositePosition> {
+        let composite = self.position_at(now);
+        let source = composite.map(|c| c.source);
+        if source != self.last_source {
+            match source {
+                Some(PositionSource::Gps) if self.last_source.is_some() => info!("GPS fix restored, dead reckoning stopped"),
+                Some(PositionSource::Gps) => {}
+                Some(PositionSource::DeadReckoning) => warn!("GPS fix lost, estimating position by dead reckoning"),
+                None if self.last_source.is_some() => warn!("No position available: GPS fix lost and dead reckoning not possible"),
+                None => {}
+            }
+            self.last_source = source;
+        }
+        composite
+    }
+
+    fn process_heading(&mut self, heading: &VesselHeading, now: Instant) {
+        let heading_deg = heading.heading.to_degrees();
+        match heading.reference {
+            HeadingReference::True => self.update_heading(heading_deg, now),
+            HeadingReference::Magnetic => {
+                let variation = self.last_fix
+                    .and_then(|(pos, _)| get_variation_deg(pos.latitude, pos.longitude, chrono::Utc::now()).ok())
+                    .unwrap_or(0.0); // Better magnetic than nothing
+                self.update_heading(heading_deg + variation, now);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl nmea2k::MessageHandler for DeadReckoning {
+    fn handle_message(&mut self, frame: &nmea2k::N2kFrame, now: Instant) {
+        match &frame.message {
+            N2kMessage::PositionRapidUpdate(pos) => {
+                self.update_fix(Position { latitude: pos.latitude, longitude: pos.longitude }, now);
+            }
+            N2kMessage::VesselHeading(heading) => {
+                self.process_heading(heading, now);
+            }
+            N2kMessage::SpeedWaterReferenced(speed) => {
+                self.update_water_speed(speed.speed_knots(), now);
+            }
+            N2kMessage::CogSogRapidUpdate(cog_sog) => {
+                self.update_sog(cog_sog.sog_knots(), now);
+            }
+            _ => {} // Ignore messages we're not interested in
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::{haversine_distance_nm, haversine_heading};
+
+    const START: Position = Position { latitude: 43.5, longitude: 10.2 };
+
+    fn estimator() -> DeadReckoning {
+        DeadReckoning::new(Duration::from_secs(3), Duration::from_secs(120))
+    }
+
+    #[test]
+    fn test_gps_position_while_fixes_arrive() {
+        let mut dr = estimator();
+        let now = Instant::now();
+        assert!(dr.position_at(now).is_none());
+
+        dr.update_fix(START, now);
+        let composite = dr.position_at(now + Duration::from_secs(2)).unwrap();
+        assert_eq!(composite.source, PositionSource::Gps);
+        assert!(!composite.is_estimated());
+        assert_eq!(composite.position.latitude, START.latitude);
+    }
+
+    #[test]
+    fn test_dead_reckoning_displacement() {
+        let mut dr = estimator();
+        let t0 = Instant::now();
+        dr.update_fix(START, t0);
+        dr.update_heading(90.0, t0);
+        dr.update_water_speed(6.0, t0);
+
+        // 60s at 6 knots = 0.1 nm heading east
+        let composite = dr.position_at(t0 + Duration::from_secs(60)).unwrap();
+        assert!(composite.is_estimated());
+        assert_eq!(composite.fix_age, Duration::from_secs(60));
+        let distance = haversine_distance_nm(START.latitude, START.longitude, composite.position.latitude, composite.position.longitude);
+        let course = haversine_heading(START.latitude, START.longitude, composite.position.latitude, composite.position.longitude);
+        assert!((distance - 0.1).abs() < 1e-6);
+        assert!((course - 90.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_dead_reckoning_follows_heading_changes() {
+        let mut dr = estimator();
+        let t0 = Instant::now();
+        dr.update_fix(START, t0);
+        dr.update_heading(0.0, t0);
+        dr.update_sog(6.0, t0); // No water speed, SOG at the time of the fix
+
+        // 30s north, then 30s south at the same speed: back to the start
+        for i in 1..=6 {
+            dr.update_heading(0.0, t0 + Duration::from_secs(5 * i));
+            dr.update_sog(6.0, t0 + Duration::from_secs(5 * i));
+        }
+        let t30 = t0 + Duration::from_secs(30);
+        let north = dr.position_at(t30).unwrap().position;
+        assert!((haversine_distance_nm(START.latitude, START.longitude, north.latitude, north.longitude) - 0.05).abs() < 1e-6);
+        assert!(north.latitude > START.latitude);
+
+        for i in 0..=6 {
+            dr.update_heading(180.0, t30 + Duration::from_secs(5 * i));
+            dr.update_sog(6.0, t30 + Duration::from_secs(5 * i));
+        }
+        let back = dr.position_at(t0 + Duration::from_secs(60)).unwrap().position;
+        assert!(haversine_distance_nm(START.latitude, START.longitude, back.latitude, back.longitude) < 1e-6);
+    }
+
+    #[test]
+    fn test_snaps_back_to_gps() {
+        let mut dr = estimator();
+        let t0 = Instant::now();
+        dr.update_fix(START, t0);
+        dr.update_heading(90.0, t0);
+        dr.update_water_speed(6.0, t0);
+        assert!(dr.update(t0 + Duration::from_secs(30)).unwrap().is_estimated());
+
+        let new_fix = Position { latitude: 43.6, longitude: 10.3 };
+        dr.update_fix(new_fix, t0 + Duration::from_secs(31));
+        let composite = dr.update(t0 + Duration::from_secs(32)).unwrap();
+        assert_eq!(composite.source, PositionSource::Gps);
+        assert_eq!(composite.position.latitude, new_fix.latitude);
+        assert_eq!(composite.position.longitude, new_fix.longitude);
+    }
+
+    #[test]
+    fn test_no_position_after_max_duration() {
+        let mut dr = estimator();
+        let t0 = Instant::now();
+        dr.update_fix(START, t0);
+        dr.update_heading(90.0, t0);
+        dr.update_water_speed(6.0, t0);
+        assert!(dr.position_at(t0 + Duration::from_secs(120)).is_some());
+        assert!(dr.position_at(t0 + Duration::from_secs(121)).is_none());
+    }
+
+    #[test]
+    fn test_no_estimate_without_heading_or_speed() {
+        let mut dr = estimator();
+        let t0 = Instant::now();
+        dr.update_fix(START, t0);
+        dr.update_water_speed(6.0, t0);
+        assert!(dr.position_at(t0 + Duration::from_secs(10)).is_none());
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use nmea2k::pgns::N2kMessage;
+use serde_json::Value;
+
+use crate::config::DedupConfig;
+
+/// Rapid update PGNs (position, COG/SOG, heading, rate of turn, attitude), never deduplicated
+/// when `bypass_rapid_update` is set: their consumers rely on the regular rate
+pub const RAPID_UPDATE_PGNS: [u32; 5] = [129025, 129026, 127250, 127251, 127257];
+
+/// Whether two decoded messages are the same, numbers compared within `epsilon`
+pub fn json_close(a: &Value, b: &Value, epsilon: f64) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => (x - y).abs() <= epsilon,
+            _ => x == y,
+        },
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(x, y)| json_close(x, y, epsilon))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len() && x.iter().all(|(key, x)| y.get(key).is_some_and(|y| json_close(x, y, epsilon)))
+        }
+        _ => a == b,
+    }
+}
+
+/// Drops the messages repeating the last one forwarded for the same PGN and source,
+/// within the minimum interval
+///
+/// A message is forwarded at least every `min_interval` even if the value doesn't change.
+pub struct MessageDeduplicator {
+    min_interval: Duration,
+    epsilon: f64,
+    bypass_rapid_update: bool,
+    last_forwarded: HashMap<(u32, u8), (Value, Instant)>,
+}
+
+impl MessageDeduplicator {
+    pub fn new(config: &DedupConfig) -> Self {
+        Self {
+            min_interval: config.min_interval(),
+            epsilon: config.epsilon,
+            bypass_rapid_update: config.bypass_rapid_update,
+            last_forwarded: HashMap::new(),
+        }
+    }
+
+    /// Whether the message repeats the last one forwarded and must be dropped
+    /// Messages not dropped become the reference of their PGN and source
+    pub fn is_duplicate(&mut self, source: u8, message: &N2kMessage, now: Instant) -> bool {
+        let pgn = message.pgn();
+        if self.bypass_rapid_update && RAPID_UPDATE_PGNS.contains(&pgn) {
+            return false;
+        }
+        let data = message.to_json();
+        let key = (pgn, source);
+        if let Some((last, time)) = self.last_forwarded.get(&key)
+            && now.saturating_duration_since(*time) < self.min_interval
+            && json_close(last, &data, self.epsilon) {
+            return true;
+        }
+        self.last_forwarded.insert(key, (data, now));
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nmea2k::pgns::WindData;
+    use serde_json::json;
+
+    fn config(min_interval_ms: u64, epsilon: f64) -> DedupConfig {
+        DedupConfig { enabled: true, min_interval_ms, epsilon, ..DedupConfig::default() }
+    }
+
+    fn wind(speed: f64) -> N2kMessage {
+        N2kMessage::WindData(WindData::new_apparent(speed, 0.5))
+    }
+
+    #[test]
+    fn test_json_close() {
+        let a = json!({"speed": 5.0, "angle": 0.5, "reference": "Apparent"});
+        assert!(json_close(&a, &json!({"speed": 5.0005, "angle": 0.5, "reference": "Apparent"}), 0.001));
+        assert!(!json_close(&a, &json!({"speed": 5.01, "angle": 0.5, "reference": "Apparent"}), 0.001));
+        assert!(!json_close(&a, &json!({"speed": 5.0, "angle": 0.5, "reference": "True"}), 0.001));
+        assert!(!json_close(&a, &json!({"speed": 5.0, "angle": 0.5}), 0.001));
+        // With no epsilon only identical values match
+        assert!(json_close(&a, &a.clone(), 0.0));
+        assert!(!json_close(&json!([1.0, null]), &json!([1.0, 2.0]), 0.5));
+    }
+
+    #[test]
+    fn test_near_identical_wind_gated_by_interval() {
+        let mut dedup = MessageDeduplicator::new(&config(1000, 0.01));
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        assert!(!dedup.is_duplicate(0x10, &wind(5.0), at(0)));
+        // Same speed within epsilon, 10 times per second
+        for ms in (100..1000).step_by(100) {
+            assert!(dedup.is_duplicate(0x10, &wind(5.001 + ms as f64 * 1e-6), at(ms)));
+        }
+        // A message goes through once the interval has elapsed
+        assert!(!dedup.is_duplicate(0x10, &wind(5.001), at(1000)));
+        assert!(dedup.is_duplicate(0x10, &wind(5.0), at(1100)));
+        // A change beyond epsilon goes through right away
+        assert!(!dedup.is_duplicate(0x10, &wind(5.5), at(1200)));
+    }
+
+    #[test]
+    fn test_sources_deduplicated_separately() {
+        let mut dedup = MessageDeduplicator::new(&config(1000, 0.0));
+        let now = Instant::now();
+        assert!(!dedup.is_duplicate(0x10, &wind(5.0), now));
+        assert!(!dedup.is_duplicate(0x11, &wind(5.0), now));
+        assert!(dedup.is_duplicate(0x11, &wind(5.0), now));
+    }
+
+    #[test]
+    fn test_rapid_update_bypass() {
+        let position = N2kMessage::Unknown(129025, vec![0; 8]);
+        let now = Instant::now();
+
+        let mut dedup = MessageDeduplicator::new(&config(1000, 0.0));
+        assert!(!dedup.is_duplicate(0x05, &position, now));
+        assert!(!dedup.is_duplicate(0x05, &position, now));
+
+        let mut dedup = MessageDeduplicator::new(&DedupConfig { bypass_rapid_update: false, ..config(1000, 0.0) });
+        assert!(!dedup.is_duplicate(0x05, &position, now));
+        assert!(dedup.is_duplicate(0x05, &position, now));
+    }
+}
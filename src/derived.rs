@@ -0,0 +1,148 @@
+use std::time::{Duration, Instant};
+
+use nmea2k::pgns::pgn130306::WindReference;
+use nmea2k::pgns::{HeadingReference, N2kMessage, WindData};
+
+use crate::config::DerivedConfig;
+use crate::utilities::calculate_true_wind_vector;
+
+const MS_TO_KN: f64 = 1.94384;
+
+/// Computes messages derived from the received ones, to broadcast them with the received messages
+///
+/// On each apparent wind message, the true wind referenced to true north is computed from the
+/// latest true heading and COG/SOG, when both were received within the maximum age.
+pub struct DerivedMessageEngine {
+    max_age: Duration,
+    /// True heading in degrees
+    heading: Option<(f64, Instant)>,
+    /// True COG in degrees and SOG in knots
+    cog_sog: Option<(f64, f64, Instant)>,
+}
+
+impl DerivedMessageEngine {
+    pub fn new(config: &DerivedConfig) -> Self {
+        Self {
+            max_age: config.max_age(),
+            heading: None,
+            cog_sog: None,
+        }
+    }
+
+    /// Keep the latest heading and COG/SOG, and return the message derived from this one, if any
+    pub fn handle_message(&mut self, message: &N2kMessage, now: Instant) -> Option<N2kMessage> {
+        match message {
+            // A magnetic heading would need a reliable variation
+            N2kMessage::VesselHeading(heading) if heading.reference == HeadingReference::True => {
+                self.heading = Some((heading.heading.to_degrees(), now));
+                None
+            }
+            N2kMessage::CogSogRapidUpdate(cog_sog) if cog_sog.cog_reference => {
+                self.cog_sog = Some((cog_sog.cog.to_degrees(), cog_sog.sog * MS_TO_KN, now));
+                None
+            }
+            N2kMessage::WindData(wind) if matches!(wind.reference, WindReference::Apparent) => {
+                self.true_wind_north(wind, now).map(N2kMessage::WindData)
+            }
+            _ => None,
+        }
+    }
+
+    /// True wind referenced to true north, None if the heading or COG/SOG is missing or stale
+    fn true_wind_north(&self, apparent: &WindData, now: Instant) -> Option<WindData> {
+        let fresh = |time: Instant| now.saturating_duration_since(time) <= self.max_age;
+        let (heading_deg, _) = self.heading.filter(|(_, time)| fresh(*time))?;
+        let (cog_deg, sog_kn, _) = self.cog_sog.filter(|(_, _, time)| fresh(*time))?;
+
+        let (speed_kn, direction_deg) = calculate_true_wind_vector(
+            apparent.speed_knots(),
+            apparent.angle.to_degrees(),
+            heading_deg,
+            cog_deg,
+            sog_kn,
+        );
+        Some(WindData::new(speed_kn / MS_TO_KN, direction_deg.to_radians(), WindReference::TrueGroundNorth))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use nmea2k::pgns::{CogSogRapidUpdate, VesselHeading};
+
+    fn engine() -> DerivedMessageEngine {
+        DerivedMessageEngine::new(&DerivedConfig { true_wind_north: true, ..DerivedConfig::default() })
+    }
+
+    fn heading(degrees: f64, reference: HeadingReference) -> N2kMessage {
+        N2kMessage::VesselHeading(VesselHeading::new(degrees.to_radians(), reference))
+    }
+
+    fn cog_sog(cog_deg: f64, sog_kn: f64) -> N2kMessage {
+        N2kMessage::CogSogRapidUpdate(CogSogRapidUpdate::new(true, cog_deg.to_radians(), sog_kn / MS_TO_KN))
+    }
+
+    fn apparent_wind(speed_kn: f64, angle_deg: f64) -> N2kMessage {
+        N2kMessage::WindData(WindData::new_apparent(speed_kn / MS_TO_KN, angle_deg.to_radians()))
+    }
+
+    #[test]
+    fn test_true_wind_north_known_geometry() {
+        let mut engine = engine();
+        let now = Instant::now();
+        // Heading and going east at 5 kn, 15 kn of apparent wind on the bow: 10 kn from the east
+        assert!(engine.handle_message(&heading(90.0, HeadingReference::True), now).is_none());
+        assert!(engine.handle_message(&cog_sog(90.0, 5.0), now).is_none());
+
+        let Some(N2kMessage::WindData(wind)) = engine.handle_message(&apparent_wind(15.0, 0.0), now) else {
+            panic!("Expected a derived wind message");
+        };
+        assert!(matches!(wind.reference, WindReference::TrueGroundNorth));
+        assert_abs_diff_eq!(wind.speed_knots(), 10.0, epsilon = 1e-6);
+        assert_abs_diff_eq!(wind.angle.to_degrees(), 90.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_true_wind_north_with_drift() {
+        let mut engine = engine();
+        let now = Instant::now();
+        // Heading north but drifting west at 3 kn, 10 kn of apparent wind from the starboard beam
+        engine.handle_message(&heading(0.0, HeadingReference::True), now);
+        engine.handle_message(&cog_sog(270.0, 3.0), now);
+
+        let Some(N2kMessage::WindData(wind)) = engine.handle_message(&apparent_wind(10.0, 90.0), now) else {
+            panic!("Expected a derived wind message");
+        };
+        assert_abs_diff_eq!(wind.speed_knots(), 13.0, epsilon = 1e-6);
+        assert_abs_diff_eq!(wind.angle.to_degrees(), 90.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_no_derived_message_without_fresh_inputs() {
+        let mut engine = engine();
+        let now = Instant::now();
+        assert!(engine.handle_message(&apparent_wind(15.0, 0.0), now).is_none());
+
+        // Magnetic heading ignored
+        engine.handle_message(&heading(90.0, HeadingReference::Magnetic), now);
+        engine.handle_message(&cog_sog(90.0, 5.0), now);
+        assert!(engine.handle_message(&apparent_wind(15.0, 0.0), now).is_none());
+
+        engine.handle_message(&heading(90.0, HeadingReference::True), now);
+        assert!(engine.handle_message(&apparent_wind(15.0, 0.0), now).is_some());
+        // Stale heading and COG/SOG
+        assert!(engine.handle_message(&apparent_wind(15.0, 0.0), now + Duration::from_secs(2)).is_none());
+    }
+
+    #[test]
+    fn test_only_apparent_wind_derived() {
+        let mut engine = engine();
+        let now = Instant::now();
+        engine.handle_message(&heading(90.0, HeadingReference::True), now);
+        engine.handle_message(&cog_sog(90.0, 5.0), now);
+
+        let true_wind = N2kMessage::WindData(WindData::new(5.0, 1.0, WindReference::TrueGroundNorth));
+        assert!(engine.handle_message(&true_wind, now).is_none());
+    }
+}
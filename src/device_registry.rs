@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use nmea2k::pgns::ProductInformation;
+use nmea2k::{MessageHandler, N2kFrame, N2kMessage};
+use tracing::info;
+
+/// Devices on the bus, by source address
+///
+/// Keeps the latest Product Information (PGN 126996) received from each source,
+/// so that logs can show a device name instead of a numeric address.
+pub struct DeviceRegistry {
+    devices: HashMap<u8, ProductInformation>,
+}
+
+impl DeviceRegistry {
+    pub fn new() -> Self {
+        Self {
+            devices: HashMap::new(),
+        }
+    }
+
+    /// Record the Product Information of a source address
+    /// Returns true when the device is new or its information changed
+    /// (e.g. another device claimed the address)
+    pub fn update(&mut self, source: u8, product_info: &ProductInformation) -> bool {
+        if self.devices.get(&source) == Some(product_info) {
+            return false;
+        }
+        self.devices.insert(source, product_info.clone());
+        true
+    }
+
+    /// Model name of the device at the source address, if it sent its Product Information
+    pub fn name_for(&self, source: u8) -> Option<&str> {
+        self.devices
+            .get(&source)
+            .map(|device| device.model_id.as_str())
+            .filter(|name| !name.is_empty())
+    }
+}
+
+impl Default for DeviceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MessageHandler for DeviceRegistry {
+    fn handle_message(&mut self, frame: &N2kFrame, _timestamp: std::time::Instant) {
+        if let N2kMessage::ProductInformation(product_info) = &frame.message
+            && self.update(frame.source(), product_info)
+        {
+            info!(
+                "Device at source {}: {} (software {}, serial {})",
+                frame.source(),
+                product_info.model_id,
+                product_info.software_version,
+                product_info.model_serial_code
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_devices() {
+        let mut registry = DeviceRegistry::new();
+        assert!(registry.update(5, &ProductInformation::new("GPS 19x NMEA 2000", "4.10", "3G1234567")));
+        assert!(registry.update(35, &ProductInformation::new("i70s", "1.08", "1100123")));
+
+        assert_eq!(registry.name_for(5), Some("GPS 19x NMEA 2000"));
+        assert_eq!(registry.name_for(35), Some("i70s"));
+        assert_eq!(registry.name_for(12), None);
+        assert_eq!(registry.devices[&35].model_serial_code, "1100123");
+    }
+
+    #[test]
+    fn test_update_keeps_latest() {
+        let mut registry = DeviceRegistry::new();
+        let gps = ProductInformation::new("GPS 19x NMEA 2000", "4.10", "3G1234567");
+        assert!(registry.update(5, &gps));
+        // Periodic repeats of the same information are not a change
+        assert!(!registry.update(5, &gps));
+
+        // Another device claimed the address
+        assert!(registry.update(5, &ProductInformation::new("AIS 700", "2.30", "4A7654321")));
+        assert_eq!(registry.name_for(5), Some("AIS 700"));
+        assert_eq!(registry.devices.len(), 1);
+
+        // A device without a model name has no name
+        registry.update(6, &ProductInformation::new("", "1.0", ""));
+        assert_eq!(registry.name_for(6), None);
+    }
+}
@@ -1,12 +1,16 @@
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
-use nmea2k::pgns::{ActualPressure, Attitude, Humidity, Temperature, VesselHeading, WindData};
-use crate::utilities::calculate_true_wind;
+use nmea2k::pgns::pgn127251::RateOfTurn;
+use nmea2k::pgns::pgn130306::WindReference;
+use nmea2k::pgns::{ActualPressure, Attitude, EnvironmentalParameters, Humidity, Rudder, Temperature, TemperatureExtended, VesselHeading, WaterDepth, WindData};
+use crate::stuck_sensor::StuckSensorDetector;
+use crate::utilities::{angle_diff, average_angle, calculate_true_wind, normalize0_360};
 use crate::vessel_monitor::Position;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum MetricId {
     Pressure = 1,
@@ -16,6 +20,11 @@ pub enum MetricId {
     WindSpeed = 5,
     WindDir = 6,
     Roll = 7,
+    Depth = 8,
+    DepthCorrected = 9,
+    RudderActivity = 10,
+    WindGust = 11,
+    RateOfTurn = 12,
 }
 
 impl MetricId {
@@ -32,6 +41,11 @@ impl MetricId {
             MetricId::WindSpeed => 4,
             MetricId::WindDir => 5,
             MetricId::Roll => 6,
+            MetricId::Depth => 7,
+            MetricId::DepthCorrected => 8,
+            MetricId::RudderActivity => 9,
+            MetricId::WindGust => 10,
+            MetricId::RateOfTurn => 11,
         }
     }
     
@@ -44,6 +58,11 @@ impl MetricId {
             MetricId::WindSpeed => "Kn",
             MetricId::WindDir => "deg",
             MetricId::Roll => "deg",
+            MetricId::Depth => "m",
+            MetricId::DepthCorrected => "m",
+            MetricId::RudderActivity => "deg",
+            MetricId::WindGust => "Kn",
+            MetricId::RateOfTurn => "deg/s",
         }
     }
     
@@ -57,10 +76,15 @@ impl MetricId {
             MetricId::WindSpeed => "wind_speed",
             MetricId::WindDir => "wind_dir",
             MetricId::Roll => "roll",
+            MetricId::Depth => "depth",
+            MetricId::DepthCorrected => "depth_corrected",
+            MetricId::RudderActivity => "rudder_activity",
+            MetricId::WindGust => "gust_peak_kn",
+            MetricId::RateOfTurn => "rate_of_turn",
         }
     }
 
-    pub const ALL_METRICS: [MetricId; 7] = [
+    pub const ALL_METRICS: [MetricId; 12] = [
         MetricId::Pressure,
         MetricId::CabinTemp,
         MetricId::WaterTemp,
@@ -68,10 +92,25 @@ impl MetricId {
         MetricId::WindSpeed,
         MetricId::WindDir,
         MetricId::Roll,
+        MetricId::Depth,
+        MetricId::DepthCorrected,
+        MetricId::RudderActivity,
+        MetricId::WindGust,
+        MetricId::RateOfTurn,
     ];
+
+    /// Look up a metric by its name (as returned by `name()`)
+    pub fn from_name(name: &str) -> Option<MetricId> {
+        Self::ALL_METRICS.iter().copied().find(|m| m.name() == name)
+    }
+
+    /// Look up a metric by its name ("pressure") or by its id ("1")
+    pub fn parse(metric: &str) -> Option<MetricId> {
+        Self::ALL_METRICS.iter().copied().find(|m| m.name() == metric || m.as_u8().to_string() == metric)
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetricData {
     pub avg: Option<f64>,
     pub max: Option<f64>,
@@ -87,15 +126,38 @@ pub struct Sample<T> {
 }
 
 pub struct EnvironmentalMonitor {
-    pub data_samples: [VecDeque<Sample<f64>>; 7],
+    pub data_samples: [VecDeque<Sample<f64>>; 12],
     last_heading_event: Option<Instant>,
     last_heading_degrees: Option<f64>,
     last_boat_speed_knots: Option<f64>,
     last_boat_speed_event: Option<Instant>,
     last_position_event: Option<Instant>,
     last_position: Option<Position>,
+    /// Last true wind referenced to true north (derived), preferred to the one computed from the apparent wind
+    last_ground_wind_event: Option<Instant>,
+    depth_offset_m: Option<f64>,
+    rudder_positions: VecDeque<Sample<f64>>,
+    rudder_activity_window: Duration,
+    /// (instance, source) of the PGN 130314 sensors accepted as atmospheric pressure
+    pressure_sensors: Vec<(u8, u8)>,
+    /// (instance, source) of the PGN 130313 sensors accepted as humidity, empty to accept any
+    humidity_sensors: Vec<(u8, u8)>,
+    /// Last set value reported by the accepted humidity sensors (e.g. dehumidifier setting)
+    humidity_set_point: Option<f64>,
+    stuck_detector: Option<StuckSensorDetector>,
+    /// Age after which the samples of each metric are dropped, None keeps them until persisted
+    sample_windows: [Option<Duration>; 12],
 }
 
+/// Default window over which rudder position variability is computed
+const DEFAULT_RUDDER_ACTIVITY_WINDOW: Duration = Duration::from_secs(30);
+
+/// Minimum number of rudder positions in the window to compute the activity
+const MIN_RUDDER_SAMPLES: usize = 5;
+
+/// Default pressure sensor: instance 0, source 0 (Atmospheric)
+pub const DEFAULT_PRESSURE_SENSOR: (u8, u8) = (0, 0);
+
 impl EnvironmentalMonitor {
     pub fn new() -> Self {
         Self {
@@ -107,6 +169,11 @@ impl EnvironmentalMonitor {
                 VecDeque::new(), // WindSpeed
                 VecDeque::new(), // WindDir
                 VecDeque::new(), // Roll
+                VecDeque::new(), // Depth
+                VecDeque::new(), // DepthCorrected
+                VecDeque::new(), // RudderActivity
+                VecDeque::new(), // WindGust
+                VecDeque::new(), // RateOfTurn
             ],
             last_heading_event: None,
             last_heading_degrees: None,
@@ -114,36 +181,124 @@ impl EnvironmentalMonitor {
             last_boat_speed_event: None,
             last_position_event: None,
             last_position: None,
+            last_ground_wind_event: None,
+            depth_offset_m: None,
+            rudder_positions: VecDeque::new(),
+            rudder_activity_window: DEFAULT_RUDDER_ACTIVITY_WINDOW,
+            pressure_sensors: vec![DEFAULT_PRESSURE_SENSOR],
+            humidity_sensors: Vec::new(),
+            humidity_set_point: None,
+            stuck_detector: None,
+            sample_windows: [None; 12],
+        }
+    }
+
+    /// Override the transducer offset reported in PGN 128267
+    /// Positive values are the distance from transducer to waterline,
+    /// negative values the distance from transducer to keel.
+    /// None means the offset decoded from the message is used.
+    pub fn set_depth_offset(&mut self, offset_m: Option<f64>) {
+        self.depth_offset_m = offset_m;
+    }
+
+    /// Set the window over which the rudder activity (position variability) is computed
+    pub fn set_rudder_activity_window(&mut self, window: Duration) {
+        self.rudder_activity_window = window;
+    }
+
+    /// Set the (instance, source) pairs of the PGN 130314 sensors used for the pressure metric
+    pub fn set_pressure_sensors(&mut self, sensors: Vec<(u8, u8)>) {
+        self.pressure_sensors = sensors;
+    }
+
+    /// Set the (instance, source) pairs of the PGN 130313 sensors used for the humidity metric
+    /// An empty list accepts any sensor
+    pub fn set_humidity_sensors(&mut self, sensors: Vec<(u8, u8)>) {
+        self.humidity_sensors = sensors;
+    }
+
+    /// Last humidity set point announced by the humidity sensor (PGN 130313), if any
+    pub fn humidity_set_point(&self) -> Option<f64> {
+        self.humidity_set_point
+    }
+
+    /// Enable the detection of stuck sensors (values repeated unchanged for too long)
+    pub fn set_stuck_detector(&mut self, detector: StuckSensorDetector) {
+        self.stuck_detector = Some(detector);
+    }
+
+    /// Keep the samples of a metric for at most `window`, typically its persistence interval,
+    /// so that samples not persisted (e.g. database down) don't pile up into the next average
+    pub fn set_sample_window(&mut self, metric_id: MetricId, window: Duration) {
+        self.sample_windows[metric_id.as_index()] = Some(window);
+    }
+
+    fn add_sample(&mut self, metric_id: MetricId, value: f64, now: Instant) {
+        if let Some(detector) = self.stuck_detector.as_mut() {
+            detector.observe(metric_id, value, now);
+        }
+        let samples = &mut self.data_samples[metric_id.as_index()];
+        if let Some(window) = self.sample_windows[metric_id.as_index()] {
+            while let Some(oldest) = samples.front()
+                && now.saturating_duration_since(oldest.timestamp) > window {
+                samples.pop_front();
+            }
         }
+        samples.push_back(Sample { value, timestamp: now });
     }
 
     /// Process a temperature message (PGN 130312)
     /// Instance 0 is typically the cabin temperature (and source 4 is "Inside Ambient")
     pub fn process_temperature(&mut self, temp: &Temperature, now: Instant) {
-        if temp.instance == 0 { // Cabin temperature
-            let celsius = temp.temperature - 273.15;
-            let source = temp.source;
-            let instance = temp.instance;
-            
-            if source==4 && instance==0 {
+        self.add_temperature_sample(temp.instance, temp.source, temp.temperature, now);
+    }
+
+    /// Process an extended range temperature message (PGN 130316), same sensors as PGN 130312
+    pub fn process_temperature_extended(&mut self, temp: &TemperatureExtended, now: Instant) {
+        if let Some(kelvin) = temp.temperature {
+            self.add_temperature_sample(temp.instance, temp.source, kelvin, now);
+        }
+    }
+
+    fn add_temperature_sample(&mut self, instance: u8, source: u8, kelvin: f64, now: Instant) {
+        if instance == 0 { // Cabin temperature
+            let celsius = kelvin - 273.15;
+
+            if source == 4 {
                 // Source 4 is "Inside Ambient"
-                self.data_samples[MetricId::CabinTemp.as_index()].push_back(Sample {
-                    value: celsius,
-                    timestamp: now,
-                });
-            } else if source==0 && instance==0 {
+                self.add_sample(MetricId::CabinTemp, celsius, now);
+            } else if source == 0 {
                 // Source 0 is water temperature
-                self.data_samples[MetricId::WaterTemp.as_index()].push_back(Sample {
-                    value: celsius,
-                    timestamp: now,
-                });
+                self.add_sample(MetricId::WaterTemp, celsius, now);
             }
         }
     }
 
     /// Process wind data message (PGN 130306)
+    /// The true wind referenced to true north is taken as is, and replaces the one computed from
+    /// the apparent wind while it keeps coming. Other references are ignored.
     fn process_wind(&mut self, wind: &WindData, now: Instant) {
-        
+        match wind.reference {
+            WindReference::TrueGroundNorth => self.process_true_wind_north(wind, now),
+            WindReference::Apparent if !self.has_fresh_ground_wind(now) => self.process_apparent_wind(wind, now),
+            _ => {}
+        }
+    }
+
+    fn has_fresh_ground_wind(&self, now: Instant) -> bool {
+        self.last_ground_wind_event.is_some_and(|event| now.saturating_duration_since(event) <= Duration::from_secs(1))
+    }
+
+    /// True wind over ground, the direction is already referenced to true north
+    fn process_true_wind_north(&mut self, wind: &WindData, now: Instant) {
+        self.last_ground_wind_event = Some(now);
+        self.add_sample(MetricId::WindSpeed, wind.speed_knots(), now);
+        self.add_sample(MetricId::WindGust, wind.speed_knots(), now);
+        self.add_sample(MetricId::WindDir, normalize0_360(wind.angle.to_degrees()), now);
+    }
+
+    /// True wind computed from the apparent wind, the boat speed and the heading
+    fn process_apparent_wind(&mut self, wind: &WindData, now: Instant) {
         // To compute wind direction, we need boat heading and speed
         self.reset_stale_heading(now); // prevent using stale heading
         if self.last_boat_speed_knots.is_none() || self.last_heading_degrees.is_none() {
@@ -152,56 +307,70 @@ impl EnvironmentalMonitor {
         }
 
         //process wind speed
-        let boat_speed = if self.last_boat_speed_knots.is_none() || self.last_boat_speed_event.is_none() || now.duration_since(self.last_boat_speed_event.unwrap()) > Duration::from_secs(1) {
+        let boat_speed = match (self.last_boat_speed_knots, self.last_boat_speed_event) {
+            (Some(speed), Some(event)) if now.duration_since(event) <= Duration::from_secs(1) => speed,
             // Boat speed data is none or stale
-            return;
-        } else {
-            self.last_boat_speed_knots.unwrap()
+            _ => return,
         };
         let (true_wind_speed, true_wind_angle_deg) = calculate_true_wind(wind.speed_knots(), wind.angle.to_degrees(), boat_speed);
-        self.data_samples[MetricId::WindSpeed.as_index()].push_back(Sample {
-            value: true_wind_speed,
-            timestamp: now,
-        });
+        self.add_sample(MetricId::WindSpeed, true_wind_speed, now);
+        // Same samples, persisted as the single peak of the interval
+        self.add_sample(MetricId::WindGust, true_wind_speed, now);
         
         // now process wind angle
-        let boat_heading = if self.last_heading_degrees.is_none() || self.last_heading_event.is_none() || now.duration_since(self.last_heading_event.unwrap()) > Duration::from_secs(1) {
+        let boat_heading = match (self.last_heading_degrees, self.last_heading_event) {
+            (Some(heading), Some(event)) if now.duration_since(event) <= Duration::from_secs(1) => heading,
             // Heading data is none or stale
-            return;
-        } else {
-            self.last_heading_degrees.unwrap()
+            _ => return,
         };
 
         let absolute_angle = (boat_heading + true_wind_angle_deg) % 360.0;
         // Store wind direction (convert radians to degrees)
-        self.data_samples[MetricId::WindDir.as_index()].push_back(Sample {
-            value: absolute_angle,
-            timestamp: now,
-        });
+        self.add_sample(MetricId::WindDir, absolute_angle, now);
     }
     
     /// Process a humidity message (PGN 130313)
-    /// Standalone humidity sensor reading
+    /// Standalone humidity sensor reading, only the configured sensors are accepted (any if none is configured)
     fn process_humidity(&mut self, hum: &Humidity, now: Instant) {
-        
-        self.data_samples[MetricId::Humidity.as_index()].push_back(Sample {
-            value: hum.actual_humidity,
-            timestamp: now,
-        });
+        if !self.humidity_sensors.is_empty() && !self.humidity_sensors.contains(&(hum.instance, hum.source)) {
+            return;
+        }
+
+        if let Some(set_humidity) = hum.set_humidity && self.humidity_set_point != Some(set_humidity) {
+            info!("Humidity set value changed to {:.1}% (instance {}, source {})", set_humidity, hum.instance, hum.source);
+            self.humidity_set_point = Some(set_humidity);
+        }
+        self.add_sample(MetricId::Humidity, hum.actual_humidity, now);
     }
     
     /// Process an actual pressure message (PGN 130314)
-    /// Standalone pressure sensor reading
+    /// Standalone pressure sensor reading, only the configured sensors are accepted
     fn process_actual_pressure(&mut self, pressure: &ActualPressure, now: Instant) {
-        let instance = pressure.instance;
-        let source = pressure.source;
-
-        if instance == 0 && source == 0 {
-            // Primary atmospheric pressure sensor
-            self.data_samples[MetricId::Pressure.as_index()].push_back(Sample {
-                value: pressure.pressure,
-                timestamp: now,
-            });
+        if self.pressure_sensors.contains(&(pressure.instance, pressure.source)) {
+            self.add_sample(MetricId::Pressure, pressure.pressure, now);
+        }
+    }
+    
+    /// Process combined environmental parameters (PGN 130310/130311)
+    /// Readings are routed as the standalone messages of instance 0: temperature source 4 (Inside Ambient)
+    /// is the cabin and source 0 the water temperature, pressure is the atmospheric sensor
+    fn process_environmental_parameters(&mut self, env: &EnvironmentalParameters, now: Instant) {
+        if let Some(temperature) = env.temperature {
+            let celsius = temperature - 273.15;
+            match env.temperature_source {
+                4 => self.add_sample(MetricId::CabinTemp, celsius, now),
+                0 => self.add_sample(MetricId::WaterTemp, celsius, now),
+                _ => {}
+            }
+        }
+
+        if let Some(humidity) = env.humidity
+            && (self.humidity_sensors.is_empty() || self.humidity_sensors.contains(&(0, env.humidity_source))) {
+            self.add_sample(MetricId::Humidity, humidity, now);
+        }
+
+        if let Some(pressure) = env.atmospheric_pressure && self.pressure_sensors.contains(&DEFAULT_PRESSURE_SENSOR) {
+            self.add_sample(MetricId::Pressure, pressure, now);
         }
     }
     
@@ -210,13 +379,67 @@ impl EnvironmentalMonitor {
     fn process_attitude(&mut self, attitude: &Attitude, now: Instant) {
         if let Some(roll_deg) = attitude.roll_degrees() {
             
-            self.data_samples[MetricId::Roll.as_index()].push_back(Sample {
-                value: roll_deg,
-                timestamp: now,
-            });
+            self.add_sample(MetricId::Roll, roll_deg, now);
+        }
+    }
+
+    /// Process a water depth message (PGN 128267)
+    /// Stores the raw depth below transducer and the depth with the transducer offset applied
+    fn process_water_depth(&mut self, depth: &WaterDepth, now: Instant) {
+        let offset = self.depth_offset_m.unwrap_or(depth.offset);
+
+        self.add_sample(MetricId::Depth, depth.depth, now);
+        self.add_sample(MetricId::DepthCorrected, depth.depth + offset, now);
+    }
+
+    /// Process a rate of turn message (PGN 127251), positive to starboard
+    fn process_rate_of_turn(&mut self, rot: &RateOfTurn, now: Instant) {
+        self.add_sample(MetricId::RateOfTurn, rot.rate.to_degrees(), now);
+    }
+
+    /// Process a rudder message (PGN 127245)
+    /// Rudder activity is the standard deviation of the rudder position (degrees) over the
+    /// configured window: an autopilot steering in a seaway makes frequent small corrections
+    /// and shows a higher activity than a boat hand-steered or sailing on a fixed helm.
+    fn process_rudder(&mut self, rudder: &Rudder, now: Instant) {
+        if rudder.instance != 0 { // Main rudder only
+            return;
+        }
+        let Some(position_deg) = rudder.position_degrees() else {
+            return;
+        };
+
+        self.rudder_positions.push_back(Sample {
+            value: position_deg,
+            timestamp: now,
+        });
+        while let Some(oldest) = self.rudder_positions.front() {
+            if now.duration_since(oldest.timestamp) > self.rudder_activity_window {
+                self.rudder_positions.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if let Some(activity) = self.rudder_activity() {
+            self.add_sample(MetricId::RudderActivity, activity, now);
         }
     }
 
+    /// Current rudder activity (standard deviation of the rudder position in degrees)
+    /// None when there are not enough positions in the window
+    pub fn rudder_activity(&self) -> Option<f64> {
+        if self.rudder_positions.len() < MIN_RUDDER_SAMPLES {
+            return None;
+        }
+        let count = self.rudder_positions.len() as f64;
+        let mean = self.rudder_positions.iter().map(|s| s.value).sum::<f64>() / count;
+        let variance = self.rudder_positions.iter()
+            .map(|s| (s.value - mean).powi(2))
+            .sum::<f64>() / count;
+        Some(variance.sqrt())
+    }
+
     fn reset_stale_heading(&mut self, now: Instant) {
         if now.duration_since(self.last_heading_event.unwrap_or(now)) > Duration::from_secs(10) {
             self.last_heading_event = None;
@@ -228,10 +451,8 @@ impl EnvironmentalMonitor {
         if msg_heading.reference == nmea2k::pgns::HeadingReference::Magnetic {
             if let Some(pos) = self.last_position {
                 let heading_deg = msg_heading.heading.to_degrees();
-                let var = match crate::utilities::get_variation_deg(pos.latitude, pos.longitude, chrono::Utc::now()) {
-                    Ok(v) => v,
-                    Err(_) => 0.0, // Unable to get variation, revert to magnetic - better than nothing
-                };
+                // Unable to get variation, revert to magnetic - better than nothing
+                let var = crate::utilities::get_variation_deg(pos.latitude, pos.longitude, chrono::Utc::now()).unwrap_or(0.0);
                 let true_heading_deg = crate::utilities::normalize0_360(heading_deg + var);
                 self.last_heading_event = Some(now);
                 self.last_heading_degrees = Some(true_heading_deg);
@@ -263,7 +484,10 @@ impl EnvironmentalMonitor {
 
     pub fn calculate_metric_data(&self, metric_id: MetricId) -> Option<MetricData> {
         let samples = &self.data_samples[metric_id.as_index()];
-        self.calculate(samples)
+        match metric_id {
+            MetricId::WindDir => self.calculate_angular(samples),
+            _ => self.calculate(samples),
+        }
     }
 
     fn calculate(&self, samples: &VecDeque<Sample<f64>>) -> Option<MetricData> {
@@ -294,6 +518,41 @@ impl EnvironmentalMonitor {
         })
     }
     
+    /// Circular statistics for directions (0-360°): the average is the vector mean, min and max
+    /// are the edges of the sector covered by the samples, going clockwise from min to max
+    fn calculate_angular(&self, samples: &VecDeque<Sample<f64>>) -> Option<MetricData> {
+        if samples.is_empty() {
+            return None;
+        }
+        let angles: Vec<f64> = samples.iter().map(|s| s.value).collect();
+        let avg = average_angle(&angles);
+
+        // Largest deviation on each side of the average
+        let (mut left, mut right) = (0.0f64, 0.0f64);
+        for angle in &angles {
+            let diff = angle_diff(*angle, avg);
+            left = left.min(diff);
+            right = right.max(diff);
+        }
+        Some(MetricData {
+            avg: Some(avg),
+            max: Some(normalize0_360(avg + right)),
+            min: Some(normalize0_360(avg + left)),
+            count: Some(samples.len()),
+        })
+    }
+
+    /// Get the sample with the highest value for a specific metric (e.g. the wind gust peak)
+    pub fn peak_sample(&self, metric_id: MetricId) -> Option<Sample<f64>> {
+        self.data_samples[metric_id.as_index()]
+            .iter()
+            .fold(None, |peak: Option<&Sample<f64>>, sample| match peak {
+                Some(p) if p.value >= sample.value => Some(p),
+                _ => Some(sample),
+            })
+            .cloned()
+    }
+
     /// Check if there are samples for a specific metric
     pub fn has_samples(&self, metric: MetricId) -> bool {
         !self.data_samples[metric.as_index()].is_empty()
@@ -313,6 +572,9 @@ impl nmea2k::MessageHandler for EnvironmentalMonitor {
             nmea2k::pgns::N2kMessage::Temperature(temp) => {
                 self.process_temperature(temp, now);
             }
+            nmea2k::pgns::N2kMessage::TemperatureExtended(temp) => {
+                self.process_temperature_extended(temp, now);
+            }
             nmea2k::pgns::N2kMessage::WindData(wind) => {
                 self.process_wind(wind, now);
             }
@@ -322,9 +584,21 @@ impl nmea2k::MessageHandler for EnvironmentalMonitor {
             nmea2k::pgns::N2kMessage::ActualPressure(pressure) => {
                 self.process_actual_pressure(pressure, now);
             }
+            nmea2k::pgns::N2kMessage::EnvironmentalParameters(env) => {
+                self.process_environmental_parameters(env, now);
+            }
             nmea2k::pgns::N2kMessage::Attitude(attitude) => {
                 self.process_attitude(attitude, now);
             }
+            nmea2k::pgns::N2kMessage::WaterDepth(depth) => {
+                self.process_water_depth(depth, now);
+            }
+            nmea2k::pgns::N2kMessage::Rudder(rudder) => {
+                self.process_rudder(rudder, now);
+            }
+            nmea2k::pgns::N2kMessage::RateOfTurn(rot) => {
+                self.process_rate_of_turn(rot, now);
+            }
             nmea2k::pgns::N2kMessage::VesselHeading(heading) => {
                 self.process_vessel_heading(heading, now);
             }
@@ -352,6 +626,11 @@ mod tests {
         assert_eq!(MetricId::WindSpeed.as_u8(), 5);
         assert_eq!(MetricId::WindDir.as_u8(), 6);
         assert_eq!(MetricId::Roll.as_u8(), 7);
+        assert_eq!(MetricId::Depth.as_u8(), 8);
+        assert_eq!(MetricId::DepthCorrected.as_u8(), 9);
+        assert_eq!(MetricId::RudderActivity.as_u8(), 10);
+        assert_eq!(MetricId::WindGust.as_u8(), 11);
+        assert_eq!(MetricId::RateOfTurn.as_u8(), 12);
     }
 
     #[test]
@@ -363,6 +642,11 @@ mod tests {
         assert_eq!(MetricId::WindSpeed.unit(), "Kn");
         assert_eq!(MetricId::WindDir.unit(), "deg");
         assert_eq!(MetricId::Roll.unit(), "deg");
+        assert_eq!(MetricId::Depth.unit(), "m");
+        assert_eq!(MetricId::DepthCorrected.unit(), "m");
+        assert_eq!(MetricId::RudderActivity.unit(), "deg");
+        assert_eq!(MetricId::WindGust.unit(), "Kn");
+        assert_eq!(MetricId::RateOfTurn.unit(), "deg/s");
     }
 
     #[test]
@@ -374,6 +658,21 @@ mod tests {
         assert_eq!(MetricId::WindSpeed.name(), "wind_speed");
         assert_eq!(MetricId::WindDir.name(), "wind_dir");
         assert_eq!(MetricId::Roll.name(), "roll");
+        assert_eq!(MetricId::Depth.name(), "depth");
+        assert_eq!(MetricId::DepthCorrected.name(), "depth_corrected");
+        assert_eq!(MetricId::RudderActivity.name(), "rudder_activity");
+        assert_eq!(MetricId::WindGust.name(), "gust_peak_kn");
+        assert_eq!(MetricId::RateOfTurn.name(), "rate_of_turn");
+    }
+
+    #[test]
+    fn test_metric_id_parse() {
+        assert_eq!(MetricId::parse("pressure"), Some(MetricId::Pressure));
+        assert_eq!(MetricId::parse("11"), Some(MetricId::WindGust));
+        assert_eq!(MetricId::parse("gust_peak_kn"), Some(MetricId::WindGust));
+        assert_eq!(MetricId::parse("12"), Some(MetricId::RateOfTurn));
+        assert_eq!(MetricId::parse("13"), None);
+        assert_eq!(MetricId::parse("pressure' OR '1'='1"), None);
     }
 
     #[test]
@@ -400,6 +699,26 @@ mod tests {
         assert_eq!(monitor.data_samples[MetricId::Pressure.as_index()].len(), 1);
     }
 
+    #[test]
+    fn test_pressure_sensor_on_other_instance() {
+        let mut monitor = EnvironmentalMonitor::new();
+        let pressure_msg = ActualPressure::new(1, 0, 101325.0);
+
+        // Not configured: dropped
+        monitor.process_actual_pressure(&pressure_msg, Instant::now());
+        assert!(!monitor.has_samples(MetricId::Pressure));
+
+        // Configured: accepted
+        monitor.set_pressure_sensors(vec![DEFAULT_PRESSURE_SENSOR, (1, 0)]);
+        monitor.process_actual_pressure(&pressure_msg, Instant::now());
+        assert_eq!(monitor.data_samples[MetricId::Pressure.as_index()].len(), 1);
+        assert_eq!(monitor.data_samples[MetricId::Pressure.as_index()][0].value, 101325.0);
+
+        // Other sources on the same instance (e.g. water pressure) are still dropped
+        monitor.process_actual_pressure(&ActualPressure::new(1, 1, 200000.0), Instant::now());
+        assert_eq!(monitor.data_samples[MetricId::Pressure.as_index()].len(), 1);
+    }
+
     #[test]
     fn test_process_temperature_cabin() {
         let mut monitor = EnvironmentalMonitor::new();
@@ -419,6 +738,45 @@ mod tests {
         assert_eq!(monitor.data_samples[MetricId::CabinTemp.as_index()].len(), 1);
     }
 
+    #[test]
+    fn test_samples_kept_for_metric_window() {
+        let mut monitor = EnvironmentalMonitor::new();
+        monitor.set_sample_window(MetricId::CabinTemp, Duration::from_secs(300));
+        let data = vec![0x01, 0x00, 0x04, 0x25, 0x72, 0x00];
+        let temp_msg = Temperature::from_bytes(&data).unwrap();
+
+        // One sample a minute: all of them are still there when the 300s interval elapses
+        let start = Instant::now();
+        for minute in 0..=5 {
+            monitor.process_temperature(&temp_msg, start + Duration::from_secs(60 * minute));
+        }
+        assert_eq!(monitor.data_samples[MetricId::CabinTemp.as_index()].len(), 6);
+
+        // Not persisted: the samples older than the interval are dropped
+        monitor.process_temperature(&temp_msg, start + Duration::from_secs(420));
+        let samples = &monitor.data_samples[MetricId::CabinTemp.as_index()];
+        assert_eq!(samples.len(), 5);
+        assert_eq!(samples[0].timestamp, start + Duration::from_secs(120));
+    }
+
+    #[test]
+    fn test_sample_windows_are_per_metric() {
+        let mut monitor = EnvironmentalMonitor::new();
+        monitor.set_sample_window(MetricId::CabinTemp, Duration::from_secs(300));
+        monitor.set_sample_window(MetricId::Pressure, Duration::from_secs(60));
+        let start = Instant::now();
+        for secs in [0, 90, 180] {
+            let now = start + Duration::from_secs(secs);
+            monitor.add_sample(MetricId::CabinTemp, 20.0, now);
+            monitor.add_sample(MetricId::Pressure, 101325.0, now);
+            monitor.add_sample(MetricId::Humidity, 55.0, now);
+        }
+        assert_eq!(monitor.data_samples[MetricId::CabinTemp.as_index()].len(), 3);
+        assert_eq!(monitor.data_samples[MetricId::Pressure.as_index()].len(), 1);
+        // No window: kept until persisted
+        assert_eq!(monitor.data_samples[MetricId::Humidity.as_index()].len(), 3);
+    }
+
     #[test]
     fn test_process_temperature_water() {
         let mut monitor = EnvironmentalMonitor::new();
@@ -438,6 +796,20 @@ mod tests {
         assert_eq!(monitor.data_samples[MetricId::WaterTemp.as_index()].len(), 1);
     }
 
+    #[test]
+    fn test_process_temperature_extended() {
+        let mut monitor = EnvironmentalMonitor::new();
+
+        // Water temperature (instance 0, source 0) at 300.000 K
+        monitor.process_temperature_extended(&TemperatureExtended::new(0, 0, Some(300.0), None), Instant::now());
+        let water = monitor.calculate_metric_data(MetricId::WaterTemp).unwrap();
+        assert!((water.avg.unwrap() - 26.85).abs() < 1e-9);
+
+        // Not available temperature is ignored
+        monitor.process_temperature_extended(&TemperatureExtended::new(0, 4, None, None), Instant::now());
+        assert!(!monitor.has_samples(MetricId::CabinTemp));
+    }
+
     #[test]
     fn test_process_humidity() {
         let mut monitor = EnvironmentalMonitor::new();
@@ -457,6 +829,68 @@ mod tests {
         assert_eq!(monitor.data_samples[MetricId::Humidity.as_index()].len(), 1);
     }
 
+    #[test]
+    fn test_humidity_sensor_filter() {
+        let mut monitor = EnvironmentalMonitor::new();
+        monitor.set_humidity_sensors(vec![(0, 0)]);
+
+        // Outside humidity (source 1) is not the configured sensor
+        monitor.process_humidity(&Humidity::new(0, 1, 80.0, None), Instant::now());
+        assert!(!monitor.has_samples(MetricId::Humidity));
+
+        monitor.process_humidity(&Humidity::new(0, 0, 55.0, None), Instant::now());
+        assert_eq!(monitor.data_samples[MetricId::Humidity.as_index()].len(), 1);
+        assert_eq!(monitor.data_samples[MetricId::Humidity.as_index()][0].value, 55.0);
+        assert_eq!(monitor.humidity_set_point(), None);
+    }
+
+    #[test]
+    fn test_humidity_set_value() {
+        let mut monitor = EnvironmentalMonitor::new();
+        monitor.process_humidity(&Humidity::new(0, 0, 62.0, Some(50.0)), Instant::now());
+        assert_eq!(monitor.humidity_set_point(), Some(50.0));
+
+        // A reading without set value keeps the last known one
+        monitor.process_humidity(&Humidity::new(0, 0, 61.0, None), Instant::now());
+        assert_eq!(monitor.humidity_set_point(), Some(50.0));
+        assert_eq!(monitor.data_samples[MetricId::Humidity.as_index()].len(), 2);
+    }
+
+    #[test]
+    fn test_environmental_parameters_routing() {
+        let mut monitor = EnvironmentalMonitor::new();
+        let now = Instant::now();
+
+        // Inside ambient temperature, inside humidity and pressure
+        monitor.process_environmental_parameters(&EnvironmentalParameters::new(4, Some(293.15), 0, Some(60.0), Some(101300.0)), now);
+        // Sea temperature, humidity and pressure not available
+        monitor.process_environmental_parameters(&EnvironmentalParameters::new(0, Some(288.15), 0, None, None), now);
+        // Other temperature sources (e.g. engine room) are not routed
+        monitor.process_environmental_parameters(&EnvironmentalParameters::new(3, Some(320.0), 0, None, None), now);
+
+        let cabin = &monitor.data_samples[MetricId::CabinTemp.as_index()];
+        assert_eq!(cabin.len(), 1);
+        assert!((cabin[0].value - 20.0).abs() < 1e-9);
+        let water = &monitor.data_samples[MetricId::WaterTemp.as_index()];
+        assert_eq!(water.len(), 1);
+        assert!((water[0].value - 15.0).abs() < 1e-9);
+        assert_eq!(monitor.data_samples[MetricId::Humidity.as_index()].len(), 1);
+        assert_eq!(monitor.data_samples[MetricId::Pressure.as_index()].len(), 1);
+        assert_eq!(monitor.data_samples[MetricId::Pressure.as_index()][0].value, 101300.0);
+    }
+
+    #[test]
+    fn test_environmental_parameters_sensor_filters() {
+        let mut monitor = EnvironmentalMonitor::new();
+        monitor.set_humidity_sensors(vec![(0, 0)]);
+        monitor.set_pressure_sensors(vec![(1, 0)]);
+
+        // Outside humidity is not the configured sensor, pressure comes from another instance
+        monitor.process_environmental_parameters(&EnvironmentalParameters::new(4, None, 1, Some(80.0), Some(101300.0)), Instant::now());
+        assert!(!monitor.has_samples(MetricId::Humidity));
+        assert!(!monitor.has_samples(MetricId::Pressure));
+    }
+
     #[test]
     fn test_process_wind_but_no_boat_speed() {
         let mut monitor = EnvironmentalMonitor::new();
@@ -497,13 +931,109 @@ mod tests {
         monitor.process_wind(&wind_msg, Instant::now());
         assert_eq!(monitor.data_samples[MetricId::WindSpeed.as_index()].len(), 1);
         assert_eq!(monitor.data_samples[MetricId::WindDir.as_index()].len(), 1);
+        assert_eq!(monitor.data_samples[MetricId::WindGust.as_index()].len(), 1);
+    }
+
+    #[test]
+    fn test_spiky_wind_gust_is_peak() {
+        let mut monitor = EnvironmentalMonitor::new();
+        let start = Instant::now();
+        let speeds_ms = [5.0, 5.2, 14.0, 5.1, 4.9];
+        for (i, speed) in speeds_ms.iter().enumerate() {
+            let now = start + Duration::from_millis(100 * i as u64);
+            // Boat not moving: true wind is the apparent wind
+            monitor.last_boat_speed_knots = Some(0.0);
+            monitor.last_boat_speed_event = Some(now);
+            monitor.last_heading_degrees = Some(90.0);
+            monitor.last_heading_event = Some(now);
+            monitor.process_wind(&WindData::new_apparent(*speed, 0.5), now);
+        }
+        let spike_kn = WindData::new_apparent(14.0, 0.5).speed_knots();
+
+        // The average is smoothed, the max of the interval is the raw spike
+        let wind_speed = monitor.calculate_metric_data(MetricId::WindSpeed).unwrap();
+        assert_eq!(wind_speed.max, Some(spike_kn));
+        assert!(wind_speed.avg.unwrap() < spike_kn * 0.6);
+        assert_eq!(wind_speed.count, Some(speeds_ms.len()));
+
+        let gust = monitor.peak_sample(MetricId::WindGust).unwrap();
+        assert_eq!(gust.value, spike_kn);
+        assert_eq!(gust.timestamp, start + Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_true_wind_north_replaces_apparent_wind() {
+        let mut monitor = EnvironmentalMonitor::new();
+        let now = Instant::now();
+        monitor.last_boat_speed_knots = Some(0.0);
+        monitor.last_boat_speed_event = Some(now);
+        monitor.last_heading_degrees = Some(90.0);
+        monitor.last_heading_event = Some(now);
+
+        // Derived true wind over ground: 10 kn from 270° (west)
+        let ground_wind = WindData::new(10.0 / 1.94384, 270f64.to_radians(), WindReference::TrueGroundNorth);
+        monitor.process_wind(&ground_wind, now);
+        // The apparent wind of the same instant is not counted twice
+        monitor.process_wind(&WindData::new_apparent(8.0, 0.5), now);
+        assert_eq!(monitor.data_samples[MetricId::WindSpeed.as_index()].len(), 1);
+        assert!((monitor.data_samples[MetricId::WindSpeed.as_index()][0].value - 10.0).abs() < 1e-9);
+        assert!((monitor.data_samples[MetricId::WindDir.as_index()][0].value - 270.0).abs() < 1e-9);
+
+        // Without derived wind for a while, the apparent wind is used again
+        let later = now + Duration::from_secs(2);
+        monitor.last_boat_speed_event = Some(later);
+        monitor.last_heading_event = Some(later);
+        monitor.process_wind(&WindData::new_apparent(8.0, 0.5), later);
+        assert_eq!(monitor.data_samples[MetricId::WindSpeed.as_index()].len(), 2);
+
+        // Instrument true wind is not mistaken for apparent wind
+        monitor.process_wind(&WindData::new(8.0, 0.5, WindReference::TrueWater), later);
+        assert_eq!(monitor.data_samples[MetricId::WindSpeed.as_index()].len(), 2);
+    }
+
+    #[test]
+    fn test_wind_dir_average_across_north() {
+        let mut monitor = EnvironmentalMonitor::new();
+        let start = Instant::now();
+        for (i, dir) in [350.0, 355.0, 5.0, 10.0].iter().enumerate() {
+            monitor.data_samples[MetricId::WindDir.as_index()].push_back(Sample {
+                value: *dir,
+                timestamp: start + Duration::from_secs(i as u64),
+            });
+        }
+
+        let data = monitor.calculate_metric_data(MetricId::WindDir).unwrap();
+        assert!(angle_diff(data.avg.unwrap(), 0.0).abs() < 1e-6);
+        // The sector spans 350° to 10° clockwise, not 5° to 355°
+        assert!((data.min.unwrap() - 350.0).abs() < 1e-6);
+        assert!((data.max.unwrap() - 10.0).abs() < 1e-6);
+        assert_eq!(data.count, Some(4));
+    }
+
+    #[test]
+    fn test_peak_sample() {
+        let mut monitor = EnvironmentalMonitor::new();
+        assert!(monitor.peak_sample(MetricId::WindGust).is_none());
+
+        let start = Instant::now();
+        for (i, speed) in [12.0, 18.5, 14.0, 18.5, 9.0].iter().enumerate() {
+            monitor.data_samples[MetricId::WindGust.as_index()].push_back(Sample {
+                value: *speed,
+                timestamp: start + Duration::from_secs(i as u64),
+            });
+        }
+
+        let peak = monitor.peak_sample(MetricId::WindGust).unwrap();
+        assert_eq!(peak.value, 18.5);
+        // The first occurrence of the peak is kept
+        assert_eq!(peak.timestamp, start + Duration::from_secs(1));
     }
 
     #[test]
     fn test_process_attitude_roll() {
         let mut monitor = EnvironmentalMonitor::new();
         
-        let attitude_msg = Attitude::from_bytes(&vec![
+        let attitude_msg = Attitude::from_bytes(&[
             0x01,
             0x00, 0x00,
             0x00, 0x00,
@@ -514,6 +1044,118 @@ mod tests {
         assert_eq!(monitor.data_samples[MetricId::Roll.as_index()].len(), 1);
     }
 
+    #[test]
+    fn test_process_water_depth_decoded_offset() {
+        let mut monitor = EnvironmentalMonitor::new();
+
+        let depth_msg = WaterDepth::from_bytes(&[
+            0x01, // SID
+            0xF4, 0x01, 0x00, 0x00, // Depth = 500 * 0.01 = 5.0 m
+            0xF4, 0x01, // Offset = 500 * 0.001 = 0.5 m (transducer to waterline)
+        ]).unwrap();
+
+        monitor.process_water_depth(&depth_msg, Instant::now());
+
+        let raw = monitor.calculate_metric_data(MetricId::Depth).unwrap();
+        let corrected = monitor.calculate_metric_data(MetricId::DepthCorrected).unwrap();
+        assert!((raw.avg.unwrap() - 5.0).abs() < 1e-9);
+        assert!((corrected.avg.unwrap() - 5.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_process_water_depth_configured_offset() {
+        let mut monitor = EnvironmentalMonitor::new();
+        monitor.set_depth_offset(Some(-1.2)); // Transducer to keel
+
+        let depth_msg = WaterDepth::from_bytes(&[
+            0x01, // SID
+            0xF4, 0x01, 0x00, 0x00, // Depth = 5.0 m
+            0xF4, 0x01, // Offset = 0.5 m, overridden by configuration
+        ]).unwrap();
+
+        monitor.process_water_depth(&depth_msg, Instant::now());
+
+        let raw = monitor.calculate_metric_data(MetricId::Depth).unwrap();
+        let corrected = monitor.calculate_metric_data(MetricId::DepthCorrected).unwrap();
+        assert!((raw.avg.unwrap() - 5.0).abs() < 1e-9);
+        assert!((corrected.avg.unwrap() - 3.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_process_rate_of_turn() {
+        let mut monitor = EnvironmentalMonitor::new();
+        let start = Instant::now();
+        // 0.05 rad/s (2.86°/s) to starboard, then 0.03 rad/s to port
+        for (i, rate) in [50_000i32, -30_000].iter().enumerate() {
+            let mut data = vec![0x01];
+            data.extend_from_slice(&rate.to_le_bytes());
+            let rot = RateOfTurn::from_bytes(&data).unwrap();
+            monitor.process_rate_of_turn(&rot, start + Duration::from_secs(i as u64));
+        }
+
+        let data = monitor.calculate_metric_data(MetricId::RateOfTurn).unwrap();
+        assert_eq!(data.count, Some(2));
+        assert!((data.max.unwrap() - 0.05f64.to_degrees()).abs() < 1e-9);
+        assert!((data.min.unwrap() + 0.03f64.to_degrees()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rudder_activity_oscillating_vs_steady() {
+        let start = Instant::now();
+
+        // Autopilot in a seaway: rudder swinging +/-10 degrees
+        let mut active = EnvironmentalMonitor::new();
+        // Hand steering on a fixed helm: rudder held around 2 degrees
+        let mut steady = EnvironmentalMonitor::new();
+        for i in 0..20 {
+            let now = start + Duration::from_millis(500 * i);
+            let swing = if i % 2 == 0 { 10.0_f64 } else { -10.0 };
+            active.process_rudder(&Rudder::new(0, Some(swing.to_radians()), None), now);
+            let hold = if i % 2 == 0 { 2.0_f64 } else { 2.2 };
+            steady.process_rudder(&Rudder::new(0, Some(hold.to_radians()), None), now);
+        }
+
+        let active_activity = active.rudder_activity().unwrap();
+        let steady_activity = steady.rudder_activity().unwrap();
+        assert!((active_activity - 10.0).abs() < 0.01);
+        assert!((steady_activity - 0.1).abs() < 0.01);
+
+        let metric = active.calculate_metric_data(MetricId::RudderActivity).unwrap();
+        assert_eq!(metric.count, Some(20 - MIN_RUDDER_SAMPLES + 1));
+        assert!((metric.max.unwrap() - 10.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_rudder_activity_window() {
+        let mut monitor = EnvironmentalMonitor::new();
+        monitor.set_rudder_activity_window(Duration::from_secs(5));
+        let start = Instant::now();
+
+        // Oscillations first, then the rudder settles at the center
+        for i in 0..10 {
+            let swing = if i % 2 == 0 { 8.0_f64 } else { -8.0 };
+            monitor.process_rudder(&Rudder::new(0, Some(swing.to_radians()), None), start + Duration::from_secs(i));
+        }
+        assert!(monitor.rudder_activity().unwrap() > 7.0);
+
+        for i in 10..20 {
+            monitor.process_rudder(&Rudder::new(0, Some(0.0), None), start + Duration::from_secs(i));
+        }
+        // The oscillations are out of the window
+        assert!(monitor.rudder_activity().unwrap() < 1e-9);
+    }
+
+    #[test]
+    fn test_rudder_activity_requires_samples() {
+        let mut monitor = EnvironmentalMonitor::new();
+        let now = Instant::now();
+        monitor.process_rudder(&Rudder::new(0, Some(0.1), None), now);
+        monitor.process_rudder(&Rudder::new(0, None, None), now); // Position not available
+        monitor.process_rudder(&Rudder::new(1, Some(0.2), None), now); // Secondary rudder ignored
+        assert!(monitor.rudder_activity().is_none());
+        assert!(!monitor.has_samples(MetricId::RudderActivity));
+    }
+
     #[test]
     fn test_metric_data_all_none() {
         let data = MetricData {
@@ -1,27 +1,23 @@
 use std::collections::HashMap;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
+use std::sync::Arc;
 use tracing::{debug, warn};
 use crate::config::EnvironmentalConfig;
-use crate::db::VesselDatabase;
-use crate::environmental_monitor::{EnvironmentalMonitor, MetricId};
-use crate::utilities::dirty_instant_to_systemtime;
+use crate::db::Database;
+use crate::environmental_monitor::{EnvironmentalMonitor, MetricData, MetricId};
+use crate::utilities::WallClock;
+use crate::write_buffer::{BufferedWrite, WriteBuffer};
+use crate::db_writer::{DbQueue, DbWrite};
 
 /// State for tracking environmental metric persistence
 struct EnvironmentalStatusState {
     timing: HashMap<MetricId, Instant>,
     config: EnvironmentalConfig,
+    wall_clock: WallClock,
 }
 
 fn get_period(config: &EnvironmentalConfig, metric: MetricId) -> Duration {
-    match metric {
-        MetricId::WindSpeed => config.wind_speed_interval(),
-        MetricId::WindDir => config.wind_direction_interval(),
-        MetricId::Roll => config.roll_interval(),
-        MetricId::Pressure => config.pressure_interval(),
-        MetricId::CabinTemp => config.cabin_temp_interval(),
-        MetricId::WaterTemp => config.water_temp_interval(),
-        MetricId::Humidity => config.humidity_interval(),
-    }
+    config.metric_interval(metric)
 }
 
 impl EnvironmentalStatusState {
@@ -30,35 +26,56 @@ impl EnvironmentalStatusState {
         let mut x = Self {
             timing: HashMap::new(),
             config: environmental_config.clone(),
+            wall_clock: WallClock::new(),
         };
         let now = Instant::now();
         x.timing.insert(
             MetricId::WindSpeed,
-            now.checked_sub(get_period(&environmental_config, MetricId::WindSpeed)).unwrap(),
+            now.checked_sub(get_period(environmental_config, MetricId::WindSpeed)).unwrap(),
         );
         x.timing.insert(
             MetricId::WindDir,
-            now.checked_sub(get_period(&environmental_config, MetricId::WindDir)).unwrap(),
+            now.checked_sub(get_period(environmental_config, MetricId::WindDir)).unwrap(),
         );
         x.timing.insert(
             MetricId::Roll,
-            now.checked_sub(get_period(&environmental_config, MetricId::Roll)).unwrap(),
+            now.checked_sub(get_period(environmental_config, MetricId::Roll)).unwrap(),
         );
         x.timing.insert(
             MetricId::Pressure,
-            now.checked_sub(get_period(&environmental_config, MetricId::Pressure)).unwrap(),    
+            now.checked_sub(get_period(environmental_config, MetricId::Pressure)).unwrap(),    
         );
         x.timing.insert(
             MetricId::CabinTemp,
-            now.checked_sub(get_period(&environmental_config, MetricId::CabinTemp)).unwrap(),
+            now.checked_sub(get_period(environmental_config, MetricId::CabinTemp)).unwrap(),
         );
         x.timing.insert(
             MetricId::WaterTemp,
-            now.checked_sub(get_period(&environmental_config, MetricId::WaterTemp)).unwrap(),
+            now.checked_sub(get_period(environmental_config, MetricId::WaterTemp)).unwrap(),
         );
         x.timing.insert(
             MetricId::Humidity,
-            now.checked_sub(get_period(&environmental_config, MetricId::Humidity)).unwrap(),
+            now.checked_sub(get_period(environmental_config, MetricId::Humidity)).unwrap(),
+        );
+        x.timing.insert(
+            MetricId::Depth,
+            now.checked_sub(get_period(environmental_config, MetricId::Depth)).unwrap(),
+        );
+        x.timing.insert(
+            MetricId::DepthCorrected,
+            now.checked_sub(get_period(environmental_config, MetricId::DepthCorrected)).unwrap(),
+        );
+        x.timing.insert(
+            MetricId::RudderActivity,
+            now.checked_sub(get_period(environmental_config, MetricId::RudderActivity)).unwrap(),
+        );
+        x.timing.insert(
+            MetricId::WindGust,
+            now.checked_sub(get_period(environmental_config, MetricId::WindGust)).unwrap(),
+        );
+        x.timing.insert(
+            MetricId::RateOfTurn,
+            now.checked_sub(get_period(environmental_config, MetricId::RateOfTurn)).unwrap(),
         );
         x
     }
@@ -85,26 +102,73 @@ impl EnvironmentalStatusState {
 /// Handler for environmental status reporting and persistence
 pub struct EnvironmentalStatusHandler {
     state: EnvironmentalStatusState,
+    write_buffer: Option<WriteBuffer>,
+    /// Queue of the database writer thread, the database is written directly when not set
+    db_queue: Option<DbQueue>,
 }
 
 impl EnvironmentalStatusHandler {
     pub fn new(environmental_config: &EnvironmentalConfig) -> Self {
         Self {
             state: EnvironmentalStatusState::new(environmental_config),
+            write_buffer: None,
+            db_queue: None,
         }
     }
 
+    /// Set the buffer receiving the environmental metrics while the database is unavailable
+    pub fn set_write_buffer(&mut self, write_buffer: Option<WriteBuffer>) {
+        self.write_buffer = write_buffer;
+    }
+
+    /// Set the queue of the database writer thread, metrics are then written in the background
+    pub fn set_db_queue(&mut self, db_queue: Option<DbQueue>) {
+        self.db_queue = db_queue;
+    }
+
+    /// Set the clock converting the sample instants to the timestamps written
+    pub fn set_wall_clock(&mut self, wall_clock: WallClock) {
+        self.state.wall_clock = wall_clock;
+    }
+
+    /// Replace the persistence intervals, e.g. on configuration reload
+    /// The time of the last write of each metric is kept
+    pub fn set_config(&mut self, environmental_config: &EnvironmentalConfig) {
+        self.state.config = environmental_config.clone();
+    }
+
     /// Handle environmental status reporting and persistence
-    /// Returns Ok(count) with the number of environmental metrics written to the database
-    /// Returns Err if there was a database error
+    /// Returns Ok(count) with the number of environmental metrics written to the database (or to the write buffer when disconnected)
+    /// Returns Err if there was a database or write buffer error
     pub fn handle_environment_status(
         &mut self,
-        vessel_db: &Option<VesselDatabase>,
+        vessel_db: &Option<Arc<dyn Database>>,
         env_monitor: &mut EnvironmentalMonitor,
         now: Instant,
     ) -> Result<usize, Box<dyn std::error::Error>> {
+        if vessel_db.is_none() && let Some(ref buffer) = self.write_buffer {
+            return defer_environment_status(env_monitor, &mut self.state, now, |metric_id, timestamp, data| {
+                buffer.append(&BufferedWrite::Metric { metric_id, timestamp, data })
+            });
+        }
+        if vessel_db.is_some() && let Some(ref queue) = self.db_queue {
+            return defer_environment_status(env_monitor, &mut self.state, now, |metric_id, timestamp, data| {
+                Ok(queue.send(DbWrite::Metric { metric_id, timestamp, data })?)
+            });
+        }
         handle_environment_status(vessel_db, env_monitor, &mut self.state, now)
     }
+
+    /// Persist all environmental metrics immediately, regardless of their intervals
+    /// Returns the metrics written along with the id of their database row
+    pub fn flush(
+        &mut self,
+        db: &dyn Database,
+        env_monitor: &mut EnvironmentalMonitor,
+        now: Instant,
+    ) -> Result<Vec<(MetricId, i64)>, Box<dyn std::error::Error>> {
+        flush_environment_status(db, env_monitor, &mut self.state, now)
+    }
 }
 
 /// Handles environmental status persistence to the database
@@ -112,51 +176,132 @@ impl EnvironmentalStatusHandler {
 /// This function processes environmental metrics and writes them to the database
 /// when conditions are met (database connected, time synchronized, metrics ready).
 fn handle_environment_status(
-    vessel_db: &Option<VesselDatabase>,
+    vessel_db: &Option<Arc<dyn Database>>,
     env_monitor: &mut EnvironmentalMonitor,
     state: &mut EnvironmentalStatusState,
     now: Instant,
 ) -> Result<usize, Box<dyn std::error::Error>> {
     let mut written_count = 0;
     // Write to database if connected, time to persist, and time is synchronized
-    if let Some(ref db) = *vessel_db {
-        let now_timestamp = dirty_instant_to_systemtime(now); // used for database timestamp
+    if let Some(db) = vessel_db.as_deref() {
         let metrics_to_persist = state.get_metrics_to_persist(env_monitor, now);
-        if !metrics_to_persist.is_empty() {
-            for metricid in metrics_to_persist.iter() {
-                debug!("Persisting environmental metric: {}", metricid.name());
-                let data = env_monitor.calculate_metric_data(*metricid);
-                if let Some(metric_data) = data {
-                    debug!("Metric Data for {}: avg={:?}, max={:?}, min={:?}, count={:?}", 
-                        metricid.name(), 
-                        metric_data.avg, 
-                        metric_data.max, 
-                        metric_data.min,
-                        metric_data.count);
-                    if let Err(e) = db.insert_environmental_metrics(&metric_data, *metricid, now_timestamp) {
-                        warn!("Error writing {} data to database: {}", metricid.name(), e);
-                        return Err(e);
-                    } else {
-                        state.mark_metric_persisted(*metricid, now);
-                        env_monitor.cleanup_all_samples(*metricid);
-                        debug!("Environmental metric {} written to database", metricid.name());
-                        written_count += 1;
-                    }
-                } else {
-                    debug!("No data available for metric: {}", metricid.name());
-                }
+        for metricid in metrics_to_persist.iter() {
+            if persist_metric(db, env_monitor, state, *metricid, now)?.is_some() {
+                written_count += 1;
             }
         }
     }
     Ok(written_count)
 }
 
+/// Hands the environmental metrics due for persistence over to `write`: the write buffer, replayed
+/// to the database once the connection is restored, or the queue of the database writer thread
+fn defer_environment_status(
+    env_monitor: &mut EnvironmentalMonitor,
+    state: &mut EnvironmentalStatusState,
+    now: Instant,
+    mut write: impl FnMut(MetricId, SystemTime, MetricData) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut written_count = 0;
+    let metrics_to_persist = state.get_metrics_to_persist(env_monitor, now);
+    for metricid in metrics_to_persist.iter() {
+        if let Some((data, timestamp)) = metric_row(env_monitor, &state.wall_clock, *metricid, now) {
+            if let Err(e) = write(*metricid, timestamp, data) {
+                warn!("Error writing {} data: {}", metricid.name(), e);
+                return Err(e);
+            }
+            written_count += 1;
+        }
+        state.mark_metric_persisted(*metricid, now);
+        env_monitor.cleanup_all_samples(*metricid);
+    }
+    Ok(written_count)
+}
+
+/// Persists all environmental metrics having samples, regardless of their intervals
+/// Returns the metrics written along with the id of their database row
+fn flush_environment_status(
+    db: &dyn Database,
+    env_monitor: &mut EnvironmentalMonitor,
+    state: &mut EnvironmentalStatusState,
+    now: Instant,
+) -> Result<Vec<(MetricId, i64)>, Box<dyn std::error::Error>> {
+    let mut written = Vec::new();
+    for metricid in MetricId::ALL_METRICS.iter() {
+        if env_monitor.has_samples(*metricid)
+            && let Some(id) = persist_metric(db, env_monitor, state, *metricid, now)? {
+            written.push((*metricid, id));
+        }
+    }
+    Ok(written)
+}
+
+/// Computes the data and the database timestamp of the row to write for a metric
+/// The wind gust is stored as the single peak sample of the interval, timestamped at the peak,
+/// all the other metrics as avg/max/min over the interval, timestamped now
+fn metric_row(
+    env_monitor: &EnvironmentalMonitor,
+    wall_clock: &WallClock,
+    metricid: MetricId,
+    now: Instant,
+) -> Option<(MetricData, SystemTime)> {
+    match metricid {
+        MetricId::WindGust => env_monitor.peak_sample(metricid).map(|peak| {
+            let data = MetricData {
+                avg: Some(peak.value),
+                max: Some(peak.value),
+                min: Some(peak.value),
+                count: Some(1),
+            };
+            (data, wall_clock.to_system_time(peak.timestamp))
+        }),
+        _ => env_monitor
+            .calculate_metric_data(metricid)
+            .map(|data| (data, wall_clock.to_system_time(now))),
+    }
+}
+
+/// Writes a single metric to the database, marks it persisted and clears its samples
+/// Returns the id of the row written, None if there was no data for the metric
+fn persist_metric(
+    db: &dyn Database,
+    env_monitor: &mut EnvironmentalMonitor,
+    state: &mut EnvironmentalStatusState,
+    metricid: MetricId,
+    now: Instant,
+) -> Result<Option<i64>, Box<dyn std::error::Error>> {
+    debug!("Persisting environmental metric: {}", metricid.name());
+    if let Some((metric_data, now_timestamp)) = metric_row(env_monitor, &state.wall_clock, metricid, now) {
+        debug!("Metric Data for {}: avg={:?}, max={:?}, min={:?}, count={:?}", 
+            metricid.name(), 
+            metric_data.avg, 
+            metric_data.max, 
+            metric_data.min,
+            metric_data.count);
+        match db.insert_environmental_metrics(&metric_data, metricid, now_timestamp) {
+            Err(e) => {
+                warn!("Error writing {} data to database: {}", metricid.name(), e);
+                Err(e)
+            }
+            Ok(id) => {
+                state.mark_metric_persisted(metricid, now);
+                env_monitor.cleanup_all_samples(metricid);
+                debug!("Environmental metric {} written to database", metricid.name());
+                Ok(id)
+            }
+        }
+    } else {
+        debug!("No data available for metric: {}", metricid.name());
+        Ok(None)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::environmental_monitor::{EnvironmentalMonitor, MetricId, Sample};
     use crate::config::EnvironmentalConfig;
-    use std::time::Instant;
+    use std::time::{Instant, UNIX_EPOCH};
 
     #[test]
     fn test_mark_metric_persisted() {
@@ -195,8 +340,104 @@ mod tests {
              samples.push_back(Sample { value: 10.0, timestamp: now });
         }
         
-        // Now all 7 should be ready as they have data and haven't been persisted
+        // Now all 12 should be ready as they have data and haven't been persisted
         let metrics = state.get_metrics_to_persist(&monitor, now.checked_add(Duration::from_secs(600)).unwrap());
-        assert_eq!(metrics.len(), 7);
+        assert_eq!(metrics.len(), 12);
+    }
+
+    #[test]
+    fn test_depth_metrics_persisted_together() {
+        let config = EnvironmentalConfig::default();
+        let mut monitor = EnvironmentalMonitor::new();
+        let state = EnvironmentalStatusState::new(&config);
+
+        let now = Instant::now();
+        monitor.data_samples[MetricId::Depth.as_index()].push_back(Sample { value: 5.0, timestamp: now });
+        monitor.data_samples[MetricId::DepthCorrected.as_index()].push_back(Sample { value: 5.5, timestamp: now });
+
+        let metrics = state.get_metrics_to_persist(&monitor, now + config.depth_interval());
+        assert_eq!(metrics, vec![MetricId::Depth, MetricId::DepthCorrected]);
+    }
+
+    #[test]
+    fn test_wind_gust_row_is_peak_sample() {
+        let mut monitor = EnvironmentalMonitor::new();
+        let start = Instant::now() - Duration::from_secs(60);
+        let speeds = [11.0, 14.5, 23.2, 17.0, 12.3];
+        for (i, speed) in speeds.iter().enumerate() {
+            let sample = Sample { value: *speed, timestamp: start + Duration::from_secs(5 * i as u64) };
+            monitor.data_samples[MetricId::WindSpeed.as_index()].push_back(sample.clone());
+            monitor.data_samples[MetricId::WindGust.as_index()].push_back(sample);
+        }
+        let now = start + Duration::from_secs(30);
+
+        let wall_clock = WallClock::new();
+        let (gust, gust_timestamp) = metric_row(&monitor, &wall_clock, MetricId::WindGust, now).unwrap();
+        let max_sample = speeds.iter().cloned().fold(f64::MIN, f64::max);
+        assert_eq!(gust.max, Some(max_sample));
+        assert_eq!(gust.avg, Some(max_sample));
+        assert_eq!(gust.min, Some(max_sample));
+        // Timestamped at the peak, not at persistence time
+        let peak_time = wall_clock.to_system_time(start + Duration::from_secs(10));
+        let diff = gust_timestamp.duration_since(peak_time).unwrap_or_else(|e| e.duration());
+        assert!(diff < Duration::from_millis(5));
+
+        // The average wind speed row is unaffected
+        let (speed, _) = metric_row(&monitor, &wall_clock, MetricId::WindSpeed, now).unwrap();
+        assert!((speed.avg.unwrap() - 15.6).abs() < 1e-9);
+        assert_eq!(speed.max, Some(max_sample));
+    }
+
+    #[test]
+    fn test_clock_step_keeps_sample_timestamps() {
+        let mut monitor = EnvironmentalMonitor::new();
+        let start = Instant::now() - Duration::from_secs(60);
+        let start_time = UNIX_EPOCH + Duration::from_secs(1_717_236_000);
+        for (i, speed) in [11.0, 23.2, 12.3].iter().enumerate() {
+            let sample = Sample { value: *speed, timestamp: start + Duration::from_secs(5 * i as u64) };
+            monitor.data_samples[MetricId::WindSpeed.as_index()].push_back(sample.clone());
+            monitor.data_samples[MetricId::WindGust.as_index()].push_back(sample);
+        }
+
+        // The system clock is stepped an hour back after the gust
+        let mut wall_clock = WallClock::anchored_at(start, start_time);
+        let now = start + Duration::from_secs(30);
+        let jump = wall_clock.check_jump(now, start_time + Duration::from_secs(30) - Duration::from_secs(3600), Duration::from_secs(1));
+        assert_eq!(jump, Some(-3_600_000));
+
+        // The gust keeps the time it was measured at, the average is written at the new time
+        let (_, gust_timestamp) = metric_row(&monitor, &wall_clock, MetricId::WindGust, now).unwrap();
+        assert_eq!(gust_timestamp, start_time + Duration::from_secs(5));
+        let (_, speed_timestamp) = metric_row(&monitor, &wall_clock, MetricId::WindSpeed, now).unwrap();
+        assert_eq!(speed_timestamp, start_time + Duration::from_secs(30) - Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_metrics_buffered_without_database() {
+        let config = EnvironmentalConfig::default();
+        let path = std::env::temp_dir().join(format!("nmea_router_env_buffer_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut handler = EnvironmentalStatusHandler::new(&config);
+        handler.set_write_buffer(Some(WriteBuffer::new(&path)));
+
+        let mut monitor = EnvironmentalMonitor::new();
+        let now = Instant::now();
+        monitor.data_samples[MetricId::Pressure.as_index()].push_back(Sample { value: 101300.0, timestamp: now });
+        monitor.data_samples[MetricId::Pressure.as_index()].push_back(Sample { value: 101400.0, timestamp: now });
+
+        let written = handler.handle_environment_status(&None, &mut monitor, now).unwrap();
+        assert_eq!(written, 1);
+        assert!(!monitor.has_samples(MetricId::Pressure));
+
+        let buffered = WriteBuffer::new(&path).read_all().unwrap();
+        assert_eq!(buffered.len(), 1);
+        match &buffered[0] {
+            BufferedWrite::Metric { metric_id, data, .. } => {
+                assert_eq!(*metric_id, MetricId::Pressure);
+                assert_eq!(data.avg, Some(101350.0));
+            }
+            other => panic!("Unexpected entry {:?}", other),
+        }
+        let _ = std::fs::remove_file(&path);
     }
 }
@@ -0,0 +1,140 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+/// Maximum time the web API waits for the main loop to perform a flush
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An environmental metric row written by a flush
+#[derive(Debug, Clone, Serialize)]
+pub struct FlushedMetric {
+    pub metric: String,
+    pub id: i64,
+}
+
+/// What was written to the database by a manual flush
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct FlushReport {
+    pub vessel_status_id: Option<i64>,
+    pub environmental: Vec<FlushedMetric>,
+}
+
+struct FlushRequest {
+    reply: oneshot::Sender<Result<FlushReport, String>>,
+}
+
+/// Web side of the flush channel, used to ask the main loop for an immediate flush
+#[derive(Clone)]
+pub struct FlushRequester {
+    sender: Sender<FlushRequest>,
+}
+
+/// Main loop side of the flush channel
+pub struct FlushReceiver {
+    receiver: Receiver<FlushRequest>,
+}
+
+/// Create a connected requester/receiver pair
+pub fn flush_channel() -> (FlushRequester, FlushReceiver) {
+    let (sender, receiver) = mpsc::channel();
+    (FlushRequester { sender }, FlushReceiver { receiver })
+}
+
+impl FlushRequester {
+    /// Ask the main loop to flush and wait for the report
+    pub async fn request(&self) -> Result<FlushReport, String> {
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .send(FlushRequest { reply })
+            .map_err(|_| "Main loop is not running".to_string())?;
+        match tokio::time::timeout(FLUSH_TIMEOUT, response).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err("Flush request dropped".to_string()),
+            Err(_) => Err("Timed out waiting for flush".to_string()),
+        }
+    }
+}
+
+impl FlushReceiver {
+    /// Serve pending flush requests, if any, using the given flush function
+    /// Returns the number of requests served
+    pub fn serve<F>(&self, mut flush: F) -> usize
+    where
+        F: FnMut() -> Result<FlushReport, String>,
+    {
+        let mut served = 0;
+        while let Ok(request) = self.receiver.try_recv() {
+            // The requester may have timed out in the meantime, nothing to do about it
+            let _ = request.reply.send(flush());
+            served += 1;
+        }
+        served
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serve_without_requests_does_not_flush() {
+        let (_requester, receiver) = flush_channel();
+        let served = receiver.serve(|| panic!("flush must not run without a request"));
+        assert_eq!(served, 0);
+    }
+
+    #[test]
+    fn test_flush_returns_written_ids() {
+        let (requester, receiver) = flush_channel();
+
+        let main_loop = std::thread::spawn(move || {
+            let mut served = 0;
+            while served == 0 {
+                served = receiver.serve(|| {
+                    Ok(FlushReport {
+                        vessel_status_id: Some(42),
+                        environmental: vec![FlushedMetric { metric: "pressure".to_string(), id: 7 }],
+                    })
+                });
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        });
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let report = rt.block_on(requester.request()).unwrap();
+        main_loop.join().unwrap();
+
+        assert_eq!(report.vessel_status_id, Some(42));
+        assert!(!report.environmental.is_empty());
+        assert_eq!(report.environmental[0].metric, "pressure");
+        assert_eq!(report.environmental[0].id, 7);
+    }
+
+    #[test]
+    fn test_flush_error_is_reported() {
+        let (requester, receiver) = flush_channel();
+
+        let main_loop = std::thread::spawn(move || {
+            while receiver.serve(|| Err("Database unavailable".to_string())) == 0 {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        });
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt.block_on(requester.request());
+        main_loop.join().unwrap();
+
+        assert_eq!(result.unwrap_err(), "Database unavailable");
+    }
+
+    #[test]
+    fn test_request_fails_when_main_loop_gone() {
+        let (requester, receiver) = flush_channel();
+        drop(receiver);
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        assert!(rt.block_on(requester.request()).is_err());
+    }
+}
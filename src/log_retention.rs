@@ -0,0 +1,170 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::LogConfig;
+
+/// Time between two sweeps of the log directory
+const SWEEP_INTERVAL: Duration = Duration::from_secs(24 * 3600);
+
+/// A log file of the directory, as listed by the sweeper
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFile {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Select the log files to delete so that at most `max_files` files taking at most
+/// `max_total_bytes` are left, deleting the oldest first. A limit of 0 is no limit.
+///
+/// The daily files are named `<prefix>.YYYY-MM-DD`, so the newest sort last. The newest file is
+/// the one being written and is always kept.
+pub fn select_for_deletion(files: &[LogFile], max_files: usize, max_total_bytes: u64) -> Vec<String> {
+    let mut newest_first: Vec<&LogFile> = files.iter().collect();
+    newest_first.sort_by(|a, b| b.name.cmp(&a.name));
+
+    let mut kept_bytes = 0u64;
+    let mut to_delete = Vec::new();
+    for (index, file) in newest_first.into_iter().enumerate() {
+        kept_bytes += file.size;
+        let too_many = max_files > 0 && index >= max_files;
+        let too_large = max_total_bytes > 0 && kept_bytes > max_total_bytes;
+        if index > 0 && (too_many || too_large) {
+            to_delete.push(file.name.clone());
+        }
+    }
+    to_delete
+}
+
+/// Deletes the oldest rotated log files on startup and once a day, as configured in `logging`
+pub struct LogRetention {
+    directory: PathBuf,
+    file_prefix: String,
+    max_files: usize,
+    max_total_bytes: u64,
+    last_sweep: Option<Instant>,
+}
+
+impl LogRetention {
+    pub fn new(config: &LogConfig) -> Self {
+        Self {
+            directory: PathBuf::from(&config.directory),
+            file_prefix: config.file_prefix.clone(),
+            max_files: config.max_files,
+            max_total_bytes: config.max_total_size_mb * 1024 * 1024,
+            last_sweep: None,
+        }
+    }
+
+    /// Sweep the log directory if it was never swept or the last sweep is a day old
+    pub fn check(&mut self, now: Instant) {
+        if self.last_sweep.is_none_or(|last| now.saturating_duration_since(last) >= SWEEP_INTERVAL) {
+            self.last_sweep = Some(now);
+            self.sweep();
+        }
+    }
+
+    /// Delete the oldest log files exceeding the limits
+    pub fn sweep(&self) {
+        if self.max_files == 0 && self.max_total_bytes == 0 {
+            return;
+        }
+        let files = match list_log_files(&self.directory, &self.file_prefix) {
+            Ok(files) => files,
+            Err(e) => {
+                warn!("Failed to list the log directory {}: {}", self.directory.display(), e);
+                return;
+            }
+        };
+        for name in select_for_deletion(&files, self.max_files, self.max_total_bytes) {
+            match fs::remove_file(self.directory.join(&name)) {
+                Ok(()) => info!("Deleted old log file {}", name),
+                Err(e) => warn!("Failed to delete old log file {}: {}", name, e),
+            }
+        }
+    }
+}
+
+/// The files of the directory written by the rolling appender (`<prefix>.<date>`)
+fn list_log_files(directory: &Path, prefix: &str) -> std::io::Result<Vec<LogFile>> {
+    let rotated_prefix = format!("{}.", prefix);
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if metadata.is_file() && name.starts_with(&rotated_prefix) {
+            files.push(LogFile { name, size: metadata.len() });
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, size: u64) -> LogFile {
+        LogFile { name: name.to_string(), size }
+    }
+
+    fn listing() -> Vec<LogFile> {
+        vec![
+            file("nmea_router.2024-06-03", 300),
+            file("nmea_router.2024-06-01", 100),
+            file("nmea_router.2024-06-04", 400),
+            file("nmea_router.2024-06-02", 200),
+        ]
+    }
+
+    #[test]
+    fn test_select_by_count() {
+        let deleted = select_for_deletion(&listing(), 2, 0);
+        assert_eq!(deleted, ["nmea_router.2024-06-02", "nmea_router.2024-06-01"]);
+    }
+
+    #[test]
+    fn test_select_by_total_size() {
+        // 400 + 300 fit in 750, adding 200 doesn't
+        let deleted = select_for_deletion(&listing(), 0, 750);
+        assert_eq!(deleted, ["nmea_router.2024-06-02", "nmea_router.2024-06-01"]);
+    }
+
+    #[test]
+    fn test_newest_file_always_kept() {
+        let deleted = select_for_deletion(&listing(), 0, 10);
+        assert_eq!(deleted.len(), 3);
+        assert!(!deleted.contains(&"nmea_router.2024-06-04".to_string()));
+    }
+
+    #[test]
+    fn test_no_limits() {
+        assert!(select_for_deletion(&listing(), 0, 0).is_empty());
+        assert!(select_for_deletion(&listing(), 10, 10_000).is_empty());
+    }
+
+    #[test]
+    fn test_sweep_deletes_oldest_files() {
+        let directory = std::env::temp_dir().join(format!("nmea_router_log_retention_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        for name in ["nmea_router.2024-06-01", "nmea_router.2024-06-02", "nmea_router.2024-06-03", "other.2024-06-01"] {
+            fs::write(directory.join(name), "log line\n").unwrap();
+        }
+
+        let config = LogConfig {
+            directory: directory.to_string_lossy().into_owned(),
+            max_files: 2,
+            ..LogConfig::default()
+        };
+        LogRetention::new(&config).sweep();
+
+        let mut remaining: Vec<String> = fs::read_dir(&directory).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, ["nmea_router.2024-06-02", "nmea_router.2024-06-03", "other.2024-06-01"]);
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
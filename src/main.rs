@@ -1,11 +1,13 @@
 use std::{error::Error, time::Duration};
-use tracing::{info, warn};
+use tracing::{info, trace, warn};
 
 mod vessel_monitor;
 mod time_monitor;
 mod environmental_monitor;
+mod stuck_sensor;
 mod application_state;
 mod db;
+mod sqlite_db;
 mod config;
 mod trip;
 mod vessel_status_handler;
@@ -14,29 +16,54 @@ mod app_metrics;
 mod frame_filter;
 mod web;
 mod udp_broadcaster;
+mod tcp_server;
+mod signalk;
+mod flush;
+mod dead_reckoning;
+mod write_buffer;
+mod can_error_stats;
+mod data_quality;
+mod device_registry;
+mod replay;
+mod recorder;
+mod pgn_stats;
+mod busload;
+mod watchdog;
+mod shutdown;
+mod db_writer;
+mod log_retention;
+mod config_reload;
+mod dedup;
+mod derived;
 pub mod utilities;
 
-use vessel_monitor::VesselMonitor;
+use vessel_monitor::{EngineHysteresis, Position, VesselMonitor};
 use time_monitor::TimeMonitor;
-use environmental_monitor::EnvironmentalMonitor;
-use db::{VesselDatabase, HealthCheckManager};
+use environmental_monitor::{EnvironmentalMonitor, MetricId};
+use db::HealthCheckManager;
 use config::Config;
-use app_metrics::{AppMetrics, MetricsLogger};
+use app_metrics::{AppMetrics, MetricsLogger, SharedMetricTotals};
 use frame_filter::should_process_n2k_message;
 use frame_filter::should_process_frame_by_id;
 use udp_broadcaster::UdpBroadcaster;
+use tcp_server::TcpBroadcaster;
+use signalk::SignalKBroadcaster;
 // use crate::application_state::ApplicationState; // Removed: module does not exist
 
 // Import from nmea2k crate
-use nmea2k::{CanBus, Identifier, MessageHandler, N2kStreamReader};
+use nmea2k::{CanBus, Identifier, MessageHandler, N2kFrame, N2kStreamReader};
+use nmea2k::pgns::N2kMessage;
 
 use crate::application_state::ApplicationState;
 use crate::time_monitor::TimeSyncStatus;
 
 // ========== Logging Setup ==========
 
-fn init_logging(log_config: &config::LogConfig) -> Result<(), Box<dyn Error>> {
-    use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+/// Handle replacing the log level filter at runtime
+type LogReloadHandle = tracing_subscriber::reload::Handle<tracing_subscriber::EnvFilter, tracing_subscriber::Registry>;
+
+fn init_logging(log_config: &config::LogConfig) -> Result<LogReloadHandle, Box<dyn Error>> {
+    use tracing_subscriber::{fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter};
     use tracing_appender::rolling;
     
     // Create log directory if it doesn't exist
@@ -64,6 +91,7 @@ fn init_logging(log_config: &config::LogConfig) -> Result<(), Box<dyn Error>> {
     // Parse log level from config
     let env_filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new(&log_config.level));
+    let (env_filter, reload_handle) = reload::Layer::new(env_filter);
     
     tracing_subscriber::registry()
         .with(env_filter)
@@ -71,7 +99,61 @@ fn init_logging(log_config: &config::LogConfig) -> Result<(), Box<dyn Error>> {
         .with(file_layer)
         .init();
     
-    Ok(())
+    Ok(reload_handle)
+}
+
+/// Apply a reloaded log level, unless the level is set with the `RUST_LOG` environment variable
+fn reload_log_level(reload_handle: &LogReloadHandle, level: &str) {
+    if std::env::var_os("RUST_LOG").is_some() {
+        warn!("Log level set by RUST_LOG, the configured level {} is not applied", level);
+        return;
+    }
+    match reload_handle.reload(tracing_subscriber::EnvFilter::new(level)) {
+        Ok(()) => info!("Log level set to {}", level),
+        Err(e) => warn!("Failed to change the log level: {}", e),
+    }
+}
+
+// ========== Component Setup ==========
+
+/// Apply the vessel status settings that can change at runtime
+fn configure_vessel_monitor(vessel_monitor: &mut VesselMonitor, config: &config::VesselStatusConfig) {
+    vessel_monitor.set_status_intervals(config.interval_moored(), config.interval_underway());
+    vessel_monitor.set_min_position_interval(config.min_position_interval());
+    vessel_monitor.set_position_smoothing_alpha(config.position_smoothing_alpha);
+    vessel_monitor.set_engine_hysteresis(EngineHysteresis {
+        start_rpm: config.engine_start_rpm,
+        stop_rpm: config.engine_stop_rpm,
+        hold: config.engine_hysteresis(),
+    });
+    match config.anchor_watch {
+        Some(watch) => {
+            info!("Anchor watch at {:.5}, {:.5} with a {:.0} m radius", watch.latitude, watch.longitude, watch.radius_m);
+            vessel_monitor.set_anchor(Position { latitude: watch.latitude, longitude: watch.longitude }, watch.radius_m);
+        }
+        None => vessel_monitor.clear_anchor(),
+    }
+}
+
+/// Apply the environmental settings that can change at runtime
+fn configure_env_monitor(env_monitor: &mut EnvironmentalMonitor, config: &config::EnvironmentalConfig) {
+    env_monitor.set_depth_offset(config.depth_offset_m);
+    env_monitor.set_rudder_activity_window(config.rudder_activity_window());
+    env_monitor.set_pressure_sensors(config.pressure_sensor_ids());
+    env_monitor.set_humidity_sensors(config.humidity_sensor_ids());
+    for metric in MetricId::ALL_METRICS {
+        env_monitor.set_sample_window(metric, config.metric_interval(metric));
+    }
+}
+
+/// Apply the raw passthrough and unknown PGN settings of the source filter
+fn configure_reader(reader: &mut N2kStreamReader, source_filter: &config::SourceFilterConfig) {
+    // Replace the registered PGNs, the ones removed from the configuration are no longer passed through
+    reader.clear_raw_passthrough();
+    for pgn in &source_filter.raw_passthrough_pgns {
+        reader.register_raw_passthrough(*pgn);
+    }
+    reader.set_drop_unknown(source_filter.drop_unknown_pgns);
 }
 
 // ========== Main Application ==========
@@ -89,6 +171,8 @@ fn main() -> Result<(), Box<dyn Error>> {
         println!();
         println!("OPTIONS:");
         println!("    --validate-config, --validate, -v    Validate configuration and exit");
+        println!("    --replay <file>                      Read CAN frames from a candump -L log instead of the CAN bus");
+        println!("    --replay-realtime                    Replay with the recorded timing (default: as fast as possible)");
         println!("    --help, -h                           Show this help message");
         println!();
         println!("Configuration file:");
@@ -100,6 +184,15 @@ fn main() -> Result<(), Box<dyn Error>> {
                      || args.contains(&"--validate".to_string())
                      || args.contains(&"-v".to_string());
     
+    let replay_file = args.iter().position(|arg| arg == "--replay").map(|i| match args.get(i + 1) {
+        Some(file) => file.clone(),
+        None => {
+            eprintln!("--replay requires a candump log file");
+            std::process::exit(1);
+        }
+    });
+    let replay_realtime = args.contains(&"--replay-realtime".to_string());
+    
     // Load configuration - try /etc/nmea_router/config.json first, then ./config.json
     let config_path = if std::path::Path::new("/etc/nmea_router/config.json").exists() {
         "/etc/nmea_router/config.json"
@@ -109,7 +202,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     
     info!("Loading configuration from: {}", config_path);
     
-    let config = match Config::from_file(config_path) {
+    let mut config = match Config::from_file(config_path) {
         Ok(cfg) => {
             if validate_only {
                 println!("✓ Configuration validation successful");
@@ -142,28 +235,48 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     };
     
-    let application_state = std::sync::Arc::new(std::sync::Mutex::new(ApplicationState::new(config.clone())));
+    // A recording is not live: its time must not be checked against, nor set on, the system clock
+    if replay_file.is_some() {
+        config.time.skew_threshold_ms = i64::MAX;
+        config.time.set_system_time = false;
+        config.time.escalation.clear();
+    }
+    
+    let application_state = std::sync::Arc::new(std::sync::Mutex::new(ApplicationState::new()));
 
     // Initialize logging
-    init_logging(&config.logging)?;
+    let log_reload_handle = init_logging(&config.logging)?;
     info!("NMEA2000 Router starting...");
     info!("Loaded configuration");
+
+    // Delete the oldest log files now and once a day
+    let mut log_retention = log_retention::LogRetention::new(&config.logging);
+    log_retention.check(std::time::Instant::now());
     
-    // Open CAN socket with retry
-    let interface = &config.can_interface;
-    info!("Opening CAN interface: {}", interface);
-    
-    let mut socket = CanBus::open_can_socket_with_retry(interface);
-    CanBus::configure_nmea2k_socket(&mut socket).expect("Failed to configure CAN socket");
+    // Open CAN socket with retry, or the candump log to replay
+    let interface = config.can_interface.clone();
+    let mut replay = match replay_file {
+        Some(ref file) => {
+            info!("Replaying CAN frames from: {}{}", file, if replay_realtime { " (realtime)" } else { "" });
+            Some(replay::CandumpReader::open(file, replay_realtime)?)
+        }
+        None => None,
+    };
+    let mut socket = if replay.is_none() {
+        info!("Opening CAN interface: {}", interface);
+        let mut socket = CanBus::open_can_socket_with_retry(&interface);
+        CanBus::configure_nmea2k_socket(&mut socket, config.can_read_timeout()).expect("Failed to configure CAN socket");
+        Some(socket)
+    } else {
+        None
+    };
     
     info!("Listening for NMEA2000 messages");
     
     // Create database connection using config
-    let db_url = config.database.connection.connection_url();
-    
-    let mut vessel_db = match VesselDatabase::new(&db_url) {
+    let mut vessel_db = match db::connect(&config.database.connection) {
         Ok(db) => {
-            info!("Database connection established");
+            info!("Database connection established ({})", config.database.connection.backend);
             Some(db)
         }
         Err(e) => {
@@ -174,10 +287,21 @@ fn main() -> Result<(), Box<dyn Error>> {
     };
     
     // Create NMEA2000 stream reader
-    let mut reader = N2kStreamReader::new();
+    let mut reader = N2kStreamReader::with_extra_fast_packet_pgns(
+        config.fast_packet.max_buffers,
+        config.fast_packet.timeout(),
+        config.fast_packet.extra_fast_packet_pgns.iter().copied().collect(),
+    );
+    reader.set_field_layouts(config.field_decoding.layouts());
+    configure_reader(&mut reader, &config.source_filter);
     
     // Create vessel monitor with config
-    let mut vessel_monitor = VesselMonitor::new(application_state.clone());
+    let mut vessel_monitor = VesselMonitor::new(application_state.clone(), &config.database.vessel_status);
+    vessel_monitor.set_quality_config(config.quality_score.clone());
+    configure_vessel_monitor(&mut vessel_monitor, &config.database.vessel_status);
+    
+    // Names of the devices on the bus, from their Product Information
+    let mut device_registry = device_registry::DeviceRegistry::new();
     
     // Create time monitor
     let mut time_monitor = TimeMonitor::new(
@@ -185,9 +309,31 @@ fn main() -> Result<(), Box<dyn Error>> {
         config.time.skew_threshold_ms,
         config.time.set_system_time
     );
+    time_monitor.set_source_timeout(config.time.source_timeout());
+    if !config.time.escalation.is_empty() {
+        time_monitor.set_escalation(
+            time_monitor::SkewEscalation::new(config.time.escalation_steps(), config.time.action_cooldown()),
+            config.time.ntp_resync_command.clone(),
+        );
+    }
     
     // Create environmental monitor with config
     let mut env_monitor = EnvironmentalMonitor::new();
+    configure_env_monitor(&mut env_monitor, &config.database.environmental);
+    if config.stuck_sensor.enabled {
+        env_monitor.set_stuck_detector(stuck_sensor::StuckSensorDetector::new(
+            config.stuck_sensor.metric_ids(),
+            config.stuck_sensor.min_samples,
+            config.stuck_sensor.min_duration(),
+            config.stuck_sensor.warning_interval(),
+        ));
+    }
+    
+    // Create dead reckoning estimator (if enabled)
+    let mut dead_reckoning = config.dead_reckoning.enabled.then(|| {
+        info!("Dead reckoning enabled (max {}s without GPS fix)", config.dead_reckoning.max_duration_seconds);
+        dead_reckoning::DeadReckoning::new(config.dead_reckoning.gps_timeout(), config.dead_reckoning.max_duration())
+    });
     
     // Create vessel status handler
     let mut vessel_status_handler = vessel_status_handler::VesselStatusHandler::new(config.database.vessel_status.clone());
@@ -195,41 +341,84 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Create environmental status handler
     let mut environmental_status_handler = environmental_status_handler::EnvironmentalStatusHandler::new(&config.database.environmental);
     
+    // Buffer database writes to disk while the database is unavailable
+    let write_buffer = config.database.write_buffer_path.as_ref().map(write_buffer::WriteBuffer::new);
+    vessel_status_handler.set_write_buffer(write_buffer.clone());
+    environmental_status_handler.set_write_buffer(write_buffer.clone());
+    
+    // Write to the database from a background thread, so a slow database does not stall the CAN processing
+    let db_writer = db_writer::DbWriter::spawn(vessel_db.clone(), write_buffer.clone(), db_writer::DB_WRITER_QUEUE_CAPACITY);
+    vessel_status_handler.set_db_queue(Some(db_writer.queue()));
+    environmental_status_handler.set_db_queue(Some(db_writer.queue()));
+    
     // Create UDP broadcaster with config
     let mut udp_broadcaster = UdpBroadcaster::new(
-        config.udp.address.clone(),
-        config.udp.enabled
+        config.udp.destinations(),
+        config.udp.enabled,
+        config.udp.json_style,
     );
     
     if config.udp.enabled {
-        info!("UDP broadcaster enabled: {}", config.udp.address);
+        info!("UDP broadcaster enabled: {}", config.udp.destinations().join(", "));
+    }
+
+    // Create TCP server with config
+    let mut tcp_broadcaster = TcpBroadcaster::new(&config.tcp.listen, config.tcp.enabled);
+
+    if config.tcp.enabled {
+        info!("TCP server enabled: {}", config.tcp.listen);
+    }
+
+    // Create SignalK delta output with config
+    let mut signalk_broadcaster = SignalKBroadcaster::new(&config.signalk);
+
+    if config.signalk.enabled {
+        info!("SignalK output enabled: {:?} {}", config.signalk.transport, config.signalk.address);
     }
     
     // Load the last trip from database if available
-    if let Some(ref db) = vessel_db {
+    if let Some(db) = vessel_db.as_deref() {
         vessel_status_handler.load_last_trip(db);
     }
 
-    // Start web server if enabled and database is available
+    // Channel used by the web API to request an immediate flush from the main loop
+    let (flush_requester, flush_receiver) = flush::flush_channel();
+
+    // Decoded messages streamed to the web clients of /api/live
+    let mut live_feed = web::LiveFeed::new(web::LIVE_FEED_CAPACITY);
+
+    // Application counters published for the Prometheus exposition
+    let metric_totals = SharedMetricTotals::default();
+
+    // Arrival rate of each PGN, served by /api/pgn_rates
+    let pgn_rates = pgn_stats::SharedPgnRateTracker::default();
+    // Estimated utilization of the CAN bus, served by /api/pgn_rates and logged with the metrics
+    let mut bus_load = busload::BusLoadEstimator::default();
+
+    // Database connection of the web server, kept in sync with the reconnections
+    let web_db: db::SharedDatabase = std::sync::Arc::new(std::sync::Mutex::new(vessel_db.clone()));
+
+    // Start web server if enabled, also without database: the health check reports it
     if config.web.enabled {
-        if let Some(ref db) = vessel_db {
-            let db_arc = std::sync::Arc::new(db.clone());
-            let web_port = config.web.port;
-            
-            // Spawn web server in a separate thread
-            std::thread::spawn(move || {
-                let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
-                rt.block_on(async {
-                    if let Err(e) = web::start_web_server(db_arc, web_port).await {
-                        warn!("Web server error: {}", e);
-                    }
-                });
+        let web_db = web_db.clone();
+        let web_config = config.web.clone();
+        let flush_requester = flush_requester.clone();
+        let live_feed = live_feed.clone();
+        let metric_totals = metric_totals.clone();
+        let pgn_rates = pgn_rates.clone();
+        let timezone = config.tz();
+        
+        // Spawn web server in a separate thread
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+            rt.block_on(async {
+                if let Err(e) = web::start_web_server(web_db, web_config, flush_requester, live_feed, metric_totals, pgn_rates, timezone).await {
+                    warn!("Web server error: {}", e);
+                }
             });
-            
-            info!("Web server started on port {}", config.web.port);
-        } else {
-            warn!("Web server disabled - database connection unavailable");
-        }
+        });
+        
+        info!("Web server started on {}", config.web.socket_addr());
     } else {
         info!("Web server disabled in configuration");
     }
@@ -237,15 +426,123 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Application metrics tracking
     let mut metrics = AppMetrics::new();
     let mut metrics_logger = MetricsLogger::new(Duration::from_secs(60));
+    let mut can_error_stats = config.diagnostics.persist_can_errors.then(can_error_stats::CanErrorStats::new);
+    let mut source_watchdog = config.watchdog.enabled.then(|| watchdog::SourceWatchdog::new(config.watchdog.pgn_timeouts()));
+    let mut deduplicator = config.dedup.enabled.then(|| dedup::MessageDeduplicator::new(&config.dedup));
+    let mut derived_engine = config.derived.true_wind_north.then(|| derived::DerivedMessageEngine::new(&config.derived));
+    
+    // Raw CAN traffic recording (candump format)
+    let mut recorder = recorder::FrameRecorder::new(
+        config.recorder.enabled,
+        &config.recorder.directory,
+        &config.recorder.file_prefix,
+        &interface,
+    );
+    
+    // Wall clock of the persisted timestamps, re-anchored when the system clock jumps
+    let mut wall_clock = utilities::WallClock::new();
     
     // Database health check manager
     let mut db_health_check = HealthCheckManager::new(Duration::from_secs(60));
 
+    // Stop on SIGINT/SIGTERM, polled at least every CAN read timeout (500 ms)
+    let shutdown = shutdown::install_shutdown_handler();
+    // Reload the configuration on SIGHUP, without reconnecting the CAN bus nor losing the trip
+    let reload = config_reload::install_reload_handler();
+
+    // Time of the frame being processed: the recorded time when replaying, so that the intervals
+    // (statuses, metrics, trips, dedup) play out as on the boat even when replaying fast
+    let mut now = std::time::Instant::now();
+    let mut replay_clock_pending = replay.is_some();
+
     // Read CAN frames in a loop
     loop {
-        match CanBus::read_nmea2k_frame(&socket) {
-            Ok((extended_id, data)) => {
+        if shutdown.is_requested() {
+            break;
+        }
+
+        if reload.take() {
+            match Config::from_file(config_path) {
+                Ok(new_config) => {
+                    let changes = config_reload::diff_config(&config, &new_config);
+                    if changes.source_filter {
+                        configure_reader(&mut reader, &new_config.source_filter);
+                        config.source_filter = new_config.source_filter.clone();
+                        info!("Configuration reload: source filter applied");
+                    }
+                    if changes.vessel_status {
+                        configure_vessel_monitor(&mut vessel_monitor, &new_config.database.vessel_status);
+                        vessel_status_handler.set_config(new_config.database.vessel_status.clone());
+                        config.database.vessel_status = new_config.database.vessel_status.clone();
+                        info!("Configuration reload: vessel status settings applied");
+                    }
+                    if changes.environmental {
+                        configure_env_monitor(&mut env_monitor, &new_config.database.environmental);
+                        environmental_status_handler.set_config(&new_config.database.environmental);
+                        config.database.environmental = new_config.database.environmental.clone();
+                        info!("Configuration reload: environmental settings applied");
+                    }
+                    if changes.log_level {
+                        reload_log_level(&log_reload_handle, &new_config.logging.level);
+                        config.logging.level = new_config.logging.level.clone();
+                    }
+                    if changes.udp {
+                        udp_broadcaster.set_destinations(new_config.udp.destinations());
+                        udp_broadcaster.set_json_style(new_config.udp.json_style);
+                        config.udp.address = new_config.udp.address.clone();
+                        config.udp.extra_addresses = new_config.udp.extra_addresses.clone();
+                        config.udp.json_style = new_config.udp.json_style;
+                        info!("Configuration reload: UDP destinations {}", config.udp.destinations().join(", "));
+                    }
+                    for setting in &changes.restart_required {
+                        warn!("Configuration reload: {} changed, restart to apply it", setting);
+                    }
+                    if changes.is_empty() {
+                        info!("Configuration reload: no changes");
+                    }
+                }
+                Err(e) => warn!("Configuration reload failed, keeping the current configuration: {}", e),
+            }
+        }
+
+        if replay.is_none()
+            && let Some(jump_ms) = wall_clock.check_jump(std::time::Instant::now(), std::time::SystemTime::now(), config.time.clock_jump_threshold()) {
+            warn!("System clock jumped by {} ms: samples taken before the jump keep their original timestamps", jump_ms);
+            vessel_status_handler.set_wall_clock(wall_clock.clone());
+            environmental_status_handler.set_wall_clock(wall_clock.clone());
+        }
+        
+        let frame = match (replay.as_mut(), socket.as_ref()) {
+            (Some(replay), _) => match replay.next() {
+                Some(frame) => frame,
+                None => {
+                    info!("Replay finished ({} lines skipped)", replay.skipped_lines());
+                    break;
+                }
+            },
+            (None, Some(socket)) => CanBus::read_nmea2k_frame(socket).map(|(id, data)| (id, data, std::time::Instant::now())),
+            (None, None) => unreachable!("Either the CAN socket or the replay is open"),
+        };
+        now = match frame {
+            Ok((_, _, time)) => time,
+            Err(_) => std::time::Instant::now(),
+        };
+        // The records of a replay are timestamped with the recorded time
+        if replay_clock_pending && let Some(recording_clock) = replay.as_ref().and_then(|replay| replay.wall_clock()) {
+            replay_clock_pending = false;
+            wall_clock = recording_clock;
+            vessel_status_handler.set_wall_clock(wall_clock.clone());
+            environmental_status_handler.set_wall_clock(wall_clock.clone());
+        }
+        match frame {
+            Ok((extended_id, data, _)) => {
+                // Recorded before any filtering, to replay the bus as it was
+                recorder.record(extended_id, &data);
+                bus_load.record(data.len(), now);
                 metrics.can_frames += 1;
+                if let Some(ref mut stats) = can_error_stats {
+                    stats.record_frame();
+                }
                 
                 let id = Identifier::from_can_id(extended_id);
                 if !should_process_frame_by_id(&config, id) {
@@ -255,23 +552,73 @@ fn main() -> Result<(), Box<dyn Error>> {
                 metrics.can_processed_frames += 1;
 
                 // Process the frame through the stream reader
-                if let Some(n2k_frame) = reader.process_frame(extended_id, &data) {
+                let n2k_frame = reader.process_frame_at(extended_id, &data, now);
+                metrics.fast_packet_evictions += reader.take_evicted_count();
+                metrics.fast_packet_expired += reader.take_expired_count();
+                if let Some(mut n2k_frame) = n2k_frame {
                     metrics.nmea_messages += 1;
+                    pgn_rates.lock().unwrap().record(n2k_frame.pgn(), now);
                     
                     if !should_process_n2k_message(&config, &n2k_frame.message) {
                         continue;
                     }
 
+                    // Sensors repeating the same value many times per second
+                    if let Some(ref mut dedup) = deduplicator
+                        && dedup.is_duplicate(n2k_frame.source(), &n2k_frame.message, now) {
+                        metrics.duplicate_messages += 1;
+                        continue;
+                    }
+
                     metrics.nmea_processed_messages += 1;
-                    
-                    let now = std::time::Instant::now();
+
+                    // Battery current is handed over as charge-positive whatever the device convention
+                    if let N2kMessage::BatteryStatus(ref mut battery) = n2k_frame.message {
+                        config.normalize_battery_current(battery);
+                    }
+
+
+                    device_registry.handle_message(&n2k_frame, now);
+                    if let Some(ref mut watchdog) = source_watchdog {
+                        watchdog.handle_message(&n2k_frame, now);
+                    }
+                    trace!(
+                        pgn = n2k_frame.pgn(),
+                        source = n2k_frame.source(),
+                        device = device_registry.name_for(n2k_frame.source()).unwrap_or("unknown"),
+                        "NMEA2000 message"
+                    );
 
                     time_monitor.handle_message(&n2k_frame, now);
+                    if let Some(action) = time_monitor.take_last_action() {
+                        metrics.skew_action = Some(action);
+                    }
                     
-                    // Broadcast message via UDP (if enabled)
+                    // Broadcast message via UDP, TCP, SignalK (if enabled) and to the web live feed
                     udp_broadcaster.handle_message(&n2k_frame, now);
+                    tcp_broadcaster.handle_message(&n2k_frame, now);
+                    signalk_broadcaster.handle_message(&n2k_frame, now);
+                    live_feed.handle_message(&n2k_frame, now);
+
+                    // Messages computed from this one go to the same outputs, attributed to its source
+                    let derived_frame = derived_engine.as_mut()
+                        .and_then(|engine| engine.handle_message(&n2k_frame.message, now))
+                        .map(|message| N2kFrame::derived(n2k_frame.priority(), n2k_frame.source(), message));
+                    if let Some(ref derived_frame) = derived_frame {
+                        udp_broadcaster.handle_message(derived_frame, now);
+                        tcp_broadcaster.handle_message(derived_frame, now);
+                        signalk_broadcaster.handle_message(derived_frame, now);
+                        live_feed.handle_message(derived_frame, now);
+                    }
+
+                    // Keep the best available position (GPS or dead reckoning) up to date
+                    if let Some(ref mut dr) = dead_reckoning {
+                        dr.handle_message(&n2k_frame, now);
+                        let position = dr.update(now);
+                        application_state.lock().unwrap().update_composite_position(position);
+                    }
                     
-                    let sync_status_and_skew = time_monitor.time_sync_status();
+                    let sync_status_and_skew = time_monitor.time_sync_status(now);
                     metrics.gnss_time_skew = sync_status_and_skew.skew;
                     metrics.gnss_time_skew_status = sync_status_and_skew.status;
                     if sync_status_and_skew.status == TimeSyncStatus::Synchronized {
@@ -284,8 +631,15 @@ fn main() -> Result<(), Box<dyn Error>> {
                                     warn!("Database error during vessel status write: {}", e);
                                 }
                             }
+                            if let Some(distance_m) = vessel_monitor.check_anchor_drift() {
+                                warn!("Anchor alarm: vessel is {:.0} m from the anchor point", distance_m);
+                            }
                         }
 
+                        // The derived true wind goes first: the monitor then skips the apparent wind it replaces
+                        if let Some(ref derived_frame) = derived_frame {
+                            env_monitor.handle_message(derived_frame, now);
+                        }
                         env_monitor.handle_message(&n2k_frame, now);
                         match environmental_status_handler.handle_environment_status(&vessel_db, &mut env_monitor, now) {
                             Ok(count) => metrics.env_reports += count as u64,
@@ -298,38 +652,120 @@ fn main() -> Result<(), Box<dyn Error>> {
                     }
                 }
             }
+            Err(e) if CanBus::is_read_timeout(&e) => {
+                // No frame within the read timeout: the bus is idle, not lost
+                // Don't log or count as error, the periodic tasks below run as usual
+            }
             Err(e) => {
-                // Check if this is just a timeout (no data available)
-                if e.kind() == std::io::ErrorKind::WouldBlock || e.kind() == std::io::ErrorKind::TimedOut {
-                    // Timeout is expected - just continue to allow metrics and health checks
-                    // Don't log or count as error
-                } else {
-                    // Actual error - log and reconnect
-                    metrics.can_errors += 1;
-                    warn!("Error reading CAN frame: {}", e);
-                    warn!("CAN bus connection lost. Attempting to reconnect...");
-                    
-                    // Try to reconnect
-                    socket = CanBus::open_can_socket_with_retry(interface);
-                    CanBus::configure_nmea2k_socket(&mut socket).expect("Failed to configure CAN socket");
-                    
-                    info!("Reconnected to CAN bus. Resuming operation");
-                    
-                    // Wait before resuming to allow bus to stabilize
-                    std::thread::sleep(std::time::Duration::from_millis(500));
+                // Actual error - log and reconnect
+                metrics.can_errors += 1;
+                if let Some(ref mut stats) = can_error_stats {
+                    stats.record_error();
                 }
+                warn!("Error reading CAN frame: {}", e);
+                if replay.is_some() {
+                    warn!("Stopping replay");
+                    break;
+                }
+                warn!("CAN bus connection lost. Attempting to reconnect...");
+                
+                // Try to reconnect
+                let mut new_socket = CanBus::open_can_socket_with_retry(&interface);
+                CanBus::configure_nmea2k_socket(&mut new_socket, config.can_read_timeout()).expect("Failed to configure CAN socket");
+                socket = Some(new_socket);
+                
+                info!("Reconnected to CAN bus. Resuming operation");
+                
+                // Wait before resuming to allow bus to stabilize
+                std::thread::sleep(std::time::Duration::from_millis(500));
+            }
+        }
+        
+        // Track the trips created by the database writer
+        for event in db_writer.take_events() {
+            let db_writer::DbWriteEvent::TripCreated { start, id, description } = event;
+            vessel_status_handler.trip_created(start, id, &description);
+        }
+        
+        // Serve manual flush requests from the web API
+        flush_receiver.serve(|| {
+            let db = vessel_db.as_deref().ok_or("Database unavailable")?;
+            // The flush writes directly: complete the queued writes first to keep the rows in order
+            db_writer.sync();
+            if time_monitor.time_sync_status(now).status != TimeSyncStatus::Synchronized {
+                return Err("Time not synchronized".to_string());
             }
+            let vessel_status_id = match vessel_monitor.force_status(now) {
+                Some(status) => Some(vessel_status_handler.flush_vessel_status(db, status).map_err(|e| e.to_string())?),
+                None => None,
+            };
+            let environmental = environmental_status_handler.flush(db, &mut env_monitor, now)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .map(|(metric, id)| flush::FlushedMetric { metric: metric.name().to_string(), id })
+                .collect();
+            Ok(flush::FlushReport { vessel_status_id, environmental })
+        });
+        
+        // Warn about the sources that stopped transmitting
+        if let Some(ref mut watchdog) = source_watchdog {
+            watchdog.check(now);
         }
         
-        // Log metrics periodically
+        // Publish the totals for the Prometheus exposition and log metrics periodically
+        metrics.humidity_set_point = env_monitor.humidity_set_point();
+        *metric_totals.lock().unwrap() = metrics.totals();
+        metrics.bus_load_percent = bus_load.load_percent(now);
+        pgn_rates.lock().unwrap().set_bus_load_percent(metrics.bus_load_percent);
         metrics_logger.check_and_log(&mut metrics);
+        log_retention.check(std::time::Instant::now());
+        
+        // Persist the CAN error statistics of each completed hour
+        if let Some(bucket) = can_error_stats.as_mut().and_then(|stats| stats.roll(std::time::SystemTime::now())) {
+            match vessel_db.as_deref() {
+                Some(db) => {
+                    if let Err(e) = db.insert_can_error_bucket(&bucket) {
+                        warn!("Failed to persist CAN error statistics for {}: {}", bucket.hour, e);
+                    }
+                }
+                None => warn!("Database unavailable, CAN error statistics for {} not persisted", bucket.hour),
+            }
+        }
         
         // Database health check using manager
-        if db_health_check.check_and_reconnect(&mut vessel_db, &db_url) {
-            // Reload last trip if reconnection occurred
-            if let Some(ref db) = vessel_db {
+        if db_health_check.check_and_reconnect(&mut vessel_db, &config.database.connection) {
+            // The web server follows the reconnections, and reports the database down meanwhile
+            *web_db.lock().unwrap() = vessel_db.clone();
+            if let Some(db) = vessel_db.as_deref() {
+                // Complete the writes queued while disconnected (buffered) and switch the writer to the new connection
+                db_writer.sync();
+                db_writer.set_database(vessel_db.clone());
+                // Replay the writes buffered while disconnected, in timestamp order
+                if let Some(ref buffer) = write_buffer
+                    && buffer.has_pending()
+                    && let Err(e) = buffer.drain(db) {
+                    warn!("Failed to replay buffered writes, will retry: {}", e);
+                }
+                // Reload last trip if reconnection occurred
                 vessel_status_handler.load_last_trip(db);
             }
         }
     }
+    
+    // End of the replay or shutdown: close the trip with a last vessel status and persist what is still pending
+    db_writer.sync();
+    for event in db_writer.take_events() {
+        let db_writer::DbWriteEvent::TripCreated { start, id, description } = event;
+        vessel_status_handler.trip_created(start, id, &description);
+    }
+    if let Err(e) = vessel_status_handler.finalize(&vessel_db, vessel_monitor.force_status(now)) {
+        warn!("Failed to write the last vessel status: {}", e);
+    }
+    if let Some(db) = vessel_db.as_deref()
+        && let Err(e) = environmental_status_handler.flush(db, &mut env_monitor, now) {
+        warn!("Failed to write the last environmental metrics: {}", e);
+    }
+    db_writer.stop();
+    metrics.log();
+    Ok(())
 }
\ No newline at end of file
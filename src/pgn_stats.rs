@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Messages received for one PGN
+#[derive(Debug, Clone, Copy)]
+struct PgnCounter {
+    count: u64,
+    first: Instant,
+    last: Instant,
+}
+
+/// Arrival rate of each PGN, to spot sensors transmitting irregularly
+///
+/// The rate is computed over the window between the first and the last message of the PGN.
+#[derive(Debug, Default)]
+pub struct PgnRateTracker {
+    counters: HashMap<u32, PgnCounter>,
+    /// Last estimated bus load in percent, published by the main loop
+    bus_load_percent: f64,
+}
+
+/// Tracker fed by the main loop and read by the web server
+pub type SharedPgnRateTracker = Arc<Mutex<PgnRateTracker>>;
+
+impl PgnRateTracker {
+    /// Record a message of the PGN received at `now`
+    pub fn record(&mut self, pgn: u32, now: Instant) {
+        self.counters
+            .entry(pgn)
+            .and_modify(|counter| {
+                counter.count += 1;
+                counter.last = now;
+            })
+            .or_insert(PgnCounter { count: 1, first: now, last: now });
+    }
+
+    pub fn set_bus_load_percent(&mut self, percent: f64) {
+        self.bus_load_percent = percent;
+    }
+
+    pub fn bus_load_percent(&self) -> f64 {
+        self.bus_load_percent
+    }
+
+    /// Messages per second of each PGN, sorted by PGN
+    /// A PGN seen only once (or all within the same instant) has a rate of 0
+    pub fn rates(&self) -> Vec<(u32, f64)> {
+        let mut rates: Vec<(u32, f64)> = self.counters
+            .iter()
+            .map(|(pgn, counter)| {
+                let window = counter.last.duration_since(counter.first).as_secs_f64();
+                let rate = if window > 0.0 { (counter.count - 1) as f64 / window } else { 0.0 };
+                (*pgn, rate)
+            })
+            .collect();
+        rates.sort_by_key(|(pgn, _)| *pgn);
+        rates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rates_over_simulated_window() {
+        let mut tracker = PgnRateTracker::default();
+        let start = Instant::now();
+        // Position at 10 Hz and wind at 1 Hz for 10 seconds
+        for i in 0..=100 {
+            tracker.record(129025, start + Duration::from_millis(i * 100));
+        }
+        for i in 0..=10 {
+            tracker.record(130306, start + Duration::from_secs(i));
+        }
+
+        let rates = tracker.rates();
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].0, 129025);
+        assert!((rates[0].1 - 10.0).abs() < 1e-9, "Expected 10, got {}", rates[0].1);
+        assert_eq!(rates[1].0, 130306);
+        assert!((rates[1].1 - 1.0).abs() < 1e-9, "Expected 1, got {}", rates[1].1);
+    }
+
+    #[test]
+    fn test_single_message_has_no_rate() {
+        let mut tracker = PgnRateTracker::default();
+        tracker.record(126996, Instant::now());
+        assert_eq!(tracker.rates(), vec![(126996, 0.0)]);
+        assert!(PgnRateTracker::default().rates().is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+use nmea2k::ExtendedId;
+use tracing::{info, warn};
+
+/// Maximum time frames stay in the write buffer before reaching the file
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Records the raw CAN frames in `candump -L` format, to be replayed with `--replay`
+///
+/// A file is written per UTC day, named `<file_prefix>.YYYY-MM-DD` like the log files.
+/// Timestamps follow the monotonic clock from the wall-clock time the recorder started,
+/// so a system clock step does not break the order of the frames.
+pub struct FrameRecorder {
+    enabled: bool,
+    directory: PathBuf,
+    file_prefix: String,
+    interface: String,
+    start: (Instant, SystemTime),
+    /// Day of the current file and its writer
+    file: Option<(String, BufWriter<File>)>,
+    last_flush: Instant,
+}
+
+/// Format a frame as a `candump -L` line: `(1436509052.249713) can0 09F8027F#00FC0D0D4B000000`
+pub fn candump_line(timestamp: Duration, interface: &str, id: ExtendedId, data: &[u8]) -> String {
+    let data: String = data.iter().map(|b| format!("{:02X}", b)).collect();
+    format!(
+        "({}.{:06}) {} {:08X}#{}",
+        timestamp.as_secs(),
+        timestamp.subsec_micros(),
+        interface,
+        id.as_raw(),
+        data
+    )
+}
+
+impl FrameRecorder {
+    pub fn new<P: AsRef<Path>>(enabled: bool, directory: P, file_prefix: &str, interface: &str) -> Self {
+        Self {
+            enabled,
+            directory: directory.as_ref().to_path_buf(),
+            file_prefix: file_prefix.to_string(),
+            interface: interface.to_string(),
+            start: (Instant::now(), SystemTime::now()),
+            file: None,
+            last_flush: Instant::now(),
+        }
+    }
+
+    /// Path of the recording of the given day
+    pub fn path_for(&self, day: &str) -> PathBuf {
+        self.directory.join(format!("{}.{}", self.file_prefix, day))
+    }
+
+    /// Record a frame received now
+    /// Recording stops, with a warning, on the first write error
+    pub fn record(&mut self, id: ExtendedId, data: &[u8]) {
+        if !self.enabled {
+            return;
+        }
+        let (start_instant, start_time) = self.start;
+        let time = start_time + start_instant.elapsed();
+        if let Err(e) = self.record_at(time, id, data) {
+            warn!("Failed to record CAN frame, recording stopped: {}", e);
+            self.enabled = false;
+            self.file = None;
+        }
+    }
+
+    fn record_at(&mut self, time: SystemTime, id: ExtendedId, data: &[u8]) -> io::Result<()> {
+        let day = DateTime::<Utc>::from(time).format("%Y-%m-%d").to_string();
+        if self.file.as_ref().is_none_or(|(current, _)| *current != day) {
+            self.roll(day)?;
+        }
+        let timestamp = time.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO);
+        let line = candump_line(timestamp, &self.interface, id, data);
+        if let Some((_, writer)) = self.file.as_mut() {
+            writeln!(writer, "{}", line)?;
+            if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+                writer.flush()?;
+                self.last_flush = Instant::now();
+            }
+        }
+        Ok(())
+    }
+
+    /// Close the current file and open (append to) the file of the given day
+    fn roll(&mut self, day: String) -> io::Result<()> {
+        self.flush()?;
+        fs::create_dir_all(&self.directory)?;
+        let path = self.path_for(&day);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        info!("Recording CAN frames to {}", path.display());
+        self.file = Some((day, BufWriter::new(file)));
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some((_, writer)) = self.file.as_mut() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FrameRecorder {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::CandumpReader;
+
+    fn id(raw: u32) -> ExtendedId {
+        ExtendedId::new(raw).unwrap()
+    }
+
+    #[test]
+    fn test_candump_line() {
+        let line = candump_line(Duration::from_micros(1_436_509_052_249_713), "can0", id(0x09F8027F), &[0x00, 0xFC, 0x0D]);
+        assert_eq!(line, "(1436509052.249713) can0 09F8027F#00FC0D");
+        assert_eq!(candump_line(Duration::from_secs(10), "can0", id(0x18EAFF00), &[]), "(10.000000) can0 18EAFF00#");
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let directory = std::env::temp_dir().join(format!("nmea_router_recorder_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let frames = [
+            (id(0x09F80105), vec![0x40, 0xE7, 0xEC, 0x19, 0xA0, 0xD0, 0x21, 0x06]),
+            (id(0x18EAFF00), vec![]),
+            (id(0x0DF80503), vec![0x00, 0x2B, 0x01, 0x02]),
+        ];
+
+        let path = {
+            let mut recorder = FrameRecorder::new(true, &directory, "can", "can0");
+            for (id, data) in &frames {
+                recorder.record(*id, data);
+            }
+            let day = recorder.file.as_ref().unwrap().0.clone();
+            recorder.path_for(&day)
+        };
+
+        let replayed: Vec<(ExtendedId, Vec<u8>)> = CandumpReader::open(&path, false)
+            .unwrap()
+            .map(|frame| {
+                let (id, data, _) = frame.unwrap();
+                (id, data)
+            })
+            .collect();
+        assert_eq!(replayed, frames);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_daily_files() {
+        let directory = std::env::temp_dir().join(format!("nmea_router_recorder_days_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let mut recorder = FrameRecorder::new(true, &directory, "can", "can0");
+        let midnight = UNIX_EPOCH + Duration::from_secs(1_717_286_400); // 2024-06-02 00:00:00 UTC
+        recorder.record_at(midnight - Duration::from_secs(1), id(0x09F80105), &[0x01]).unwrap();
+        recorder.record_at(midnight, id(0x09F80105), &[0x02]).unwrap();
+        recorder.flush().unwrap();
+
+        let first = fs::read_to_string(recorder.path_for("2024-06-01")).unwrap();
+        let second = fs::read_to_string(recorder.path_for("2024-06-02")).unwrap();
+        assert_eq!(first, "(1717286399.000000) can0 09F80105#01\n");
+        assert_eq!(second, "(1717286400.000000) can0 09F80105#02\n");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_disabled_recorder_writes_nothing() {
+        let directory = std::env::temp_dir().join(format!("nmea_router_recorder_off_{}", std::process::id()));
+        let mut recorder = FrameRecorder::new(false, &directory, "can", "can0");
+        recorder.record(id(0x09F80105), &[0x01]);
+        assert!(!directory.exists());
+    }
+}
@@ -0,0 +1,281 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Lines};
+use std::path::Path;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use nmea2k::ExtendedId;
+use tracing::warn;
+
+use crate::utilities::WallClock;
+
+/// A CAN frame recorded by `candump -L`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandumpFrame {
+    /// Recording time, seconds since the Unix epoch
+    pub timestamp: f64,
+    pub id: ExtendedId,
+    pub data: Vec<u8>,
+}
+
+/// Parse a `candump -L` log line: `(1436509052.249713) can0 09F8027F#00FC0D0D4B000000`
+///
+/// Returns None for lines that are not NMEA2000 data frames: blank lines, comments,
+/// 11-bit identifiers, remote requests and CAN FD frames.
+pub fn parse_candump_line(line: &str) -> Option<CandumpFrame> {
+    let mut fields = line.split_whitespace();
+    let timestamp = fields.next()?.strip_prefix('(')?.strip_suffix(')')?.parse::<f64>().ok()?;
+    let _interface = fields.next()?;
+    let (id, data) = fields.next()?.split_once('#')?;
+
+    // NMEA2000 uses 29-bit identifiers, always printed with 8 hex digits
+    if id.len() != 8 {
+        return None;
+    }
+    let id = ExtendedId::new(u32::from_str_radix(id, 16).ok()?)?;
+
+    if data.len() % 2 != 0 || data.len() > 16 {
+        return None;
+    }
+    let data = (0..data.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&data[i..i + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    Some(CandumpFrame { timestamp, id, data })
+}
+
+/// Reads the CAN frames of a candump log, in place of the CAN socket
+///
+/// With `realtime` set the frames are handed over with the recorded inter-frame timing,
+/// otherwise as fast as they are read. Either way each frame comes with the instant it was
+/// recorded at, relative to the first frame, to drive the time-based processing as on the boat.
+pub struct CandumpReader<R> {
+    lines: Lines<R>,
+    realtime: bool,
+    /// Recording time of the first frame and when it was handed over
+    start: Option<(f64, Instant)>,
+    /// Instant of the last frame, out-of-order timestamps do not go back in time
+    last_instant: Option<Instant>,
+    skipped_lines: usize,
+}
+
+impl CandumpReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P, realtime: bool) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?), realtime))
+    }
+}
+
+impl<R: BufRead> CandumpReader<R> {
+    pub fn new(reader: R, realtime: bool) -> Self {
+        Self {
+            lines: reader.lines(),
+            realtime,
+            start: None,
+            last_instant: None,
+            skipped_lines: 0,
+        }
+    }
+
+    /// Lines of the log that were not NMEA2000 data frames
+    pub fn skipped_lines(&self) -> usize {
+        self.skipped_lines
+    }
+
+    /// Wall clock of the recording: the instants of the frames map to their recorded time
+    /// None before the first frame
+    pub fn wall_clock(&self) -> Option<WallClock> {
+        let (first, started) = self.start?;
+        let recorded = UNIX_EPOCH + Duration::try_from_secs_f64(first).unwrap_or(Duration::ZERO);
+        Some(WallClock::anchored_at(started, recorded))
+    }
+
+    /// Instant of the frame recorded at `timestamp`, waiting for it in realtime mode
+    fn instant_of(&mut self, timestamp: f64) -> Instant {
+        let (first, started) = *self.start.get_or_insert((timestamp, Instant::now()));
+        // Out-of-order timestamps are handed over right away
+        let offset = Duration::try_from_secs_f64(timestamp - first).unwrap_or(Duration::ZERO);
+        if self.realtime {
+            let elapsed = started.elapsed();
+            if offset > elapsed {
+                std::thread::sleep(offset - elapsed);
+            }
+        }
+        let instant = self.last_instant.map_or(started + offset, |last| last.max(started + offset));
+        self.last_instant = Some(instant);
+        instant
+    }
+}
+
+impl<R: BufRead> Iterator for CandumpReader<R> {
+    /// The frame and the instant it was recorded at
+    type Item = io::Result<(ExtendedId, Vec<u8>, Instant)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            match parse_candump_line(&line) {
+                Some(frame) => {
+                    let instant = self.instant_of(frame.timestamp);
+                    return Some(Ok((frame.id, frame.data, instant)));
+                }
+                None => {
+                    if !line.trim().is_empty() {
+                        self.skipped_lines += 1;
+                        if self.skipped_lines == 1 {
+                            warn!("Skipping candump line that is not an NMEA2000 frame: {}", line);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nmea2k::N2kStreamReader;
+    use nmea2k::pgns::N2kMessage;
+
+    #[test]
+    fn test_parse_candump_line() {
+        let frame = parse_candump_line("(1436509052.249713) can0 09F8027F#00FC0D0D4B000000").unwrap();
+        assert_eq!(frame.timestamp, 1436509052.249713);
+        assert_eq!(frame.id.as_raw(), 0x09F8027F);
+        assert_eq!(frame.data, [0x00, 0xFC, 0x0D, 0x0D, 0x4B, 0x00, 0x00, 0x00]);
+
+        // Lowercase hex, short data
+        let frame = parse_candump_line("(1436509052.250000) vcan0 1df90b23#a1b2").unwrap();
+        assert_eq!(frame.id.as_raw(), 0x1DF90B23);
+        assert_eq!(frame.data, [0xA1, 0xB2]);
+    }
+
+    #[test]
+    fn test_parse_candump_empty_data() {
+        let frame = parse_candump_line("(1436509052.249713) can0 18EAFF00#").unwrap();
+        assert_eq!(frame.id.as_raw(), 0x18EAFF00);
+        assert!(frame.data.is_empty());
+    }
+
+    #[test]
+    fn test_parse_candump_invalid_lines() {
+        assert!(parse_candump_line("").is_none());
+        assert!(parse_candump_line("# capture of 2024-06-01").is_none());
+        // 11-bit identifier
+        assert!(parse_candump_line("(1436509052.249713) can0 123#0011").is_none());
+        // Remote request
+        assert!(parse_candump_line("(1436509052.249713) can0 09F8027F#R").is_none());
+        // CAN FD
+        assert!(parse_candump_line("(1436509052.249713) can0 09F8027F##100112233").is_none());
+        // Odd number of hex digits, too much data for classic CAN
+        assert!(parse_candump_line("(1436509052.249713) can0 09F8027F#001").is_none());
+        assert!(parse_candump_line("(1436509052.249713) can0 09F8027F#001122334455667788").is_none());
+        // Missing timestamp
+        assert!(parse_candump_line("can0 09F8027F#00FC0D0D4B000000").is_none());
+    }
+
+    #[test]
+    fn test_replay_through_stream_reader() {
+        // Two position rapid updates (129025) and a line to skip
+        let log = "(1717236000.000000) can0 09F80105#40E7EC19A0D02106\n\
+                   (1717236000.100000) can0 123#00\n\
+                   \n\
+                   (1717236000.200000) can0 09F80105#41E7EC19A0D02106\n";
+        let mut reader = N2kStreamReader::new();
+        let mut candump = CandumpReader::new(log.as_bytes(), false);
+        let messages: Vec<N2kMessage> = candump
+            .by_ref()
+            .filter_map(|frame| {
+                let (id, data, _) = frame.unwrap();
+                reader.process_frame(id, &data).map(|frame| frame.message)
+            })
+            .collect();
+
+        assert_eq!(messages.len(), 2);
+        assert!(messages.iter().all(|m| matches!(m, N2kMessage::PositionRapidUpdate(_))));
+        assert_eq!(candump.skipped_lines(), 1);
+    }
+
+    #[test]
+    fn test_replay_realtime() {
+        let log = "(100.000) can0 09F80105#40E7EC19A0D02106\n\
+                   (100.050) can0 09F80105#41E7EC19A0D02106\n";
+        let started = Instant::now();
+        let frames = CandumpReader::new(log.as_bytes(), true).count();
+        assert_eq!(frames, 2);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_replay_produces_vessel_statuses() {
+        use crate::application_state::ApplicationState;
+        use crate::config::VesselStatusConfig;
+        use crate::db::Database;
+        use crate::vessel_monitor::VesselMonitor;
+        use crate::vessel_status_handler::VesselStatusHandler;
+        use nmea2k::MessageHandler;
+        use std::sync::{Arc, Mutex};
+
+        // Ten minutes heading north at ~5 knots, a position per second
+        let log: String = (0..600)
+            .map(|i| {
+                let latitude: i32 = 435_000_000 + i * 231;
+                let longitude: i32 = 102_000_000;
+                let data: String = latitude.to_le_bytes().iter().chain(&longitude.to_le_bytes()).map(|b| format!("{:02X}", b)).collect();
+                format!("({}.000000) can0 09F80105#{}\n", 1717236000 + i, data)
+            })
+            .collect();
+
+        let db: Arc<dyn Database> = Arc::new(crate::sqlite_db::SqliteDatabase::new(":memory:").unwrap());
+        let vessel_db = Some(db.clone());
+        let config = VesselStatusConfig::default();
+        let mut monitor = VesselMonitor::new(Arc::new(Mutex::new(ApplicationState::new())), &config);
+        let mut handler = VesselStatusHandler::new(config);
+        let mut reader = N2kStreamReader::new();
+        let mut candump = CandumpReader::new(log.as_bytes(), false);
+
+        let mut written = 0;
+        while let Some(frame) = candump.next() {
+            let (id, data, now) = frame.unwrap();
+            if let Some(wall_clock) = candump.wall_clock() {
+                handler.set_wall_clock(wall_clock);
+            }
+            let Some(frame) = reader.process_frame_at(id, &data, now) else {
+                continue;
+            };
+            monitor.handle_message(&frame, now);
+            if let Some(status) = monitor.generate_status(now)
+                && handler.handle_vessel_status(&vessel_db, status).unwrap() {
+                written += 1;
+            }
+        }
+
+        // A status every 30 s underway, although the replay took a fraction of a second
+        assert!(written >= 18, "Expected a status every 30 s, got {}", written);
+        let trip = db.get_last_trip().unwrap().unwrap();
+        // Timestamped with the recorded time
+        let recorded_start = UNIX_EPOCH + Duration::from_secs(1717236000);
+        assert!(trip.start_timestamp.duration_since(recorded_start).unwrap() < Duration::from_secs(60));
+        assert!(trip.end_timestamp.duration_since(trip.start_timestamp).unwrap() >= Duration::from_secs(540));
+        assert!((trip.total_distance_sailed - 0.8).abs() < 0.1, "Expected ~0.8 nm, got {}", trip.total_distance_sailed);
+    }
+
+    #[test]
+    fn test_replay_recorded_instants() {
+        // An hour of recording, the third frame out of order
+        let log = "(1717236000.000) can0 09F80105#40E7EC19A0D02106\n\
+                   (1717239600.000) can0 09F80105#41E7EC19A0D02106\n\
+                   (1717238000.000) can0 09F80105#42E7EC19A0D02106\n";
+        let mut candump = CandumpReader::new(log.as_bytes(), false);
+        assert!(candump.wall_clock().is_none());
+        let instants: Vec<Instant> = candump.by_ref().map(|frame| frame.unwrap().2).collect();
+
+        assert_eq!(instants[1] - instants[0], Duration::from_secs(3600));
+        assert_eq!(instants[2], instants[1]);
+        let wall_clock = candump.wall_clock().unwrap();
+        assert_eq!(wall_clock.to_system_time(instants[1]), UNIX_EPOCH + Duration::from_secs(1717239600));
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{info, warn};
+
+/// Flag raised on SIGINT or SIGTERM, polled by the main loop
+#[derive(Clone, Default)]
+pub struct ShutdownFlag(Arc<AtomicBool>);
+
+impl ShutdownFlag {
+    pub fn is_requested(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub fn request(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Raise the returned flag when the process receives SIGINT (Ctrl-C) or SIGTERM
+///
+/// The signals are awaited on a dedicated thread; the main loop notices the flag
+/// within the CAN read timeout.
+pub fn install_shutdown_handler() -> ShutdownFlag {
+    let flag = ShutdownFlag::default();
+    let handler_flag = flag.clone();
+    std::thread::spawn(move || {
+        let rt = match tokio::runtime::Builder::new_current_thread().enable_io().build() {
+            Ok(rt) => rt,
+            Err(e) => {
+                warn!("Failed to create the signal handler runtime, shutdown only on kill: {}", e);
+                return;
+            }
+        };
+        rt.block_on(async {
+            let mut terminate = match signal(SignalKind::terminate()) {
+                Ok(terminate) => terminate,
+                Err(e) => {
+                    warn!("Failed to install the SIGTERM handler: {}", e);
+                    return;
+                }
+            };
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => info!("SIGINT received, shutting down"),
+                _ = terminate.recv() => info!("SIGTERM received, shutting down"),
+            }
+            handler_flag.request();
+        });
+    });
+    flag
+}
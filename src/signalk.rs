@@ -0,0 +1,393 @@
+use std::io::Write;
+use std::net::{TcpStream, UdpSocket};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn, error};
+use nmea2k::pgns::pgn130306::WindReference;
+use nmea2k::pgns::{HeadingReference, N2kMessage};
+use nmea2k::{MessageHandler, N2kFrame};
+use serde_json::{json, Value};
+
+use crate::config::{SignalKConfig, SignalKTransport};
+
+/// Label of the deltas, SignalK builds the `$source` from it and the NMEA2000 source address
+const SOURCE_LABEL: &str = "nmea_router";
+
+/// Minimum time between two attempts to connect to the SignalK server over TCP
+const TCP_RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Timeout of a TCP connection attempt, the main loop is blocked meanwhile
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Temperature source of PGN 130312/130316 for the sea water
+const TEMPERATURE_SOURCE_SEA: u8 = 0;
+/// Temperature source of PGN 130312/130316 for the outside air
+const TEMPERATURE_SOURCE_OUTSIDE: u8 = 1;
+/// Temperature source of PGN 130312/130316 for the inside air
+const TEMPERATURE_SOURCE_INSIDE: u8 = 2;
+/// Humidity source of PGN 130313 for the inside air
+const HUMIDITY_SOURCE_INSIDE: u8 = 0;
+/// Humidity source of PGN 130313 for the outside air
+const HUMIDITY_SOURCE_OUTSIDE: u8 = 1;
+/// Pressure source of PGN 130314 for the atmospheric pressure
+const PRESSURE_SOURCE_ATMOSPHERIC: u8 = 0;
+
+/// Connection to the SignalK server
+enum SignalKOutput {
+    Udp(UdpSocket),
+    Tcp {
+        stream: Option<TcpStream>,
+        last_attempt: Option<Instant>,
+    },
+}
+
+/// SignalK delta output for NMEA2000 messages
+///
+/// Converts each message into a SignalK delta using the standard paths and SI units
+/// and sends it, one JSON object per line, to a SignalK server over UDP or TCP.
+/// Messages without a SignalK mapping are skipped.
+pub struct SignalKBroadcaster {
+    address: String,
+    output: Option<SignalKOutput>,
+    message_count: u64,
+    error_count: u64,
+}
+
+impl SignalKBroadcaster {
+    /// Create a new SignalK broadcaster
+    ///
+    /// # Arguments
+    /// * `config` - SignalK output configuration
+    pub fn new(config: &SignalKConfig) -> Self {
+        let output = if !config.enabled {
+            debug!("SignalK output disabled in configuration");
+            None
+        } else {
+            match config.transport {
+                SignalKTransport::Udp => match Self::create_udp_socket() {
+                    Ok(socket) => Some(SignalKOutput::Udp(socket)),
+                    Err(e) => {
+                        error!("Failed to create UDP socket for SignalK {}: {}. SignalK output disabled.", config.address, e);
+                        None
+                    }
+                },
+                // Connected lazily, so the server can be started after the router
+                SignalKTransport::Tcp => Some(SignalKOutput::Tcp { stream: None, last_attempt: None }),
+            }
+        };
+
+        Self {
+            address: config.address.clone(),
+            output,
+            message_count: 0,
+            error_count: 0,
+        }
+    }
+
+    /// Create a non-blocking UDP socket
+    fn create_udp_socket() -> Result<UdpSocket, std::io::Error> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_nonblocking(true)?;
+        Ok(socket)
+    }
+
+    /// Connect to the SignalK server, the stream is non-blocking once connected
+    fn connect_tcp(address: &str) -> Result<TcpStream, std::io::Error> {
+        let socket_address = std::net::ToSocketAddrs::to_socket_addrs(address)?
+            .next()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "no address resolved"))?;
+        let stream = TcpStream::connect_timeout(&socket_address, TCP_CONNECT_TIMEOUT)?;
+        stream.set_nonblocking(true)?;
+        let _ = stream.set_nodelay(true);
+        Ok(stream)
+    }
+
+    /// Convert an NMEA2000 message and send the delta to the SignalK server
+    fn send_message(&mut self, message: &N2kMessage, source: u8, now: Instant) {
+        let Some(output) = self.output.as_mut() else {
+            return;
+        };
+        let Some(delta) = message_to_delta(message, source) else {
+            return;
+        };
+        let mut line = delta.to_string();
+        line.push('\n');
+
+        let result = match output {
+            SignalKOutput::Udp(socket) => socket.send_to(line.as_bytes(), &self.address).map(|_| ()),
+            SignalKOutput::Tcp { stream, last_attempt } => {
+                if stream.is_none() {
+                    if last_attempt.is_some_and(|t| now.duration_since(t) < TCP_RECONNECT_INTERVAL) {
+                        return;
+                    }
+                    *last_attempt = Some(now);
+                    match Self::connect_tcp(&self.address) {
+                        Ok(s) => {
+                            info!("Connected to SignalK server {}", self.address);
+                            *stream = Some(s);
+                        }
+                        Err(e) => {
+                            warn!("Failed to connect to SignalK server {}: {}", self.address, e);
+                            return;
+                        }
+                    }
+                }
+                let result = stream.as_mut().map_or(Ok(()), |s| s.write_all(line.as_bytes()));
+                if let Err(ref e) = result {
+                    info!("Dropping connection to SignalK server {}: {}", self.address, e);
+                    *stream = None;
+                }
+                result
+            }
+        };
+
+        match result {
+            Ok(_) => {
+                self.message_count += 1;
+                if self.message_count.is_multiple_of(1000) {
+                    debug!("Sent {} SignalK deltas to {}", self.message_count, self.address);
+                }
+            }
+            Err(e) => {
+                if self.error_count < 10 {
+                    warn!("Failed to send SignalK delta to {}: {}", self.address, e);
+                }
+                self.error_count += 1;
+            }
+        }
+    }
+
+    /// Get statistics - for future uses
+    /// Returns (message_count, error_count)
+    #[allow(dead_code)]
+    pub fn stats(&self) -> (u64, u64) {
+        (self.message_count, self.error_count)
+    }
+}
+
+/// A SignalK path with its value
+fn path_value(path: impl Into<String>, value: Value) -> Value {
+    json!({ "path": path.into(), "value": value })
+}
+
+/// SignalK path of a temperature by its source, None for the sources without a mapping
+fn temperature_path(source: u8) -> Option<&'static str> {
+    match source {
+        TEMPERATURE_SOURCE_SEA => Some("environment.water.temperature"),
+        TEMPERATURE_SOURCE_OUTSIDE => Some("environment.outside.temperature"),
+        TEMPERATURE_SOURCE_INSIDE => Some("environment.inside.temperature"),
+        _ => None,
+    }
+}
+
+/// SignalK path and value pairs of an NMEA2000 message, all values are in SI units
+/// Returns an empty list for the messages without a SignalK mapping
+fn message_values(message: &N2kMessage) -> Vec<Value> {
+    let mut values = Vec::new();
+    match message {
+        N2kMessage::PositionRapidUpdate(msg) => {
+            values.push(path_value("navigation.position", json!({
+                "latitude": msg.latitude,
+                "longitude": msg.longitude,
+            })));
+        }
+        N2kMessage::GnssPositionData(msg) => {
+            values.push(path_value("navigation.position", json!({
+                "latitude": msg.latitude,
+                "longitude": msg.longitude,
+                "altitude": msg.altitude,
+            })));
+        }
+        N2kMessage::CogSogRapidUpdate(msg) => {
+            values.push(path_value("navigation.speedOverGround", json!(msg.sog)));
+            let cog_path = if msg.cog_reference {
+                "navigation.courseOverGroundTrue"
+            } else {
+                "navigation.courseOverGroundMagnetic"
+            };
+            values.push(path_value(cog_path, json!(msg.cog)));
+        }
+        N2kMessage::WindData(msg) => {
+            let (speed_path, angle_path) = match msg.reference {
+                WindReference::Apparent => ("environment.wind.speedApparent", "environment.wind.angleApparent"),
+                WindReference::TrueWater => ("environment.wind.speedTrue", "environment.wind.angleTrueWater"),
+                WindReference::TrueBoat => ("environment.wind.speedOverGround", "environment.wind.angleTrueGround"),
+                WindReference::TrueGroundNorth => ("environment.wind.speedOverGround", "environment.wind.directionTrue"),
+                WindReference::Magnetic => ("environment.wind.speedOverGround", "environment.wind.directionMagnetic"),
+            };
+            values.push(path_value(speed_path, json!(msg.speed)));
+            values.push(path_value(angle_path, json!(msg.angle)));
+        }
+        N2kMessage::VesselHeading(msg) => match msg.reference {
+            HeadingReference::True => values.push(path_value("navigation.headingTrue", json!(msg.heading))),
+            HeadingReference::Magnetic => values.push(path_value("navigation.headingMagnetic", json!(msg.heading))),
+            HeadingReference::Error | HeadingReference::Null => {}
+        },
+        N2kMessage::RateOfTurn(msg) => {
+            values.push(path_value("navigation.rateOfTurn", json!(msg.rate)));
+        }
+        N2kMessage::Attitude(msg) if msg.roll.is_some() || msg.pitch.is_some() || msg.yaw.is_some() => {
+            values.push(path_value("navigation.attitude", json!({
+                "roll": msg.roll,
+                "pitch": msg.pitch,
+                "yaw": msg.yaw,
+            })));
+        }
+        N2kMessage::SpeedWaterReferenced(msg) => {
+            values.push(path_value("navigation.speedThroughWater", json!(msg.speed)));
+        }
+        N2kMessage::WaterDepth(msg) => {
+            values.push(path_value("environment.depth.belowTransducer", json!(msg.depth)));
+            // Positive offset: distance from the transducer to the waterline, negative: to the keel
+            if msg.offset > 0.0 {
+                values.push(path_value("environment.depth.belowSurface", json!(msg.depth + msg.offset)));
+            } else if msg.offset < 0.0 {
+                values.push(path_value("environment.depth.belowKeel", json!(msg.depth + msg.offset)));
+            }
+        }
+        N2kMessage::Temperature(msg) => {
+            if let Some(path) = temperature_path(msg.source) {
+                values.push(path_value(path, json!(msg.temperature)));
+            }
+        }
+        N2kMessage::TemperatureExtended(msg) => {
+            if let (Some(path), Some(temperature)) = (temperature_path(msg.source), msg.temperature) {
+                values.push(path_value(path, json!(temperature)));
+            }
+        }
+        N2kMessage::Humidity(msg) => {
+            // SignalK relative humidity is a ratio (0-1)
+            let path = match msg.source {
+                HUMIDITY_SOURCE_INSIDE => Some("environment.inside.relativeHumidity"),
+                HUMIDITY_SOURCE_OUTSIDE => Some("environment.outside.relativeHumidity"),
+                _ => None,
+            };
+            if let Some(path) = path {
+                values.push(path_value(path, json!(msg.actual_humidity / 100.0)));
+            }
+        }
+        N2kMessage::ActualPressure(msg) if msg.source == PRESSURE_SOURCE_ATMOSPHERIC => {
+            values.push(path_value("environment.outside.pressure", json!(msg.pressure)));
+        }
+        N2kMessage::Rudder(msg) => {
+            if let Some(position) = msg.position {
+                values.push(path_value("steering.rudderAngle", json!(position)));
+            }
+        }
+        N2kMessage::EngineRapidUpdate(msg) => {
+            // SignalK revolutions are in Hz
+            if let Some(rpm) = msg.engine_speed {
+                values.push(path_value(format!("propulsion.{}.revolutions", msg.engine_instance), json!(rpm / 60.0)));
+            }
+        }
+        N2kMessage::DcDetailedStatus(msg) => {
+            // SignalK state of charge is a ratio (0-1), time remaining in seconds
+            let prefix = format!("electrical.batteries.{}.capacity", msg.instance);
+            if let Some(soc) = msg.state_of_charge {
+                values.push(path_value(format!("{}.stateOfCharge", prefix), json!(soc as f64 / 100.0)));
+            }
+            if let Some(minutes) = msg.time_remaining_min {
+                values.push(path_value(format!("{}.timeRemaining", prefix), json!(minutes as u32 * 60)));
+            }
+        }
+        N2kMessage::BatteryStatus(msg) => {
+            let prefix = format!("electrical.batteries.{}", msg.instance);
+            if let Some(voltage) = msg.voltage {
+                values.push(path_value(format!("{}.voltage", prefix), json!(voltage)));
+            }
+            if let Some(current) = msg.current {
+                values.push(path_value(format!("{}.current", prefix), json!(current)));
+            }
+            if let Some(temperature) = msg.temperature {
+                values.push(path_value(format!("{}.temperature", prefix), json!(temperature)));
+            }
+        }
+        _ => {}
+    }
+    values
+}
+
+/// Convert an NMEA2000 message into a SignalK delta
+/// Returns None for the messages without a SignalK mapping
+pub fn message_to_delta(message: &N2kMessage, source: u8) -> Option<Value> {
+    let values = message_values(message);
+    if values.is_empty() {
+        return None;
+    }
+    Some(json!({
+        "updates": [{
+            "source": {
+                "label": SOURCE_LABEL,
+                "type": "NMEA2000",
+                "src": source.to_string(),
+            },
+            "values": values,
+        }]
+    }))
+}
+
+impl MessageHandler for SignalKBroadcaster {
+    fn handle_message(&mut self, frame: &N2kFrame, timestamp: Instant) {
+        self.send_message(&frame.message, frame.source(), timestamp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nmea2k::pgns::{CogSogRapidUpdate, PositionRapidUpdate, WindData};
+
+    /// Values of the single update of a delta
+    fn delta_values(delta: &Value) -> &Vec<Value> {
+        delta["updates"][0]["values"].as_array().unwrap()
+    }
+
+    #[test]
+    fn test_position_delta() {
+        let msg = N2kMessage::PositionRapidUpdate(PositionRapidUpdate::new(43.6777, 10.2876));
+        let delta = message_to_delta(&msg, 22).unwrap();
+
+        let values = delta_values(&delta);
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0]["path"], "navigation.position");
+        assert_eq!(values[0]["value"]["latitude"], 43.6777);
+        assert_eq!(values[0]["value"]["longitude"], 10.2876);
+        assert_eq!(delta["updates"][0]["source"]["src"], "22");
+    }
+
+    #[test]
+    fn test_sog_delta() {
+        // 5 m/s over ground, course 1 rad true
+        let msg = N2kMessage::CogSogRapidUpdate(CogSogRapidUpdate::new(true, 1.0, 5.0));
+        let delta = message_to_delta(&msg, 22).unwrap();
+
+        let values = delta_values(&delta);
+        assert_eq!(values[0]["path"], "navigation.speedOverGround");
+        assert_eq!(values[0]["value"], 5.0);
+        assert_eq!(values[1]["path"], "navigation.courseOverGroundTrue");
+        assert_eq!(values[1]["value"], 1.0);
+    }
+
+    #[test]
+    fn test_apparent_wind_delta() {
+        let msg = N2kMessage::WindData(WindData::new_apparent(7.5, 0.5));
+        let delta = message_to_delta(&msg, 10).unwrap();
+
+        let values = delta_values(&delta);
+        assert_eq!(values[0]["path"], "environment.wind.speedApparent");
+        assert_eq!(values[0]["value"], 7.5);
+        assert_eq!(values[1]["path"], "environment.wind.angleApparent");
+        assert_eq!(values[1]["value"], 0.5);
+    }
+
+    #[test]
+    fn test_unmapped_message_has_no_delta() {
+        let msg = N2kMessage::Unknown(65280, vec![0; 8]);
+        assert!(message_to_delta(&msg, 10).is_none());
+    }
+
+    #[test]
+    fn test_disabled_broadcaster() {
+        let broadcaster = SignalKBroadcaster::new(&SignalKConfig::default());
+        assert!(broadcaster.output.is_none());
+    }
+}
@@ -0,0 +1,1126 @@
+use chrono::NaiveDateTime;
+use rusqlite::{named_params, params, params_from_iter, Connection, OptionalExtension};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use crate::db::{build_metrics_query, CanErrorBucket, CurrentStatus, Database, MetricBucket, Session, SqlDialect, TrackPaging, TrackPoint, TripOperation, TripSummary, VesselStats, VesselStatusOperation, WebMetricData};
+use crate::environmental_monitor::{MetricData, MetricId};
+use crate::trip::{numbered_description_pattern, unique_trip_description, Trip};
+
+/// Tables created when the database file is opened, same columns as schema.sql
+const SCHEMA: &str = r"
+CREATE TABLE IF NOT EXISTS vessel_status (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp TEXT NOT NULL,
+    latitude REAL,
+    longitude REAL,
+    average_speed_kn REAL NOT NULL,
+    max_speed_kn REAL NOT NULL,
+    average_wind_speed_kn REAL,
+    average_wind_angle_deg REAL,
+    is_moored INTEGER NOT NULL,
+    engine_on INTEGER NOT NULL DEFAULT 0,
+    total_distance_nm REAL NOT NULL DEFAULT 0,
+    total_time_ms INTEGER NOT NULL DEFAULT 0,
+    cog_deg REAL,
+    average_heading_deg REAL,
+    quality_score REAL,
+    session_id INTEGER
+);
+CREATE INDEX IF NOT EXISTS idx_vessel_status_timestamp ON vessel_status (timestamp);
+CREATE INDEX IF NOT EXISTS idx_vessel_status_session ON vessel_status (session_id);
+
+CREATE TABLE IF NOT EXISTS environmental_data (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp TEXT NOT NULL,
+    metric_id INTEGER NOT NULL,
+    value_avg REAL,
+    value_max REAL,
+    value_min REAL,
+    value_count INTEGER,
+    unit TEXT,
+    session_id INTEGER,
+    UNIQUE (timestamp, metric_id)
+);
+CREATE INDEX IF NOT EXISTS idx_environmental_metric ON environmental_data (metric_id, timestamp);
+CREATE INDEX IF NOT EXISTS idx_environmental_session ON environmental_data (session_id);
+
+CREATE TABLE IF NOT EXISTS trips (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    description TEXT NOT NULL,
+    start_timestamp TEXT NOT NULL,
+    end_timestamp TEXT NOT NULL,
+    total_distance_sailed REAL NOT NULL DEFAULT 0,
+    total_distance_motoring REAL NOT NULL DEFAULT 0,
+    total_time_sailing INTEGER NOT NULL DEFAULT 0,
+    total_time_motoring INTEGER NOT NULL DEFAULT 0,
+    total_time_moored INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS idx_trips_start_timestamp ON trips (start_timestamp);
+CREATE INDEX IF NOT EXISTS idx_trips_end_timestamp ON trips (end_timestamp);
+
+CREATE TABLE IF NOT EXISTS can_error_stats (
+    hour TEXT PRIMARY KEY,
+    can_frames INTEGER NOT NULL DEFAULT 0,
+    can_errors INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    start_timestamp TEXT NOT NULL,
+    end_timestamp TEXT
+);
+";
+
+/// Id of the session covering the timestamp bound to ?1, NULL outside of sessions
+/// The latest session wins at the boundary between two sessions
+const SESSION_AT: &str = "(SELECT MAX(id) FROM sessions WHERE start_timestamp <= ?1 AND COALESCE(end_timestamp, ?1) >= ?1)";
+
+/// Columns selected for the sessions of the web API
+const SESSION_COLUMNS: &str = "id, name, substr(start_timestamp, 1, 19), substr(end_timestamp, 1, 19)";
+
+/// Columns selected for the trip summaries of the web API
+const TRIP_SUMMARY_COLUMNS: &str = "id, description,
+    substr(start_timestamp, 1, 19) AS start_ts,
+    substr(end_timestamp, 1, 19) AS end_ts,
+    (total_distance_sailed + total_distance_motoring) AS total_distance,
+    (total_time_sailing + total_time_motoring + total_time_moored) AS total_time,
+    total_time_sailing, total_time_motoring, total_time_moored,
+    total_distance_sailed, total_distance_motoring";
+
+/// Timestamps are stored as UTC text, so they sort and compare like MySQL DATETIME values
+fn format_timestamp(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time).format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+}
+
+fn parse_timestamp(timestamp: &str) -> Result<SystemTime, Box<dyn Error>> {
+    let dt = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.f")?;
+    Ok(SystemTime::from(dt.and_utc()))
+}
+
+/// Columns selected to load a trip, read by `trip`
+const TRIP_COLUMNS: &str = "id, description, start_timestamp, end_timestamp,
+    total_distance_sailed, total_distance_motoring,
+    total_time_sailing, total_time_motoring, total_time_moored";
+
+fn trip(row: &rusqlite::Row) -> rusqlite::Result<Trip> {
+    let timestamp = |idx: usize| -> rusqlite::Result<SystemTime> {
+        let text: String = row.get(idx)?;
+        let dt = NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f")
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e)))?;
+        Ok(SystemTime::from(dt.and_utc()))
+    };
+    Ok(Trip {
+        id: Some(row.get(0)?),
+        description: row.get(1)?,
+        start_timestamp: timestamp(2)?,
+        end_timestamp: timestamp(3)?,
+        total_distance_sailed: row.get(4)?,
+        total_distance_motoring: row.get(5)?,
+        total_time_sailing: row.get(6)?,
+        total_time_motoring: row.get(7)?,
+        total_time_moored: row.get(8)?,
+    })
+}
+
+/// Descriptions of the trips named like the base description or its numbered variants ("<base> #N")
+fn trip_descriptions(conn: &Connection, base_description: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT description FROM trips WHERE description = ?1 OR description LIKE ?2 ESCAPE '\\'")?;
+    stmt.query_map(params![base_description, numbered_description_pattern(base_description)], |row| row.get(0))?
+        .collect()
+}
+
+/// Write the time range and the totals of an existing trip
+fn update_trip(conn: &Connection, trip: &Trip, trip_id: i64) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE trips
+            SET start_timestamp = ?1,
+                end_timestamp = ?2,
+                total_distance_sailed = ?3,
+                total_distance_motoring = ?4,
+                total_time_sailing = ?5,
+                total_time_motoring = ?6,
+                total_time_moored = ?7
+            WHERE id = ?8",
+        params![
+            format_timestamp(trip.start_timestamp),
+            format_timestamp(trip.end_timestamp),
+            trip.total_distance_sailed,
+            trip.total_distance_motoring,
+            trip.total_time_sailing,
+            trip.total_time_motoring,
+            trip.total_time_moored,
+            trip_id,
+        ],
+    )
+}
+
+fn trip_summary(row: &rusqlite::Row) -> rusqlite::Result<TripSummary> {
+    Ok(TripSummary {
+        id: row.get("id")?,
+        description: row.get("description")?,
+        start_date: row.get("start_ts")?,
+        end_date: row.get("end_ts")?,
+        total_distance_nm: row.get("total_distance")?,
+        total_time_ms: row.get("total_time")?,
+        sailing_time_ms: row.get("total_time_sailing")?,
+        motoring_time_ms: row.get("total_time_motoring")?,
+        moored_time_ms: row.get("total_time_moored")?,
+        sailing_distance_nm: row.get("total_distance_sailed")?,
+        motoring_distance_nm: row.get("total_distance_motoring")?,
+        start_date_local: None,
+        end_date_local: None,
+    })
+}
+
+fn track_point(row: &rusqlite::Row) -> rusqlite::Result<TrackPoint> {
+    Ok(TrackPoint {
+        timestamp: row.get("timestamp")?,
+        latitude: row.get::<_, Option<f64>>("latitude")?.unwrap_or(0.0),
+        longitude: row.get::<_, Option<f64>>("longitude")?.unwrap_or(0.0),
+        avg_speed_kn: row.get("average_speed_kn")?,
+        max_speed_kn: row.get("max_speed_kn")?,
+        moored: row.get("is_moored")?,
+        engine_on: row.get("engine_on")?,
+        timestamp_local: None,
+    })
+}
+
+fn session(row: &rusqlite::Row) -> rusqlite::Result<Session> {
+    Ok(Session {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        start_date: row.get(2)?,
+        end_date: row.get(3)?,
+    })
+}
+
+fn web_metric(row: &rusqlite::Row) -> rusqlite::Result<WebMetricData> {
+    Ok(WebMetricData {
+        timestamp: row.get("timestamp")?,
+        metric_id: row.get::<_, i64>("metric_id")?.to_string(),
+        avg_value: row.get("avg_value")?,
+        max_value: row.get("max_value")?,
+        min_value: row.get("min_value")?,
+        count: row.get("count")?,
+    })
+}
+
+/// Embedded SQLite database
+///
+/// Alternative to the MySQL backend for installations without a database server.
+/// The tables are created on first use, the single connection is shared behind a mutex.
+pub struct SqliteDatabase {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteDatabase {
+    /// Open (or create) the database file
+    ///
+    /// Example path: "/var/lib/nmea_router/nmea_router.db" (":memory:" for a transient database)
+    pub fn new(path: &str) -> Result<Self, Box<dyn Error>> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(SqliteDatabase { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>, Box<dyn Error>> {
+        self.conn.lock().map_err(|_| "SQLite connection lock poisoned".into())
+    }
+}
+
+impl Database for SqliteDatabase {
+    fn health_check(&self) -> Result<(), Box<dyn Error>> {
+        self.conn()?.query_row("SELECT 1", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    fn update_trip_description(&self, trip_id: i64, new_description: &str) -> Result<(), Box<dyn Error>> {
+        self.conn()?.execute(
+            "UPDATE trips SET description = ?1 WHERE id = ?2",
+            params![new_description, trip_id],
+        )?;
+        Ok(())
+    }
+
+    fn delete_trip(&self, trip_id: i64) -> Result<bool, Box<dyn Error>> {
+        let deleted = self.conn()?.execute("DELETE FROM trips WHERE id = ?1", params![trip_id])?;
+        Ok(deleted > 0)
+    }
+
+    fn fetch_trip_descriptions(&self, base_description: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(trip_descriptions(&*self.conn()?, base_description)?)
+    }
+
+    fn merge_trips(&self, primary_id: i64, secondary_id: i64) -> Result<bool, Box<dyn Error>> {
+        if primary_id == secondary_id {
+            return Err("Cannot merge a trip with itself".into());
+        }
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let query = format!("SELECT {} FROM trips WHERE id = ?1", TRIP_COLUMNS);
+        let primary = tx.query_row(&query, params![primary_id], trip).optional()?;
+        let secondary = tx.query_row(&query, params![secondary_id], trip).optional()?;
+        let (Some(mut primary), Some(secondary)) = (primary, secondary) else {
+            return Ok(false);
+        };
+
+        primary.merge(&secondary);
+        update_trip(&tx, &primary, primary_id)?;
+        tx.execute("DELETE FROM trips WHERE id = ?1", params![secondary_id])?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    fn split_trip(&self, trip_id: i64, at: SystemTime) -> Result<Option<i64>, Box<dyn Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let query = format!("SELECT {} FROM trips WHERE id = ?1", TRIP_COLUMNS);
+        let Some(mut head) = tx.query_row(&query, params![trip_id], trip).optional()? else {
+            return Ok(None);
+        };
+        if at <= head.start_timestamp || at >= head.end_timestamp {
+            return Err("The split time must be within the trip".into());
+        }
+
+        // The statuses from the split time on make the new trip
+        let description = unique_trip_description(&head.description, &trip_descriptions(&tx, &head.description)?);
+        let mut tail = Trip::new(at, description);
+        {
+            let mut stmt = tx.prepare(
+                "SELECT timestamp, total_distance_nm, total_time_ms, engine_on, is_moored
+                 FROM vessel_status WHERE timestamp >= ?1 AND timestamp <= ?2 ORDER BY timestamp",
+            )?;
+            let mut rows = stmt.query(params![format_timestamp(at), format_timestamp(head.end_timestamp)])?;
+            while let Some(row) = rows.next()? {
+                let time = parse_timestamp(&row.get::<_, String>(0)?)?;
+                tail.update(time, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?);
+            }
+        }
+        let head_end = tx
+            .query_row(
+                "SELECT MAX(timestamp) FROM vessel_status WHERE timestamp >= ?1 AND timestamp < ?2",
+                params![format_timestamp(head.start_timestamp), format_timestamp(at)],
+                |row| row.get::<_, Option<String>>(0),
+            )?
+            .map(|timestamp| parse_timestamp(&timestamp))
+            .transpose()?
+            .unwrap_or(head.start_timestamp);
+        head.truncate(head_end, &tail);
+
+        update_trip(&tx, &head, trip_id)?;
+        tx.execute(
+            "INSERT INTO trips
+                (description, start_timestamp, end_timestamp,
+                 total_distance_sailed, total_distance_motoring,
+                 total_time_sailing, total_time_motoring, total_time_moored)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                tail.description,
+                format_timestamp(tail.start_timestamp),
+                format_timestamp(tail.end_timestamp),
+                tail.total_distance_sailed,
+                tail.total_distance_motoring,
+                tail.total_time_sailing,
+                tail.total_time_motoring,
+                tail.total_time_moored,
+            ],
+        )?;
+        let tail_id = tx.last_insert_rowid();
+        tx.commit()?;
+        Ok(Some(tail_id))
+    }
+
+    fn insert_status_and_trip(
+        &self,
+        status_op: VesselStatusOperation,
+        trip_operation: TripOperation,
+    ) -> Result<(i64, Option<i64>), Box<dyn Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+
+        tx.execute(
+            &format!(
+                "INSERT INTO vessel_status
+                (timestamp, latitude, longitude, average_speed_kn, max_speed_kn, is_moored, engine_on, total_distance_nm, total_time_ms, average_wind_speed_kn, average_wind_angle_deg, cog_deg, average_heading_deg, quality_score, session_id)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, {})",
+                SESSION_AT
+            ),
+            params![
+                format_timestamp(status_op.time),
+                status_op.latitude,
+                status_op.longitude,
+                status_op.average_speed_kn,
+                status_op.max_speed_kn,
+                status_op.is_moored,
+                status_op.engine_on,
+                status_op.total_distance_nm,
+                status_op.total_time_ms,
+                status_op.average_wind_speed_kn,
+                status_op.average_wind_angle_deg,
+                status_op.cog_deg,
+                status_op.average_heading_deg,
+                status_op.quality_score,
+            ],
+        )?;
+        let status_id = tx.last_insert_rowid();
+
+        let trip_id = match trip_operation {
+            TripOperation::CreateTrip(trip) => {
+                tx.execute(
+                    "INSERT INTO trips
+                        (description, start_timestamp, end_timestamp,
+                         total_distance_sailed, total_distance_motoring,
+                         total_time_sailing, total_time_motoring, total_time_moored)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        trip.description,
+                        format_timestamp(trip.start_timestamp),
+                        format_timestamp(trip.end_timestamp),
+                        trip.total_distance_sailed,
+                        trip.total_distance_motoring,
+                        trip.total_time_sailing,
+                        trip.total_time_motoring,
+                        trip.total_time_moored,
+                    ],
+                )?;
+                Some(tx.last_insert_rowid())
+            }
+            TripOperation::UpdateTrip(trip) => {
+                if let Some(trip_id) = trip.id {
+                    tx.execute(
+                        "UPDATE trips
+                            SET end_timestamp = ?1,
+                                total_distance_sailed = ?2,
+                                total_distance_motoring = ?3,
+                                total_time_sailing = ?4,
+                                total_time_motoring = ?5,
+                                total_time_moored = ?6
+                            WHERE id = ?7",
+                        params![
+                            format_timestamp(trip.end_timestamp),
+                            trip.total_distance_sailed,
+                            trip.total_distance_motoring,
+                            trip.total_time_sailing,
+                            trip.total_time_motoring,
+                            trip.total_time_moored,
+                            trip_id,
+                        ],
+                    )?;
+                }
+                None
+            }
+            TripOperation::None => None,
+        };
+
+        tx.commit()?;
+        Ok((status_id, trip_id))
+    }
+
+    fn insert_environmental_metrics(
+        &self,
+        data: &MetricData,
+        metric_id: MetricId,
+        now: SystemTime,
+    ) -> Result<Option<i64>, Box<dyn Error>> {
+        if data.avg.is_some() || data.max.is_some() || data.min.is_some() {
+            let id = self.conn()?.query_row(
+                &format!(
+                    "INSERT INTO environmental_data
+                    (timestamp, metric_id, value_avg, value_max, value_min, value_count, unit, session_id)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, {})
+                    ON CONFLICT (timestamp, metric_id) DO UPDATE SET
+                        value_avg = excluded.value_avg,
+                        value_max = excluded.value_max,
+                        value_min = excluded.value_min,
+                        value_count = excluded.value_count,
+                        unit = excluded.unit,
+                        session_id = excluded.session_id
+                    RETURNING id",
+                    SESSION_AT
+                ),
+                params![
+                    format_timestamp(now),
+                    metric_id.as_u8(),
+                    data.avg,
+                    data.max,
+                    data.min,
+                    data.count.map(|count| count as i64),
+                    metric_id.unit(),
+                ],
+                |row| row.get(0),
+            )?;
+            return Ok(Some(id));
+        }
+
+        Ok(None)
+    }
+
+    fn get_last_trip(&self) -> Result<Option<Trip>, Box<dyn Error>> {
+        let query = format!("SELECT {} FROM trips ORDER BY end_timestamp DESC LIMIT 1", TRIP_COLUMNS);
+        Ok(self.conn()?.query_row(&query, [], trip).optional()?)
+    }
+
+    fn fetch_trip(&self, trip_id: u32) -> Result<Option<TripSummary>, Box<dyn Error>> {
+        let query = format!("SELECT {} FROM trips WHERE id = ?1", TRIP_SUMMARY_COLUMNS);
+        let trip = self.conn()?.query_row(&query, params![trip_id], trip_summary).optional()?;
+        Ok(trip)
+    }
+
+    fn fetch_trips(&self, year: Option<i32>, last_months: Option<u32>, since: Option<&str>) -> Result<Vec<TripSummary>, Box<dyn Error>> {
+        let (condition, value) = if let Some(year) = year {
+            ("strftime('%Y', start_timestamp) = ?1", format!("{:04}", year))
+        } else {
+            // default last 12 months
+            ("start_timestamp >= datetime('now', ?1)", format!("-{} months", last_months.unwrap_or(12)))
+        };
+        let mut values = vec![value];
+        // The end of a trip moves forward with each status report, so it is its last modification
+        // Compared in whole seconds, the precision of `since`
+        let since_condition = match since {
+            Some(since) => {
+                values.push(since.to_string());
+                " AND substr(end_timestamp, 1, 19) > ?2"
+            }
+            None => "",
+        };
+        let query = format!(
+            "SELECT {} FROM trips WHERE {}{} ORDER BY start_timestamp DESC",
+            TRIP_SUMMARY_COLUMNS, condition, since_condition
+        );
+
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&query)?;
+        let trips = stmt
+            .query_map(params_from_iter(values), trip_summary)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(trips)
+    }
+
+    fn fetch_track(&self, trip_id: Option<u32>, start: Option<&str>, end: Option<&str>, paging: TrackPaging) -> Result<Vec<TrackPoint>, Box<dyn Error>> {
+        let conn = self.conn()?;
+        let limit = paging.limit_clause("-1");
+        let track = if let Some(trip_id) = trip_id {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT substr(vs.timestamp, 1, 19) AS timestamp,
+                        vs.latitude, vs.longitude, vs.average_speed_kn, vs.max_speed_kn,
+                        vs.is_moored, vs.engine_on
+                 FROM vessel_status vs
+                 JOIN trips t ON vs.timestamp BETWEEN t.start_timestamp AND t.end_timestamp
+                 WHERE t.id = ?1
+                 ORDER BY vs.timestamp{}",
+                limit
+            ))?;
+            stmt.query_map(params![trip_id], track_point)?.collect::<Result<Vec<_>, _>>()?
+        } else if let (Some(start), Some(end)) = (start, end) {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT substr(timestamp, 1, 19) AS timestamp,
+                        latitude, longitude, average_speed_kn, max_speed_kn, is_moored, engine_on
+                 FROM vessel_status WHERE timestamp BETWEEN ?1 AND ?2 ORDER BY timestamp{}",
+                limit
+            ))?;
+            stmt.query_map(params![start, end], track_point)?.collect::<Result<Vec<_>, _>>()?
+        } else {
+            return Err("Either trip_id or both start and end timestamps are required".into());
+        };
+
+        Ok(paging.downsample(track))
+    }
+
+    fn fetch_metrics(&self, metric: &str, trip_id: Option<u32>, start: Option<&str>, end: Option<&str>, bucket: Option<MetricBucket>) -> Result<Vec<WebMetricData>, Box<dyn Error>> {
+        // The metric can be given by name ("pressure") or by id ("1")
+        let metric_id = MetricId::parse(metric)
+            .ok_or_else(|| format!("Unknown metric: {}", metric))?
+            .as_u8();
+
+        let conn = self.conn()?;
+        let metrics = if let Some(trip_id) = trip_id {
+            let mut stmt = conn.prepare(&build_metrics_query(SqlDialect::Sqlite, true, bucket))?;
+            stmt.query_map(named_params! { ":trip_id": trip_id, ":metric_id": metric_id }, web_metric)?
+                .collect::<Result<Vec<_>, _>>()?
+        } else if let (Some(start), Some(end)) = (start, end) {
+            let mut stmt = conn.prepare(&build_metrics_query(SqlDialect::Sqlite, false, bucket))?;
+            stmt.query_map(named_params! { ":metric_id": metric_id, ":start": start, ":end": end }, web_metric)?
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            return Err("Either trip_id or both start and end timestamps are required".into());
+        };
+
+        Ok(metrics)
+    }
+
+    fn fetch_latest_status(&self) -> Result<CurrentStatus, Box<dyn Error>> {
+        let conn = self.conn()?;
+        let status = conn.query_row(
+            "SELECT substr(timestamp, 1, 19) AS timestamp,
+                    latitude, longitude, average_speed_kn, max_speed_kn, is_moored, engine_on
+             FROM vessel_status ORDER BY timestamp DESC LIMIT 1",
+            [],
+            track_point,
+        ).optional()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT substr(timestamp, 1, 19) AS timestamp, metric_id,
+                    value_avg AS avg_value, value_max AS max_value, value_min AS min_value,
+                    value_count AS count
+             FROM environmental_data
+             WHERE metric_id = ?1
+             ORDER BY timestamp DESC LIMIT 1",
+        )?;
+        let mut metrics = BTreeMap::new();
+        for metric in MetricId::ALL_METRICS {
+            if let Some(row) = stmt.query_row(params![metric.as_u8()], web_metric).optional()? {
+                metrics.insert(metric.name().to_string(), row);
+            }
+        }
+        Ok(CurrentStatus { status, metrics })
+    }
+
+    fn fetch_stats(&self, year: Option<i32>) -> Result<VesselStats, Box<dyn Error>> {
+        let year = year.map(|y| format!("{:04}", y));
+        let conn = self.conn()?;
+        let (trip_count, total_distance_nm, sailing_time_ms, motoring_time_ms) = conn.query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(total_distance_sailed + total_distance_motoring), 0.0),
+                    COALESCE(SUM(total_time_sailing), 0),
+                    COALESCE(SUM(total_time_motoring), 0)
+             FROM trips
+             WHERE ?1 IS NULL OR strftime('%Y', start_timestamp) = ?1",
+            params![year],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        let (max_speed_kn, days_underway) = conn.query_row(
+            "SELECT COALESCE(MAX(max_speed_kn), 0.0),
+                    COUNT(DISTINCT CASE WHEN is_moored = 0 THEN substr(timestamp, 1, 10) END)
+             FROM vessel_status
+             WHERE ?1 IS NULL OR strftime('%Y', timestamp) = ?1",
+            params![year],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(VesselStats {
+            trip_count,
+            total_distance_nm,
+            sailing_time_ms,
+            motoring_time_ms,
+            max_speed_kn,
+            days_underway,
+        })
+    }
+
+    fn insert_can_error_bucket(&self, bucket: &CanErrorBucket) -> Result<(), Box<dyn Error>> {
+        self.conn()?.execute(
+            "INSERT INTO can_error_stats (hour, can_frames, can_errors) VALUES (?1, ?2, ?3)
+             ON CONFLICT (hour) DO UPDATE SET
+                can_frames = can_frames + excluded.can_frames,
+                can_errors = can_errors + excluded.can_errors",
+            params![bucket.hour, bucket.can_frames as i64, bucket.can_errors as i64],
+        )?;
+        Ok(())
+    }
+
+    fn fetch_can_error_buckets(&self, start: Option<&str>, end: Option<&str>) -> Result<Vec<CanErrorBucket>, Box<dyn Error>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT hour, can_frames, can_errors FROM can_error_stats
+             WHERE (?1 IS NULL OR hour >= ?1) AND (?2 IS NULL OR hour <= ?2)
+             ORDER BY hour",
+        )?;
+        let buckets = stmt
+            .query_map(params![start, end], |row| {
+                Ok(CanErrorBucket {
+                    hour: row.get(0)?,
+                    can_frames: row.get::<_, i64>(1)? as u64,
+                    can_errors: row.get::<_, i64>(2)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(buckets)
+    }
+
+    fn start_session(&self, name: &str, now: SystemTime) -> Result<Session, Box<dyn Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute("UPDATE sessions SET end_timestamp = ?1 WHERE end_timestamp IS NULL", params![format_timestamp(now)])?;
+        tx.execute(
+            "INSERT INTO sessions (name, start_timestamp) VALUES (?1, ?2)",
+            params![name, format_timestamp(now)],
+        )?;
+        let id = tx.last_insert_rowid();
+        let session = tx.query_row(&format!("SELECT {} FROM sessions WHERE id = ?1", SESSION_COLUMNS), params![id], session)?;
+        tx.commit()?;
+        Ok(session)
+    }
+
+    fn stop_session(&self, now: SystemTime) -> Result<Option<Session>, Box<dyn Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let Some(id) = tx
+            .query_row("SELECT MAX(id) FROM sessions WHERE end_timestamp IS NULL", [], |row| row.get::<_, Option<i64>>(0))?
+        else {
+            return Ok(None);
+        };
+        tx.execute("UPDATE sessions SET end_timestamp = ?1 WHERE end_timestamp IS NULL", params![format_timestamp(now)])?;
+        let session = tx.query_row(&format!("SELECT {} FROM sessions WHERE id = ?1", SESSION_COLUMNS), params![id], session)?;
+        tx.commit()?;
+        Ok(Some(session))
+    }
+
+    fn fetch_session(&self, session_id: i64) -> Result<Option<Session>, Box<dyn Error>> {
+        let query = format!("SELECT {} FROM sessions WHERE id = ?1", SESSION_COLUMNS);
+        let session = self.conn()?.query_row(&query, params![session_id], session).optional()?;
+        Ok(session)
+    }
+
+    fn fetch_session_track(&self, session_id: i64) -> Result<Vec<TrackPoint>, Box<dyn Error>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT substr(timestamp, 1, 19) AS timestamp,
+                    latitude, longitude, average_speed_kn, max_speed_kn, is_moored, engine_on
+             FROM vessel_status WHERE session_id = ?1 ORDER BY timestamp",
+        )?;
+        let track = stmt.query_map(params![session_id], track_point)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(track)
+    }
+
+    fn fetch_session_metrics(&self, metric: &str, session_id: i64) -> Result<Vec<WebMetricData>, Box<dyn Error>> {
+        let metric_id = MetricId::parse(metric)
+            .ok_or_else(|| format!("Unknown metric: {}", metric))?
+            .as_u8();
+
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT substr(timestamp, 1, 19) AS timestamp, metric_id,
+                    value_avg AS avg_value, value_max AS max_value, value_min AS min_value,
+                    value_count AS count
+             FROM environmental_data
+             WHERE session_id = ?1 AND metric_id = ?2
+             ORDER BY timestamp",
+        )?;
+        let metrics = stmt.query_map(params![session_id, metric_id], web_metric)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(metrics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn start_time() -> SystemTime {
+        // 2024-06-01 10:00:00 UTC
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_717_236_000)
+    }
+
+    fn status_at(time: SystemTime, engine_on: bool) -> VesselStatusOperation {
+        VesselStatusOperation {
+            time,
+            latitude: 43.5,
+            longitude: 10.2,
+            average_speed_kn: 5.5,
+            max_speed_kn: 6.5,
+            is_moored: false,
+            engine_on,
+            total_distance_nm: 0.05,
+            total_time_ms: 30000,
+            average_wind_speed_kn: Some(12.0),
+            wind_speed_variance: None,
+            average_wind_angle_deg: Some(45.0),
+            wind_angle_variance: None,
+            cog_deg: Some(180.0),
+            average_heading_deg: None,
+            quality_score: None,
+        }
+    }
+
+    #[test]
+    fn test_status_and_trip_round_trip() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        db.health_check().unwrap();
+        assert!(db.get_last_trip().unwrap().is_none());
+
+        let t0 = start_time();
+        let mut trip = Trip::new(t0, "Trip 2024-06-01".to_string());
+        let (_, trip_id) = db.insert_status_and_trip(status_at(t0, false), TripOperation::CreateTrip(trip.clone())).unwrap();
+        trip.id = trip_id;
+        assert!(trip_id.is_some());
+
+        let t1 = t0 + Duration::from_secs(30);
+        trip.update(t1, 0.05, 30000, true, false);
+        let (status_id, no_trip) = db.insert_status_and_trip(status_at(t1, true), TripOperation::UpdateTrip(trip.clone())).unwrap();
+        assert_eq!(status_id, 2);
+        assert!(no_trip.is_none());
+
+        let last = db.get_last_trip().unwrap().unwrap();
+        assert_eq!(last.id, trip_id);
+        assert_eq!(last.start_timestamp, t0);
+        assert_eq!(last.end_timestamp, t1);
+        assert_eq!(last.total_time_motoring, 30000);
+        assert_eq!(last.total_distance_motoring, 0.05);
+
+        let track = db.fetch_track(trip_id.map(|id| id as u32), None, None, TrackPaging::default()).unwrap();
+        assert_eq!(track.len(), 2);
+        assert_eq!(track[0].timestamp, "2024-06-01 10:00:00");
+        assert!(!track[0].engine_on);
+        assert!(track[1].engine_on);
+
+        let range = db.fetch_track(None, Some("2024-06-01 10:00:10"), Some("2024-06-01 11:00:00"), TrackPaging::default()).unwrap();
+        assert_eq!(range.len(), 1);
+        assert!(db.fetch_track(None, None, None, TrackPaging::default()).is_err());
+    }
+
+    #[test]
+    fn test_trip_descriptions_with_wildcards() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        let t0 = start_time();
+        for (i, description) in ["Run_1 50%", "Run_1 50% #2", "RunX1 50% #3", "Run_1 50%, wet #4"].iter().enumerate() {
+            let t = t0 + Duration::from_secs(3600 * i as u64);
+            db.insert_status_and_trip(status_at(t, false), TripOperation::CreateTrip(Trip::new(t, description.to_string()))).unwrap();
+        }
+
+        // The wildcards of the base description are matched literally
+        let mut descriptions = db.fetch_trip_descriptions("Run_1 50%").unwrap();
+        descriptions.sort();
+        assert_eq!(descriptions, ["Run_1 50%", "Run_1 50% #2"]);
+    }
+
+    #[test]
+    fn test_trip_summaries_and_descriptions() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        let t0 = start_time();
+        let (_, trip_id) = db.insert_status_and_trip(status_at(t0, false), TripOperation::CreateTrip(Trip::new(t0, "Trip 2024-06-01".to_string()))).unwrap();
+        let t1 = t0 + Duration::from_secs(3600);
+        db.insert_status_and_trip(status_at(t1, false), TripOperation::CreateTrip(Trip::new(t1, "Trip 2024-06-01 #2".to_string()))).unwrap();
+
+        let mut descriptions = db.fetch_trip_descriptions("Trip 2024-06-01").unwrap();
+        descriptions.sort();
+        assert_eq!(descriptions, ["Trip 2024-06-01", "Trip 2024-06-01 #2"]);
+
+        let trip_id = trip_id.unwrap();
+        db.update_trip_description(trip_id, "Elba").unwrap();
+        let summary = db.fetch_trip(trip_id as u32).unwrap().unwrap();
+        assert_eq!(summary.description, "Elba");
+        assert_eq!(summary.start_date, "2024-06-01 10:00:00");
+        assert!(db.fetch_trip(99).unwrap().is_none());
+
+        let trips = db.fetch_trips(Some(2024), None, None).unwrap();
+        assert_eq!(trips.len(), 2);
+        assert_eq!(trips[0].description, "Trip 2024-06-01 #2"); // Most recent first
+        assert!(db.fetch_trips(Some(2023), None, None).unwrap().is_empty());
+
+        // Only the trips ended after `since`
+        let modified = db.fetch_trips(Some(2024), None, Some("2024-06-01 10:30:00")).unwrap();
+        assert_eq!(modified.len(), 1);
+        assert_eq!(modified[0].description, "Trip 2024-06-01 #2");
+        assert!(db.fetch_trips(Some(2024), None, Some("2024-06-01 11:00:00")).unwrap().is_empty());
+    }
+
+    /// Record a trip with a status every 30 seconds, the engine state of each status in order
+    fn record_trip(db: &SqliteDatabase, start: SystemTime, description: &str, engine: &[bool]) -> i64 {
+        let mut trip = Trip::new(start, description.to_string());
+        for (i, engine_on) in engine.iter().enumerate() {
+            let time = start + Duration::from_secs(30 * i as u64);
+            trip.update(time, 0.05, 30000, *engine_on, false);
+            let operation = if trip.id.is_some() { TripOperation::UpdateTrip(trip.clone()) } else { TripOperation::CreateTrip(trip.clone()) };
+            if let (_, Some(trip_id)) = db.insert_status_and_trip(status_at(time, *engine_on), operation).unwrap() {
+                trip.id = Some(trip_id);
+            }
+        }
+        trip.id.unwrap()
+    }
+
+    #[test]
+    fn test_merge_trips_sums_fields() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        let t0 = start_time();
+        let first = record_trip(&db, t0, "Day 1", &[false, false, true]);
+        let second = record_trip(&db, t0 + Duration::from_secs(30 * 3600), "Day 2", &[false, true]);
+
+        assert!(db.merge_trips(first, second).unwrap());
+        let merged = db.fetch_trip(first as u32).unwrap().unwrap();
+        assert_eq!(merged.description, "Day 1");
+        assert_eq!(merged.start_date, "2024-06-01 10:00:00");
+        assert_eq!(merged.end_date, "2024-06-02 16:00:30");
+        assert!((merged.sailing_distance_nm - 0.15).abs() < 1e-9);
+        assert!((merged.motoring_distance_nm - 0.1).abs() < 1e-9);
+        assert_eq!(merged.sailing_time_ms, 90000);
+        assert_eq!(merged.motoring_time_ms, 60000);
+        assert_eq!(merged.total_time_ms, 150000);
+        assert!(db.fetch_trip(second as u32).unwrap().is_none());
+
+        assert!(!db.merge_trips(first, second).unwrap());
+        assert!(db.merge_trips(first, first).is_err());
+    }
+
+    #[test]
+    fn test_split_trip() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        let t0 = start_time();
+        let trip_id = record_trip(&db, t0, "Test", &[false, false, true, true]);
+
+        let tail_id = db.split_trip(trip_id, t0 + Duration::from_secs(45)).unwrap().unwrap();
+        let head = db.fetch_trip(trip_id as u32).unwrap().unwrap();
+        assert_eq!(head.end_date, "2024-06-01 10:00:30");
+        assert!((head.sailing_distance_nm - 0.1).abs() < 1e-9);
+        assert_eq!(head.sailing_time_ms, 60000);
+        assert_eq!(head.motoring_time_ms, 0);
+
+        let tail = db.fetch_trip(tail_id as u32).unwrap().unwrap();
+        assert_eq!(tail.description, "Test #2");
+        assert_eq!(tail.start_date, "2024-06-01 10:00:45");
+        assert_eq!(tail.end_date, "2024-06-01 10:01:30");
+        assert!((tail.motoring_distance_nm - 0.1).abs() < 1e-9);
+        assert_eq!(tail.motoring_time_ms, 60000);
+        assert_eq!(tail.sailing_time_ms, 0);
+        assert_eq!(db.fetch_track(Some(tail_id as u32), None, None, TrackPaging::default()).unwrap().len(), 2);
+
+        // Outside of the trip, or no such trip
+        assert!(db.split_trip(trip_id, t0).is_err());
+        assert!(db.split_trip(99, t0 + Duration::from_secs(45)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_delete_trip_keeps_statuses() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        let t0 = start_time();
+        let (_, trip_id) = db.insert_status_and_trip(status_at(t0, false), TripOperation::CreateTrip(Trip::new(t0, "Test".to_string()))).unwrap();
+        let trip_id = trip_id.unwrap();
+
+        assert!(db.delete_trip(trip_id).unwrap());
+        assert!(db.fetch_trip(trip_id as u32).unwrap().is_none());
+        assert!(!db.delete_trip(trip_id).unwrap());
+
+        // The status is still there, just no longer part of a trip
+        assert!(db.fetch_track(Some(trip_id as u32), None, None, TrackPaging::default()).unwrap().is_empty());
+        let track = db.fetch_track(None, Some("2024-06-01 00:00:00"), Some("2024-06-02 00:00:00"), TrackPaging::default()).unwrap();
+        assert_eq!(track.len(), 1);
+    }
+
+    #[test]
+    fn test_environmental_metrics_upsert() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        let t0 = start_time();
+        let data = MetricData { avg: Some(101300.0), max: Some(101400.0), min: Some(101200.0), count: Some(10) };
+        let id = db.insert_environmental_metrics(&data, MetricId::Pressure, t0).unwrap();
+        assert!(id.is_some());
+
+        // Same metric and timestamp replaces the row
+        let updated = MetricData { avg: Some(101350.0), ..data };
+        assert_eq!(db.insert_environmental_metrics(&updated, MetricId::Pressure, t0).unwrap(), id);
+
+        let empty = MetricData { avg: None, max: None, min: None, count: None };
+        assert!(db.insert_environmental_metrics(&empty, MetricId::Humidity, t0).unwrap().is_none());
+
+        let metrics = db.fetch_metrics("pressure", None, Some("2024-06-01 00:00:00"), Some("2024-06-02 00:00:00"), None).unwrap();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].metric_id, MetricId::Pressure.as_u8().to_string());
+        assert_eq!(metrics[0].avg_value, Some(101350.0));
+        assert_eq!(metrics[0].max_value, Some(101400.0));
+        assert_eq!(metrics[0].min_value, Some(101200.0));
+        assert_eq!(metrics[0].count, Some(10));
+        assert!(db.fetch_metrics("unknown", None, Some("2024-06-01 00:00:00"), Some("2024-06-02 00:00:00"), None).is_err());
+    }
+
+    #[test]
+    fn test_metrics_hour_buckets() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        let t0 = start_time();
+        let data = |avg, max, min, count| MetricData { avg: Some(avg), max: Some(max), min: Some(min), count: Some(count) };
+        db.insert_environmental_metrics(&data(101300.0, 101400.0, 101200.0, 10), MetricId::Pressure, t0).unwrap();
+        db.insert_environmental_metrics(&data(101500.0, 101600.0, 101100.0, 30), MetricId::Pressure, t0 + Duration::from_secs(1800)).unwrap();
+        db.insert_environmental_metrics(&data(101000.0, 101000.0, 101000.0, 10), MetricId::Pressure, t0 + Duration::from_secs(7200)).unwrap();
+
+        let range = (Some("2024-06-01 00:00:00"), Some("2024-06-02 00:00:00"));
+        let metrics = db.fetch_metrics("pressure", None, range.0, range.1, Some(MetricBucket::Hour)).unwrap();
+        assert_eq!(metrics.len(), 2);
+        assert_eq!(metrics[0].timestamp, "2024-06-01 10:00:00");
+        assert_eq!(metrics[0].metric_id, MetricId::Pressure.as_u8().to_string());
+        // Weighted by the sample count of each row
+        assert_eq!(metrics[0].avg_value, Some(101450.0));
+        assert_eq!(metrics[0].max_value, Some(101600.0));
+        assert_eq!(metrics[0].min_value, Some(101100.0));
+        assert_eq!(metrics[0].count, Some(40));
+        assert_eq!(metrics[1].timestamp, "2024-06-01 12:00:00");
+
+        let metrics = db.fetch_metrics("pressure", None, range.0, range.1, Some(MetricBucket::Day)).unwrap();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].timestamp, "2024-06-01 00:00:00");
+        assert_eq!(metrics[0].count, Some(50));
+        assert_eq!(db.fetch_metrics("pressure", None, range.0, range.1, None).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_latest_status() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        assert!(db.fetch_latest_status().unwrap().is_empty());
+
+        let t0 = start_time();
+        let t1 = t0 + Duration::from_secs(30);
+        let trip = Trip::new(t0, "Trip 2024-06-01".to_string());
+        db.insert_status_and_trip(status_at(t0, false), TripOperation::CreateTrip(trip)).unwrap();
+        db.insert_status_and_trip(status_at(t1, true), TripOperation::None).unwrap();
+        let data = |avg| MetricData { avg: Some(avg), max: Some(avg), min: Some(avg), count: Some(1) };
+        db.insert_environmental_metrics(&data(101300.0), MetricId::Pressure, t0).unwrap();
+        db.insert_environmental_metrics(&data(101350.0), MetricId::Pressure, t1).unwrap();
+        db.insert_environmental_metrics(&data(21.5), MetricId::CabinTemp, t0).unwrap();
+
+        let latest = db.fetch_latest_status().unwrap();
+        let status = latest.status.unwrap();
+        assert_eq!(status.timestamp, "2024-06-01 10:00:30");
+        assert!(status.engine_on);
+        assert_eq!(latest.metrics.len(), 2);
+        assert_eq!(latest.metrics["pressure"].avg_value, Some(101350.0));
+        assert_eq!(latest.metrics["pressure"].timestamp, "2024-06-01 10:00:30");
+        assert_eq!(latest.metrics["cabin_temp"].avg_value, Some(21.5));
+    }
+
+    #[test]
+    fn test_stats() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        assert_eq!(db.fetch_stats(None).unwrap(), VesselStats::default());
+
+        let t0 = start_time();
+        let mut trip = Trip::new(t0, "Trip 2024-06-01".to_string());
+        let (_, trip_id) = db.insert_status_and_trip(status_at(t0, false), TripOperation::CreateTrip(trip.clone())).unwrap();
+        trip.id = trip_id;
+        let t1 = t0 + Duration::from_secs(30);
+        trip.update(t1, 0.05, 30000, true, false);
+        db.insert_status_and_trip(status_at(t1, true), TripOperation::UpdateTrip(trip.clone())).unwrap();
+
+        // Second trip the next day, one moored status only
+        let t2 = t0 + Duration::from_secs(86400);
+        let mut moored = status_at(t2, false);
+        moored.is_moored = true;
+        moored.max_speed_kn = 0.2;
+        db.insert_status_and_trip(moored, TripOperation::CreateTrip(Trip::new(t2, "Trip 2024-06-02".to_string()))).unwrap();
+
+        let stats = db.fetch_stats(Some(2024)).unwrap();
+        assert_eq!(stats.trip_count, 2);
+        assert_eq!(stats.total_distance_nm, 0.05);
+        assert_eq!(stats.motoring_time_ms, 30000);
+        assert_eq!(stats.sailing_time_ms, 0);
+        assert_eq!(stats.max_speed_kn, 6.5);
+        assert_eq!(stats.days_underway, 1);
+
+        assert_eq!(db.fetch_stats(None).unwrap(), stats);
+        assert_eq!(db.fetch_stats(Some(2023)).unwrap(), VesselStats::default());
+    }
+
+    #[test]
+    fn test_track_paging() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        let t0 = start_time();
+        for i in 0..10 {
+            db.insert_status_and_trip(status_at(t0 + Duration::from_secs(i * 30), false), TripOperation::None).unwrap();
+        }
+        let range = (Some("2024-06-01 00:00:00"), Some("2024-06-02 00:00:00"));
+        let fetch = |paging| db.fetch_track(None, range.0, range.1, paging).unwrap();
+        let timestamps = |track: Vec<TrackPoint>| track.into_iter().map(|p| p.timestamp).collect::<Vec<_>>();
+
+        assert_eq!(fetch(TrackPaging::default()).len(), 10);
+        assert_eq!(
+            timestamps(fetch(TrackPaging { limit: Some(2), offset: Some(3), max_points: None })),
+            ["2024-06-01 10:01:30", "2024-06-01 10:02:00"]
+        );
+        assert_eq!(fetch(TrackPaging { limit: None, offset: Some(8), max_points: None }).len(), 2);
+        assert_eq!(
+            timestamps(fetch(TrackPaging { limit: None, offset: None, max_points: Some(4) })),
+            ["2024-06-01 10:00:00", "2024-06-01 10:01:30", "2024-06-01 10:03:00", "2024-06-01 10:04:30"]
+        );
+    }
+
+    #[test]
+    fn test_can_error_buckets() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        let bucket = |hour: &str, can_frames, can_errors| CanErrorBucket { hour: hour.to_string(), can_frames, can_errors };
+
+        db.insert_can_error_bucket(&bucket("2024-06-01 10:00:00", 36000, 2)).unwrap();
+        db.insert_can_error_bucket(&bucket("2024-06-01 11:00:00", 35000, 0)).unwrap();
+        // Same hour again (e.g. after a restart): counts are added
+        db.insert_can_error_bucket(&bucket("2024-06-01 10:00:00", 1000, 3)).unwrap();
+
+        assert_eq!(
+            db.fetch_can_error_buckets(None, None).unwrap(),
+            [bucket("2024-06-01 10:00:00", 37000, 5), bucket("2024-06-01 11:00:00", 35000, 0)]
+        );
+        assert_eq!(
+            db.fetch_can_error_buckets(Some("2024-06-01 10:30:00"), None).unwrap(),
+            [bucket("2024-06-01 11:00:00", 35000, 0)]
+        );
+        assert!(db.fetch_can_error_buckets(Some("2024-06-02 00:00:00"), Some("2024-06-03 00:00:00")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_session_rows() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        let t0 = start_time();
+        let pressure = MetricData { avg: Some(101300.0), max: Some(101400.0), min: Some(101200.0), count: Some(10) };
+        let write = |time: SystemTime| {
+            db.insert_status_and_trip(status_at(time, false), TripOperation::None).unwrap();
+            db.insert_environmental_metrics(&pressure, MetricId::Pressure, time).unwrap();
+        };
+
+        // Before the session
+        write(t0);
+        assert!(db.stop_session(t0).unwrap().is_none());
+
+        let session = db.start_session("Leg 1", t0 + Duration::from_secs(60)).unwrap();
+        assert_eq!(session.start_date, "2024-06-01 10:01:00");
+        assert!(session.end_date.is_none());
+        write(t0 + Duration::from_secs(60));
+        write(t0 + Duration::from_secs(90));
+
+        let stopped = db.stop_session(t0 + Duration::from_secs(120)).unwrap().unwrap();
+        assert_eq!(stopped.id, session.id);
+        assert_eq!(stopped.end_date.as_deref(), Some("2024-06-01 10:02:00"));
+        // After the session
+        write(t0 + Duration::from_secs(150));
+
+        let track = db.fetch_session_track(session.id).unwrap();
+        let timestamps: Vec<String> = track.into_iter().map(|p| p.timestamp).collect();
+        assert_eq!(timestamps, ["2024-06-01 10:01:00", "2024-06-01 10:01:30"]);
+        let metrics = db.fetch_session_metrics("pressure", session.id).unwrap();
+        assert_eq!(metrics.len(), 2);
+        assert!(db.fetch_session_metrics("humidity", session.id).unwrap().is_empty());
+        assert!(db.fetch_session_metrics("unknown", session.id).is_err());
+
+        // Trips are unaffected by the sessions
+        let all = db.fetch_track(None, Some("2024-06-01 00:00:00"), Some("2024-06-02 00:00:00"), TrackPaging::default()).unwrap();
+        assert_eq!(all.len(), 4);
+        assert_eq!(db.fetch_session(session.id).unwrap(), Some(stopped));
+        assert!(db.fetch_session(99).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_start_session_stops_the_active_one() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        let t0 = start_time();
+        let first = db.start_session("Leg 1", t0).unwrap();
+        let second = db.start_session("Leg 2", t0 + Duration::from_secs(600)).unwrap();
+        db.insert_status_and_trip(status_at(t0 + Duration::from_secs(600), false), TripOperation::None).unwrap();
+
+        assert_eq!(db.fetch_session(first.id).unwrap().unwrap().end_date.as_deref(), Some("2024-06-01 10:10:00"));
+        // A row at the boundary belongs to the new session
+        assert!(db.fetch_session_track(first.id).unwrap().is_empty());
+        assert_eq!(db.fetch_session_track(second.id).unwrap().len(), 1);
+        assert_eq!(db.stop_session(t0 + Duration::from_secs(900)).unwrap().map(|s| s.id), Some(second.id));
+        assert!(db.stop_session(t0 + Duration::from_secs(900)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_injection_in_range_is_bound() {
+        let db = SqliteDatabase::new(":memory:").unwrap();
+        let t0 = start_time();
+        db.insert_status_and_trip(status_at(t0, false), TripOperation::None).unwrap();
+        db.insert_environmental_metrics(&MetricData { avg: Some(1.0), max: Some(1.0), min: Some(1.0), count: Some(1) }, MetricId::Pressure, t0).unwrap();
+
+        // Interpolated, the range would become "BETWEEN '' OR '1'='1' AND ..." and match every row
+        let injection = "' OR '1'='1";
+        assert!(db.fetch_track(None, Some(injection), Some("2000-01-01 00:00:00"), TrackPaging::default()).unwrap().is_empty());
+        assert!(db.fetch_metrics("pressure", None, Some(injection), Some("2000-01-01 00:00:00"), None).unwrap().is_empty());
+        assert!(db.fetch_metrics("pressure' OR '1'='1", None, Some("2024-06-01 00:00:00"), Some("2024-06-02 00:00:00"), None).is_err());
+        assert_eq!(db.fetch_metrics("pressure", None, Some("2024-06-01 00:00:00"), Some("2024-06-02 00:00:00"), None).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::environmental_monitor::MetricId;
+
+struct StuckState {
+    /// Bit pattern of the repeated value, so that only exact repetitions count
+    value_bits: u64,
+    since: Instant,
+    count: usize,
+    last_warning: Option<Instant>,
+}
+
+/// Detects sensors that keep reporting the very same value
+///
+/// A real wind or depth sensor never reports a bit-identical value for minutes:
+/// when a monitored metric repeats the same value for at least `min_samples`
+/// samples and `min_duration`, a warning is logged, then repeated at most once
+/// every `warning_interval` while the value stays frozen.
+pub struct StuckSensorDetector {
+    metrics: Vec<MetricId>,
+    min_samples: usize,
+    min_duration: Duration,
+    warning_interval: Duration,
+    states: HashMap<MetricId, StuckState>,
+}
+
+impl StuckSensorDetector {
+    pub fn new(metrics: Vec<MetricId>, min_samples: usize, min_duration: Duration, warning_interval: Duration) -> Self {
+        Self {
+            metrics,
+            min_samples,
+            min_duration,
+            warning_interval,
+            states: HashMap::new(),
+        }
+    }
+
+    /// Check a new sample of a metric
+    /// Returns true if a stuck sensor warning was raised
+    pub fn observe(&mut self, metric_id: MetricId, value: f64, now: Instant) -> bool {
+        if !self.metrics.contains(&metric_id) {
+            return false;
+        }
+
+        let value_bits = value.to_bits();
+        let state = self.states.entry(metric_id).or_insert(StuckState {
+            value_bits,
+            since: now,
+            count: 0,
+            last_warning: None,
+        });
+
+        if state.value_bits != value_bits {
+            if state.last_warning.is_some() {
+                info!("Sensor for {} is reporting changing values again", metric_id.name());
+            }
+            *state = StuckState {
+                value_bits,
+                since: now,
+                count: 1,
+                last_warning: None,
+            };
+            return false;
+        }
+
+        state.count += 1;
+        let stuck_for = now.saturating_duration_since(state.since);
+        if state.count < self.min_samples || stuck_for < self.min_duration {
+            return false;
+        }
+        if state.last_warning.is_some_and(|t| now.saturating_duration_since(t) < self.warning_interval) {
+            return false; // Throttled
+        }
+
+        state.last_warning = Some(now);
+        warn!(
+            "Sensor for {} may be stuck: value {} unchanged for {} samples ({}s)",
+            metric_id.name(), value, state.count, stuck_for.as_secs()
+        );
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detector() -> StuckSensorDetector {
+        StuckSensorDetector::new(
+            Vec::from([MetricId::WindSpeed, MetricId::Depth]),
+            10,
+            Duration::from_secs(60),
+            Duration::from_secs(600),
+        )
+    }
+
+    #[test]
+    fn test_unchanging_value_raises_throttled_warning() {
+        let mut detector = detector();
+        let t0 = Instant::now();
+
+        // One sample per second with the same value
+        let warnings: Vec<u64> = (0..=700)
+            .filter(|i| detector.observe(MetricId::WindSpeed, 12.5, t0 + Duration::from_secs(*i)))
+            .collect();
+        // First warning once both the sample count and the duration are exceeded, then throttled
+        assert_eq!(warnings, [60, 660]);
+    }
+
+    #[test]
+    fn test_varying_value_no_warning() {
+        let mut detector = detector();
+        let t0 = Instant::now();
+        for i in 0..300u64 {
+            let value = 12.0 + (i % 7) as f64 * 0.1;
+            assert!(!detector.observe(MetricId::WindSpeed, value, t0 + Duration::from_secs(i)));
+        }
+    }
+
+    #[test]
+    fn test_value_change_resets_detection() {
+        let mut detector = detector();
+        let t0 = Instant::now();
+        for i in 0..=60u64 {
+            detector.observe(MetricId::Depth, 4.2, t0 + Duration::from_secs(i));
+        }
+        // A different value restarts both the sample count and the duration
+        let first_warning = (61..=200u64)
+            .find(|i| detector.observe(MetricId::Depth, 4.3, t0 + Duration::from_secs(*i)));
+        assert_eq!(first_warning, Some(121));
+    }
+
+    #[test]
+    fn test_unmonitored_metric_ignored() {
+        let mut detector = detector();
+        let t0 = Instant::now();
+        for i in 0..1000u64 {
+            assert!(!detector.observe(MetricId::Pressure, 101325.0, t0 + Duration::from_secs(i)));
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tracing::{debug, info, warn, error};
+use nmea2k::pgns::N2kMessage;
+use nmea2k::{MessageHandler, N2kFrame};
+
+use crate::udp_broadcaster::message_to_json;
+
+/// TCP server for NMEA2000 messages
+///
+/// Accepts client connections on a background thread and streams each
+/// serialized message (same JSON format as the UDP broadcaster, one per line)
+/// to all the connected clients. Clients are dropped on write errors.
+pub struct TcpBroadcaster {
+    clients: Arc<Mutex<Vec<TcpStream>>>,
+    local_addr: Option<SocketAddr>,
+    enabled: bool,
+    error_count: u64,
+    message_count: u64,
+}
+
+impl TcpBroadcaster {
+    /// Create a new TCP broadcaster
+    ///
+    /// # Arguments
+    /// * `listen` - Address to listen on (e.g., "0.0.0.0:10110")
+    /// * `enabled` - Whether the TCP server is enabled
+    pub fn new(listen: &str, enabled: bool) -> Self {
+        let clients = Arc::new(Mutex::new(Vec::new()));
+
+        let local_addr = if enabled {
+            match Self::start_listener(listen, Arc::clone(&clients)) {
+                Ok(addr) => {
+                    debug!("TCP server listening on {}", addr);
+                    Some(addr)
+                }
+                Err(e) => {
+                    error!("Failed to start TCP server on {}: {}. TCP output disabled.", listen, e);
+                    None
+                }
+            }
+        } else {
+            debug!("TCP server disabled in configuration");
+            None
+        };
+
+        Self {
+            clients,
+            local_addr,
+            enabled,
+            error_count: 0,
+            message_count: 0,
+        }
+    }
+
+    /// Bind the listener and accept connections on a background thread
+    fn start_listener(listen: &str, clients: Arc<Mutex<Vec<TcpStream>>>) -> Result<SocketAddr, std::io::Error> {
+        let listener = TcpListener::bind(listen)?;
+        let local_addr = listener.local_addr()?;
+
+        thread::Builder::new()
+            .name("tcp-server".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+                            // Non-blocking so a slow client never stalls the main loop,
+                            // a client that can't keep up is dropped on the next write
+                            if let Err(e) = stream.set_nonblocking(true) {
+                                warn!("Failed to configure TCP client {}: {}", peer, e);
+                                continue;
+                            }
+                            let _ = stream.set_nodelay(true);
+                            info!("TCP client connected: {}", peer);
+                            clients.lock().unwrap().push(stream);
+                        }
+                        Err(e) => warn!("Failed to accept TCP connection: {}", e),
+                    }
+                }
+            })?;
+
+        Ok(local_addr)
+    }
+
+    /// Address the server is actually bound to, if running
+    #[allow(dead_code)]
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Number of currently connected clients
+    #[allow(dead_code)]
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// Serialize and send an NMEA2000 message to all the connected clients
+    fn broadcast_message(&mut self, message: &N2kMessage, source: u8, priority: u8) {
+        if !self.enabled || self.local_addr.is_none() {
+            return;
+        }
+
+        let mut clients = self.clients.lock().unwrap();
+        if clients.is_empty() {
+            return;
+        }
+
+        let mut line = match message_to_json(message, source, priority) {
+            Ok(j) => j,
+            Err(e) => {
+                if self.error_count < 10 {
+                    warn!("Failed to serialize message: {}", e);
+                }
+                self.error_count += 1;
+                return;
+            }
+        };
+        line.push('\n');
+
+        clients.retain_mut(|client| match client.write_all(line.as_bytes()) {
+            Ok(_) => true,
+            Err(e) => {
+                let peer = client.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+                info!("Dropping TCP client {}: {}", peer, e);
+                false
+            }
+        });
+
+        self.message_count += 1;
+    }
+
+    /// Get statistics - for future uses
+    /// Returns (message_count, error_count)
+    #[allow(dead_code)]
+    pub fn stats(&self) -> (u64, u64) {
+        (self.message_count, self.error_count)
+    }
+}
+
+impl MessageHandler for TcpBroadcaster {
+    fn handle_message(&mut self, frame: &N2kFrame, _timestamp: std::time::Instant) {
+        self.broadcast_message(&frame.message, frame.source(), frame.priority());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::time::{Duration, Instant};
+    use nmea2k::pgns::NMEASystemTime;
+
+    #[test]
+    fn test_create_disabled_server() {
+        let server = TcpBroadcaster::new("127.0.0.1:0", false);
+        assert!(server.local_addr().is_none());
+        assert_eq!(server.client_count(), 0);
+    }
+
+    #[test]
+    fn test_client_receives_message() {
+        let mut server = TcpBroadcaster::new("127.0.0.1:0", true);
+        let addr = server.local_addr().unwrap();
+
+        let client = TcpStream::connect(addr).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        // Wait for the accept thread to register the client
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.client_count() == 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(server.client_count(), 1);
+
+        let msg = NMEASystemTime {
+            pgn: 126992,
+            sid: 0,
+            source: 0,
+            date_time: nmea2k::pgns::nmea2000_date_time::N2kDateTime {
+                date: 19000,
+                time: 43200.0,
+            },
+        };
+        server.broadcast_message(&N2kMessage::NMEASystemTime(msg), 1, 3);
+
+        let mut line = String::new();
+        BufReader::new(client).read_line(&mut line).unwrap();
+        let json: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(json["message_type"], "NMEASystemTime");
+        assert_eq!(json["pgn"], 126992);
+        assert_eq!(json["source"], 1);
+        assert_eq!(json["priority"], 3);
+        assert_eq!(server.stats(), (1, 0));
+    }
+}
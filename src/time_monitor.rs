@@ -1,8 +1,10 @@
-use std::time::{SystemTime as StdSystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime as StdSystemTime, UNIX_EPOCH};
 use nmea2k::pgns::NMEASystemTime;
-use nix::time::{ClockId, clock_settime};
-use nix::sys::time::TimeSpec;
+use nmea2k::pgns::nmea2000_date_time::N2kDateTime;
+#[cfg(target_os = "linux")]
+use nix::{errno::Errno, sys::time::TimeSpec, time::{ClockId, clock_settime}};
 use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
 use crate::application_state::ApplicationState;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -10,6 +12,8 @@ pub enum TimeSyncStatus {
     NotInitialized = 0,
     TimeSkewDetected = 1,
     Synchronized = 2,
+    /// The NMEA2000 time was received before but not within the source timeout
+    NoSource = 3,
 }
 
 impl std::fmt::Display for TimeSyncStatus {
@@ -18,6 +22,7 @@ impl std::fmt::Display for TimeSyncStatus {
             TimeSyncStatus::NotInitialized => write!(f, "Not Initialized"),
             TimeSyncStatus::TimeSkewDetected => write!(f, "Time Skew Detected"),
             TimeSyncStatus::Synchronized => write!(f, "Synchronized"),
+            TimeSyncStatus::NoSource => write!(f, "No Time Source"),
         }
     }
 }
@@ -33,10 +38,116 @@ impl std::fmt::Display for TimeSyncStatusAndSkew {
             TimeSyncStatus::NotInitialized => write!(f, "Not Initialized"),
             TimeSyncStatus::TimeSkewDetected => write!(f, "Time Skew Detected ({} ms)", self.skew),
             TimeSyncStatus::Synchronized => write!(f, "Synchronized ({} ms skew)", self.skew),
+            TimeSyncStatus::NoSource => write!(f, "No Time Source (last skew {} ms)", self.skew),
         }
     }
 }
 
+/// Action taken when the time skew persists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkewAction {
+    /// Only warn (the warning is always printed, whatever the action)
+    Warn,
+    /// Run the configured NTP resync command
+    NtpResync,
+    /// Set the system time from the NMEA2000 time
+    SetFromNmea,
+}
+
+impl std::fmt::Display for SkewAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SkewAction::Warn => write!(f, "warn"),
+            SkewAction::NtpResync => write!(f, "ntp_resync"),
+            SkewAction::SetFromNmea => write!(f, "set_from_nmea"),
+        }
+    }
+}
+
+struct EscalationStep {
+    action: SkewAction,
+    after: Duration,
+    last_taken: Option<Instant>,
+}
+
+/// Decides which action to take while the time skew persists
+///
+/// Each action becomes eligible once the skew has lasted for its delay, the most
+/// escalated eligible action is taken and then repeated at most once per cooldown.
+/// The escalation restarts when the time is synchronized again.
+pub struct SkewEscalation {
+    steps: Vec<EscalationStep>,
+    cooldown: Duration,
+    skew_since: Option<Instant>,
+}
+
+impl SkewEscalation {
+    /// `steps` are (action, delay since the skew was first detected), in any order
+    pub fn new(steps: Vec<(SkewAction, Duration)>, cooldown: Duration) -> Self {
+        let mut steps: Vec<EscalationStep> = steps
+            .into_iter()
+            .map(|(action, after)| EscalationStep { action, after, last_taken: None })
+            .collect();
+        steps.sort_by_key(|step| step.after);
+        Self {
+            steps,
+            cooldown,
+            skew_since: None,
+        }
+    }
+
+    /// Feed the result of a skew check
+    /// Returns the action to take now, if any
+    pub fn decide(&mut self, skewed: bool, now: Instant) -> Option<SkewAction> {
+        if !skewed {
+            self.skew_since = None;
+            self.steps.iter_mut().for_each(|step| step.last_taken = None);
+            return None;
+        }
+
+        let since = *self.skew_since.get_or_insert(now);
+        let skew_duration = now.saturating_duration_since(since);
+        let cooldown = self.cooldown;
+        let step = self.steps.iter_mut().rev().find(|step| skew_duration >= step.after)?;
+        if step.last_taken.is_some_and(|t| now.saturating_duration_since(t) < cooldown) {
+            return None;
+        }
+        step.last_taken = Some(now);
+        Some(step.action)
+    }
+}
+
+/// Time without NMEA2000 system time after which the time source is considered lost
+pub const DEFAULT_TIME_SOURCE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Minimum time between two settings of the system clock from the NMEA time
+pub const SYSTEM_TIME_SET_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Whether the date and time are set, not the "not available" values
+fn is_valid_nmea_time(date_time: &N2kDateTime) -> bool {
+    // Time is in units of 0.0001 seconds since midnight
+    date_time.date < 0xFFFE && date_time.time < 86_400.0 * 10_000.0
+}
+
+/// Set the realtime clock, requires root (or CAP_SYS_TIME)
+#[cfg(target_os = "linux")]
+fn apply_system_time(time: StdSystemTime) -> Result<(), String> {
+    let since_epoch = time
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| "NMEA time is before the Unix epoch".to_string())?;
+    match clock_settime(ClockId::CLOCK_REALTIME, TimeSpec::from_duration(since_epoch)) {
+        Ok(()) => Ok(()),
+        Err(Errno::EPERM) => Err("permission denied, setting the system time requires root".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn apply_system_time(_time: StdSystemTime) -> Result<(), String> {
+    Err("setting the system time is only supported on Linux".to_string())
+}
+
 pub struct TimeMonitor {
     application_state: Arc<Mutex<ApplicationState>>,
     last_warning_time: Option<StdSystemTime>,
@@ -46,6 +157,13 @@ pub struct TimeMonitor {
     last_measured_skew_ms: i64,
     is_initialized: bool,
     set_system_time_enabled: bool,
+    /// None to just warn, and set the time if enabled, with each skew warning
+    escalation: Option<SkewEscalation>,
+    ntp_resync_command: String,
+    last_action: Option<SkewAction>,
+    last_system_time_set: Option<Instant>,
+    last_time_message: Option<Instant>,
+    source_timeout: Duration,
 }
 
 impl TimeMonitor {
@@ -59,20 +177,52 @@ impl TimeMonitor {
             last_measured_skew_ms: 0,
             is_initialized: false,
             set_system_time_enabled,
+            escalation: None,
+            ntp_resync_command: String::new(),
+            last_action: None,
+            last_system_time_set: None,
+            last_time_message: None,
+            source_timeout: DEFAULT_TIME_SOURCE_TIMEOUT,
         }
     }
 
+    /// Set the actions taken on sustained skew and the command used by `SkewAction::NtpResync`
+    pub fn set_escalation(&mut self, escalation: SkewEscalation, ntp_resync_command: String) {
+        self.escalation = Some(escalation);
+        self.ntp_resync_command = ntp_resync_command;
+    }
+
+    /// Set the time without NMEA2000 system time after which the status is `NoSource`
+    pub fn set_source_timeout(&mut self, source_timeout: Duration) {
+        self.source_timeout = source_timeout;
+    }
+
+    /// Action taken on skew since the last call, if any
+    pub fn take_last_action(&mut self) -> Option<SkewAction> {
+        self.last_action.take()
+    }
+
     pub fn is_initialized(&self) -> bool {
         self.is_initialized
     }
 
-    pub fn time_sync_status(&self) -> TimeSyncStatusAndSkew {
+    /// Whether the NMEA2000 system time was received before but not within the source timeout
+    fn is_source_lost(&self, now: Instant) -> bool {
+        self.last_time_message.is_some_and(|last| now.saturating_duration_since(last) >= self.source_timeout)
+    }
+
+    pub fn time_sync_status(&self, now: Instant) -> TimeSyncStatusAndSkew {
         if !self.is_initialized() {
             TimeSyncStatusAndSkew {
                 status: TimeSyncStatus::NotInitialized,
                 skew: 0,
             }
-        } else if self.is_time_synchronized() == false {
+        } else if self.is_source_lost(now) {
+            TimeSyncStatusAndSkew {
+                status: TimeSyncStatus::NoSource,
+                skew: self.last_measured_skew_ms,
+            }
+        } else if self.has_time_skew {
             TimeSyncStatusAndSkew {
                 status: TimeSyncStatus::TimeSkewDetected,
                 skew: self.last_measured_skew_ms,
@@ -85,8 +235,8 @@ impl TimeMonitor {
         }
     }
 
-    /// Process a system time message and check for time skew
-    pub fn process_system_time(&mut self, nmea_time: &NMEASystemTime) {
+    /// Process a system time message received at `received`, and check for time skew
+    pub fn process_system_time(&mut self, nmea_time: &NMEASystemTime, received: Instant) {
         // Get current system time
         let now = StdSystemTime::now();
         let system_timestamp = match now.duration_since(UNIX_EPOCH) {
@@ -124,38 +274,82 @@ impl TimeMonitor {
             if should_warn {
                 self.print_time_skew_warning(time_skew_ms, system_timestamp, nmea_time.date_time.to_unix_timestamp());
                 self.last_warning_time = Some(now);
-                
-                // Attempt to set system time if enabled
-                if self.set_system_time_enabled {
-                    self.set_system_time(nmea_time);
-                }
+            }
+
+            let action = match self.escalation.as_mut() {
+                Some(escalation) => escalation.decide(true, received),
+                // Without escalation the time is set along with the warning
+                None if should_warn && self.set_system_time_enabled => Some(SkewAction::SetFromNmea),
+                None if should_warn => Some(SkewAction::Warn),
+                None => None,
+            };
+            if let Some(action) = action {
+                self.take_action(action, nmea_time, time_skew_ms, received);
             }
         } else {
             self.has_time_skew = false;
+            if let Some(escalation) = self.escalation.as_mut() {
+                escalation.decide(false, received);
+            }
         }
         self.is_initialized = true;
         self.last_measured_skew_ms = time_skew_ms;
+        self.last_time_message = Some(received);
+    }
+
+    /// Whether the system clock must be set from the NMEA time now: only when enabled, with a valid
+    /// NMEA time, a skew beyond the threshold and at most once per `SYSTEM_TIME_SET_INTERVAL`
+    fn should_set_system_time(&self, date_time: &N2kDateTime, skew_ms: i64, now: Instant) -> bool {
+        self.set_system_time_enabled
+            && is_valid_nmea_time(date_time)
+            && skew_ms.abs() > self.time_skew_threshold_ms
+            && self.last_system_time_set.is_none_or(|last| now.saturating_duration_since(last) >= SYSTEM_TIME_SET_INTERVAL)
     }
 
-    /// Check if time is synchronized (no skew above threshold)
-    /// Returns true if it's safe to write to database
-    pub fn is_time_synchronized(&self) -> bool {
-        !self.has_time_skew
+    fn take_action(&mut self, action: SkewAction, nmea_time: &NMEASystemTime, skew_ms: i64, now: Instant) {
+        self.last_action = Some(action);
+        match action {
+            SkewAction::Warn => {} // Warning already printed
+            SkewAction::NtpResync => self.request_ntp_resync(),
+            SkewAction::SetFromNmea => {
+                if !self.set_system_time_enabled {
+                    tracing::warn!("Skipping system time update from NMEA: set_system_time is disabled");
+                } else if self.should_set_system_time(&nmea_time.date_time, skew_ms, now) {
+                    self.last_system_time_set = Some(now);
+                    self.set_system_time(nmea_time);
+                } else {
+                    tracing::debug!("Skipping system time update from NMEA: invalid NMEA time or set less than a minute ago");
+                }
+            }
+        }
+    }
+
+    /// Run the NTP resync command in the background, logging its outcome
+    /// The command is split on whitespace into the program and its arguments, no shell is involved
+    fn request_ntp_resync(&self) {
+        let mut args = self.ntp_resync_command.split_whitespace().map(str::to_string);
+        let Some(program) = args.next() else {
+            tracing::warn!("NTP resync requested but no command is configured");
+            return;
+        };
+        tracing::warn!("Sustained time skew, running NTP resync: {}", self.ntp_resync_command);
+        let args: Vec<String> = args.collect();
+        std::thread::spawn(move || {
+            match std::process::Command::new(&program).args(&args).output() {
+                Ok(output) if output.status.success() => tracing::info!("NTP resync command completed"),
+                Ok(output) => tracing::error!(
+                    "NTP resync command failed ({}): {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+                Err(e) => tracing::error!("Failed to run NTP resync command: {}", e),
+            }
+        });
     }
 
     fn set_system_time(&self, nmea_time: &NMEASystemTime) {
         let unix_timestamp = nmea_time.date_time.to_unix_timestamp();
-        let millis = nmea_time.date_time.milliseconds() as i64;
-        
-        // TimeSpec expects different types on 32-bit vs 64-bit systems
-        // On 64-bit: (i64, i64), on 32-bit: (i32, i32)
-        #[cfg(target_pointer_width = "64")]
-        let timespec = TimeSpec::new(unix_timestamp, millis * 1_000_000);
-        
-        #[cfg(target_pointer_width = "32")]
-        let timespec = TimeSpec::new(unix_timestamp as i32, (millis * 1_000_000) as i32);
-        
-        match clock_settime(ClockId::CLOCK_REALTIME, timespec) {
+        match apply_system_time(nmea_time.date_time.to_system_time()) {
             Ok(_) => {
                 tracing::info!(
                     "System time successfully set to NMEA time: {} (Unix timestamp)",
@@ -169,10 +363,7 @@ impl TimeMonitor {
                 println!("╚════════════════════════════════════════════════════════════╝\n");
             }
             Err(err) => {
-                tracing::error!(
-                    "Failed to set system time: {}. This typically requires root/sudo privileges.",
-                    err
-                );
+                tracing::error!("Failed to set system time: {}", err);
                 println!("\n╔════════════════════════════════════════════════════════════╗");
                 println!("║  FAILED TO SET SYSTEM TIME                                 ║");
                 println!("╠════════════════════════════════════════════════════════════╣");
@@ -214,20 +405,15 @@ impl TimeMonitor {
 
 impl Default for TimeMonitor {
     fn default() -> Self {
-        use crate::config::Config;
-        let config = Config::default();
-        let app_state = Arc::new(Mutex::new(ApplicationState::new(config)));
+        let app_state = Arc::new(Mutex::new(ApplicationState::new()));
         Self::new(app_state, 500, false)
     }
 }
 
 impl nmea2k::MessageHandler for TimeMonitor {
-    fn handle_message(&mut self, frame: &nmea2k::N2kFrame, _timestamp: std::time::Instant) {
-        match &frame.message {
-            nmea2k::pgns::N2kMessage::NMEASystemTime(sys_time) => {
-                self.process_system_time(sys_time);
-            }
-            _ => {} // Ignore messages we're not interested in
+    fn handle_message(&mut self, frame: &nmea2k::N2kFrame, timestamp: std::time::Instant) {
+        if let nmea2k::pgns::N2kMessage::NMEASystemTime(sys_time) = &frame.message {
+            self.process_system_time(sys_time, timestamp);
         }
     }
 }
@@ -253,15 +439,14 @@ mod tests {
     #[test]
     fn test_is_time_synchronized_initially() {
         let monitor = TimeMonitor::default();
-        assert!(monitor.is_time_synchronized());
+        assert!(!monitor.has_time_skew);
+        assert_eq!(monitor.time_sync_status(Instant::now()).status, TimeSyncStatus::NotInitialized);
     }
 
     #[test]
     fn test_time_skew_detection_within_threshold() {
         // Use a larger threshold to account for processing delays in tests
-        use crate::config::Config;
-        let config = Config::default();
-        let app_state = Arc::new(Mutex::new(ApplicationState::new(config)));
+        let app_state = Arc::new(Mutex::new(ApplicationState::new()));
         let mut monitor = TimeMonitor::new(app_state, 2000, false);
         
         // Create a system time close to current time (within threshold)
@@ -283,10 +468,10 @@ mod tests {
             },
         };
         
-        monitor.process_system_time(&nmea_time);
+        monitor.process_system_time(&nmea_time, Instant::now());
         
         // Time should be synchronized (skew within threshold)
-        assert!(monitor.is_time_synchronized());
+        assert_eq!(monitor.time_sync_status(Instant::now()).status, TimeSyncStatus::Synchronized);
     }
 
     #[test]
@@ -305,10 +490,10 @@ mod tests {
             },
         };
         
-        monitor.process_system_time(&nmea_time);
+        monitor.process_system_time(&nmea_time, Instant::now());
         
         // Time should NOT be synchronized (large skew)
-        assert!(!monitor.is_time_synchronized());
+        assert_ne!(monitor.time_sync_status(Instant::now()).status, TimeSyncStatus::Synchronized);
     }
 
     #[test]
@@ -347,4 +532,181 @@ mod tests {
         // 12345 * 0.0001 * 1000 = 1234.5 -> 1234 ms (integer part)
         assert_eq!(ms, 234); // 234 ms within the current second
     }
+
+    fn escalation() -> SkewEscalation {
+        SkewEscalation::new(
+            vec![
+                (SkewAction::SetFromNmea, Duration::from_secs(300)),
+                (SkewAction::Warn, Duration::ZERO),
+                (SkewAction::NtpResync, Duration::from_secs(60)),
+            ],
+            Duration::from_secs(120),
+        )
+    }
+
+    #[test]
+    fn test_escalation_order() {
+        let mut escalation = escalation();
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+
+        assert_eq!(escalation.decide(true, at(0)), Some(SkewAction::Warn));
+        assert_eq!(escalation.decide(true, at(30)), None); // Warn cooling down
+        assert_eq!(escalation.decide(true, at(60)), Some(SkewAction::NtpResync));
+        assert_eq!(escalation.decide(true, at(120)), None);
+        assert_eq!(escalation.decide(true, at(180)), Some(SkewAction::NtpResync)); // Cooldown elapsed
+        assert_eq!(escalation.decide(true, at(300)), Some(SkewAction::SetFromNmea));
+        assert_eq!(escalation.decide(true, at(400)), None);
+        assert_eq!(escalation.decide(true, at(420)), Some(SkewAction::SetFromNmea));
+    }
+
+    #[test]
+    fn test_escalation_restarts_after_sync() {
+        let mut escalation = escalation();
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+
+        assert_eq!(escalation.decide(true, at(0)), Some(SkewAction::Warn));
+        assert_eq!(escalation.decide(true, at(90)), Some(SkewAction::NtpResync));
+        assert_eq!(escalation.decide(false, at(100)), None);
+
+        // A new skew starts again from the first step, without cooldown from the previous one
+        assert_eq!(escalation.decide(true, at(110)), Some(SkewAction::Warn));
+        assert_eq!(escalation.decide(true, at(150)), None);
+        assert_eq!(escalation.decide(true, at(170)), Some(SkewAction::NtpResync));
+    }
+
+    #[test]
+    fn test_escalated_actions_use_message_time() {
+        let mut monitor = monitor();
+        monitor.set_escalation(escalation(), String::new());
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+
+        monitor.process_system_time(&nmea_time(-3600), at(0));
+        assert_eq!(monitor.take_last_action(), Some(SkewAction::Warn));
+        monitor.process_system_time(&nmea_time(-3600), at(30));
+        assert_eq!(monitor.take_last_action(), None);
+        monitor.process_system_time(&nmea_time(-3600), at(60));
+        assert_eq!(monitor.take_last_action(), Some(SkewAction::NtpResync));
+        assert_eq!(monitor.last_time_message, Some(at(60)));
+    }
+
+    fn date_time(date: u16, time: f64) -> N2kDateTime {
+        N2kDateTime { date, time }
+    }
+
+    fn nmea_time(offset_secs: i64) -> NMEASystemTime {
+        let time = StdSystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64 + offset_secs;
+        NMEASystemTime {
+            pgn: 126992,
+            sid: 0,
+            source: 0,
+            date_time: date_time((time / 86400) as u16, (time % 86400) as f64 * 10000.0),
+        }
+    }
+
+    /// Monitor with a threshold above the sub-second truncation of `nmea_time`
+    fn monitor() -> TimeMonitor {
+        let app_state = Arc::new(Mutex::new(ApplicationState::new()));
+        let mut monitor = TimeMonitor::new(app_state, 2000, false);
+        monitor.set_source_timeout(Duration::from_secs(30));
+        monitor
+    }
+
+    #[test]
+    fn test_time_sync_status_transitions() {
+        let mut monitor = monitor();
+        assert_eq!(monitor.time_sync_status(Instant::now()).status, TimeSyncStatus::NotInitialized);
+
+        // NotInitialized -> Synchronized
+        monitor.process_system_time(&nmea_time(0), Instant::now());
+        assert_eq!(monitor.time_sync_status(Instant::now()).status, TimeSyncStatus::Synchronized);
+
+        // Synchronized -> TimeSkewDetected
+        monitor.process_system_time(&nmea_time(-3600), Instant::now());
+        let status = monitor.time_sync_status(Instant::now());
+        assert_eq!(status.status, TimeSyncStatus::TimeSkewDetected);
+        assert!(status.skew >= 3_599_000);
+        assert_ne!(monitor.time_sync_status(Instant::now()).status, TimeSyncStatus::Synchronized);
+
+        // TimeSkewDetected -> Synchronized
+        monitor.process_system_time(&nmea_time(0), Instant::now());
+        assert_eq!(monitor.time_sync_status(Instant::now()).status, TimeSyncStatus::Synchronized);
+        assert_eq!(monitor.time_sync_status(Instant::now()).status, TimeSyncStatus::Synchronized);
+    }
+
+    #[test]
+    fn test_time_source_lost() {
+        let mut monitor = monitor();
+        monitor.process_system_time(&nmea_time(0), Instant::now());
+        let last = monitor.last_time_message.unwrap();
+
+        assert_eq!(monitor.time_sync_status(last + Duration::from_secs(29)).status, TimeSyncStatus::Synchronized);
+        // Synchronized -> NoSource
+        assert_eq!(monitor.time_sync_status(last + Duration::from_secs(30)).status, TimeSyncStatus::NoSource);
+
+        // No database writes without a time source
+        monitor.set_source_timeout(Duration::ZERO);
+        assert_eq!(monitor.time_sync_status(Instant::now()).status, TimeSyncStatus::NoSource);
+        assert_ne!(monitor.time_sync_status(Instant::now()).status, TimeSyncStatus::Synchronized);
+
+        // NoSource -> Synchronized once the time is received again
+        monitor.set_source_timeout(Duration::from_secs(30));
+        monitor.process_system_time(&nmea_time(0), Instant::now());
+        assert_eq!(monitor.time_sync_status(Instant::now()).status, TimeSyncStatus::Synchronized);
+        assert_eq!(monitor.time_sync_status(Instant::now()).status, TimeSyncStatus::Synchronized);
+
+        // A skewed source is lost as well
+        monitor.process_system_time(&nmea_time(-3600), Instant::now());
+        let last = monitor.last_time_message.unwrap();
+        assert_eq!(monitor.time_sync_status(last + Duration::from_secs(30)).status, TimeSyncStatus::NoSource);
+    }
+
+    #[test]
+    fn test_should_set_system_time() {
+        let app_state = Arc::new(Mutex::new(ApplicationState::new()));
+        let mut monitor = TimeMonitor::new(app_state, 500, true);
+        let t0 = Instant::now();
+        let valid = date_time(19875, 36_000_000.0);
+
+        assert!(monitor.should_set_system_time(&valid, 1500, t0));
+        assert!(monitor.should_set_system_time(&valid, -1500, t0)); // NMEA ahead
+        // Skew within the threshold
+        assert!(!monitor.should_set_system_time(&valid, 400, t0));
+        // Not available date or time
+        assert!(!monitor.should_set_system_time(&date_time(0xFFFF, 36_000_000.0), 1500, t0));
+        assert!(!monitor.should_set_system_time(&date_time(19875, 4_294_967_295.0), 1500, t0));
+
+        // At most once per minute
+        monitor.last_system_time_set = Some(t0);
+        assert!(!monitor.should_set_system_time(&valid, 1500, t0 + Duration::from_secs(30)));
+        assert!(monitor.should_set_system_time(&valid, 1500, t0 + SYSTEM_TIME_SET_INTERVAL));
+
+        // Never when disabled
+        let monitor = TimeMonitor::default();
+        assert!(!monitor.should_set_system_time(&valid, 1500, t0));
+    }
+
+    #[test]
+    fn test_skew_action_recorded() {
+        let mut monitor = TimeMonitor::default();
+        let nmea_time = NMEASystemTime {
+            pgn: 126992,
+            sid: 0,
+            source: 0,
+            date_time: nmea2k::pgns::nmea2000_date_time::N2kDateTime {
+                date: 10000,
+                time: 0.0,
+            },
+        };
+
+        monitor.process_system_time(&nmea_time, Instant::now());
+        assert_eq!(monitor.take_last_action(), Some(SkewAction::Warn));
+        assert_eq!(monitor.take_last_action(), None);
+
+        // Without escalation the actions follow the cadence of the skew warnings
+        monitor.process_system_time(&nmea_time, Instant::now());
+        assert_eq!(monitor.take_last_action(), None);
+    }
 }
@@ -1,4 +1,4 @@
-use std::{time::{SystemTime}};
+use std::time::{Duration, SystemTime};
 
 #[derive(Debug, Clone)]
 pub struct Trip {
@@ -49,8 +49,8 @@ impl Trip {
         }
     }
     
-    /// Check if the trip is still active (end timestamp is within 24 hours of the given time)
-    pub fn is_active(&self, current_time: SystemTime) -> bool {
+    /// Check if the trip is still active (end timestamp is within the inactivity timeout of the given time)
+    pub fn is_active(&self, current_time: SystemTime, timeout: Duration) -> bool {
         let duration = if current_time > self.end_timestamp {
             current_time.duration_since(self.end_timestamp)
         } else {
@@ -58,11 +58,34 @@ impl Trip {
         };
         
         match duration {
-            Err(_) => return false, // SystemTime error
-            Ok(d) => return d.as_secs() <= 24 * 60 * 60 // 24 hours
-        };
+            Err(_) => false, // SystemTime error
+            Ok(d) => d <= timeout,
+        }
     }
     
+    /// Merge another trip into this one, e.g. a passage split by an overnight stop
+    /// The distances and times are summed, the time range covers both trips
+    pub fn merge(&mut self, other: &Trip) {
+        self.start_timestamp = self.start_timestamp.min(other.start_timestamp);
+        self.end_timestamp = self.end_timestamp.max(other.end_timestamp);
+        self.total_distance_sailed += other.total_distance_sailed;
+        self.total_distance_motoring += other.total_distance_motoring;
+        self.total_time_sailing += other.total_time_sailing;
+        self.total_time_motoring += other.total_time_motoring;
+        self.total_time_moored += other.total_time_moored;
+    }
+
+    /// Remove the tail split off this trip, ending it at the given time
+    /// The distances and times of the tail are subtracted, never going below zero
+    pub fn truncate(&mut self, end_timestamp: SystemTime, tail: &Trip) {
+        self.end_timestamp = end_timestamp;
+        self.total_distance_sailed = (self.total_distance_sailed - tail.total_distance_sailed).max(0.0);
+        self.total_distance_motoring = (self.total_distance_motoring - tail.total_distance_motoring).max(0.0);
+        self.total_time_sailing = self.total_time_sailing.saturating_sub(tail.total_time_sailing);
+        self.total_time_motoring = self.total_time_motoring.saturating_sub(tail.total_time_motoring);
+        self.total_time_moored = self.total_time_moored.saturating_sub(tail.total_time_moored);
+    }
+
     /// Get total distance (sailing + motoring)
     pub fn total_distance(&self) -> f64 {
         self.total_distance_sailed + self.total_distance_motoring
@@ -74,10 +97,41 @@ impl Trip {
     }
 }
 
+/// Build the base trip description from a template
+/// `{date}` is replaced with the trip start date (YYYY-MM-DD, UTC)
+pub fn trip_base_description(template: &str, start_timestamp: SystemTime) -> String {
+    let datetime = chrono::DateTime::<chrono::Utc>::from(start_timestamp);
+    template.replace("{date}", &datetime.format("%Y-%m-%d").to_string())
+}
+
+/// Pick a description that does not collide with the existing ones
+/// The base description is used as-is if free, otherwise " #2", " #3", ... is appended
+pub fn unique_trip_description(base: &str, existing: &[String]) -> String {
+    if !existing.iter().any(|d| d.as_str() == base) {
+        return base.to_string();
+    }
+    let mut sequence = 2;
+    loop {
+        let candidate = format!("{} #{}", base, sequence);
+        if !existing.contains(&candidate) {
+            return candidate;
+        }
+        sequence += 1;
+    }
+}
+
+/// LIKE pattern of the numbered variants of a description ("<base> #N")
+/// The wildcards of the base description are escaped with `\`, the query needs `ESCAPE '\'`
+pub fn numbered_description_pattern(base: &str) -> String {
+    let escaped = base.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("{} #%", escaped)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::Duration;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
 
     #[test]
     fn test_new_trip() {
@@ -143,7 +197,7 @@ mod tests {
         let trip = Trip::new(now, "Test Trip".to_string());
         
         let later = now + Duration::from_secs(23 * 60 * 60); // 23 hours later
-        assert!(trip.is_active(later));
+        assert!(trip.is_active(later, DAY));
     }
 
     #[test]
@@ -152,7 +206,21 @@ mod tests {
         let trip = Trip::new(now, "Test Trip".to_string());
         
         let later = now + Duration::from_secs(25 * 60 * 60); // 25 hours later
-        assert!(!trip.is_active(later));
+        assert!(!trip.is_active(later, DAY));
+    }
+
+    #[test]
+    fn test_is_active_custom_timeout() {
+        let now = SystemTime::now();
+        let mut trip = Trip::new(now, "Test Trip".to_string());
+        trip.update(now + Duration::from_secs(600), 1.0, 600000, false, false);
+        let timeout = Duration::from_secs(3 * 60 * 60);
+
+        // The gap is measured from the end of the trip
+        let end = trip.end_timestamp;
+        assert!(trip.is_active(end + timeout - Duration::from_secs(1), timeout));
+        assert!(trip.is_active(end + timeout, timeout));
+        assert!(!trip.is_active(end + timeout + Duration::from_secs(1), timeout));
     }
 
     #[test]
@@ -179,4 +247,86 @@ mod tests {
         
         assert_eq!(trip.total_time(), 120000);
     }
+
+    #[test]
+    fn test_merge_sums_fields() {
+        let now = SystemTime::now();
+        let mut first = Trip::new(now, "Day 1".to_string());
+        first.update(now + Duration::from_secs(3600), 5.0, 3_000_000, false, false);
+        first.update(now + Duration::from_secs(7200), 2.0, 600_000, true, false);
+        let second_start = now + Duration::from_secs(30 * 3600);
+        let mut second = Trip::new(second_start, "Day 2".to_string());
+        second.update(second_start + Duration::from_secs(3600), 4.0, 2_000_000, false, false);
+        second.update(second_start + Duration::from_secs(5400), 0.0, 900_000, false, true);
+
+        // Merging in either order gives the same totals and range
+        let mut merged = second.clone();
+        merged.merge(&first);
+        first.merge(&second);
+        for trip in [&first, &merged] {
+            assert_eq!(trip.start_timestamp, now);
+            assert_eq!(trip.end_timestamp, second_start + Duration::from_secs(5400));
+            assert_eq!(trip.total_distance_sailed, 9.0);
+            assert_eq!(trip.total_distance_motoring, 2.0);
+            assert_eq!(trip.total_time_sailing, 5_000_000);
+            assert_eq!(trip.total_time_motoring, 600_000);
+            assert_eq!(trip.total_time_moored, 900_000);
+        }
+        assert_eq!(first.description, "Day 1");
+    }
+
+    #[test]
+    fn test_truncate_subtracts_tail() {
+        let now = SystemTime::now();
+        let mut trip = Trip::new(now, "Test Trip".to_string());
+        trip.update(now + Duration::from_secs(100), 3.0, 100_000, false, false);
+        trip.update(now + Duration::from_secs(200), 1.0, 100_000, true, false);
+
+        let split_at = now + Duration::from_secs(150);
+        let mut tail = Trip::new(split_at, "Tail".to_string());
+        tail.update(now + Duration::from_secs(200), 1.5, 100_000, true, false);
+
+        trip.truncate(now + Duration::from_secs(100), &tail);
+        assert_eq!(trip.end_timestamp, now + Duration::from_secs(100));
+        assert_eq!(trip.total_distance_sailed, 3.0);
+        assert_eq!(trip.total_distance_motoring, 0.0); // Clamped
+        assert_eq!(trip.total_time_sailing, 100_000);
+        assert_eq!(trip.total_time_motoring, 0);
+    }
+
+    #[test]
+    fn test_trip_base_description() {
+        // 2024-05-01 10:00:00 UTC
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1714557600);
+        assert_eq!(trip_base_description("Trip {date}", start), "Trip 2024-05-01");
+        assert_eq!(trip_base_description("{date} sail", start), "2024-05-01 sail");
+        assert_eq!(trip_base_description("Cruise", start), "Cruise");
+    }
+
+    #[test]
+    fn test_unique_description_first_trip_of_day() {
+        let existing = vec!["Trip 2024-04-30".to_string()];
+        assert_eq!(unique_trip_description("Trip 2024-05-01", &existing), "Trip 2024-05-01");
+    }
+
+    #[test]
+    fn test_unique_description_second_trip_same_day() {
+        let existing = vec!["Trip 2024-05-01".to_string()];
+        assert_eq!(unique_trip_description("Trip 2024-05-01", &existing), "Trip 2024-05-01 #2");
+    }
+
+    #[test]
+    fn test_unique_description_fills_next_sequence() {
+        let existing = vec![
+            "Trip 2024-05-01".to_string(),
+            "Trip 2024-05-01 #2".to_string(),
+            "Trip 2024-05-01 #3".to_string(),
+        ];
+        assert_eq!(unique_trip_description("Trip 2024-05-01", &existing), "Trip 2024-05-01 #4");
+    }
+    #[test]
+    fn test_numbered_description_pattern_escapes_wildcards() {
+        assert_eq!(numbered_description_pattern("Trip 2024-05-01"), "Trip 2024-05-01 #%");
+        assert_eq!(numbered_description_pattern(r"Run_1 50% C:\logs"), r"Run\_1 50\% C:\\logs #%");
+    }
 }
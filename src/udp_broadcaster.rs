@@ -1,13 +1,15 @@
+use std::collections::HashMap;
 use std::net::UdpSocket;
-use std::sync::{Arc, Mutex};
 use tracing::{debug, warn, error};
 use nmea2k::pgns::N2kMessage;
 use nmea2k::{MessageHandler, N2kFrame};
 use serde::Serialize;
 
+use crate::config::JsonStyle;
+
 /// Wrapper struct for serializing NMEA2000 messages to JSON
 #[derive(Debug, Serialize)]
-struct N2kMessageWrapper {
+pub(crate) struct N2kMessageWrapper {
     /// Message type identifier
     message_type: String,
     /// PGN (Parameter Group Number)
@@ -20,56 +22,104 @@ struct N2kMessageWrapper {
     data: serde_json::Value,
 }
 
+/// Per-destination delivery counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DestinationStats {
+    pub message_count: u64,
+    pub error_count: u64,
+}
+
+/// A UDP target with its own socket and counters
+struct UdpDestination {
+    address: String,
+    socket: Option<UdpSocket>,
+    stats: DestinationStats,
+}
+
 /// UDP broadcaster for NMEA2000 messages
 /// 
 /// Serializes incoming NMEA2000 messages to JSON and broadcasts them
-/// over UDP to a configured destination address.
+/// over UDP to all the configured destination addresses.
 pub struct UdpBroadcaster {
-    socket: Arc<Mutex<Option<UdpSocket>>>,
-    destination: String,
+    destinations: Vec<UdpDestination>,
     enabled: bool,
+    json_style: JsonStyle,
     error_count: u64,
-    message_count: u64,
 }
 
 impl UdpBroadcaster {
     /// Create a new UDP broadcaster
     /// 
     /// # Arguments
-    /// * `destination` - UDP destination address (e.g., "192.168.1.255:10110")
+    /// * `destinations` - UDP destination addresses (e.g., "192.168.1.255:10110")
     /// * `enabled` - Whether UDP broadcasting is enabled
-    pub fn new(destination: String, enabled: bool) -> Self {
-        let socket = if enabled {
-            match Self::create_socket(&destination) {
+    /// * `json_style` - Format of the JSON messages
+    pub fn new(destinations: Vec<String>, enabled: bool, json_style: JsonStyle) -> Self {
+        if !enabled {
+            debug!("UDP broadcaster disabled in configuration");
+        }
+        let mut broadcaster = Self {
+            destinations: Vec::new(),
+            enabled,
+            json_style,
+            error_count: 0,
+        };
+        for destination in destinations {
+            broadcaster.add_destination(destination);
+        }
+        broadcaster
+    }
+
+    /// Add a destination address, messages are sent to every destination
+    pub fn add_destination(&mut self, address: String) {
+        if self.destinations.iter().any(|d| d.address == address) {
+            return;
+        }
+        let socket = if self.enabled {
+            match Self::create_socket(&address) {
                 Ok(sock) => {
-                    debug!("UDP broadcaster initialized: {}", destination);
+                    debug!("UDP broadcaster initialized: {}", address);
                     Some(sock)
                 }
                 Err(e) => {
-                    error!("Failed to create UDP socket: {}. Broadcasting disabled.", e);
+                    error!("Failed to create UDP socket for {}: {}. Broadcasting to it disabled.", address, e);
                     None
                 }
             }
         } else {
-            debug!("UDP broadcaster disabled in configuration");
             None
         };
+        self.destinations.push(UdpDestination {
+            address,
+            socket,
+            stats: DestinationStats::default(),
+        });
+    }
 
-        Self {
-            socket: Arc::new(Mutex::new(socket)),
-            destination,
-            enabled,
-            error_count: 0,
-            message_count: 0,
+    /// Replace the destination addresses, keeping the socket and counters of the ones still listed
+    pub fn set_destinations(&mut self, destinations: Vec<String>) {
+        self.destinations.retain(|d| destinations.contains(&d.address));
+        for destination in destinations {
+            self.add_destination(destination);
         }
     }
 
+    pub fn set_json_style(&mut self, json_style: JsonStyle) {
+        self.json_style = json_style;
+    }
+
+    /// Check whether the destination host is a broadcast address (x.x.x.255)
+    fn is_broadcast_address(destination: &str) -> bool {
+        let host = destination.rsplit_once(':').map(|(host, _)| host).unwrap_or(destination);
+        host.ends_with(".255")
+    }
+
     /// Create and configure a UDP socket
     fn create_socket(destination: &str) -> Result<UdpSocket, std::io::Error> {
         let socket = UdpSocket::bind("0.0.0.0:0")?;
         
         // Enable broadcast if destination is a broadcast address
-        if destination.contains(".255") {
+        if Self::is_broadcast_address(destination) {
             socket.set_broadcast(true)?;
         }
         
@@ -81,215 +131,98 @@ impl UdpBroadcaster {
 
     /// Serialize and broadcast an NMEA2000 message
     fn broadcast_message(&mut self, message: &N2kMessage, source: u8, priority: u8) {
-        if !self.enabled {
+        if !self.enabled || self.destinations.iter().all(|d| d.socket.is_none()) {
             return;
         }
 
-        let socket_guard = match self.socket.lock() {
-            Ok(guard) => {
-                if guard.is_none() {
-                    return;
-                }
-                guard
-            }
-            Err(e) => {
-                if self.error_count < 10 {
-                    warn!("Failed to acquire UDP socket lock: {}", e);
-                }
-                self.error_count += 1;
-                return;
-            }
-        };
-
         // Serialize message to JSON
-        let wrapper = match self.serialize_message(message, source, priority) {
-            Ok(w) => w,
-            Err(e) => {
-                if self.error_count < 10 {
-                    warn!("Failed to serialize message: {}", e);
-                }
-                self.error_count += 1;
-                return;
-            }
-        };
-
-        let json = match serde_json::to_string(&wrapper) {
+        let json = match format_message(message, source, priority, self.json_style) {
             Ok(j) => j,
             Err(e) => {
                 if self.error_count < 10 {
-                    warn!("Failed to convert message to JSON: {}", e);
+                    warn!("Failed to serialize message: {}", e);
                 }
                 self.error_count += 1;
                 return;
             }
         };
 
-        // Send UDP packet
-        if let Some(ref socket) = *socket_guard {
-            match socket.send_to(json.as_bytes(), &self.destination) {
-                Ok(_) => {
-                    self.message_count += 1;
-                    if self.message_count % 1000 == 0 {
-                        debug!("Broadcasted {} messages via UDP", self.message_count);
+        // Send UDP packet to every destination
+        for destination in self.destinations.iter_mut() {
+            if let Some(ref socket) = destination.socket {
+                match socket.send_to(json.as_bytes(), &destination.address) {
+                    Ok(_) => {
+                        destination.stats.message_count += 1;
+                        if destination.stats.message_count % 1000 == 0 {
+                            debug!("Broadcasted {} messages via UDP to {}", destination.stats.message_count, destination.address);
+                        }
                     }
-                }
-                Err(e) => {
-                    if self.error_count < 10 {
-                        warn!("Failed to send UDP packet: {}", e);
+                    Err(e) => {
+                        if destination.stats.error_count < 10 {
+                            warn!("Failed to send UDP packet to {}: {}", destination.address, e);
+                        }
+                        destination.stats.error_count += 1;
                     }
-                    self.error_count += 1;
                 }
             }
         }
     }
 
-    /// Serialize an NMEA2000 message to the wrapper format
-    fn serialize_message(
-        &self,
-        message: &N2kMessage,
-        source: u8,
-        priority: u8,
-    ) -> Result<N2kMessageWrapper, serde_json::Error> {
-        let (message_type, pgn, data) = match message {
-            N2kMessage::NMEASystemTime(msg) => {
-                let data = serde_json::json!({
-                    "date": format!("{:?}", msg.date_time.date),
-                    "time": format!("{:?}", msg.date_time.time)
-                });
-                ("NMEASystemTime", 126992, data)
-            }
-            N2kMessage::PositionRapidUpdate(msg) => {
-                let data = serde_json::json!({
-                    "latitude": msg.latitude,
-                    "longitude": msg.longitude,
-                });
-                ("PositionRapidUpdate", 129025, data)
-            }
-            N2kMessage::CogSogRapidUpdate(msg) => {
-                let data = serde_json::json!({
-                    "sog": msg.sog,
-                    "cog": msg.cog,
-                    "cog_reference": msg.cog_reference
-                });
-                ("CogSogRapidUpdate", 129026, data)
-            }
-            N2kMessage::GnssPositionData(msg) => {
-                let data = serde_json::json!({
-                    "date": format!("{:?}", msg.date_time.date),
-                    "time": format!("{:?}", msg.date_time.time),
-                    "latitude": msg.latitude,
-                    "longitude": msg.longitude,
-                    "altitude": msg.altitude,
-                });
-                ("GnssPositionData", 129029, data)
-            }
-            N2kMessage::WindData(msg) => {
-                let data = serde_json::json!({
-                    "speed": msg.speed,
-                    "angle": msg.angle,
-                    "reference": format!("{:?}", msg.reference)
-                });
-                ("WindData", 130306, data)
-            }
-            N2kMessage::Temperature(msg) => {
-                let data = serde_json::json!({
-                    "instance": msg.instance,
-                    "source": msg.source,
-                    "temperature": msg.temperature,
-                    "set_temperature": msg.set_temperature,
-                });
-                ("Temperature", 130312, data)
-            }
-            N2kMessage::Humidity(msg) => {
-                let data = serde_json::json!({
-                    "instance": msg.instance,
-                    "source": msg.source,
-                    "actual_humidity": msg.actual_humidity,
-                    "set_humidity": msg.set_humidity,
-                });
-                ("Humidity", 130313, data)
-            }
-            N2kMessage::ActualPressure(msg) => {
-                let data = serde_json::json!({
-                    "instance": msg.instance,
-                    "source": msg.source,
-                    "pressure": msg.pressure,
-                });
-                ("ActualPressure", 130314, data)
-            }
-            N2kMessage::EngineRapidUpdate(msg) => {
-                let data = serde_json::json!({
-                    "engine_instance": msg.engine_instance,
-                    "engine_speed": msg.engine_speed,
-                    "engine_boost_pressure": msg.engine_boost_pressure,
-                    "engine_tilt_trim": msg.engine_tilt_trim,
-                });
-                ("EngineRapidUpdate", 127488, data)
-            }
-            N2kMessage::Attitude(msg) => {
-                let data = serde_json::json!({
-                    "yaw": msg.yaw,
-                    "pitch": msg.pitch,
-                    "roll": msg.roll,
-                });
-                ("Attitude", 127257, data)
-            }
-            N2kMessage::VesselHeading(msg) => {
-                let data = serde_json::json!({
-                    "heading": msg.heading,
-                    "reference": format!("{:?}", msg.reference),
-                });
-                ("VesselHeading", 127250, data)
-            }
-            N2kMessage::RateOfTurn(msg) => {
-                let data = serde_json::json!({
-                    "rate": msg.rate,
-                });
-                ("RateOfTurn", 127251, data)
-            }
-            N2kMessage::SpeedWaterReferenced(msg) => {
-                let data = serde_json::json!({
-                    "speed": msg.speed,
-                });
-                ("SpeedWaterReferenced", 128259, data)
-            }
-            N2kMessage::WaterDepth(msg) => {
-                let data = serde_json::json!({
-                    "depth": msg.depth,
-                    "offset": msg.offset,
-                });
-                ("WaterDepth", 128267, data)
-            }
-            N2kMessage::Unknown(pgn, raw_data) => {
-                let data = serde_json::json!({
-                    "raw": raw_data
-                });
-                ("Unknown", *pgn, data)
-            }
-        };
-
-        Ok(N2kMessageWrapper {
-            message_type: message_type.to_string(),
-            pgn,
-            source,
-            priority,
-            data,
-        })
-    }
-
     /// Get statistics - for future uses
-    /// /// Returns (message_count, error_count)
+    /// Returns the message and error counters for each destination address
     #[allow(dead_code)]
-    pub fn stats(&self) -> (u64, u64) {
-        (self.message_count, self.error_count)
+    pub fn stats(&self) -> HashMap<String, DestinationStats> {
+        self.destinations
+            .iter()
+            .map(|d| (d.address.clone(), d.stats))
+            .collect()
+    }
+}
+
+/// Serialize an NMEA2000 message to the wrapper format
+/// Shared by all the network outputs (UDP, TCP)
+pub(crate) fn serialize_message(
+    message: &N2kMessage,
+    source: u8,
+    priority: u8,
+) -> Result<N2kMessageWrapper, serde_json::Error> {
+    Ok(N2kMessageWrapper {
+        message_type: message.message_type().to_string(),
+        pgn: message.pgn(),
+        source,
+        priority,
+        data: message.to_json(),
+    })
+}
+
+/// Serialize an NMEA2000 message to its JSON representation
+pub(crate) fn message_to_json(
+    message: &N2kMessage,
+    source: u8,
+    priority: u8,
+) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&serialize_message(message, source, priority)?)
+}
+
+/// Serialize an NMEA2000 message to JSON in the given style
+/// `Lines` appends a newline, so the messages can be split on newlines when packets coalesce
+pub(crate) fn format_message(
+    message: &N2kMessage,
+    source: u8,
+    priority: u8,
+    style: JsonStyle,
+) -> Result<String, serde_json::Error> {
+    let wrapper = serialize_message(message, source, priority)?;
+    match style {
+        JsonStyle::Compact => serde_json::to_string(&wrapper),
+        JsonStyle::Lines => serde_json::to_string(&wrapper).map(|json| json + "\n"),
+        JsonStyle::Pretty => serde_json::to_string_pretty(&wrapper),
     }
 }
 
 impl MessageHandler for UdpBroadcaster {
     fn handle_message(&mut self, frame: &N2kFrame, _timestamp: std::time::Instant) {
-        // For now, use dummy source and priority values
-        // These will be passed from the actual frame in the main loop
-        self.broadcast_message(&frame.message, frame.identifier.source(), frame.identifier.priority());
+        self.broadcast_message(&frame.message, frame.source(), frame.priority());
     }
 }
 
@@ -300,16 +233,87 @@ mod tests {
 
     #[test]
     fn test_create_disabled_broadcaster() {
-        let broadcaster = UdpBroadcaster::new("127.0.0.1:10110".to_string(), false);
+        let broadcaster = UdpBroadcaster::new(vec!["127.0.0.1:10110".to_string()], false, JsonStyle::Compact);
         assert!(!broadcaster.enabled);
-        assert!(broadcaster.socket.lock().unwrap().is_none());
+        assert!(broadcaster.destinations.iter().all(|d| d.socket.is_none()));
     }
 
     #[test]
-    fn test_serialize_system_time() {
-        let broadcaster = UdpBroadcaster::new("127.0.0.1:10110".to_string(), false);
+    fn test_disabled_broadcaster_tracks_all_destinations() {
+        let mut broadcaster = UdpBroadcaster::new(
+            vec!["192.168.1.255:10110".to_string(), "10.0.0.5:2000".to_string()],
+            false,
+            JsonStyle::Compact,
+        );
+        broadcaster.add_destination("10.0.0.5:2000".to_string()); // Duplicate is ignored
+
+        let stats = broadcaster.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats["192.168.1.255:10110"], DestinationStats::default());
+        assert_eq!(stats["10.0.0.5:2000"], DestinationStats::default());
+    }
 
+    #[test]
+    fn test_set_destinations_keeps_counters() {
+        let mut broadcaster = UdpBroadcaster::new(
+            vec!["192.168.1.255:10110".to_string(), "10.0.0.5:2000".to_string()],
+            false,
+            JsonStyle::Compact,
+        );
+        broadcaster.destinations[1].stats.message_count = 7;
+
+        broadcaster.set_destinations(vec!["10.0.0.5:2000".to_string(), "10.0.0.6:2000".to_string()]);
+        let stats = broadcaster.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats["10.0.0.5:2000"].message_count, 7);
+        assert_eq!(stats["10.0.0.6:2000"], DestinationStats::default());
+    }
 
+    #[test]
+    fn test_broadcast_address_detection() {
+        assert!(UdpBroadcaster::is_broadcast_address("192.168.1.255:10110"));
+        assert!(!UdpBroadcaster::is_broadcast_address("192.168.255.10:10110"));
+        assert!(!UdpBroadcaster::is_broadcast_address("10.0.0.5:2000"));
+    }
+
+    fn system_time() -> N2kMessage {
+        N2kMessage::NMEASystemTime(NMEASystemTime {
+            pgn: 126992,
+            sid: 0,
+            source: 0,
+            date_time: nmea2k::pgns::nmea2000_date_time::N2kDateTime {
+                date: 19000,
+                time: 43200.0,
+            },
+        })
+    }
+
+    #[test]
+    fn test_compact_style() {
+        let json = format_message(&system_time(), 1, 3, JsonStyle::Compact).unwrap();
+        assert!(!json.contains('\n'));
+        assert_eq!(json, message_to_json(&system_time(), 1, 3).unwrap());
+    }
+
+    #[test]
+    fn test_lines_style_is_newline_terminated() {
+        let json = format_message(&system_time(), 1, 3, JsonStyle::Lines).unwrap();
+        assert!(json.ends_with('\n'));
+        assert_eq!(json.matches('\n').count(), 1);
+        let value: serde_json::Value = serde_json::from_str(json.trim_end()).unwrap();
+        assert_eq!(value["pgn"], 126992);
+    }
+
+    #[test]
+    fn test_pretty_style_is_multi_line() {
+        let json = format_message(&system_time(), 1, 3, JsonStyle::Pretty).unwrap();
+        assert!(json.lines().count() > 1);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["source"], 1);
+    }
+
+    #[test]
+    fn test_serialize_system_time() {
         let msg = NMEASystemTime {
             pgn: 126992,
             sid: 0,
@@ -320,7 +324,7 @@ mod tests {
             },
         };
 
-        let wrapper = broadcaster.serialize_message(&N2kMessage::NMEASystemTime(msg), 1, 3).unwrap();
+        let wrapper = serialize_message(&N2kMessage::NMEASystemTime(msg), 1, 3).unwrap();
         assert_eq!(wrapper.message_type, "NMEASystemTime");
         assert_eq!(wrapper.pgn, 126992);
         assert_eq!(wrapper.source, 1);
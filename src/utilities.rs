@@ -1,4 +1,4 @@
-/// Utility functions for NMEA2000 router
+//! Utility functions for NMEA2000 router
 
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
@@ -46,15 +46,109 @@ pub fn calculate_true_wind(
     (tw_speed, tw_angle_deg)
 }
 
-pub fn dirty_instant_to_systemtime(instant: Instant) -> SystemTime {
-    let now_instant = Instant::now();
-    let now_systemtime = SystemTime::now();
-    if instant <= now_instant {
-        let duration_ago = now_instant.duration_since(instant);
-        now_systemtime.checked_sub(duration_ago).unwrap_or(UNIX_EPOCH)
+/// Calculate the true (ground) wind speed and direction from apparent wind, heading and ground track.
+/// 
+/// Unlike `calculate_true_wind`, the boat motion is taken from COG/SOG in the ground frame,
+/// so that leeway and current do not bias the result.
+/// 
+/// # Arguments
+/// * `apparent_wind_speed_kn` - Apparent wind speed in knots
+/// * `apparent_wind_angle_deg` - Apparent wind angle in degrees (relative to bow)
+/// * `heading_deg` - True heading in degrees
+/// * `cog_deg` - True course over ground in degrees
+/// * `sog_kn` - Speed over ground in knots
+/// 
+/// # Returns
+/// Tuple of (true wind speed in knots, direction the true wind comes from in degrees, referenced to true north)
+#[allow(dead_code)]
+pub fn calculate_true_wind_vector(
+    apparent_wind_speed_kn: f64,
+    apparent_wind_angle_deg: f64,
+    heading_deg: f64,
+    cog_deg: f64,
+    sog_kn: f64,
+) -> (f64, f64) {
+    // Apparent wind, from the direction it comes from, in north/east components
+    let aw_dir_rad = (heading_deg + apparent_wind_angle_deg).to_radians();
+    let aw_n = apparent_wind_speed_kn * aw_dir_rad.cos();
+    let aw_e = apparent_wind_speed_kn * aw_dir_rad.sin();
+
+    // The motion over ground induces a wind coming from the COG at SOG
+    let cog_rad = cog_deg.to_radians();
+    let tw_n = aw_n - sog_kn * cog_rad.cos();
+    let tw_e = aw_e - sog_kn * cog_rad.sin();
+
+    let tw_speed = (tw_n.powi(2) + tw_e.powi(2)).sqrt();
+    let tw_direction_deg = normalize0_360(tw_e.atan2(tw_n).to_degrees());
+
+    (tw_speed, tw_direction_deg)
+}
+
+/// Maximum number of clock anchors kept, older instants are converted with the oldest one
+const MAX_CLOCK_ANCHORS: usize = 8;
+
+/// Converts `Instant`s to wall-clock time, anchored on the wall clock of when they were taken
+///
+/// Converting relative to the current wall clock would move the timestamps of the samples
+/// taken before a system clock step (e.g. NTP): instead a new anchor is added when a jump
+/// is detected, and each instant is converted with the last anchor preceding it.
+#[derive(Debug, Clone)]
+pub struct WallClock {
+    /// (instant, wall-clock time) pairs, in instant order
+    anchors: Vec<(Instant, SystemTime)>,
+}
+
+fn shift(time: SystemTime, from: Instant, to: Instant) -> SystemTime {
+    if to >= from {
+        time + to.duration_since(from)
     } else {
-        let duration_ahead = instant.duration_since(now_instant);
-        now_systemtime.checked_add(duration_ahead).unwrap_or(SystemTime::UNIX_EPOCH + Duration::from_secs(u64::MAX))
+        time.checked_sub(from.duration_since(to)).unwrap_or(UNIX_EPOCH)
+    }
+}
+
+impl WallClock {
+    pub fn new() -> Self {
+        Self::anchored_at(Instant::now(), SystemTime::now())
+    }
+
+    pub fn anchored_at(instant: Instant, system_time: SystemTime) -> Self {
+        Self {
+            anchors: vec![(instant, system_time)],
+        }
+    }
+
+    pub fn to_system_time(&self, instant: Instant) -> SystemTime {
+        let (anchor_instant, anchor_time) = self.anchors
+            .iter()
+            .rev()
+            .find(|(anchor, _)| *anchor <= instant)
+            .unwrap_or(&self.anchors[0]);
+        shift(*anchor_time, *anchor_instant, instant)
+    }
+
+    /// Compare the progression of the wall clock with the monotonic clock
+    /// Returns the jump in milliseconds (positive when the wall clock moved forward) if above
+    /// the threshold, in which case the wall clock is re-anchored from `instant` on
+    pub fn check_jump(&mut self, instant: Instant, system_time: SystemTime, threshold: Duration) -> Option<i64> {
+        let expected = self.to_system_time(instant);
+        let jump_ms = match system_time.duration_since(expected) {
+            Ok(duration) => duration.as_millis() as i64,
+            Err(e) => -(e.duration().as_millis() as i64),
+        };
+        if jump_ms.unsigned_abs() as u128 <= threshold.as_millis() {
+            return None;
+        }
+        self.anchors.push((instant, system_time));
+        if self.anchors.len() > MAX_CLOCK_ANCHORS {
+            self.anchors.remove(0);
+        }
+        Some(jump_ms)
+    }
+}
+
+impl Default for WallClock {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -62,9 +156,9 @@ pub fn dirty_instant_to_systemtime(instant: Instant) -> SystemTime {
 pub fn angle_diff(a: f64, b: f64) -> f64 {
     let mut xx = ((a - b) % 360.0 + 360.0) % 360.0;
     if xx > 180.0 {
-        xx = xx - 360.0;
+        xx -= 360.0;
     } else if xx < -180.0 {
-        xx = xx + 360.0;
+        xx += 360.0;
     }
     xx
 }
@@ -111,6 +205,25 @@ pub fn haversine_distance_nm(lat1_deg: f64, lon1_deg: f64, lat2_deg: f64, lon2_d
     radius_earth_nm * c
 }
 
+/// Calculate the position reached from a start position moving along a constant bearing for a distance.
+/// All lat/lon values are in degrees, bearing in degrees (0 = North, 90 = East). Returns (lat, lon) in degrees.
+pub fn destination_point(lat_deg: f64, lon_deg: f64, bearing_deg: f64, distance_nm: f64) -> (f64, f64) {
+    let radius_earth_nm = 3440.065; // Earth's radius in nautical miles
+    let angular_distance = distance_nm / radius_earth_nm;
+    let bearing_rad = bearing_deg.to_radians();
+    let lat1_rad = lat_deg.to_radians();
+    let lon1_rad = lon_deg.to_radians();
+
+    let lat2_rad = (lat1_rad.sin() * angular_distance.cos()
+        + lat1_rad.cos() * angular_distance.sin() * bearing_rad.cos()).asin();
+    let lon2_rad = lon1_rad
+        + (bearing_rad.sin() * angular_distance.sin() * lat1_rad.cos())
+            .atan2(angular_distance.cos() - lat1_rad.sin() * lat2_rad.sin());
+
+    let lon2_deg = (lon2_rad.to_degrees() + 540.0) % 360.0 - 180.0;
+    (lat2_rad.to_degrees(), lon2_deg)
+}
+
 #[derive(Debug)]
 pub enum VariationError {
     InvalidDate,
@@ -142,6 +255,31 @@ mod tests {
     use super::*;
     use approx::assert_abs_diff_eq;
     
+    #[test]
+    fn test_wall_clock_jump() {
+        let start = Instant::now();
+        let start_time = UNIX_EPOCH + Duration::from_secs(1_717_236_000);
+        let mut clock = WallClock::anchored_at(start, start_time);
+        let threshold = Duration::from_secs(1);
+
+        // Wall clock progressing with the monotonic clock
+        assert_eq!(clock.check_jump(start + Duration::from_secs(10), start_time + Duration::from_millis(10_200), threshold), None);
+
+        // NTP steps the clock one hour forward
+        let step_instant = start + Duration::from_secs(20);
+        let jump = clock.check_jump(step_instant, start_time + Duration::from_secs(20 + 3600), threshold);
+        assert_eq!(jump, Some(3_600_000));
+
+        // Instants before the step keep their wall-clock time, later ones follow the new clock
+        assert_eq!(clock.to_system_time(start + Duration::from_secs(5)), start_time + Duration::from_secs(5));
+        assert_eq!(clock.to_system_time(step_instant + Duration::from_secs(5)), start_time + Duration::from_secs(25 + 3600));
+
+        // Stepping back
+        let jump = clock.check_jump(start + Duration::from_secs(30), start_time + Duration::from_secs(30), threshold);
+        assert_eq!(jump, Some(-3_600_000));
+        assert_eq!(clock.to_system_time(start + Duration::from_secs(25)), start_time + Duration::from_secs(25 + 3600));
+    }
+
     #[test]
     fn test_true_wind_zero_boat_speed() {
         // If boat speed is zero, true wind = apparent wind
@@ -186,6 +324,29 @@ mod tests {
         assert!(tw_angle < 0.0);
     }
 
+    #[test]
+    fn test_true_wind_vector_agrees_without_leeway() {
+        // Heading == COG and SOG == boat speed: the ground frame result is the boat frame one rotated by the heading
+        for (aws, awa, heading, speed) in [(12.0, 90.0, 30.0, 6.0), (15.0, -40.0, 300.0, 5.0), (8.0, 170.0, 185.0, 4.0)] {
+            let (tw_speed, tw_angle) = calculate_true_wind(aws, awa, speed);
+            let (tw_speed_vector, tw_direction) = calculate_true_wind_vector(aws, awa, heading, heading, speed);
+            assert_abs_diff_eq!(tw_speed_vector, tw_speed, epsilon = 1e-9);
+            assert_abs_diff_eq!(angle_diff(tw_direction, normalize0_360(heading + tw_angle)), 0.0, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_true_wind_vector_with_current() {
+        // Drifting east at 2 kn with a 2 kn apparent wind from the west: no true wind
+        let (tw_speed, _) = calculate_true_wind_vector(2.0, 90.0, 0.0, 90.0, 2.0);
+        assert_abs_diff_eq!(tw_speed, 0.0, epsilon = 1e-9);
+
+        // Heading north, pushed west by the current: the wind from ahead is partly induced by the drift
+        let (tw_speed, tw_direction) = calculate_true_wind_vector(10.0, 0.0, 0.0, 270.0, 3.0);
+        assert_abs_diff_eq!(tw_speed, (100.0_f64 + 9.0).sqrt(), epsilon = 1e-9);
+        assert!(tw_direction > 0.0 && tw_direction < 90.0);
+    }
+
     #[test]
     fn test_angle_diff() {
         assert_abs_diff_eq!(angle_diff(0.0, 0.0), 0.0);
@@ -218,5 +379,18 @@ mod tests {
         assert!((avg_angle - 0.1).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_destination_point_roundtrip() {
+        let (lat, lon) = destination_point(43.5, 10.2, 45.0, 2.0);
+        assert_abs_diff_eq!(haversine_distance_nm(43.5, 10.2, lat, lon), 2.0, epsilon = 1e-6);
+        assert_abs_diff_eq!(haversine_heading(43.5, 10.2, lat, lon), 45.0, epsilon = 1e-3);
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_destination_point_north() {
+        // 60 nm north is (about) one degree of latitude
+        let (lat, lon) = destination_point(0.0, 0.0, 0.0, 60.0);
+        assert_abs_diff_eq!(lat, 1.0, epsilon = 1e-3);
+        assert_abs_diff_eq!(lon, 0.0, epsilon = 1e-9);
+    }
+}
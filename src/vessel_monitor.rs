@@ -1,32 +1,75 @@
 use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use nmea2k::pgns::{CogSogRapidUpdate, HeadingReference, PositionRapidUpdate};
+use tracing::debug;
+use nmea2k::pgns::{Attitude, CogSogRapidUpdate, Gear, HeadingReference, PositionRapidUpdate, SetDrift, TransmissionParameters};
+use nmea2k::pgns::pgn128259::SpeedWaterReferenced;
+use nmea2k::pgns::pgn129029::{GnssMethod, GnssPositionData};
 use crate::application_state::ApplicationState;
+use crate::config::{QualityScoreConfig, VesselStatusConfig};
+use crate::data_quality::{quality_score, QualitySignals};
+use crate::dead_reckoning::PositionSource;
 use crate::utilities::{angle_diff, average_angle, calculate_true_wind, haversine_distance_nm};
 
-const EVENT_INTERVAL: Duration = Duration::from_secs(10);
-const MOORING_DETECTION_WINDOW: Duration = Duration::from_secs(180); // 3 minutes
-const MOORING_THRESHOLD_METERS: f64 = 30.0; // 30 meters radius
+const EVENT_INTERVAL: Duration = Duration::from_secs(10); // Window of the samples summarized in a status (median, averages)
 const MOORING_ACCURACY: f64 = 0.90; // 90% of positions within threshold
-const MAX_VALID_SOG_KN: f64 = 25.0; // 25 knots (noise filter)
-const MAX_POSITION_DEVIATION_METERS: f64 = 100.0; // Maximum distance from median (noise filter)
-const MIN_SAMPLES_FOR_VALIDATION: usize = 10; // Minimum samples required for validation 
+const HDOP_NOT_AVAILABLE: f64 = 327.67; // 0x7FFF * 0.01
+const HEADING_FALLBACK_TIMEOUT: Duration = Duration::from_secs(5); // Attitude yaw used after this long without PGN 127250
+const GEAR_TIMEOUT: Duration = Duration::from_secs(5); // Gear of PGN 127493 ignored after this long without updates
+const CURRENT_MAX_SAMPLE_AGE: Duration = Duration::from_secs(5); // Heading, water speed and COG/SOG used to estimate the current
+const SET_DRIFT_TIMEOUT: Duration = Duration::from_secs(10); // Current of PGN 129291 ignored after this long without updates
+const WATER_SPEED_MAX_GAP: Duration = Duration::from_secs(10); // Longer gaps between water speed samples are not integrated
 
 #[derive(Debug, Clone)]
 pub struct VesselStatus {
     pub current_position: Position,
     pub median_position: Option<Position>,
+    /// Moving average of the positions, None if the smoothing is disabled
+    pub smoothed_position: Option<Position>,
     pub number_of_samples: usize,
     pub max_speed_kn: f64,       // Knots
     pub is_moored: bool,
     pub engine_on: bool,
+    /// Transmission gear, None if not reported by PGN 127493
+    pub gear: Option<Gear>,
     pub wind_speed_kn: Option<f64>,
     pub wind_speed_variance: Option<f64>,
     pub wind_angle_deg: Option<f64>,
     pub wind_angle_variance: Option<f64>,
     pub timestamp: Instant,
+    /// Average course over ground (true) reported by the GPS in the last event interval
+    pub cog_deg: Option<f64>,
     pub average_heading_deg: Option<f64>,
+    /// Data quality of the status, from 0 (unusable) to 1 (best)
+    pub quality_score: f64,
+    /// Current reported by PGN 129291 or, without it, estimated from the ground and water tracks
+    /// None without recent set & drift nor heading, water speed and COG
+    pub current: Option<Current>,
+    /// Distance through the water since the start (speed through water integrated over time), None without PGN 128259
+    pub water_log_nm: Option<f64>,
+    /// Source of the distance of the segment ending with this status
+    pub distance_source: DistanceSource,
+    /// Source of the current position, estimated by dead reckoning during GPS outages
+    pub position_source: PositionSource,
+}
+
+/// How the distance sailed is measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DistanceSource {
+    /// Between the positions of the GPS
+    #[default]
+    Gps,
+    /// From the speed through water, when no positions are available
+    WaterSpeed,
+}
+
+/// Set and drift of the current
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Current {
+    /// Direction the current flows towards, degrees true
+    pub set_deg: f64,
+    /// Speed of the current in knots
+    pub drift_kn: f64,
 }
 
 pub struct VesselVector {
@@ -51,15 +94,28 @@ impl VesselVector {
 }
 
 impl VesselStatus {
+    /// Engine running and in gear (or the gear is not reported): the distance is counted as motoring
+    pub fn is_motoring(&self) -> bool {
+        self.engine_on && self.gear != Some(Gear::Neutral)
+    }
+
     pub fn is_valid(&self) -> bool {
         self.number_of_samples > 0
+            || self.distance_source == DistanceSource::WaterSpeed
+            || self.position_source == PositionSource::DeadReckoning
     }
 
     pub fn get_effective_position(&self) -> Position {
+        // The median and the smoothed position are made of the positions before the GPS outage
+        if self.position_source == PositionSource::DeadReckoning {
+            return self.current_position;
+        }
         if self.is_moored {
             if let Some(median_pos) = self.median_position {
                 return median_pos;
             }
+        } else if let Some(smoothed_pos) = self.smoothed_position {
+            return smoothed_pos;
         }
         self.current_position
     }
@@ -68,8 +124,15 @@ impl VesselStatus {
         if let Some(previous) = last_status {
             let position_1 = previous.get_effective_position();
             let position_2 = self.get_effective_position();
-            let distance_nm = position_1.distance_to_nm(&position_2);
-            let course_from_deg = position_1.course_from_deg(&position_2);
+            let mut distance_nm = position_1.distance_to_nm(&position_2);
+            let mut course_from_deg = position_1.course_from_deg(&position_2);
+            // A segment starting or ending without positions is measured through the water
+            let without_positions = previous.distance_source == DistanceSource::WaterSpeed
+                || self.distance_source == DistanceSource::WaterSpeed;
+            if without_positions && let (Some(start), Some(end)) = (previous.water_log_nm, self.water_log_nm) {
+                distance_nm = (end - start).max(0.0);
+                course_from_deg = self.average_heading_deg.unwrap_or(course_from_deg);
+            }
             let time_msecs = self.timestamp.duration_since(previous.timestamp).as_millis() as u64;
             Some(VesselVector {
                 position_1,
@@ -92,6 +155,11 @@ pub struct Position {
 }
 
 impl Position {
+    /// Latitude within [-90, 90] and longitude within [-180, 180], NaN is never valid
+    pub fn is_valid(&self) -> bool {
+        (-90.0..=90.0).contains(&self.latitude) && (-180.0..=180.0).contains(&self.longitude)
+    }
+
     /// Returns the distance to another position in nautical miles (using Haversine formula)
     pub fn distance_to_nm(&self, other: &Position) -> f64 {
         haversine_distance_nm(self.latitude, self.longitude, other.latitude, other.longitude)
@@ -102,6 +170,21 @@ impl Position {
     }
 }
 
+/// Median of the values, in linear time (the values are reordered)
+fn median(values: &mut [f64]) -> f64 {
+    let len = values.len();
+    let mid = len / 2;
+    let (lower, upper, _) = values.select_nth_unstable_by(mid, |a, b| a.total_cmp(b));
+    let upper = *upper;
+    if len.is_multiple_of(2) {
+        // With an even count the other middle value is the largest of the lower half
+        let lower = lower.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        (lower + upper) / 2.0
+    } else {
+        upper
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct PositionSample {
     pub position: Position,
@@ -117,6 +200,14 @@ pub struct WindSample {
 
 #[derive(Debug, Clone, Copy)]
 struct SpeedSample {
+    speed_kn: f64,
+    /// Course over ground, only if referenced to true north
+    cog_deg: Option<f64>,
+    timestamp: Instant,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct WaterSpeedSample {
     speed_kn: f64,
     timestamp: Instant,
 }
@@ -127,6 +218,25 @@ struct HeadingSample {
     timestamp: Instant,
 }
 
+/// RPM thresholds and hold time switching the engine state, so idle jitter and cranking do not flap it
+/// The default (no thresholds, no hold) considers the engine running for any RPM above 0
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EngineHysteresis {
+    /// RPM to exceed for the engine to start
+    pub start_rpm: f64,
+    /// RPM to stay at or below for the engine to stop
+    pub stop_rpm: f64,
+    /// Time the RPM must stay past the threshold before the state changes
+    pub hold: Duration,
+}
+
+/// Anchor point and the radius the vessel is allowed to swing in
+#[derive(Debug, Clone, Copy)]
+pub struct AnchorWatch {
+    pub anchor: Position,
+    pub radius_m: f64,
+}
+
 #[derive(Debug)]
 
 pub struct VesselMonitor {
@@ -134,25 +244,135 @@ pub struct VesselMonitor {
     speeds: VecDeque<SpeedSample>,
     winds: VecDeque<WindSample>,
     headings: VecDeque<HeadingSample>,
-    last_event_time: Instant,
+    water_speeds: VecDeque<WaterSpeedSample>,
+    /// Distance through the water integrated from the water speed samples
+    water_log_nm: Option<f64>,
+    /// Report the distance through the water when no positions are available
+    water_distance_fallback: bool,
+    /// Time of the last PGN 127250 heading, the attitude yaw is ignored while it is recent
+    last_heading_message: Option<Instant>,
+    /// Last current reported by PGN 129291 (set true) and when it was received
+    measured_current: Option<(Current, Instant)>,
+    /// Time of the last status generated, None until the first one
+    last_event_time: Option<Instant>,
+    /// Time between two statuses while moored and underway
+    interval_moored: Duration,
+    interval_underway: Duration,
     engine_on: bool,
+    engine_hysteresis: EngineHysteresis,
+    /// Since when the RPM is past the threshold that would toggle `engine_on`
+    engine_toggle_since: Option<Instant>,
+    /// Last transmission gear and when it was received
+    gear: Option<(Gear, Instant)>,
     application_state: Arc<Mutex<ApplicationState>>,
+    /// Fix method and HDOP of the last GNSS position data
+    last_gnss: Option<(GnssMethod, Option<f64>, Instant)>,
+    /// Positions rejected by the noise filter since the last status
+    rejected_positions: usize,
+    quality_config: QualityScoreConfig,
+    /// Minimum time between two buffered positions
+    min_position_interval: Duration,
+    /// Weight of each new position in the moving average, 0 to disable the smoothing
+    position_smoothing_alpha: f64,
+    /// Exponential moving average of the accepted positions
+    smoothed_position: Option<Position>,
+    anchor_watch: Option<AnchorWatch>,
+    /// Radius the positions must stay within to consider the vessel moored
+    mooring_threshold_m: f64,
+    mooring_detection_window: Duration,
+    /// SOG above this is discarded as noise
+    max_valid_sog_kn: f64,
+    /// Positions farther than this from the median are discarded as noise
+    max_position_deviation_m: f64,
+    /// Positions required to validate new positions against the median and to report a status
+    min_samples: usize,
 }
 
 impl VesselMonitor {
-    pub fn new(application_state: Arc<Mutex<ApplicationState>>) -> Self {
-        let now = Instant::now();
+    pub fn new(application_state: Arc<Mutex<ApplicationState>>, config: &VesselStatusConfig) -> Self {
         VesselMonitor {
             positions: VecDeque::new(),
             speeds: VecDeque::new(),
             winds: VecDeque::new(),
             headings: VecDeque::new(),
-            last_event_time: now,
+            water_speeds: VecDeque::new(),
+            water_log_nm: None,
+            water_distance_fallback: config.water_distance_fallback,
+            last_heading_message: None,
+            measured_current: None,
+            last_event_time: None,
+            interval_moored: config.interval_moored(),
+            interval_underway: config.interval_underway(),
             engine_on: false,
+            engine_hysteresis: EngineHysteresis::default(),
+            engine_toggle_since: None,
+            gear: None,
             application_state,
+            last_gnss: None,
+            rejected_positions: 0,
+            quality_config: QualityScoreConfig::default(),
+            min_position_interval: Duration::ZERO,
+            position_smoothing_alpha: 0.0,
+            smoothed_position: None,
+            anchor_watch: None,
+            mooring_threshold_m: config.mooring_threshold_meters,
+            mooring_detection_window: config.mooring_detection_window(),
+            max_valid_sog_kn: config.max_valid_sog_kn,
+            max_position_deviation_m: config.max_position_deviation_meters,
+            min_samples: config.min_samples_for_validation,
         }
     }
 
+    /// Set the intervals between two vessel statuses, moored and underway
+    pub fn set_status_intervals(&mut self, interval_moored: Duration, interval_underway: Duration) {
+        self.interval_moored = interval_moored;
+        self.interval_underway = interval_underway;
+    }
+
+    /// Set the minimum time between two buffered positions
+    /// Positions received faster are dropped, protecting from GPS streaming at very high rates
+    pub fn set_min_position_interval(&mut self, interval: Duration) {
+        self.min_position_interval = interval;
+    }
+
+    /// Set the thresholds switching the engine state on and off
+    pub fn set_engine_hysteresis(&mut self, hysteresis: EngineHysteresis) {
+        self.engine_hysteresis = hysteresis;
+        self.engine_toggle_since = None;
+    }
+
+    /// Set the weight of each new position in the exponential moving average of the position
+    /// 0 disables the smoothing, the status then reports the last position
+    pub fn set_position_smoothing_alpha(&mut self, alpha: f64) {
+        self.position_smoothing_alpha = alpha;
+        self.smoothed_position = None;
+    }
+
+    /// Start watching the distance from the anchor point
+    pub fn set_anchor(&mut self, anchor: Position, radius_m: f64) {
+        self.anchor_watch = Some(AnchorWatch { anchor, radius_m });
+    }
+
+    /// Stop the anchor watch
+    pub fn clear_anchor(&mut self) {
+        self.anchor_watch = None;
+    }
+
+    /// Check the last accepted position against the anchor watch
+    /// Returns the distance from the anchor in meters if it exceeds the radius,
+    /// None if within the radius, without anchor watch or without positions
+    pub fn check_anchor_drift(&self) -> Option<f64> {
+        let watch = self.anchor_watch?;
+        let position = self.positions.back()?.position;
+        let distance_m = position.distance_to_nm(&watch.anchor) * 1852.0;
+        (distance_m > watch.radius_m).then_some(distance_m)
+    }
+
+    /// Set the weights of the signals combined into the status quality score
+    pub fn set_quality_config(&mut self, config: QualityScoreConfig) {
+        self.quality_config = config;
+    }
+
     /// Get rolling median position over a cutoff duration    
     fn get_rolling_median_position(&self, cutoff: Duration, min_num_samples: usize, now: Instant) -> (usize, Option<Position>) {
         let recent_positions: Vec<&Position> = self.positions
@@ -166,32 +386,16 @@ impl VesselMonitor {
             return (0, None);
         }
 
-        let mut lats: Vec<f64> = recent_positions.iter().map(|p| p.latitude).collect();
-        let mut lons: Vec<f64> = recent_positions.iter().map(|p| p.longitude).collect();
-        
-        lats.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        lons.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        
-        if lats.len() < min_num_samples {
-            return (lats.len(), None);
+        if recent_positions.len() < min_num_samples {
+            return (recent_positions.len(), None);
         }
 
-        let mid = lats.len() / 2;
-        let median_lat = if lats.len() % 2 == 0 {
-            (lats[mid - 1] + lats[mid]) / 2.0
-        } else {
-            lats[mid]
-        };
-        
-        let median_lon = if lons.len() % 2 == 0 {
-            (lons[mid - 1] + lons[mid]) / 2.0
-        } else {
-            lons[mid]
-        };
+        let mut lats: Vec<f64> = recent_positions.iter().map(|p| p.latitude).collect();
+        let mut lons: Vec<f64> = recent_positions.iter().map(|p| p.longitude).collect();
         
         (lats.len(), Some(Position {
-            latitude: median_lat,
-            longitude: median_lon,
+            latitude: median(&mut lats),
+            longitude: median(&mut lons),
         }))
     }
 
@@ -201,27 +405,39 @@ impl VesselMonitor {
             latitude: position_msg.latitude,
             longitude: position_msg.longitude,
         };
+        // Impossible coordinates (corrupted frame, bad sensor) would skew the median and the mooring detection
+        if !position.is_valid() {
+            self.rejected_positions += 1;
+            return;
+        }
+
+        // Decimate positions streamed faster than the configured interval
+        if let Some(last) = self.positions.back() && timestamp.saturating_duration_since(last.timestamp) < self.min_position_interval {
+            return;
+        }
 
         let cutoff = EVENT_INTERVAL;
-        let median_position = self.get_rolling_median_position(cutoff, MIN_SAMPLES_FOR_VALIDATION, timestamp);
+        let median_position = self.get_rolling_median_position(cutoff, self.min_samples, timestamp);
 
         // if we have enough samples, validate against median and reject if too far
         if let Some(median) = median_position.1 {
             let distance = position.distance_to_nm(&median) * 1852.0; // Convert nm to meters
-            if distance > MAX_POSITION_DEVIATION_METERS {
+            if distance > self.max_position_deviation_m {
+                self.rejected_positions += 1;
                 return; // Reject noisy position
             }
         }
 
         self.positions.push_back(PositionSample {
             position,
-            timestamp: timestamp,
+            timestamp,
         });
+        self.update_smoothed_position(position);
 
         self.application_state.lock().unwrap().update_position(position, median_position.1.unwrap_or(position), timestamp);
 
         // Clean up old position samples (keep only enuogh to calculate the mooring status + 30s buffer)
-        let cutoff = timestamp - MOORING_DETECTION_WINDOW - Duration::from_secs(30);
+        let cutoff = timestamp - self.mooring_detection_window - Duration::from_secs(30);
         while let Some(sample) = self.positions.front() {
             if sample.timestamp < cutoff {
                 self.positions.pop_front();
@@ -231,18 +447,40 @@ impl VesselMonitor {
         }
     }
 
+    /// Fold an accepted position into the exponential moving average
+    fn update_smoothed_position(&mut self, position: Position) {
+        let alpha = self.position_smoothing_alpha;
+        if alpha <= 0.0 {
+            return;
+        }
+        self.smoothed_position = Some(match self.smoothed_position {
+            Some(smoothed) => Position {
+                latitude: smoothed.latitude + alpha * (position.latitude - smoothed.latitude),
+                longitude: smoothed.longitude + alpha * (position.longitude - smoothed.longitude),
+            },
+            None => position,
+        });
+    }
+
+    /// Process a GNSS position data message, only the fix quality is used
+    pub fn process_gnss(&mut self, gnss_msg: &GnssPositionData, timestamp: Instant) {
+        let hdop = (gnss_msg.hdop >= 0.0 && gnss_msg.hdop < HDOP_NOT_AVAILABLE).then_some(gnss_msg.hdop);
+        self.last_gnss = Some((gnss_msg.method.clone(), hdop, timestamp));
+    }
+
     /// Process a COG & SOG rapid update message
     pub fn process_cog_sog(&mut self, cog_sog_msg: &CogSogRapidUpdate, timestamp: Instant) {
         let sog_kn = cog_sog_msg.sog_knots();
         
-        // Noise filter: Reject unrealistic SOG values (> 25 knots)
-        if sog_kn > MAX_VALID_SOG_KN {
+        // Noise filter: Reject unrealistic SOG values
+        if sog_kn > self.max_valid_sog_kn {
             return; // Reject noisy speed reading
         }
 
         self.speeds.push_back(SpeedSample {
             speed_kn: sog_kn,
-            timestamp: timestamp,
+            cog_deg: cog_sog_msg.cog_reference.then(|| crate::utilities::normalize0_360(cog_sog_msg.cog.to_degrees())),
+            timestamp,
         });
 
         // Clean up old speed samples (keep only last 30s + buffer)
@@ -272,7 +510,7 @@ impl VesselMonitor {
                 self.winds.push_back(WindSample {
                     wind_speed_kn: true_wind_speed_kn,
                     wind_angle_deg: crate::utilities::normalize0_360(true_wind_angle_deg),
-                    timestamp: timestamp,
+                    timestamp,
                 });
             }
         }
@@ -289,34 +527,142 @@ impl VesselMonitor {
     }
 
     /// Process engine rapid update to determine engine status
-    pub fn process_engine(&mut self, engine_msg: &nmea2k::pgns::EngineRapidUpdate, _timestamp: Instant) {
-        self.engine_on = engine_msg.is_engine_running();
+    /// The state changes once the RPM stays past the start (or stop) threshold for the hold time
+    pub fn process_engine(&mut self, engine_msg: &nmea2k::pgns::EngineRapidUpdate, timestamp: Instant) {
+        let rpm = engine_msg.engine_speed.unwrap_or(0.0);
+        let toggling = if self.engine_on {
+            rpm <= self.engine_hysteresis.stop_rpm
+        } else {
+            rpm > self.engine_hysteresis.start_rpm
+        };
+        if !toggling {
+            self.engine_toggle_since = None;
+            return;
+        }
+        let since = *self.engine_toggle_since.get_or_insert(timestamp);
+        if timestamp.saturating_duration_since(since) >= self.engine_hysteresis.hold {
+            self.engine_on = !self.engine_on;
+            self.engine_toggle_since = None;
+            debug!("Engine {} at {:.0} RPM", if self.engine_on { "started" } else { "stopped" }, rpm);
+        }
+    }
+
+    /// Process transmission parameters, only the gear is used
+    pub fn process_transmission(&mut self, transmission_msg: &TransmissionParameters, timestamp: Instant) {
+        self.gear = Some((transmission_msg.gear, timestamp));
     }
 
     pub fn process_heading(&mut self, heading_msg: &nmea2k::pgns::VesselHeading, timestamp: Instant) {
         if heading_msg.reference == HeadingReference::Magnetic {
-            // For magnetic heading, we would need to apply variation correction
-            // For simplicity, we skip magnetic headings in this implementation
-            if let Some(pos) = self.positions.back() {
-                let heading_deg = heading_msg.heading.to_degrees();
-                let var = match crate::utilities::get_variation_deg(pos.position.latitude, pos.position.longitude, chrono::Utc::now()) {
-                    Ok(v) => v,
-                    Err(_) => 0.0, // Unable to get variation, revert to magnetic - better than nothing
-                };
-                let true_heading_deg = crate::utilities::normalize0_360(heading_deg + var);
-                self.headings.push_back(HeadingSample {
-                    heading_deg: true_heading_deg,
-                    timestamp: timestamp,
-                });
-                self.application_state.lock().unwrap().update_heading(true_heading_deg, timestamp);
+            self.last_heading_message = Some(timestamp);
+            self.push_magnetic_heading(heading_msg.heading.to_degrees(), timestamp);
+        }
+    }
+
+    /// Process a speed through water message, used to estimate the current and the distance through the water
+    pub fn process_water_speed(&mut self, speed_msg: &SpeedWaterReferenced, timestamp: Instant) {
+        let speed_kn = speed_msg.speed_knots();
+        // Trapezoidal integration between consecutive samples
+        let water_log_nm = self.water_log_nm.get_or_insert(0.0);
+        if let Some(last) = self.water_speeds.back() {
+            let elapsed = timestamp.saturating_duration_since(last.timestamp);
+            if elapsed <= WATER_SPEED_MAX_GAP {
+                *water_log_nm += (last.speed_kn + speed_kn) / 2.0 * elapsed.as_secs_f64() / 3600.0;
+            }
+        }
+        self.water_speeds.push_back(WaterSpeedSample {
+            speed_kn,
+            timestamp,
+        });
+
+        let cutoff = timestamp - EVENT_INTERVAL - Duration::from_secs(5);
+        while let Some(sample) = self.water_speeds.front() {
+            if sample.timestamp < cutoff {
+                self.water_speeds.pop_front();
             } else {
-                // No position available to calculate variation, but better magnetic than nothing
-                self.headings.push_back(HeadingSample {
-                    heading_deg: heading_msg.heading.to_degrees(),
-                    timestamp: timestamp,
-                });
+                break;
             }
         }
+    }
+
+    /// Process the set and drift of the current measured by the instruments (PGN 129291)
+    /// A magnetic set is corrected with the variation at the last position, when known
+    pub fn process_set_drift(&mut self, set_drift_msg: &SetDrift, timestamp: Instant) {
+        let (Some(set), Some(drift_kn)) = (set_drift_msg.set, set_drift_msg.drift_knots()) else {
+            return;
+        };
+        let mut set_deg = set.to_degrees();
+        if set_drift_msg.set_reference == 1
+            && let Some(pos) = self.positions.back()
+            && let Ok(var) = crate::utilities::get_variation_deg(pos.position.latitude, pos.position.longitude, chrono::Utc::now()) {
+            set_deg += var;
+        }
+        let current = Current {
+            set_deg: crate::utilities::normalize0_360(set_deg),
+            drift_kn,
+        };
+        self.measured_current = Some((current, timestamp));
+    }
+
+    /// Current of the vessel: the one reported by PGN 129291 while recent, the estimated one otherwise
+    pub fn current(&self, now: Instant) -> Option<Current> {
+        self.measured_current
+            .filter(|(_, time)| now.saturating_duration_since(*time) <= SET_DRIFT_TIMEOUT)
+            .map(|(current, _)| current)
+            .or_else(|| self.estimate_current(now))
+    }
+
+    /// Estimate the current as the difference between the ground track (COG/SOG) and the water track
+    /// (heading and speed through water), leeway is not accounted for
+    /// Returns None unless the last heading, water speed and true COG samples are all recent
+    pub fn estimate_current(&self, now: Instant) -> Option<Current> {
+        let recent = |timestamp: Instant| now.saturating_duration_since(timestamp) <= CURRENT_MAX_SAMPLE_AGE;
+        let heading = self.headings.back().filter(|h| recent(h.timestamp))?;
+        let water = self.water_speeds.back().filter(|w| recent(w.timestamp))?;
+        let ground = self.speeds.iter().rev()
+            .find(|s| s.cog_deg.is_some())
+            .filter(|s| recent(s.timestamp))?;
+
+        let cog = ground.cog_deg?.to_radians();
+        let heading = heading.heading_deg.to_radians();
+        let north = ground.speed_kn * cog.cos() - water.speed_kn * heading.cos();
+        let east = ground.speed_kn * cog.sin() - water.speed_kn * heading.sin();
+        Some(Current {
+            set_deg: crate::utilities::normalize0_360(east.atan2(north).to_degrees()),
+            drift_kn: north.hypot(east),
+        })
+    }
+
+    /// Use the attitude yaw as heading when no heading sensor is reporting on PGN 127250
+    pub fn process_attitude(&mut self, attitude_msg: &Attitude, timestamp: Instant) {
+        let heading_recent = self.last_heading_message
+            .is_some_and(|last| timestamp.saturating_duration_since(last) < HEADING_FALLBACK_TIMEOUT);
+        if heading_recent {
+            return;
+        }
+        if let Some(yaw) = attitude_msg.yaw {
+            self.push_magnetic_heading(crate::utilities::normalize0_360(yaw.to_degrees()), timestamp);
+        }
+    }
+
+    fn push_magnetic_heading(&mut self, heading_deg: f64, timestamp: Instant) {
+        // For magnetic heading, we would need to apply variation correction
+        if let Some(pos) = self.positions.back() {
+            // Unable to get variation, revert to magnetic - better than nothing
+            let var = crate::utilities::get_variation_deg(pos.position.latitude, pos.position.longitude, chrono::Utc::now()).unwrap_or(0.0);
+            let true_heading_deg = crate::utilities::normalize0_360(heading_deg + var);
+            self.headings.push_back(HeadingSample {
+                heading_deg: true_heading_deg,
+                timestamp,
+            });
+            self.application_state.lock().unwrap().update_heading(true_heading_deg, timestamp);
+        } else {
+            // No position available to calculate variation, but better magnetic than nothing
+            self.headings.push_back(HeadingSample {
+                heading_deg,
+                timestamp,
+            });
+        }
 
         // Clean up old heading samples (keep only last interval + buffer)
         let cutoff = timestamp - EVENT_INTERVAL - Duration::from_secs(5);
@@ -331,8 +677,22 @@ impl VesselMonitor {
 
 
     /// Check if it's time to generate a status event
+    /// The first status is generated as soon as there are enough positions, the next ones after the
+    /// moored or underway interval depending on the current mooring state
     pub fn should_generate_event(&self, now: Instant) -> bool {
-        now.duration_since(self.last_event_time) >= EVENT_INTERVAL && self.positions.len() >= MIN_SAMPLES_FOR_VALIDATION
+        if self.positions.len() < self.min_samples {
+            return false;
+        }
+        let Some(last) = self.last_event_time else {
+            return true;
+        };
+        let elapsed = now.saturating_duration_since(last);
+        // Skip the mooring detection until the shorter interval has elapsed
+        if elapsed < self.interval_moored.min(self.interval_underway) {
+            return false;
+        }
+        let interval = if self.is_vessel_moored(now) { self.interval_moored } else { self.interval_underway };
+        elapsed >= interval
     }
 
     /// Generate a vessel status event
@@ -340,39 +700,86 @@ impl VesselMonitor {
         if !self.should_generate_event(now) {
             return None;
         }
+        self.build_status(now)
+    }
 
-        self.last_event_time = now;
+    /// Generate a vessel status immediately, regardless of the event interval
+    /// Returns None if there are not enough position samples
+    pub fn force_status(&mut self, now: Instant) -> Option<VesselStatus> {
+        if self.positions.len() < self.min_samples {
+            return None;
+        }
+        self.build_status(now)
+    }
 
-        let current_position = self.positions.back().unwrap().position;
-        let (number_of_samples, median_position) = self.get_rolling_median_position(EVENT_INTERVAL, MIN_SAMPLES_FOR_VALIDATION, now);
-        let (_, _, max_speed_kn) = self.calculate_average_and_max_speed(EVENT_INTERVAL);
-        let is_moored = self.is_vessel_moored();
-        let (wind_speed_kn, wind_speed_variance, wind_angle_deg, wind_angle_variance_deg) = self.calculate_wind_statistics(&self.winds, EVENT_INTERVAL);
-        let average_heading = self.calculate_average_heading(EVENT_INTERVAL);
+    fn build_status(&mut self, now: Instant) -> Option<VesselStatus> {
+        self.last_event_time = Some(now);
+
+        let (number_of_samples, median_position) = self.get_rolling_median_position(EVENT_INTERVAL, self.min_samples, now);
+        // Without positions in the last interval the dead reckoning estimate stands in for the GPS, if enabled
+        let estimate = self.application_state.lock().unwrap().composite_position
+            .filter(|composite| composite.is_estimated() && number_of_samples == 0);
+        let (_, _, max_speed_kn) = self.calculate_average_and_max_speed(EVENT_INTERVAL, now);
+        let is_moored = self.is_vessel_moored(now);
+        let (wind_speed_kn, wind_speed_variance, wind_angle_deg, wind_angle_variance_deg) = self.calculate_wind_statistics(&self.winds, EVENT_INTERVAL, now);
+        let average_heading = self.calculate_average_heading(EVENT_INTERVAL, now);
+        let cog_deg = self.calculate_average_cog(EVENT_INTERVAL, now);
+        let current = self.current(now);
+        // Without positions in the last interval the distance is measured through the water, if enabled
+        let distance_source = if number_of_samples == 0 && self.water_distance_fallback && self.water_log_nm.is_some() {
+            DistanceSource::WaterSpeed
+        } else {
+            DistanceSource::Gps
+        };
+        let gear = self.gear
+            .filter(|(_, time)| now.saturating_duration_since(*time) <= GEAR_TIMEOUT)
+            .map(|(gear, _)| gear);
+
+        // GNSS quality is only considered if recent
+        let gnss = self.last_gnss.as_ref().filter(|(_, _, time)| now.duration_since(*time) <= EVENT_INTERVAL);
+        let signals = QualitySignals {
+            fix_method: gnss.map(|(method, _, _)| method.clone()),
+            hdop: gnss.and_then(|(_, hdop, _)| *hdop),
+            number_of_samples,
+            rejected_positions: self.rejected_positions,
+        };
+        self.rejected_positions = 0;
 
         // Use the timestamp of the last position in the buffer, or current time if no positions
-        let timestamp = self.positions.back()
-            .map(|sample| sample.timestamp)
-            .unwrap_or(now);
-        
+        let (current_position, timestamp, position_source) = match (estimate, self.positions.back()) {
+            (Some(estimate), _) => {
+                debug!("Reporting the dead reckoning position, {}s after the last fix", estimate.fix_age.as_secs());
+                (estimate.position, now, PositionSource::DeadReckoning)
+            }
+            (None, Some(sample)) => (sample.position, sample.timestamp, PositionSource::Gps),
+            (None, None) => return None,
+        };
+
         Some(VesselStatus {
             current_position,
             median_position,
+            smoothed_position: self.smoothed_position,
             number_of_samples,
             max_speed_kn,
             is_moored,
             engine_on: self.engine_on,
+            gear,
             timestamp,
             wind_speed_kn,
             wind_speed_variance,
             wind_angle_deg,
             wind_angle_variance: wind_angle_variance_deg,
+            cog_deg,
             average_heading_deg: average_heading,
+            quality_score: quality_score(&signals, &self.quality_config),
+            current,
+            water_log_nm: self.water_log_nm,
+            distance_source,
+            position_source,
         })
     }
 
-    fn calculate_average_heading(&self, window: Duration) -> Option<f64> {
-        let now = Instant::now();
+    fn calculate_average_heading(&self, window: Duration, now: Instant) -> Option<f64> {
         let cutoff = now - window;
 
         let relevant_headings: Vec<&HeadingSample> = self.headings.iter().rev()
@@ -387,8 +794,23 @@ impl VesselMonitor {
         Some(mean_heading)
     }
 
-    fn calculate_wind_statistics(&self, winds: &VecDeque<WindSample>, window: Duration) -> (Option<f64>, Option<f64>, Option<f64>, Option<f64>) {
-        let now = Instant::now();
+    /// Circular mean of the true COG samples in the window
+    fn calculate_average_cog(&self, window: Duration, now: Instant) -> Option<f64> {
+        let cutoff = now - window;
+
+        let relevant_cogs: Vec<f64> = self.speeds.iter().rev()
+            .take_while(|s| s.timestamp >= cutoff)
+            .filter_map(|s| s.cog_deg)
+            .collect();
+
+        if relevant_cogs.is_empty() {
+            return None;
+        }
+
+        Some(average_angle(&relevant_cogs))
+    }
+
+    fn calculate_wind_statistics(&self, winds: &VecDeque<WindSample>, window: Duration, now: Instant) -> (Option<f64>, Option<f64>, Option<f64>, Option<f64>) {
         let cutoff = now - window;
 
         let relevant_winds: Vec<&WindSample> = winds.iter().rev()
@@ -416,8 +838,7 @@ impl VesselMonitor {
         (Some(mean_speed), Some(variance_speed), Some(mean_angle), Some(variance_angle))
     }
     
-    fn calculate_average_and_max_speed(&self, window: Duration) -> (usize, f64, f64) {
-        let now = Instant::now();
+    fn calculate_average_and_max_speed(&self, window: Duration, now: Instant) -> (usize, f64, f64) {
         let cutoff = now - window;
 
         let iterator = self.speeds.iter().rev();
@@ -443,16 +864,16 @@ impl VesselMonitor {
     }
 
     /// Determine if the vessel is moored based on position stability
-    /// Roughly, it checks if 90% of positions in the last 2 minutes are within 30 meters of the average position
-    fn is_vessel_moored(&self) -> bool {
+    /// Roughly, it checks if 90% of positions in the mooring detection window (3 minutes by default)
+    /// are within the mooring threshold (30 meters by default) of the average position
+    fn is_vessel_moored(&self, now: Instant) -> bool {
         if self.positions.len() < 2 {
             return false;
         }
 
-        let now = Instant::now();
-        let cutoff = now - MOORING_DETECTION_WINDOW;
+        let cutoff = now - self.mooring_detection_window;
 
-        // Get positions from the last 2 minutes
+        // Get positions from the detection window
         let recent_positions: Vec<&PositionSample> = self
             .positions
             .iter()
@@ -477,7 +898,7 @@ impl VesselMonitor {
         // Check if all positions are within threshold of average position
         recent_positions
             .iter()
-            .filter(|p| (p.position.distance_to_nm(&avg_position) * 1852.0) <= MOORING_THRESHOLD_METERS)
+            .filter(|p| (p.position.distance_to_nm(&avg_position) * 1852.0) <= self.mooring_threshold_m)
             .count() >= (recent_positions.len() as f64 * MOORING_ACCURACY) as usize // At least 90% within threshold
     }
 }
@@ -494,12 +915,27 @@ impl nmea2k::MessageHandler for VesselMonitor {
             nmea2k::pgns::N2kMessage::CogSogRapidUpdate(cog_sog) => {
                 self.process_cog_sog(cog_sog, timestamp);
             }
+            nmea2k::pgns::N2kMessage::GnssPositionData(gnss) => {
+                self.process_gnss(gnss, timestamp);
+            }
             nmea2k::pgns::N2kMessage::EngineRapidUpdate(engine) => {
                 self.process_engine(engine, timestamp);
             }
+            nmea2k::pgns::N2kMessage::TransmissionParameters(transmission) => {
+                self.process_transmission(transmission, timestamp);
+            }
             nmea2k::pgns::N2kMessage::VesselHeading(heading) => {
                 self.process_heading(heading, timestamp);
             }
+            nmea2k::pgns::N2kMessage::Attitude(attitude) => {
+                self.process_attitude(attitude, timestamp);
+            }
+            nmea2k::pgns::N2kMessage::SpeedWaterReferenced(speed) => {
+                self.process_water_speed(speed, timestamp);
+            }
+            nmea2k::pgns::N2kMessage::SetDrift(set_drift) => {
+                self.process_set_drift(set_drift, timestamp);
+            }
             _ => {} // Ignore messages we're not interested in
         }
     }
@@ -508,15 +944,13 @@ impl nmea2k::MessageHandler for VesselMonitor {
 impl Default for VesselMonitor {
     fn default() -> Self {
         use std::sync::{Arc, Mutex};
-        use crate::config::Config;
-        let config = Config::default();
-        let app_state = Arc::new(Mutex::new(ApplicationState::new(config)));
-        Self::new(app_state)
+        let app_state = Arc::new(Mutex::new(ApplicationState::new()));
+        Self::new(app_state, &VesselStatusConfig::default())
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
         use nmea2k::pgns::WindData;
 
         fn make_speed_sample(monitor: &mut VesselMonitor, sog_kn: f64, now: std::time::Instant) {
@@ -615,7 +1049,8 @@ mod tests {
             assert!(all_recent);
         }
     use super::*;
-    use nmea2k::pgns::{PositionRapidUpdate, CogSogRapidUpdate};
+    use crate::dead_reckoning::CompositePosition;
+    use nmea2k::pgns::{PositionRapidUpdate, CogSogRapidUpdate, VesselHeading};
 
     #[test]
     fn test_vessel_status_creation() {
@@ -643,8 +1078,8 @@ mod tests {
             expected_angles.push(crate::utilities::normalize0_360(tw_angle));
             make_wind_sample(&mut monitor, *ws, *wa, Instant::now());
         }
-        // Force last_event_time to the past to allow status generation
-        monitor.last_event_time = std::time::Instant::now() - EVENT_INTERVAL - Duration::from_secs(1);
+        // No status generated yet: the first one is generated right away
+        assert!(monitor.last_event_time.is_none());
         let status = monitor.generate_status(Instant::now()).unwrap();
         // Wind statistics should be present
         assert!(status.wind_speed_kn.is_some());
@@ -692,6 +1127,34 @@ mod tests {
         assert_eq!(pos.longitude, -122.0);
     }
 
+    #[test]
+    fn test_position_validity() {
+        assert!(Position { latitude: 43.5, longitude: 10.2 }.is_valid());
+        assert!(Position { latitude: -90.0, longitude: 180.0 }.is_valid());
+        assert!(!Position { latitude: f64::NAN, longitude: 10.2 }.is_valid());
+        assert!(!Position { latitude: 43.5, longitude: f64::NAN }.is_valid());
+        assert!(!Position { latitude: 43.5, longitude: 500.0 }.is_valid());
+        assert!(!Position { latitude: f64::INFINITY, longitude: 10.2 }.is_valid());
+    }
+
+    #[test]
+    fn test_process_position_rejects_impossible_coordinates() {
+        let mut monitor = VesselMonitor::default();
+        monitor.process_position(&PositionRapidUpdate::new(f64::NAN, 10.2), Instant::now());
+        monitor.process_position(&PositionRapidUpdate::new(43.5, 500.0), Instant::now());
+        assert!(monitor.positions.is_empty());
+        assert_eq!(monitor.rejected_positions, 2);
+    }
+
+    #[test]
+    fn test_process_position_ignores_not_available() {
+        let mut monitor = VesselMonitor::default();
+        monitor.process_position(&PositionRapidUpdate::new(214.7483647, 214.7483647), Instant::now());
+        assert!(monitor.positions.is_empty());
+        monitor.process_position(&PositionRapidUpdate::new(89.9999999, -179.5), Instant::now());
+        assert_eq!(monitor.positions.len(), 1);
+    }
+
     #[test]
     fn test_process_cog_sog() {
         let mut monitor = VesselMonitor::default();
@@ -784,6 +1247,222 @@ mod tests {
         assert_eq!(monitor.positions.len(), 11);
     }
 
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut [3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(&mut [4.0, 1.0, 3.0, 2.0]), 2.5);
+        assert_eq!(median(&mut [5.0]), 5.0);
+    }
+
+    /// Status of a vessel underway at the given position, shared by the tests of the status consumers
+    pub(crate) fn make_status(latitude: f64, longitude: f64, timestamp: Instant) -> VesselStatus {
+        let position = Position { latitude, longitude };
+        VesselStatus {
+            current_position: position,
+            median_position: Some(position),
+            smoothed_position: None,
+            number_of_samples: 10,
+            max_speed_kn: 0.0,
+            is_moored: false,
+            engine_on: false,
+            gear: None,
+            wind_speed_kn: None,
+            wind_speed_variance: None,
+            wind_angle_deg: None,
+            wind_angle_variance: None,
+            timestamp,
+            cog_deg: None,
+            average_heading_deg: None,
+            quality_score: 1.0,
+            current: None,
+            water_log_nm: None,
+            distance_source: DistanceSource::Gps,
+            position_source: PositionSource::Gps,
+        }
+    }
+
+    #[test]
+    fn test_vector_from_no_previous_status() {
+        let status = make_status(45.0, -122.0, Instant::now());
+        assert!(status.get_vector_from(&None).is_none());
+    }
+
+    #[test]
+    fn test_vector_from_previous_status() {
+        let start = Instant::now();
+        let previous = make_status(45.0, -122.0, start);
+        // 1 minute of latitude north is 1 nm, 30 seconds later
+        let status = make_status(45.0 + 1.0 / 60.0, -122.0, start + Duration::from_secs(30));
+
+        let vector = status.get_vector_from(&Some(previous)).unwrap();
+        assert!((vector.distance_nm - 1.0).abs() < 0.01, "Expected ~1 nm, got {}", vector.distance_nm);
+        assert_eq!(vector.delta_time_ms, 30_000);
+        assert!(vector.course_deg.min(360.0 - vector.course_deg) < 0.01);
+        assert!((vector.average_speed_kn() - 120.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_average_cog_across_north() {
+        let mut monitor = VesselMonitor::default();
+        let now = Instant::now();
+        for cog_deg in [350.0_f64, 355.0, 5.0, 10.0] {
+            let cog_sog_msg = CogSogRapidUpdate::new(true, cog_deg.to_radians(), 3.0);
+            monitor.process_cog_sog(&cog_sog_msg, now);
+        }
+
+        // The circular mean is 0°, not the arithmetic 180°
+        let cog = monitor.calculate_average_cog(EVENT_INTERVAL, now).unwrap();
+        let distance_from_north = cog.min(360.0 - cog);
+        assert!(distance_from_north < 1e-6, "Expected ~0, got {}", cog);
+    }
+
+    #[test]
+    fn test_heading_falls_back_to_attitude_yaw() {
+        let mut monitor = VesselMonitor::default();
+        let start = Instant::now();
+        monitor.process_attitude(&Attitude::new(0, Some(90.0_f64.to_radians()), None, Some(0.0)), start);
+        let heading = monitor.calculate_average_heading(EVENT_INTERVAL, start).unwrap();
+        assert!((heading - 90.0).abs() < 1e-6, "Expected 90, got {}", heading);
+
+        // Negative yaw is normalized
+        let mut monitor = VesselMonitor::default();
+        monitor.process_attitude(&Attitude::new(0, Some(-90.0_f64.to_radians()), None, None), start);
+        let heading = monitor.calculate_average_heading(EVENT_INTERVAL, start).unwrap();
+        assert!((heading - 270.0).abs() < 1e-6, "Expected 270, got {}", heading);
+    }
+
+    #[test]
+    fn test_attitude_yaw_ignored_while_heading_recent() {
+        let mut monitor = VesselMonitor::default();
+        let start = Instant::now();
+        monitor.process_heading(&VesselHeading::new(10.0_f64.to_radians(), HeadingReference::Magnetic), start);
+
+        // Within the timeout the heading sensor wins
+        monitor.process_attitude(&Attitude::new(0, Some(200.0_f64.to_radians()), None, None), start + Duration::from_secs(2));
+        let heading = monitor.calculate_average_heading(EVENT_INTERVAL, start + Duration::from_secs(2)).unwrap();
+        assert!((heading - 10.0).abs() < 1e-6, "Expected 10, got {}", heading);
+
+        // After the timeout the yaw is used again
+        let later = start + HEADING_FALLBACK_TIMEOUT + Duration::from_secs(1);
+        monitor.process_attitude(&Attitude::new(0, Some(20.0_f64.to_radians()), None, None), later);
+        let heading = monitor.calculate_average_heading(EVENT_INTERVAL, later).unwrap();
+        assert!((heading - 15.0).abs() < 1e-6, "Expected 15, got {}", heading);
+    }
+
+    #[test]
+    fn test_average_cog_ignores_magnetic() {
+        let mut monitor = VesselMonitor::default();
+        let now = Instant::now();
+        monitor.process_cog_sog(&CogSogRapidUpdate::new(false, 90.0_f64.to_radians(), 3.0), now);
+        assert!(monitor.calculate_average_cog(EVENT_INTERVAL, now).is_none());
+
+        monitor.process_cog_sog(&CogSogRapidUpdate::new(true, 358.0_f64.to_radians(), 3.0), now);
+        monitor.process_cog_sog(&CogSogRapidUpdate::new(true, 4.0_f64.to_radians(), 3.0), now);
+        let cog = monitor.calculate_average_cog(EVENT_INTERVAL, now).unwrap();
+        assert!((cog - 1.0).abs() < 1e-6, "Expected 1, got {}", cog);
+    }
+
+    #[test]
+    fn test_anchor_watch_in_radius() {
+        let mut monitor = VesselMonitor::default();
+        let anchor = Position { latitude: 45.0, longitude: -122.0 };
+        monitor.set_anchor(anchor, 50.0);
+
+        // ~33m north of the anchor
+        monitor.process_position(&PositionRapidUpdate::new(45.0003, -122.0), Instant::now());
+        assert!(monitor.check_anchor_drift().is_none());
+    }
+
+    #[test]
+    fn test_anchor_watch_out_of_radius() {
+        let mut monitor = VesselMonitor::default();
+        let anchor = Position { latitude: 45.0, longitude: -122.0 };
+        monitor.set_anchor(anchor, 50.0);
+
+        // ~67m north of the anchor
+        monitor.process_position(&PositionRapidUpdate::new(45.0006, -122.0), Instant::now());
+        let distance_m = monitor.check_anchor_drift().unwrap();
+        assert!((distance_m - 66.7).abs() < 0.5, "Expected ~66.7m, got {}", distance_m);
+
+        monitor.clear_anchor();
+        assert!(monitor.check_anchor_drift().is_none());
+    }
+
+    #[test]
+    fn test_anchor_watch_without_positions() {
+        let mut monitor = VesselMonitor::default();
+        monitor.set_anchor(Position { latitude: 45.0, longitude: -122.0 }, 50.0);
+        assert!(monitor.check_anchor_drift().is_none());
+    }
+
+    /// Feed positions 100ms apart, the first `before` at 45.0 and then `after` at 45.0005 (~55m north)
+    fn feed_position_step(monitor: &mut VesselMonitor, before: usize, after: usize) {
+        let start = Instant::now();
+        for i in 0..before + after {
+            let latitude = if i < before { 45.0 } else { 45.0005 };
+            let position_msg = PositionRapidUpdate::new(latitude, -122.0);
+            monitor.process_position(&position_msg, start + Duration::from_millis(100 * i as u64));
+        }
+    }
+
+    #[test]
+    fn test_position_smoothing_converges_on_step() {
+        let mut monitor = VesselMonitor::default();
+        monitor.set_position_smoothing_alpha(0.2);
+
+        feed_position_step(&mut monitor, 10, 1);
+        // Right after the step the smoothed position lags behind
+        let smoothed = monitor.smoothed_position.unwrap();
+        assert!((smoothed.latitude - 45.0001).abs() < 1e-9);
+
+        feed_position_step(&mut monitor, 0, 60);
+        let mut status = monitor.force_status(Instant::now()).unwrap();
+        status.is_moored = false;
+        let effective = status.get_effective_position();
+        assert!((effective.latitude - 45.0005).abs() < 1e-7);
+        assert!((effective.longitude + 122.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_position_smoothing_disabled_reports_last_position() {
+        let mut monitor = VesselMonitor::default();
+        monitor.set_position_smoothing_alpha(0.0);
+
+        feed_position_step(&mut monitor, 10, 1);
+        let mut status = monitor.force_status(Instant::now()).unwrap();
+        status.is_moored = false;
+        assert!(status.smoothed_position.is_none());
+        assert_eq!(status.get_effective_position().latitude, 45.0005);
+    }
+
+    #[test]
+    fn test_position_decimation_at_50hz() {
+        let mut monitor = VesselMonitor::default();
+        monitor.set_min_position_interval(Duration::from_millis(100));
+
+        // 60 seconds of positions at 50 Hz, moving north
+        let start = Instant::now();
+        for i in 0..3000u32 {
+            let position_msg = PositionRapidUpdate {
+                pgn: 129025,
+                latitude: 45.0 + i as f64 * 1e-7,
+                longitude: -122.0,
+            };
+            monitor.process_position(&position_msg, start + Duration::from_millis(i as u64 * 20));
+        }
+
+        // Only one position every 100 ms is buffered
+        assert_eq!(monitor.positions.len(), 600);
+        assert_eq!(monitor.rejected_positions, 0);
+
+        // The median of the last 10 seconds is the position of ~5 seconds ago
+        let now = start + Duration::from_millis(2999 * 20);
+        let (count, median_position) = monitor.get_rolling_median_position(EVENT_INTERVAL, monitor.min_samples, now);
+        assert_eq!(count, 100);
+        let expected = 45.0 + 2747.5 * 1e-7;
+        assert!((median_position.unwrap().latitude - expected).abs() < 1e-9);
+    }
+
     #[test]
     fn test_noise_filter_requires_minimum_samples() {
         let mut monitor = VesselMonitor::default();
@@ -845,13 +1524,38 @@ mod tests {
             std::thread::sleep(Duration::from_millis(50));
         }
         
-        let is_moored = monitor.is_vessel_moored();
+        let is_moored = monitor.is_vessel_moored(Instant::now());
         // Should detect mooring (all positions within small radius)
         assert!(is_moored);
         // Should have at least 10 samples accepted
         assert!(monitor.positions.len() >= 10);
     }
 
+    #[test]
+    fn test_mooring_threshold_configurable() {
+
+        // Positions alternating 20 m north and south of the same point
+        let offset_deg = 20.0 / 1852.0 / 60.0;
+        let swing = |monitor: &mut VesselMonitor| {
+            let now = Instant::now();
+            for i in 0..20 {
+                let latitude = if i % 2 == 0 { 45.0 + offset_deg } else { 45.0 - offset_deg };
+                monitor.process_position(&PositionRapidUpdate { pgn: 129025, latitude, longitude: -122.0 }, now);
+            }
+        };
+
+        let mut monitor = VesselMonitor::default();
+        swing(&mut monitor);
+        assert!(monitor.is_vessel_moored(Instant::now()));
+
+        let app_state = Arc::new(Mutex::new(ApplicationState::new()));
+        let config = VesselStatusConfig { mooring_threshold_meters: 10.0, ..Default::default() };
+        let mut monitor = VesselMonitor::new(app_state, &config);
+        swing(&mut monitor);
+        assert_eq!(monitor.positions.len(), 20);
+        assert!(!monitor.is_vessel_moored(Instant::now()));
+    }
+
     #[test]
     fn test_vessel_status_generation() {
         let mut monitor = VesselMonitor::default();
@@ -876,10 +1580,312 @@ mod tests {
         let cog_sog_msg = CogSogRapidUpdate::from_bytes(&data).unwrap();
         monitor.process_cog_sog(&cog_sog_msg, Instant::now());
         
-        // Wait for event interval (10 seconds)
-        std::thread::sleep(EVENT_INTERVAL + Duration::from_millis(100));
-        
         let status = monitor.generate_status(Instant::now());
         assert!(status.is_some());
     }
+
+    fn monitor_with_intervals(moored_seconds: u64, underway_seconds: u64) -> VesselMonitor {
+
+        let app_state = Arc::new(Mutex::new(ApplicationState::new()));
+        let config = VesselStatusConfig {
+            interval_moored_seconds: moored_seconds,
+            interval_underway_seconds: underway_seconds,
+            ..Default::default()
+        };
+        VesselMonitor::new(app_state, &config)
+    }
+
+    #[test]
+    fn test_status_cadence_underway() {
+        let mut monitor = monitor_with_intervals(600, 30);
+        // Positions 5 m apart: underway
+        let now = Instant::now();
+        for i in 0..20 {
+            let latitude = 45.0 + i as f64 * 5.0 / 1852.0 / 60.0;
+            monitor.process_position(&PositionRapidUpdate { pgn: 129025, latitude, longitude: -122.0 }, now);
+        }
+        assert!(!monitor.is_vessel_moored(now));
+
+        assert!(monitor.generate_status(now).is_some());
+        assert!(monitor.generate_status(now + Duration::from_secs(29)).is_none());
+        assert!(monitor.generate_status(now + Duration::from_secs(30)).is_some());
+        assert!(monitor.generate_status(now + Duration::from_secs(59)).is_none());
+        assert!(monitor.generate_status(now + Duration::from_secs(60)).is_some());
+    }
+
+    #[test]
+    fn test_status_cadence_moored() {
+        let mut monitor = monitor_with_intervals(600, 30);
+        let now = Instant::now();
+        for _ in 0..20 {
+            monitor.process_position(&PositionRapidUpdate { pgn: 129025, latitude: 45.0, longitude: -122.0 }, now);
+        }
+        assert!(monitor.is_vessel_moored(now));
+
+        assert!(monitor.generate_status(now).is_some());
+        assert!(monitor.generate_status(now + Duration::from_secs(30)).is_none());
+        assert!(monitor.generate_status(now + Duration::from_secs(600)).is_some());
+    }
+
+    #[test]
+    fn test_force_status_ignores_event_interval() {
+        let mut monitor = VesselMonitor::default();
+        let position_msg = PositionRapidUpdate {
+            pgn: 129025,
+            latitude: 45.0,
+            longitude: -122.0,
+        };
+
+        // Not enough samples yet
+        monitor.process_position(&position_msg, Instant::now());
+        assert!(monitor.force_status(Instant::now()).is_none());
+
+        for _ in 0..10 {
+            monitor.process_position(&position_msg, Instant::now());
+        }
+
+        // Event interval has not elapsed since the first status, but a forced status is still produced
+        assert!(monitor.generate_status(Instant::now()).is_some());
+        assert!(monitor.generate_status(Instant::now()).is_none());
+        let status = monitor.force_status(Instant::now());
+        assert!(status.is_some());
+        assert!(status.unwrap().is_valid());
+    }
+
+    fn track_sample(monitor: &mut VesselMonitor, heading_deg: f64, stw_kn: f64, cog_deg: f64, sog_kn: f64, timestamp: Instant) {
+        // No position: the heading is used as is, without variation
+        monitor.process_heading(&VesselHeading::new(heading_deg.to_radians(), HeadingReference::Magnetic), timestamp);
+        let stw_cmps = (stw_kn * 0.514444 * 100.0).round() as u16;
+        let [low, high] = stw_cmps.to_le_bytes();
+        monitor.process_water_speed(&SpeedWaterReferenced::from_bytes(&[0, low, high, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]).unwrap(), timestamp);
+        monitor.process_cog_sog(&CogSogRapidUpdate::new(true, cog_deg.to_radians(), sog_kn * 0.514444), timestamp);
+    }
+
+    fn water_speed(speed_kn: f64) -> SpeedWaterReferenced {
+        let [low, high] = ((speed_kn * 0.514444 * 100.0).round() as u16).to_le_bytes();
+        SpeedWaterReferenced::from_bytes(&[0, low, high, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]).unwrap()
+    }
+
+    #[test]
+    fn test_water_log_integrates_speed() {
+        let mut monitor = VesselMonitor::default();
+        let start = Instant::now();
+        // 5 kn for one hour, one sample per second
+        for second in 0..=3600 {
+            monitor.process_water_speed(&water_speed(5.0), start + Duration::from_secs(second));
+        }
+        let water_log_nm = monitor.water_log_nm.unwrap();
+        assert!((water_log_nm - 5.0).abs() < 0.01, "Expected ~5 nm, got {}", water_log_nm);
+
+        // A gap in the samples is not integrated
+        monitor.process_water_speed(&water_speed(5.0), start + Duration::from_secs(7200));
+        assert_eq!(monitor.water_log_nm, Some(water_log_nm));
+    }
+
+    #[test]
+    fn test_water_distance_fallback_without_positions() {
+        let config = VesselStatusConfig { water_distance_fallback: true, ..VesselStatusConfig::default() };
+        let app_state = Arc::new(Mutex::new(ApplicationState::new()));
+        let mut monitor = VesselMonitor::new(app_state, &config);
+        let start = Instant::now();
+        for _ in 0..10 {
+            monitor.process_position(&PositionRapidUpdate::new(43.5, 10.2), start);
+        }
+        monitor.process_water_speed(&water_speed(6.0), start);
+        let first = monitor.force_status(start).unwrap();
+        assert_eq!(first.distance_source, DistanceSource::Gps);
+
+        // The GPS drops out for 10 minutes while sailing at 6 kn
+        for second in 1..=600 {
+            monitor.process_water_speed(&water_speed(6.0), start + Duration::from_secs(second));
+        }
+        let status = monitor.force_status(start + Duration::from_secs(600)).unwrap();
+        assert_eq!(status.number_of_samples, 0);
+        assert_eq!(status.distance_source, DistanceSource::WaterSpeed);
+        assert!(status.is_valid());
+        let vector = status.get_vector_from(&Some(first)).unwrap();
+        assert!((vector.distance_nm - 1.0).abs() < 0.01, "Expected ~1 nm, got {}", vector.distance_nm);
+    }
+
+    #[test]
+    fn test_no_water_distance_fallback_by_default() {
+        let mut monitor = VesselMonitor::default();
+        let start = Instant::now();
+        for _ in 0..10 {
+            monitor.process_position(&PositionRapidUpdate::new(43.5, 10.2), start);
+        }
+        monitor.process_water_speed(&water_speed(6.0), start);
+        let status = monitor.force_status(start + Duration::from_secs(600)).unwrap();
+        assert_eq!(status.distance_source, DistanceSource::Gps);
+        assert!(!status.is_valid());
+    }
+
+    #[test]
+    fn test_current_from_ground_and_water_tracks() {
+        let mut monitor = VesselMonitor::default();
+        let now = Instant::now();
+
+        // Heading north at 5 kn through the water, pushed east by a 2 kn current
+        let sog_kn = (5.0_f64 * 5.0 + 2.0 * 2.0).sqrt();
+        let cog_deg = 2.0_f64.atan2(5.0).to_degrees();
+        track_sample(&mut monitor, 0.0, 5.0, cog_deg, sog_kn, now);
+        let current = monitor.estimate_current(now).unwrap();
+        assert!((current.set_deg - 90.0).abs() < 0.5, "Expected set 90, got {}", current.set_deg);
+        assert!((current.drift_kn - 2.0).abs() < 0.02, "Expected drift 2, got {}", current.drift_kn);
+
+        // Heading east at 6 kn against a 1 kn current, making 5 kn over ground
+        let later = now + Duration::from_secs(1);
+        track_sample(&mut monitor, 90.0, 6.0, 90.0, 5.0, later);
+        let current = monitor.estimate_current(later).unwrap();
+        assert!((current.set_deg - 270.0).abs() < 0.5, "Expected set 270, got {}", current.set_deg);
+        assert!((current.drift_kn - 1.0).abs() < 0.02, "Expected drift 1, got {}", current.drift_kn);
+    }
+
+    #[test]
+    fn test_current_requires_recent_samples() {
+        let mut monitor = VesselMonitor::default();
+        let now = Instant::now();
+        monitor.process_cog_sog(&CogSogRapidUpdate::new(true, 0.0, 2.5), now);
+        monitor.process_heading(&VesselHeading::new(0.0, HeadingReference::Magnetic), now);
+        // No water speed
+        assert!(monitor.estimate_current(now).is_none());
+
+        track_sample(&mut monitor, 0.0, 5.0, 0.0, 5.0, now);
+        assert!(monitor.estimate_current(now).is_some());
+        assert!(monitor.estimate_current(now + CURRENT_MAX_SAMPLE_AGE + Duration::from_secs(1)).is_none());
+    }
+
+    #[test]
+    fn test_dead_reckoning_position_without_fixes() {
+        let app_state = Arc::new(Mutex::new(ApplicationState::new()));
+        let mut monitor = VesselMonitor::new(app_state.clone(), &VesselStatusConfig::default());
+        let start = Instant::now();
+        for _ in 0..10 {
+            monitor.process_position(&PositionRapidUpdate::new(43.5, 10.2), start);
+        }
+        let first = monitor.force_status(start).unwrap();
+        assert_eq!(first.position_source, PositionSource::Gps);
+
+        // The GPS drops out, the estimator keeps the position moving north
+        let estimated = Position { latitude: 43.51, longitude: 10.2 };
+        app_state.lock().unwrap().update_composite_position(Some(CompositePosition {
+            position: estimated,
+            source: PositionSource::DeadReckoning,
+            fix_age: Duration::from_secs(60),
+        }));
+        let later = start + Duration::from_secs(60);
+        let status = monitor.force_status(later).unwrap();
+        assert_eq!(status.number_of_samples, 0);
+        assert_eq!(status.position_source, PositionSource::DeadReckoning);
+        assert!(status.is_valid());
+        assert_eq!(status.timestamp, later);
+        assert_eq!(status.get_effective_position().latitude, estimated.latitude);
+        let vector = status.get_vector_from(&Some(first)).unwrap();
+        assert!((vector.distance_nm - 0.6).abs() < 0.01, "Expected ~0.6 nm, got {}", vector.distance_nm);
+    }
+
+    #[test]
+    fn test_measured_current_supersedes_estimate() {
+        let mut monitor = VesselMonitor::default();
+        let now = Instant::now();
+        // Estimated: 2 kn towards east
+        let sog_kn = (5.0_f64 * 5.0 + 2.0 * 2.0).sqrt();
+        let cog_deg = 2.0_f64.atan2(5.0).to_degrees();
+        track_sample(&mut monitor, 0.0, 5.0, cog_deg, sog_kn, now);
+        assert!((monitor.current(now).unwrap().set_deg - 90.0).abs() < 0.5);
+
+        // Measured: 1 kn towards south
+        monitor.process_set_drift(&SetDrift::new(Some(180.0_f64.to_radians()), Some(1.0 / 1.94384)), now);
+        let current = monitor.current(now).unwrap();
+        assert!((current.set_deg - 180.0).abs() < 1e-6);
+        assert!((current.drift_kn - 1.0).abs() < 1e-6);
+        assert!((monitor.estimate_current(now).unwrap().set_deg - 90.0).abs() < 0.5);
+
+        // Back to the estimate when the measurement is stale
+        let later = now + SET_DRIFT_TIMEOUT + Duration::from_secs(1);
+        track_sample(&mut monitor, 0.0, 5.0, cog_deg, sog_kn, later);
+        assert!((monitor.current(later).unwrap().set_deg - 90.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_set_drift_not_available_ignored() {
+        let mut monitor = VesselMonitor::default();
+        let now = Instant::now();
+        monitor.process_set_drift(&SetDrift::new(None, Some(0.5)), now);
+        monitor.process_set_drift(&SetDrift::new(Some(1.0), None), now);
+        assert!(monitor.current(now).is_none());
+    }
+
+    fn engine_rpm(monitor: &mut VesselMonitor, rpm: f64, timestamp: Instant) {
+        monitor.process_engine(&nmea2k::pgns::EngineRapidUpdate::new(0, Some(rpm)), timestamp);
+    }
+
+    #[test]
+    fn test_engine_hysteresis_ignores_noise() {
+        let mut monitor = VesselMonitor::default();
+        monitor.set_engine_hysteresis(EngineHysteresis { start_rpm: 400.0, stop_rpm: 200.0, hold: Duration::from_secs(5) });
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        // Cranking: 2 seconds above the start threshold, then back to 0
+        for (i, rpm) in [0.0, 250.0, 450.0, 500.0, 480.0, 520.0, 0.0, 0.0].iter().enumerate() {
+            engine_rpm(&mut monitor, *rpm, at(i as u64 * 500));
+        }
+        assert!(!monitor.engine_on);
+
+        // Idling with jitter around 800 RPM: starts after 5 seconds
+        let idle = [780.0, 820.0, 790.0, 810.0, 805.0, 795.0];
+        for i in 0..10u64 {
+            engine_rpm(&mut monitor, idle[i as usize % idle.len()], at(10_000 + i * 500));
+        }
+        assert!(!monitor.engine_on);
+        engine_rpm(&mut monitor, 800.0, at(15_000));
+        assert!(monitor.engine_on);
+
+        // Brief dips below the stop threshold do not stop it
+        for (i, rpm) in [150.0, 800.0, 100.0, 0.0, 790.0, 810.0].iter().enumerate() {
+            engine_rpm(&mut monitor, *rpm, at(20_000 + i as u64 * 1000));
+            assert!(monitor.engine_on);
+        }
+
+        // Stopped for 5 seconds
+        for i in 0..=10u64 {
+            engine_rpm(&mut monitor, 0.0, at(30_000 + i * 500));
+        }
+        assert!(!monitor.engine_on);
+    }
+
+    #[test]
+    fn test_gear_reported_in_status() {
+        let mut monitor = VesselMonitor::default();
+        let position_msg = PositionRapidUpdate { pgn: 129025, latitude: 45.0, longitude: -122.0 };
+        let now = Instant::now();
+        for _ in 0..10 {
+            monitor.process_position(&position_msg, now);
+        }
+        engine_rpm(&mut monitor, 800.0, now);
+        monitor.process_transmission(&TransmissionParameters::new(0, Gear::Neutral), now);
+
+        let status = monitor.force_status(now).unwrap();
+        assert!(status.engine_on);
+        assert_eq!(status.gear, Some(Gear::Neutral));
+        assert!(!status.is_motoring());
+
+        // A stale gear is not reported, the engine alone decides
+        let status = monitor.force_status(now + GEAR_TIMEOUT + Duration::from_secs(1)).unwrap();
+        assert_eq!(status.gear, None);
+        assert!(status.is_motoring());
+    }
+
+    #[test]
+    fn test_engine_without_hysteresis_follows_rpm() {
+        let mut monitor = VesselMonitor::default();
+        let now = Instant::now();
+        engine_rpm(&mut monitor, 10.0, now);
+        assert!(monitor.engine_on);
+        engine_rpm(&mut monitor, 0.0, now);
+        assert!(!monitor.engine_on);
+        monitor.process_engine(&nmea2k::pgns::EngineRapidUpdate::new(0, None), now);
+        assert!(!monitor.engine_on);
+    }
 }
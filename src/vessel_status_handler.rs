@@ -1,11 +1,21 @@
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
+use std::sync::Arc;
 use tracing::{info, warn, debug};
 
-use crate::utilities::dirty_instant_to_systemtime;
-use crate::vessel_monitor::{VesselStatus};
-use crate::db::{VesselDatabase, TripOperation, VesselStatusOperation};
-use crate::trip::Trip;
+use crate::utilities::WallClock;
+use crate::vessel_monitor::{Position, VesselStatus, VesselVector};
+use crate::db::{Database, TripOperation, VesselStatusOperation};
+use crate::trip::{Trip, trip_base_description, unique_trip_description};
 use crate::config::VesselStatusConfig;
+use crate::write_buffer::{BufferedWrite, WriteBuffer};
+use crate::db_writer::{DbQueue, DbWrite};
+
+/// Slack on the persistence interval: the vessel monitor already generates the statuses at the same
+/// interval, this only absorbs the delay between the generation and the write
+const PERSIST_INTERVAL_TOLERANCE: Duration = Duration::from_secs(1);
+
+/// A point is written at least this often, even if the vessel did not move, so that gaps are data losses
+const TRACK_HEARTBEAT: Duration = Duration::from_secs(3600);
 
 /// State for tracking vessel status between reports
 pub struct VesselStatusState {
@@ -13,33 +23,75 @@ pub struct VesselStatusState {
     last_reported_max_speed: f64,
     current_trip: Option<Trip>,
     last_db_persist_time: Instant,
+    /// Effective position and time of the last vessel status written
+    last_written_point: Option<(Position, Instant)>,
+    /// The current trip was updated without being written (database unavailable or vessel status skipped)
+    offline_trip_changes: bool,
     config: VesselStatusConfig,
 }
 
 /// Handler for vessel status reporting and persistence
 pub struct VesselStatusHandler {
     state: VesselStatusState,
+    write_buffer: Option<WriteBuffer>,
+    /// Queue of the database writer thread, the database is written directly when not set
+    db_queue: Option<DbQueue>,
+    wall_clock: WallClock,
 }
 
 impl VesselStatusHandler {
     pub fn new(config: VesselStatusConfig) -> Self {
         Self {
             state: VesselStatusState::new(config),
+            write_buffer: None,
+            db_queue: None,
+            wall_clock: WallClock::new(),
+        }
+    }
+
+    /// Set the buffer receiving the vessel status reports while the database is unavailable
+    pub fn set_write_buffer(&mut self, write_buffer: Option<WriteBuffer>) {
+        self.write_buffer = write_buffer;
+    }
+
+    /// Set the queue of the database writer thread, vessel status reports are then written in the background
+    pub fn set_db_queue(&mut self, db_queue: Option<DbQueue>) {
+        self.db_queue = db_queue;
+    }
+
+    /// Record the id of a trip created by the database writer thread
+    pub fn trip_created(&mut self, start: SystemTime, id: i64, description: &str) {
+        if let Some(ref mut trip) = self.state.current_trip
+            && trip.id.is_none()
+            && trip.start_timestamp == start {
+            trip.id = Some(id);
+            trip.description = description.to_string();
         }
     }
 
+    /// Set the clock converting the vessel status instants to the timestamps written
+    pub fn set_wall_clock(&mut self, wall_clock: WallClock) {
+        self.wall_clock = wall_clock;
+    }
+
+    /// Replace the settings, e.g. on configuration reload
+    /// The current trip and the last status written are kept
+    pub fn set_config(&mut self, config: VesselStatusConfig) {
+        self.state.config = config;
+    }
+
     /// Load the last trip from database if available
-    pub fn load_last_trip(&mut self, vessel_db: &VesselDatabase) {
+    pub fn load_last_trip(&mut self, vessel_db: &dyn Database) {
         self.state.load_last_trip(vessel_db);
     }
 
     /// Handle vessel status reporting and persistence
-    /// Returns Ok(true) if a vessel status report was written to the database
+    /// Returns Ok(true) if a vessel status report was written to the database (or to the write buffer when disconnected)
     /// Returns Ok(false) if no write was needed
-    /// Returns Err if there was a database error
+    /// Returns Err if there was a database or write buffer error
     pub fn handle_vessel_status(
         &mut self,
-        vessel_db: &Option<VesselDatabase>,
+        vessel_db: &Option<Arc<dyn Database>>,
         status: VesselStatus,
     ) -> Result<bool, Box<dyn std::error::Error>> {
         let effective_position = status.get_effective_position();
@@ -49,81 +101,259 @@ impl VesselStatusHandler {
             status.max_speed_kn, status.wind_speed_kn, status.wind_angle_deg, 
             status.average_heading_deg,
             status.is_moored);
+        if let Some(current) = status.current {
+            debug!("Current: set={:.0}°, drift={:.2} knots", current.set_deg, current.drift_kn);
+        }
     
         // Write to database if connected, time to persist, and time is synchronized
-        if let Some(ref db) = *vessel_db && status.is_valid() && self.state.should_persist_to_db(status.is_moored) {
-            let time: Instant = status.timestamp;
-            let position = status.get_effective_position();
-            let latitude = position.latitude;
-            let longitude = position.longitude;
-            let vessel_vector = status.get_vector_from(&mut self.state.last_vessel_status);
-            let total_distance_nm = if let Some(ref vessel_vector) = vessel_vector { vessel_vector.distance_nm } else { 0.0 };
-            let total_time_ms = if let Some(ref vessel_vector) = vessel_vector { vessel_vector.delta_time_ms } else { 0 };
-            let average_speed_kn = if let Some(ref vessel_vector) = vessel_vector { vessel_vector.average_speed_kn() } else { 0.0 };
-            let cog_deg: Option<f64> = if let Some(ref vessel_vector) = vessel_vector { Some(vessel_vector.course_deg) } else { None };
-            let average_heading_deg: Option<f64> = status.average_heading_deg;
-            self.state.last_reported_max_speed = self.state.last_reported_max_speed.max(status.max_speed_kn);
-
-            // Determine trip operation (create, update, or none)
-            let trip_operation = Self::determine_trip_operation(&mut self.state.current_trip, &status, total_distance_nm, total_time_ms);
-            
-            // Create vessel status operation
-            let status_operation = VesselStatusOperation {
-                time,
-                latitude,
-                longitude,
-                average_speed_kn: average_speed_kn,
-                max_speed_kn: self.state.last_reported_max_speed,
-                is_moored: status.is_moored,
-                engine_on: status.engine_on,
-                total_distance_nm,
-                total_time_ms,
-                average_wind_speed_kn: status.wind_speed_kn,
-                wind_speed_variance: status.wind_speed_variance,
-                average_wind_angle_deg: status.wind_angle_deg,
-                wind_angle_variance: status.wind_angle_variance,
-                cog_deg,
-                average_heading_deg,
-            };
-            
-            // Perform atomic insert of vessel status and trip operation
-            match db.insert_status_and_trip(status_operation, trip_operation) {
-                Ok(new_trip_id) => {
-                    debug!("Vessel status written to database: lat={:.6}, lon={:.6}, avg_speed={:.2} knots, distance={:.3} nm, time={} ms, moored={}", 
-                        position.latitude, position.longitude, average_speed_kn, total_distance_nm, total_time_ms, status.is_moored);
-                    self.state.mark_db_persisted();
-                    self.state.last_vessel_status = Some(status.clone());
-                    self.state.last_reported_max_speed = 0.0;
-                    
-                    // Update trip ID if we created a new trip
-                    if let Some(trip_id) = new_trip_id {
-                        if let Some(ref mut trip) = self.state.current_trip {
-                            trip.id = Some(trip_id);
-                            info!("Created new trip: {} (ID: {})", trip.description, trip_id);
-                        }
-                    } else if let Some(ref trip) = self.state.current_trip {
-                        debug!("Updated trip: {} (ID: {}), total_distance={:.3}nm, total_time={}ms", 
-                            trip.description, trip.id.unwrap_or(0), trip.total_distance(), trip.total_time());
-                    }
-                    
-                    return Ok(true);
-                }
-                Err(e) => {
-                    warn!("Error writing vessel status to database: {}", e);
-                    return Err(e);
+        if !status.is_valid() || !self.state.should_persist_to_db(status.is_moored, status.timestamp) {
+            return Ok(false);
+        }
+        if (vessel_db.is_some() || self.write_buffer.is_some()) && self.skip_unmoved_status(&status) {
+            return Ok(false);
+        }
+        if let Some(db) = vessel_db.as_deref() {
+            match self.db_queue.clone() {
+                Some(queue) => self.queue_status(&queue, status)?,
+                None => {
+                    self.persist_status(db, status)?;
                 }
             }
+            return Ok(true);
+        }
+        if let Some(buffer) = self.write_buffer.clone() {
+            self.buffer_status(&buffer, status)?;
+            return Ok(true);
         }
         Ok(false)
     }
 
+    /// Persist the given vessel status immediately, regardless of the reporting interval
+    /// Returns the id of the vessel status row written to the database
+    pub fn flush_vessel_status(
+        &mut self,
+        db: &dyn Database,
+        status: VesselStatus,
+    ) -> Result<i64, Box<dyn std::error::Error>> {
+        if !status.is_valid() {
+            return Err("Vessel status is not valid".into());
+        }
+        self.persist_status(db, status)
+    }
+
+    /// Write the last vessel status before exiting, so that the current trip ends with it
+    /// The status is written regardless of the reporting interval, to the database or to the write buffer
+    /// Returns Ok(true) if the status was written, Ok(false) if there was no valid status or nowhere to write it
+    pub fn finalize(
+        &mut self,
+        vessel_db: &Option<Arc<dyn Database>>,
+        status: Option<VesselStatus>,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let Some(status) = status.filter(|s| s.is_valid()) else {
+            return Ok(false);
+        };
+        if let Some(db) = vessel_db.as_deref() {
+            self.persist_status(db, status)?;
+        } else if let Some(buffer) = self.write_buffer.clone() {
+            self.buffer_status(&buffer, status)?;
+        } else {
+            return Ok(false);
+        }
+        if let Some(ref trip) = self.state.current_trip {
+            info!("Trip finalized: {}, total_distance={:.3}nm", trip.description, trip.total_distance());
+        }
+        Ok(true)
+    }
+
+    /// Compute the vessel status row for the given status, relative to the last one reported
+    /// Returns the row along with the distance and time since the last report
+    fn prepare_status(&mut self, status: &VesselStatus) -> (VesselStatusOperation, f64, u64) {
+        let time = self.wall_clock.to_system_time(status.timestamp);
+        let position = status.get_effective_position();
+        let latitude = position.latitude;
+        let longitude = position.longitude;
+        let vessel_vector = status.get_vector_from(&self.state.last_vessel_status);
+        let total_time_ms = if let Some(ref vessel_vector) = vessel_vector { vessel_vector.delta_time_ms } else { 0 };
+        // A GPS glitch slipping past the noise filter makes an implausibly fast segment: only its time is counted
+        let vessel_vector = vessel_vector.filter(|vessel_vector| self.state.is_plausible_segment(vessel_vector));
+        let total_distance_nm = if let Some(ref vessel_vector) = vessel_vector { vessel_vector.distance_nm } else { 0.0 };
+        let average_speed_kn = if let Some(ref vessel_vector) = vessel_vector { vessel_vector.average_speed_kn() } else { 0.0 };
+        // Prefer the COG reported by the GPS, fall back on the course between the last two reports
+        let cog_deg: Option<f64> = status.cog_deg.or(vessel_vector.as_ref().map(|v| v.course_deg));
+        let average_heading_deg: Option<f64> = status.average_heading_deg;
+        self.state.last_reported_max_speed = self.state.last_reported_max_speed.max(status.max_speed_kn);
+
+        let status_operation = VesselStatusOperation {
+            time,
+            latitude,
+            longitude,
+            average_speed_kn,
+            max_speed_kn: self.state.last_reported_max_speed,
+            is_moored: status.is_moored,
+            engine_on: status.engine_on,
+            total_distance_nm,
+            total_time_ms,
+            average_wind_speed_kn: status.wind_speed_kn,
+            wind_speed_variance: status.wind_speed_variance,
+            average_wind_angle_deg: status.wind_angle_deg,
+            wind_angle_variance: status.wind_angle_variance,
+            cog_deg,
+            average_heading_deg,
+            quality_score: Some(status.quality_score),
+        };
+        (status_operation, total_distance_nm, total_time_ms)
+    }
+
+    /// Mark the status as reported, the next report is computed relative to it
+    fn mark_reported(&mut self, status: VesselStatus) {
+        self.state.last_written_point = Some((status.get_effective_position(), status.timestamp));
+        self.state.mark_db_persisted(status.timestamp);
+        self.state.last_vessel_status = Some(status);
+        self.state.last_reported_max_speed = 0.0;
+    }
+
+    /// Skip writing the status if the vessel did not move since the last written point
+    /// The trip is updated in memory (e.g. its moored time) and written with the next point
+    /// Returns true if the status was skipped
+    fn skip_unmoved_status(&mut self, status: &VesselStatus) -> bool {
+        let report_time = self.wall_clock.to_system_time(status.timestamp);
+        let inactivity_timeout = self.state.config.inactivity_timeout();
+        // Starting a new trip needs a point
+        let trip_active = self.state.current_trip
+            .as_ref()
+            .is_some_and(|trip| trip.is_active(report_time, inactivity_timeout));
+        if !trip_active || !self.state.is_unmoved(status) {
+            return false;
+        }
+
+        let (_, total_distance_nm, total_time_ms) = self.prepare_status(status);
+        let template = &self.state.config.trip_name_template;
+        Self::determine_trip_operation(&mut self.state.current_trip, &self.wall_clock, inactivity_timeout, status, total_distance_nm, total_time_ms, |start_time| {
+            trip_base_description(template, start_time)
+        });
+        self.state.offline_trip_changes = true;
+        debug!("Vessel status not written, within {} m of the last point", self.state.config.min_track_distance_m);
+        // The next point is computed relative to this status, the max speed is kept until written
+        self.state.mark_db_persisted(status.timestamp);
+        self.state.last_vessel_status = Some(status.clone());
+        true
+    }
+
+    /// Append the vessel status to the write buffer, to be replayed when the database is back
+    /// The trip is updated in memory only and written with the next report once connected
+    fn buffer_status(
+        &mut self,
+        buffer: &WriteBuffer,
+        status: VesselStatus,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (status_operation, total_distance_nm, total_time_ms) = self.prepare_status(&status);
+        if let Err(e) = buffer.append(&BufferedWrite::VesselStatus(status_operation)) {
+            warn!("Error writing vessel status to write buffer: {}", e);
+            return Err(e);
+        }
+
+        let template = &self.state.config.trip_name_template;
+        Self::determine_trip_operation(&mut self.state.current_trip, &self.wall_clock, self.state.config.inactivity_timeout(), &status, total_distance_nm, total_time_ms, |start_time| {
+            trip_base_description(template, start_time)
+        });
+        self.state.offline_trip_changes = true;
+        debug!("Vessel status buffered while the database is unavailable");
+        self.mark_reported(status);
+        Ok(())
+    }
+
+    /// Hand the vessel status and the related trip operation over to the database writer thread
+    /// The trip totals are cumulative: a status dropped because the queue is full only loses its row
+    fn queue_status(
+        &mut self,
+        queue: &DbQueue,
+        status: VesselStatus,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (status_operation, total_distance_nm, total_time_ms) = self.prepare_status(&status);
+        // The writer thread makes the description of a new trip unique
+        let template = &self.state.config.trip_name_template;
+        let trip_operation = Self::determine_trip_operation(&mut self.state.current_trip, &self.wall_clock, self.state.config.inactivity_timeout(), &status, total_distance_nm, total_time_ms, |start_time| {
+            trip_base_description(template, start_time)
+        });
+        self.state.offline_trip_changes = false;
+        self.mark_reported(status);
+        if let Err(e) = queue.send(DbWrite::StatusAndTrip(Box::new(status_operation), trip_operation)) {
+            warn!("Vessel status not written: {}", e);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Write the vessel status and the related trip operation to the database
+    fn persist_status(
+        &mut self,
+        db: &dyn Database,
+        status: VesselStatus,
+    ) -> Result<i64, Box<dyn std::error::Error>> {
+        let position = status.get_effective_position();
+        let (status_operation, total_distance_nm, total_time_ms) = self.prepare_status(&status);
+        let average_speed_kn = status_operation.average_speed_kn;
+
+        // Determine trip operation (create, update, or none)
+        let template = &self.state.config.trip_name_template;
+        let trip_operation = Self::determine_trip_operation(&mut self.state.current_trip, &self.wall_clock, self.state.config.inactivity_timeout(), &status, total_distance_nm, total_time_ms, |start_time| {
+            let base = trip_base_description(template, start_time);
+            let existing = db.fetch_trip_descriptions(&base).unwrap_or_else(|e| {
+                warn!("Failed to fetch existing trip descriptions: {}", e);
+                Vec::new()
+            });
+            unique_trip_description(&base, &existing)
+        });
+        // A trip started while the database was unavailable has not been created yet
+        let trip_operation = match trip_operation {
+            TripOperation::UpdateTrip(trip) if trip.id.is_none() => TripOperation::CreateTrip(trip),
+            op => op,
+        };
+        
+        // Perform atomic insert of vessel status and trip operation
+        match db.insert_status_and_trip(status_operation, trip_operation) {
+            Ok((status_id, new_trip_id)) => {
+                debug!("Vessel status written to database: lat={:.6}, lon={:.6}, avg_speed={:.2} knots, distance={:.3} nm, time={} ms, moored={}", 
+                    position.latitude, position.longitude, average_speed_kn, total_distance_nm, total_time_ms, status.is_moored);
+                self.mark_reported(status);
+                self.state.offline_trip_changes = false;
+                
+                // Update trip ID if we created a new trip
+                if let Some(trip_id) = new_trip_id {
+                    if let Some(ref mut trip) = self.state.current_trip {
+                        trip.id = Some(trip_id);
+                        info!("Created new trip: {} (ID: {})", trip.description, trip_id);
+                    }
+                } else if let Some(ref trip) = self.state.current_trip {
+                    debug!("Updated trip: {} (ID: {}), total_distance={:.3}nm, total_time={}ms", 
+                        trip.description, trip.id.unwrap_or(0), trip.total_distance(), trip.total_time());
+                }
+                
+                Ok(status_id)
+            }
+            Err(e) => {
+                warn!("Error writing vessel status to database: {}", e);
+                Err(e)
+            }
+        }
+    }
+
     /// Determine the trip operation to perform
-    fn determine_trip_operation(current_trip: &mut Option<Trip>, status: &VesselStatus, distance: f64, delta_time_ms: u64) -> TripOperation {
+    /// `describe_trip` provides the description for a new trip given its start time
+    fn determine_trip_operation(
+        current_trip: &mut Option<Trip>,
+        wall_clock: &WallClock,
+        inactivity_timeout: Duration,
+        status: &VesselStatus,
+        distance: f64,
+        delta_time_ms: u64,
+        describe_trip: impl FnOnce(SystemTime) -> String,
+    ) -> TripOperation {
         let report_time = status.timestamp;
-        let report_systemtime = dirty_instant_to_systemtime(report_time);
+        let report_systemtime = wall_clock.to_system_time(report_time);
         // Check if we need to create a new trip or update existing
         let should_create_new = if let Some(ref trip) = *current_trip {
-            !trip.is_active(report_systemtime)
+            !trip.is_active(report_systemtime, inactivity_timeout)
         } else {
             true // No current trip, create new one
         };
@@ -134,19 +364,17 @@ impl VesselStatusHandler {
             // Create new trip
             let start_time = report_systemtime;
             
-            // Format description with date
-            let datetime = chrono::DateTime::<chrono::Utc>::from(start_time);
-            let description = format!("Trip {}", datetime.format("%Y-%m-%d"));
-            
+            let description = describe_trip(start_time);
+
             let mut new_trip = Trip::new(start_time, description);
-            new_trip.update(report_systemtime, effective_distance, delta_time_ms, status.engine_on, status.is_moored);
+            new_trip.update(report_systemtime, effective_distance, delta_time_ms, status.is_motoring(), status.is_moored);
             
             *current_trip = Some(new_trip.clone());
             TripOperation::CreateTrip(new_trip)
         } else {
             // Update existing trip
             if let Some(ref mut trip) = *current_trip {
-                trip.update(report_systemtime, effective_distance, delta_time_ms, status.engine_on, status.is_moored);
+                trip.update(report_systemtime, effective_distance, delta_time_ms, status.is_motoring(), status.is_moored);
                 TripOperation::UpdateTrip(trip.clone())
             } else {
                 TripOperation::None
@@ -164,28 +392,62 @@ impl VesselStatusState {
             current_trip: None,
             // Initialize to far past to ensure first report is written immediately
             last_db_persist_time: now - Duration::from_secs(86400), // 24 hours ago
+            last_written_point: None,
+            offline_trip_changes: false,
             config,
         }
     }
 
     /// Check if it's time to persist status to database (adaptive based on mooring state)
-    fn should_persist_to_db(&self, is_moored: bool) -> bool {
-        let now = Instant::now();
+    /// The vessel monitor generates the statuses at these intervals: this guards against statuses
+    /// coming faster, e.g. right after the mooring state changed
+    fn should_persist_to_db(&self, is_moored: bool, now: Instant) -> bool {
         let interval = if is_moored {
             self.config.interval_moored()
         } else {
             self.config.interval_underway()
         };
-        now.duration_since(self.last_db_persist_time) >= interval
+        now.saturating_duration_since(self.last_db_persist_time) + PERSIST_INTERVAL_TOLERANCE >= interval
+    }
+
+    /// Check the speed implied by the segment since the last report, logging the implausible ones
+    fn is_plausible_segment(&self, vessel_vector: &VesselVector) -> bool {
+        let speed_kn = vessel_vector.average_speed_kn();
+        if speed_kn > self.config.max_segment_speed_kn {
+            warn!("Discarding a {:.3} nm segment covered at {:.1} knots (max {:.1}), likely a GPS glitch",
+                vessel_vector.distance_nm, speed_kn, self.config.max_segment_speed_kn);
+            return false;
+        }
+        true
+    }
+
+    /// Check if the vessel stayed within `min_track_distance_m` of the last written point, which is
+    /// recent enough for the gap not to look like a data loss
+    fn is_unmoved(&self, status: &VesselStatus) -> bool {
+        if self.config.min_track_distance_m <= 0.0 {
+            return false;
+        }
+        let Some((position, timestamp)) = self.last_written_point else {
+            return false;
+        };
+        if status.timestamp.saturating_duration_since(timestamp) >= TRACK_HEARTBEAT {
+            return false;
+        }
+        position.distance_to_nm(&status.get_effective_position()) * 1852.0 <= self.config.min_track_distance_m
     }
 
     /// Mark that we've persisted to the database
-    fn mark_db_persisted(&mut self) {
-        self.last_db_persist_time = Instant::now();
+    fn mark_db_persisted(&mut self, now: Instant) {
+        self.last_db_persist_time = now;
     }
 
     /// Load the last trip from database if available
-    fn load_last_trip(&mut self, vessel_db: &VesselDatabase) {
+    fn load_last_trip(&mut self, vessel_db: &dyn Database) {
+        if self.offline_trip_changes {
+            // The trip in memory is more recent than the one in the database, it is written with the next report
+            debug!("Keeping the trip updated while the database was unavailable");
+            return;
+        }
         match vessel_db.get_last_trip() {
             Ok(trip) => {
                 if let Some(t) = trip {
@@ -207,6 +469,7 @@ impl VesselStatusState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::vessel_monitor::tests::make_status;
     use std::time::Duration;
 
     #[test]
@@ -214,11 +477,12 @@ mod tests {
         let config = VesselStatusConfig {
             interval_moored_seconds: 0, // Set to 0 so it always needs to persist
             interval_underway_seconds: 5,
+            ..Default::default()
         };
         let state = VesselStatusState::new(config);
         
         // Should persist immediately with 0-second interval
-        assert!(state.should_persist_to_db(true));
+        assert!(state.should_persist_to_db(true, Instant::now()));
     }
 
     #[test]
@@ -226,11 +490,12 @@ mod tests {
         let config = VesselStatusConfig {
             interval_moored_seconds: 10,
             interval_underway_seconds: 0, // Set to 0 so it always needs to persist
+            ..Default::default()
         };
         let state = VesselStatusState::new(config);
         
         // Should persist immediately with 0-second interval
-        assert!(state.should_persist_to_db(false));
+        assert!(state.should_persist_to_db(false, Instant::now()));
     }
 
     #[test]
@@ -240,7 +505,7 @@ mod tests {
         
         let before = state.last_db_persist_time;
         std::thread::sleep(Duration::from_millis(10));
-        state.mark_db_persisted();
+        state.mark_db_persisted(Instant::now());
         let after = state.last_db_persist_time;
         
         assert!(after > before);
@@ -251,11 +516,183 @@ mod tests {
         let config = VesselStatusConfig {
             interval_moored_seconds: 600, // 10 minutes
             interval_underway_seconds: 30, // 30 seconds
+            ..Default::default()
         };
         let state = VesselStatusState::new(config);
         
         // First report should persist immediately (regardless of interval)
-        assert!(state.should_persist_to_db(true));
-        assert!(state.should_persist_to_db(false));
+        assert!(state.should_persist_to_db(true, Instant::now()));
+        assert!(state.should_persist_to_db(false, Instant::now()));
+    }
+
+    #[test]
+    fn test_status_buffered_without_database() {
+        let path = std::env::temp_dir().join(format!("nmea_router_status_buffer_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut handler = VesselStatusHandler::new(VesselStatusConfig::default());
+        handler.set_write_buffer(Some(WriteBuffer::new(&path)));
+
+        let status = VesselStatus { max_speed_kn: 5.0, ..make_status(43.5, 10.2, Instant::now()) };
+        assert!(handler.handle_vessel_status(&None, status).unwrap());
+
+        let buffered = WriteBuffer::new(&path).read_all().unwrap();
+        assert_eq!(buffered.len(), 1);
+        match &buffered[0] {
+            BufferedWrite::VesselStatus(op) => {
+                assert_eq!(op.latitude, 43.5);
+                assert_eq!(op.max_speed_kn, 5.0);
+            }
+            other => panic!("Unexpected entry {:?}", other),
+        }
+        // The trip is started in memory, to be created with the first report once connected
+        assert!(handler.state.current_trip.as_ref().is_some_and(|t| t.id.is_none()));
+        assert!(handler.state.offline_trip_changes);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    fn underway_status(latitude: f64, timestamp: Instant) -> VesselStatus {
+        VesselStatus { max_speed_kn: 6.0, ..make_status(latitude, 10.2, timestamp) }
+    }
+
+    #[test]
+    fn test_finalize_updates_trip_on_shutdown() {
+        let db: Arc<dyn Database> = Arc::new(crate::sqlite_db::SqliteDatabase::new(":memory:").unwrap());
+        let vessel_db = Some(db.clone());
+        let mut handler = VesselStatusHandler::new(VesselStatusConfig::default());
+        let start = Instant::now();
+
+        assert!(handler.handle_vessel_status(&vessel_db, underway_status(43.5, start)).unwrap());
+        let trip = db.get_last_trip().unwrap().unwrap();
+        assert_eq!(trip.total_distance(), 0.0);
+
+        // 10 seconds later, 0.02 nm north: within the reporting interval, not written
+        let last = underway_status(43.5 + 0.02 / 60.0, start + Duration::from_secs(10));
+        assert!(!handler.handle_vessel_status(&vessel_db, last.clone()).unwrap());
+
+        // Shutting down writes it anyway and closes the trip with it
+        assert!(handler.finalize(&vessel_db, Some(last)).unwrap());
+        let finalized = db.get_last_trip().unwrap().unwrap();
+        assert_eq!(finalized.id, trip.id);
+        assert!((finalized.total_distance_sailed - 0.02).abs() < 0.001, "Expected ~0.02 nm, got {}", finalized.total_distance_sailed);
+        assert!(finalized.end_timestamp > trip.end_timestamp);
+
+        // Nothing to write without a status
+        assert!(!handler.finalize(&vessel_db, None).unwrap());
+    }
+
+    #[test]
+    fn test_engine_in_neutral_not_motoring() {
+        use nmea2k::pgns::Gear;
+
+        let db: Arc<dyn Database> = Arc::new(crate::sqlite_db::SqliteDatabase::new(":memory:").unwrap());
+        let vessel_db = Some(db.clone());
+        let mut handler = VesselStatusHandler::new(VesselStatusConfig::default());
+        let start = Instant::now();
+        let status = |latitude: f64, seconds: u64, gear: Gear| VesselStatus {
+            engine_on: true,
+            gear: Some(gear),
+            ..underway_status(latitude, start + Duration::from_secs(seconds))
+        };
+
+        // Engine running in neutral, e.g. charging the batteries while sailing
+        assert!(handler.handle_vessel_status(&vessel_db, status(43.5, 0, Gear::Neutral)).unwrap());
+        assert!(handler.finalize(&vessel_db, Some(status(43.5 + 1.0 / 60.0, 600, Gear::Neutral))).unwrap());
+        let trip = db.get_last_trip().unwrap().unwrap();
+        assert!((trip.total_distance_sailed - 1.0).abs() < 0.01, "Expected ~1 nm sailed, got {}", trip.total_distance_sailed);
+        assert_eq!(trip.total_distance_motoring, 0.0);
+
+        // In gear
+        assert!(handler.finalize(&vessel_db, Some(status(43.5 + 2.0 / 60.0, 1200, Gear::Forward))).unwrap());
+        let trip = db.get_last_trip().unwrap().unwrap();
+        assert!((trip.total_distance_motoring - 1.0).abs() < 0.01, "Expected ~1 nm motoring, got {}", trip.total_distance_motoring);
+    }
+
+    #[test]
+    fn test_new_trip_after_inactivity_timeout() {
+        let db: Arc<dyn Database> = Arc::new(crate::sqlite_db::SqliteDatabase::new(":memory:").unwrap());
+        let vessel_db = Some(db.clone());
+        let config = VesselStatusConfig { inactivity_timeout_seconds: 3 * 3600, ..Default::default() };
+        let mut handler = VesselStatusHandler::new(config);
+        let start = Instant::now();
+        let last_trip_id = || db.get_last_trip().unwrap().unwrap().id;
+
+        assert!(handler.finalize(&vessel_db, Some(underway_status(43.5, start))).unwrap());
+        let first = last_trip_id();
+
+        // Just under the timeout: same trip
+        let resumed = start + Duration::from_secs(3 * 3600 - 1);
+        assert!(handler.finalize(&vessel_db, Some(underway_status(43.5, resumed))).unwrap());
+        assert_eq!(last_trip_id(), first);
+
+        // Just over the timeout since the last status: new trip
+        let next_day = resumed + Duration::from_secs(3 * 3600 + 1);
+        assert!(handler.finalize(&vessel_db, Some(underway_status(43.5, next_day))).unwrap());
+        assert_ne!(last_trip_id(), first);
+    }
+
+    #[test]
+    fn test_unmoved_status_skipped() {
+        let db: Arc<dyn Database> = Arc::new(crate::sqlite_db::SqliteDatabase::new(":memory:").unwrap());
+        let vessel_db = Some(db.clone());
+        let config = VesselStatusConfig {
+            interval_moored_seconds: 0,
+            interval_underway_seconds: 0,
+            min_track_distance_m: 50.0,
+            ..Default::default()
+        };
+        let mut handler = VesselStatusHandler::new(config);
+        let start = Instant::now();
+        let moored = |latitude: f64, seconds: u64| VesselStatus {
+            is_moored: true,
+            ..underway_status(latitude, start + Duration::from_secs(seconds))
+        };
+
+        assert!(handler.handle_vessel_status(&vessel_db, moored(43.5, 0)).unwrap());
+
+        // Wandering ~11 m: not written, the moored time is counted in memory
+        assert!(!handler.handle_vessel_status(&vessel_db, moored(43.5001, 600)).unwrap());
+        assert_eq!(handler.state.current_trip.as_ref().unwrap().total_time_moored, 600_000);
+        assert_eq!(db.get_last_trip().unwrap().unwrap().total_time_moored, 0);
+
+        // ~110 m from the last written point: written along with the trip
+        assert!(handler.handle_vessel_status(&vessel_db, moored(43.501, 1200)).unwrap());
+        assert_eq!(db.get_last_trip().unwrap().unwrap().total_time_moored, 1_200_000);
+        assert!(!handler.handle_vessel_status(&vessel_db, moored(43.501, 1800)).unwrap());
+
+        // Heartbeat: written after an hour even without moving
+        assert!(handler.handle_vessel_status(&vessel_db, moored(43.501, 1200 + 3600)).unwrap());
+        assert_eq!(db.get_last_trip().unwrap().unwrap().total_time_moored, 4_800_000);
+    }
+
+    #[test]
+    fn test_unmoved_status_written_without_min_distance() {
+        let db: Arc<dyn Database> = Arc::new(crate::sqlite_db::SqliteDatabase::new(":memory:").unwrap());
+        let vessel_db = Some(db.clone());
+        let config = VesselStatusConfig { interval_moored_seconds: 0, interval_underway_seconds: 0, ..Default::default() };
+        let mut handler = VesselStatusHandler::new(config);
+        let start = Instant::now();
+
+        assert!(handler.handle_vessel_status(&vessel_db, underway_status(43.5, start)).unwrap());
+        assert!(handler.handle_vessel_status(&vessel_db, underway_status(43.5, start + Duration::from_secs(30))).unwrap());
+    }
+
+    #[test]
+    fn test_glitch_segment_discarded() {
+        let db: Arc<dyn Database> = Arc::new(crate::sqlite_db::SqliteDatabase::new(":memory:").unwrap());
+        let vessel_db = Some(db.clone());
+        let mut handler = VesselStatusHandler::new(VesselStatusConfig::default());
+        let start = Instant::now();
+
+        // 1 nm in 10 minutes: kept
+        assert!(handler.finalize(&vessel_db, Some(underway_status(43.5, start))).unwrap());
+        assert!(handler.finalize(&vessel_db, Some(underway_status(43.5 + 1.0 / 60.0, start + Duration::from_secs(600)))).unwrap());
+        let trip = db.get_last_trip().unwrap().unwrap();
+        assert!((trip.total_distance_sailed - 1.0).abs() < 0.01, "Expected ~1 nm, got {}", trip.total_distance_sailed);
+
+        // 60 nm in 30 seconds: the distance is discarded, the time is kept
+        assert!(handler.finalize(&vessel_db, Some(underway_status(44.5 + 1.0 / 60.0, start + Duration::from_secs(630)))).unwrap());
+        let glitch = db.get_last_trip().unwrap().unwrap();
+        assert_eq!(glitch.total_distance_sailed, trip.total_distance_sailed);
+        assert_eq!(glitch.total_time_sailing, trip.total_time_sailing + 30000);
     }
 }
@@ -0,0 +1,144 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use nmea2k::{MessageHandler, N2kFrame};
+use tracing::{info, warn};
+
+/// Minimum time between two checks for silent sources
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Detects the sources that stop transmitting a PGN, e.g. a depth sounder switched off or disconnected
+///
+/// Each (PGN, source) seen on the bus is expected to keep transmitting within the timeout
+/// of its PGN. A warning is logged when it goes silent, and an info when it comes back.
+/// PGNs without a timeout are not watched.
+pub struct SourceWatchdog {
+    timeouts: HashMap<u32, Duration>,
+    last_seen: HashMap<(u32, u8), Instant>,
+    /// Sources already reported silent
+    silent: HashSet<(u32, u8)>,
+    last_check: Option<Instant>,
+}
+
+impl SourceWatchdog {
+    pub fn new(timeouts: HashMap<u32, Duration>) -> Self {
+        Self {
+            timeouts,
+            last_seen: HashMap::new(),
+            silent: HashSet::new(),
+            last_check: None,
+        }
+    }
+
+    /// Record a message of a source
+    /// Returns true if the source was silent and is transmitting again
+    pub fn observe(&mut self, pgn: u32, source: u8, now: Instant) -> bool {
+        if !self.timeouts.contains_key(&pgn) {
+            return false;
+        }
+        self.last_seen.insert((pgn, source), now);
+        let recovered = self.silent.remove(&(pgn, source));
+        if recovered {
+            info!("Source {} is transmitting PGN {} again", source, pgn);
+        }
+        recovered
+    }
+
+    /// Sources that did not transmit their PGN within its timeout, sorted by PGN and source
+    pub fn silent_sources(&self, now: Instant) -> Vec<(u32, u8)> {
+        let mut silent: Vec<(u32, u8)> = self.last_seen
+            .iter()
+            .filter(|((pgn, _), last)| now.saturating_duration_since(**last) >= self.timeouts[pgn])
+            .map(|(key, _)| *key)
+            .collect();
+        silent.sort_unstable();
+        silent
+    }
+
+    /// Log the sources that went silent since the last check, at most once per second
+    /// Returns the newly silent sources
+    pub fn check(&mut self, now: Instant) -> Vec<(u32, u8)> {
+        if self.last_check.is_some_and(|last| now.saturating_duration_since(last) < CHECK_INTERVAL) {
+            return Vec::new();
+        }
+        self.last_check = Some(now);
+
+        let newly_silent: Vec<(u32, u8)> = self.silent_sources(now)
+            .into_iter()
+            .filter(|key| !self.silent.contains(key))
+            .collect();
+        for (pgn, source) in &newly_silent {
+            warn!("Source {} stopped transmitting PGN {} (nothing received for {:?})", source, pgn, self.timeouts[pgn]);
+            self.silent.insert((*pgn, *source));
+        }
+        newly_silent
+    }
+}
+
+impl MessageHandler for SourceWatchdog {
+    fn handle_message(&mut self, frame: &N2kFrame, timestamp: Instant) {
+        self.observe(frame.pgn(), frame.source(), timestamp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEPTH: u32 = 128267;
+
+    fn watchdog() -> SourceWatchdog {
+        SourceWatchdog::new(HashMap::from([(DEPTH, Duration::from_secs(10))]))
+    }
+
+    #[test]
+    fn test_source_goes_silent_then_recovers() {
+        let mut watchdog = watchdog();
+        let start = Instant::now();
+        watchdog.observe(DEPTH, 35, start);
+        assert!(watchdog.check(start + Duration::from_secs(5)).is_empty());
+
+        // Silent for the timeout: reported once
+        let silent_at = start + Duration::from_secs(10);
+        assert_eq!(watchdog.silent_sources(silent_at), [(DEPTH, 35)]);
+        assert_eq!(watchdog.check(silent_at), [(DEPTH, 35)]);
+        assert!(watchdog.check(silent_at + Duration::from_secs(5)).is_empty());
+
+        // Transmitting again
+        let back_at = silent_at + Duration::from_secs(30);
+        assert!(watchdog.observe(DEPTH, 35, back_at));
+        assert!(watchdog.silent_sources(back_at).is_empty());
+        assert!(!watchdog.observe(DEPTH, 35, back_at + Duration::from_secs(1)));
+
+        // And silent again
+        assert_eq!(watchdog.check(back_at + Duration::from_secs(20)), [(DEPTH, 35)]);
+    }
+
+    #[test]
+    fn test_sources_tracked_independently() {
+        let mut watchdog = watchdog();
+        let start = Instant::now();
+        watchdog.observe(DEPTH, 35, start);
+        watchdog.observe(DEPTH, 36, start + Duration::from_secs(8));
+        assert_eq!(watchdog.check(start + Duration::from_secs(12)), [(DEPTH, 35)]);
+    }
+
+    #[test]
+    fn test_unwatched_pgn_ignored() {
+        let mut watchdog = watchdog();
+        let start = Instant::now();
+        watchdog.observe(130306, 5, start);
+        assert!(watchdog.silent_sources(start + Duration::from_secs(3600)).is_empty());
+    }
+
+    #[test]
+    fn test_check_rate_limited() {
+        let mut watchdog = watchdog();
+        let start = Instant::now();
+        watchdog.observe(DEPTH, 35, start);
+        assert!(watchdog.check(start + Duration::from_millis(9500)).is_empty());
+        // Within the check interval of the previous check
+        assert!(watchdog.check(start + Duration::from_millis(10200)).is_empty());
+        assert_eq!(watchdog.check(start + Duration::from_millis(10600)), [(DEPTH, 35)]);
+    }
+}
@@ -1,20 +1,44 @@
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    response::Json,
+    extract::{Query, Request, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
     routing::get,
     routing::post,
     Router,
 };
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
-use tracing::{info, error};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::{info, error, warn};
 use std::sync::Arc;
 
-use crate::db::{VesselDatabase, TripSummary, TrackPoint, WebMetricData};
+use crate::app_metrics::SharedMetricTotals;
+use crate::config::WebConfig;
+use crate::db::{CanErrorBucket, CurrentStatus, Database, MetricBucket, SharedDatabase, Session, TrackPaging, TripSummary, TrackPoint, VesselStats, WebMetricData};
+use crate::environmental_monitor::MetricId;
+use crate::flush::{FlushReport, FlushRequester};
+use crate::pgn_stats::SharedPgnRateTracker;
+use super::live::{get_live, LiveFeed};
+use super::units::{convert_metrics, convert_track, metric_unit, UnitSystem};
 
 #[derive(Clone)]
 pub struct AppState {
-    pub db: Arc<VesselDatabase>,
+    pub db: SharedDatabase,
+    pub admin_token: Option<String>,
+    pub flush: Option<FlushRequester>,
+    pub live: Option<LiveFeed>,
+    pub metrics: Option<SharedMetricTotals>,
+    pub pgn_rates: Option<SharedPgnRateTracker>,
+    /// Timezone of the local times added to trips and tracks
+    pub timezone: Tz,
+}
+
+impl AppState {
+    /// Current database connection, 503 while the database is unavailable
+    fn database(&self) -> Result<Arc<dyn Database>, StatusCode> {
+        self.db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?.clone().ok_or(StatusCode::SERVICE_UNAVAILABLE)
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -22,6 +46,9 @@ pub struct ApiResponse<T> {
     pub status: String,
     pub data: Option<T>,
     pub error: Option<String>,
+    /// Unit of the converted values, only set when a unit system was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<&'static str>,
 }
 
 impl<T> ApiResponse<T> {
@@ -30,14 +57,22 @@ impl<T> ApiResponse<T> {
             status: "ok".to_string(),
             data: Some(data),
             error: None,
+            unit: None,
         }
     }
 
+    /// Label the values of the response with their unit
+    pub fn with_unit(mut self, unit: Option<&'static str>) -> Self {
+        self.unit = unit;
+        self
+    }
+
     pub fn error(message: String) -> Self {
         Self {
             status: "error".to_string(),
             data: None,
             error: Some(message),
+            unit: None,
         }
     }
 }
@@ -55,11 +90,67 @@ pub struct TripDescriptionQuery {
     pub description: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct TripMergeRequest {
+    /// Trip kept, with the totals of both trips
+    pub primary_id: u32,
+    /// Trip merged into the primary one and deleted
+    pub secondary_id: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TripSplitRequest {
+    pub id: u32,
+    /// Split time in UTC ("YYYY-MM-DD HH:MM:SS")
+    pub at: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TrackQuery {
     pub trip_id: Option<u32>,
     pub start: Option<String>,
     pub end: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+    pub max_points: Option<u32>,
+    /// Unit system of the speeds, as stored (knots) if not set
+    pub units: Option<UnitSystem>,
+}
+
+impl TrackQuery {
+    pub fn paging(&self) -> TrackPaging {
+        TrackPaging {
+            limit: self.limit,
+            offset: self.offset,
+            max_points: self.max_points,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReplayQuery {
+    pub id: u32,
+    /// Playback speed multiplier (default 60, one minute per second)
+    pub speedup: Option<u32>,
+}
+
+/// Format of the trip export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// GPX track (`<trk>`), the recorded path of the vessel
+    #[default]
+    TrackGpx,
+    /// GPX route (`<rte>`), for plotters that import routes rather than tracks
+    RouteGpx,
+    /// CSV with one waypoint per line
+    Csv,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TripExportQuery {
+    pub id: u32,
+    pub format: Option<ExportFormat>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -68,23 +159,204 @@ pub struct MetricsQuery {
     pub trip_id: Option<u32>,
     pub start: Option<String>,
     pub end: Option<String>,
+    /// Unit system of the values, as stored if not set
+    pub units: Option<UnitSystem>,
+    /// Aggregate the rows per hour or day, raw rows if not set
+    pub bucket: Option<MetricBucket>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct TripsQuery {
     pub year: Option<i32>,
     pub last_months: Option<u32>,
+    /// Only the trips modified after this UTC time ("YYYY-MM-DD HH:MM:SS")
+    pub since: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DateRangeQuery {
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SessionStartRequest {
+    /// Session name, default "Session YYYY-MM-DD HH:MM" (UTC start time)
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SessionIdQuery {
+    pub id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SessionMetricsQuery {
+    pub id: i64,
+    pub metric: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsQuery {
+    pub year: Option<i32>,
+}
+
+/// Liveness/readiness check: 200 when the database answers, 503 otherwise
+pub async fn get_health(
+    State(state): State<AppState>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let Ok(db) = state.database() else {
+        warn!("Health check: database not connected");
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(ApiResponse::error("Database unavailable: not connected".to_string())));
+    };
+    // Blocking call, off the async runtime; a panicking check is reported as unhealthy
+    let result = tokio::task::spawn_blocking(move || db.health_check().map_err(|e| e.to_string()))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+    match result {
+        Ok(()) => (StatusCode::OK, Json(ApiResponse::ok(()))),
+        Err(e) => {
+            error!(error = %e, "Database health check failed");
+            (StatusCode::SERVICE_UNAVAILABLE, Json(ApiResponse::error(format!("Database unavailable: {}", e))))
+        }
+    }
+}
+
+/// Application counters in the Prometheus text exposition format
+pub async fn get_prometheus_metrics(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let totals = state.metrics.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let text = totals.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?.render_prometheus();
+    Ok(([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], text))
+}
+
+#[derive(Debug, Serialize)]
+pub struct PgnRate {
+    pub pgn: u32,
+    pub messages_per_second: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PgnRates {
+    /// Estimated CAN bus utilization in percent
+    pub bus_load_percent: f64,
+    pub rates: Vec<PgnRate>,
+}
+
+/// Arrival rate of each PGN since startup and current bus load
+pub async fn get_pgn_rates(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<PgnRates>>, StatusCode> {
+    info!("GET /api/pgn_rates called");
+    let tracker = state.pgn_rates.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let tracker = tracker.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let rates = tracker
+        .rates()
+        .into_iter()
+        .map(|(pgn, messages_per_second)| PgnRate { pgn, messages_per_second })
+        .collect();
+    Ok(Json(ApiResponse::ok(PgnRates { bus_load_percent: tracker.bus_load_percent(), rates })))
+}
+
+/// ETag of a list of trips, from the number of trips, the latest end and the trip descriptions
+/// A trip is extended with each status report, so the ETag changes whenever a trip is added,
+/// removed, extended or renamed
+pub fn trips_etag(trips: &[TripSummary]) -> String {
+    let latest_end = trips.iter().map(|trip| trip.end_date.as_str()).max().unwrap_or_default();
+    let latest_end: String = latest_end.chars().filter(char::is_ascii_digit).collect();
+    // FNV-1a of the ids and descriptions, stable across builds unlike the std hasher
+    let descriptions = trips.iter().fold(0xcbf29ce484222325_u64, |hash, trip| {
+        trip.id.to_le_bytes().iter()
+            .chain(trip.description.as_bytes())
+            .chain(&[0])
+            .fold(hash, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3))
+    });
+    format!("\"{}-{}-{:016x}\"", trips.len(), latest_end, descriptions)
+}
+
+/// Whether an `If-None-Match` header value matches the ETag (weak comparison)
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
 }
 
+/// Trips list, 304 Not Modified when the client already has it (`If-None-Match`)
 pub async fn get_trips(
     State(state): State<AppState>,
     Query(params): Query<TripsQuery>,
-) -> Result<Json<ApiResponse<Vec<TripSummary>>>, StatusCode> {
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
     info!(?params, "GET /api/trips called");
-    match state.db.fetch_trips(params.year, params.last_months) {
-        Ok(trips) => Ok(Json(ApiResponse::ok(trips))),
+    if let Some(since) = params.since.as_deref()
+        && chrono::NaiveDateTime::parse_from_str(since, "%Y-%m-%d %H:%M:%S").is_err() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    match state.database()?.fetch_trips(params.year, params.last_months, params.since.as_deref()) {
+        Ok(mut trips) => {
+            let etag = trips_etag(&trips);
+            let not_modified = headers
+                .get(header::IF_NONE_MATCH)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| etag_matches(value, &etag));
+            if not_modified {
+                return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+            }
+            trips.iter_mut().for_each(|trip| trip.localize(state.timezone));
+            Ok(([(header::ETAG, etag)], Json(ApiResponse::ok(trips))).into_response())
+        }
         Err(e) => {
             error!(error = %e, "Failed to fetch trips");
+            Ok(Json(ApiResponse::<Vec<TripSummary>>::error(e.to_string())).into_response())
+        }
+    }
+}
+
+pub async fn get_stats(
+    State(state): State<AppState>,
+    Query(params): Query<StatsQuery>,
+) -> Result<Json<ApiResponse<VesselStats>>, StatusCode> {
+    info!(?params, "GET /api/stats called");
+    match state.database()?.fetch_stats(params.year) {
+        Ok(stats) => Ok(Json(ApiResponse::ok(stats))),
+        Err(e) => {
+            error!(error = %e, "Failed to fetch stats");
+            Ok(Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+/// Newest vessel status and metric values, 204 No Content when nothing was recorded yet
+pub async fn get_current_status(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<CurrentStatus>>, StatusCode> {
+    info!("GET /api/current_status called");
+    match state.database()?.fetch_latest_status() {
+        Ok(current) if current.is_empty() => Err(StatusCode::NO_CONTENT),
+        Ok(mut current) => {
+            if let Some(status) = current.status.as_mut() {
+                status.localize(state.timezone);
+            }
+            Ok(Json(ApiResponse::ok(current)))
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to fetch the current status");
+            Ok(Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+pub async fn get_can_errors(
+    State(state): State<AppState>,
+    Query(params): Query<DateRangeQuery>,
+) -> Result<Json<ApiResponse<Vec<CanErrorBucket>>>, StatusCode> {
+    info!(?params, "GET /api/diagnostics/can_errors called");
+    match state.database()?.fetch_can_error_buckets(params.start.as_deref(), params.end.as_deref()) {
+        Ok(buckets) => Ok(Json(ApiResponse::ok(buckets))),
+        Err(e) => {
+            error!(error = %e, "Failed to fetch CAN error statistics");
             Ok(Json(ApiResponse::error(e.to_string())))
         }
     }
@@ -95,9 +367,10 @@ pub async fn get_trip(
     Query(params): Query<TripIdQuery>,
 ) -> Result<Json<ApiResponse<TripSummary>>, StatusCode> {
     info!(?params, "GET /api/trip called");
-    match state.db.fetch_trip(params.id) {
+    match state.database()?.fetch_trip(params.id) {
         Ok(res_trip) => {
-            if let Some(trip) = res_trip {
+            if let Some(mut trip) = res_trip {
+                trip.localize(state.timezone);
                 Ok(Json(ApiResponse::ok(trip)))
             } else {
                 error!(trip_id = params.id, "Trip not found");
@@ -111,17 +384,42 @@ pub async fn get_trip(
     }
 }
 
+pub async fn delete_trip(
+    State(state): State<AppState>,
+    Query(params): Query<TripIdQuery>,
+) -> Result<Json<ApiResponse<()>>, StatusCode> {
+    info!(?params, "DELETE /api/trip called");
+    match state.database()?.delete_trip(params.id as i64) {
+        Ok(true) => Ok(Json(ApiResponse::ok(()))),
+        Ok(false) => {
+            error!(trip_id = params.id, "Trip not found");
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to delete trip");
+            Ok(Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
 pub async fn get_track(
     State(state): State<AppState>,
     Query(params): Query<TrackQuery>,
 ) -> Result<Json<ApiResponse<Vec<TrackPoint>>>, StatusCode> {
     info!(?params, "GET /api/track called");
-    match state.db.fetch_track(
+    match state.database()?.fetch_track(
         params.trip_id,
         params.start.as_deref(),
         params.end.as_deref(),
+        params.paging(),
     ) {
-        Ok(track) => Ok(Json(ApiResponse::ok(track))),
+        Ok(mut track) => {
+            track.iter_mut().for_each(|point| point.localize(state.timezone));
+            if let Some(units) = params.units {
+                convert_track(&mut track, units);
+            }
+            Ok(Json(ApiResponse::ok(track).with_unit(params.units.map(UnitSystem::speed_unit))))
+        }
         Err(e) => {
             error!(error = %e, "Failed to fetch track");
             Ok(Json(ApiResponse::error(e.to_string())))
@@ -129,18 +427,267 @@ pub async fn get_track(
     }
 }
 
+/// Track timestamps are stored in UTC ("YYYY-MM-DD HH:MM:SS"), exports use RFC3339
+fn rfc3339(timestamp: &str) -> Option<String> {
+    chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|time| time.and_utc().to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+}
+
+const GPX_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+    <gpx version=\"1.1\" creator=\"nmea_router\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n";
+
+/// Render a track as a GPX 1.1 document with a single track segment
+pub fn track_to_gpx(track: &[TrackPoint]) -> String {
+    named_track_to_gpx("Track", track)
+}
+
+fn named_track_to_gpx(name: &str, track: &[TrackPoint]) -> String {
+    let mut gpx = format!("{}<trk>\n<name>{}</name>\n<trkseg>\n", GPX_HEADER, html_escape(name));
+    for point in track {
+        gpx.push_str(&format!("<trkpt lat=\"{:.6}\" lon=\"{:.6}\">", point.latitude, point.longitude));
+        if let Some(time) = rfc3339(&point.timestamp) {
+            gpx.push_str(&format!("<time>{}</time>", time));
+        }
+        gpx.push_str("</trkpt>\n");
+    }
+    gpx.push_str("</trkseg>\n</trk>\n</gpx>\n");
+    gpx
+}
+
+/// Name of the n-th waypoint of a route or CSV export
+fn waypoint_name(index: usize) -> String {
+    format!("WP{:03}", index + 1)
+}
+
+/// Render a track as a GPX 1.1 route, one named `<rtept>` per track point
+pub fn track_to_route_gpx(name: &str, track: &[TrackPoint]) -> String {
+    let mut gpx = format!("{}<rte>\n<name>{}</name>\n", GPX_HEADER, html_escape(name));
+    for (index, point) in track.iter().enumerate() {
+        gpx.push_str(&format!(
+            "<rtept lat=\"{:.6}\" lon=\"{:.6}\"><name>{}</name></rtept>\n",
+            point.latitude,
+            point.longitude,
+            waypoint_name(index)
+        ));
+    }
+    gpx.push_str("</rte>\n</gpx>\n");
+    gpx
+}
+
+/// Render a track as CSV waypoints: `name,latitude,longitude,time,speed_kn`
+/// Coordinates are decimal degrees, the time is RFC3339 (UTC) and the speed the average speed in knots
+pub fn track_to_csv(track: &[TrackPoint]) -> String {
+    let mut csv = String::from("name,latitude,longitude,time,speed_kn\n");
+    for (index, point) in track.iter().enumerate() {
+        csv.push_str(&format!(
+            "{},{:.6},{:.6},{},{:.1}\n",
+            waypoint_name(index),
+            point.latitude,
+            point.longitude,
+            rfc3339(&point.timestamp).unwrap_or_default(),
+            point.avg_speed_kn
+        ));
+    }
+    csv
+}
+
+pub async fn get_track_gpx(
+    State(state): State<AppState>,
+    Query(params): Query<TrackQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    info!(?params, "GET /api/track.gpx called");
+    match state.database()?.fetch_track(
+        params.trip_id,
+        params.start.as_deref(),
+        params.end.as_deref(),
+        params.paging(),
+    ) {
+        Ok(track) => Ok(([(header::CONTENT_TYPE, "application/gpx+xml")], track_to_gpx(&track))),
+        Err(e) => {
+            error!(error = %e, "Failed to fetch track");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Render a track as a GeoJSON FeatureCollection
+/// The first feature is the track LineString (when there are at least two points),
+/// followed by a Point feature per track point carrying its properties.
+/// Coordinates are `[longitude, latitude]` as required by GeoJSON.
+pub fn track_to_geojson(track: &[TrackPoint]) -> serde_json::Value {
+    let mut features = Vec::with_capacity(track.len() + 1);
+    if track.len() >= 2 {
+        let coordinates: Vec<[f64; 2]> = track.iter().map(|p| [p.longitude, p.latitude]).collect();
+        features.push(serde_json::json!({
+            "type": "Feature",
+            "geometry": { "type": "LineString", "coordinates": coordinates },
+            "properties": {
+                "start": track[0].timestamp,
+                "end": track[track.len() - 1].timestamp,
+            },
+        }));
+    }
+    for point in track {
+        features.push(serde_json::json!({
+            "type": "Feature",
+            "geometry": { "type": "Point", "coordinates": [point.longitude, point.latitude] },
+            "properties": {
+                "timestamp": point.timestamp,
+                "avg_speed_kn": point.avg_speed_kn,
+                "max_speed_kn": point.max_speed_kn,
+                "moored": point.moored,
+                "engine_on": point.engine_on,
+            },
+        }));
+    }
+    serde_json::json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+pub async fn get_track_geojson(
+    State(state): State<AppState>,
+    Query(params): Query<TrackQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    info!(?params, "GET /api/track.geojson called");
+    match state.database()?.fetch_track(
+        params.trip_id,
+        params.start.as_deref(),
+        params.end.as_deref(),
+        params.paging(),
+    ) {
+        Ok(track) => Ok(([(header::CONTENT_TYPE, "application/geo+json")], track_to_geojson(&track).to_string())),
+        Err(e) => {
+            error!(error = %e, "Failed to fetch track");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Page template of the trip replay export, `{{TITLE}}` and `{{DATA}}` are filled in server-side
+const REPLAY_TEMPLATE: &str = include_str!("replay_template.html");
+
+const DEFAULT_REPLAY_SPEEDUP: u32 = 60;
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render a standalone HTML page replaying the trip track
+/// The trip and the track are embedded as inline JSON, so the page works without a server
+pub fn trip_replay_html(trip: &TripSummary, track: &[TrackPoint], speedup: u32) -> String {
+    let data = serde_json::json!({
+        "trip": trip,
+        "track": track,
+        "speedup": speedup,
+    });
+    // Keep the JSON from closing the script element early
+    let data = data.to_string().replace("</", "<\\/");
+    REPLAY_TEMPLATE
+        .replace("{{TITLE}}", &html_escape(&trip.description))
+        .replace("{{DATA}}", &data)
+}
+
+pub async fn get_trip_replay(
+    State(state): State<AppState>,
+    Query(params): Query<ReplayQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    info!(?params, "GET /api/trip/replay.html called");
+    let trip = match state.database()?.fetch_trip(params.id) {
+        Ok(Some(trip)) => trip,
+        Ok(None) => {
+            error!(trip_id = params.id, "Trip not found");
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to fetch trip");
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let track = state.database()?.fetch_track(Some(params.id), None, None, TrackPaging::default()).map_err(|e| {
+        error!(error = %e, "Failed to fetch track");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let speedup = params.speedup.filter(|s| *s > 0).unwrap_or(DEFAULT_REPLAY_SPEEDUP);
+    let disposition = format!("attachment; filename=\"trip-{}.html\"", params.id);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/html; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        trip_replay_html(&trip, &track, speedup),
+    ))
+}
+
+/// Most chart plotters limit the number of points of a route, routes are downsampled to fit
+const MAX_ROUTE_POINTS: u32 = 250;
+
+pub async fn get_trip_export(
+    State(state): State<AppState>,
+    Query(params): Query<TripExportQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    info!(?params, "GET /api/trip/export called");
+    let trip = match state.database()?.fetch_trip(params.id) {
+        Ok(Some(trip)) => trip,
+        Ok(None) => {
+            error!(trip_id = params.id, "Trip not found");
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to fetch trip");
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let format = params.format.unwrap_or_default();
+    let paging = match format {
+        ExportFormat::RouteGpx => TrackPaging { max_points: Some(MAX_ROUTE_POINTS), ..TrackPaging::default() },
+        ExportFormat::TrackGpx | ExportFormat::Csv => TrackPaging::default(),
+    };
+    let track = state.database()?.fetch_track(Some(params.id), None, None, paging).map_err(|e| {
+        error!(error = %e, "Failed to fetch track");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let (content_type, extension, body) = match format {
+        ExportFormat::TrackGpx => ("application/gpx+xml", "gpx", named_track_to_gpx(&trip.description, &track)),
+        ExportFormat::RouteGpx => ("application/gpx+xml", "gpx", track_to_route_gpx(&trip.description, &track)),
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv", track_to_csv(&track)),
+    };
+    let disposition = format!("attachment; filename=\"trip-{}.{}\"", params.id, extension);
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    ))
+}
+
 pub async fn get_metrics(
     State(state): State<AppState>,
     Query(params): Query<MetricsQuery>,
 ) -> Result<Json<ApiResponse<Vec<WebMetricData>>>, StatusCode> {
     info!(?params, "GET /api/metrics called");
-    match state.db.fetch_metrics(
+    match state.database()?.fetch_metrics(
         &params.metric,
         params.trip_id,
         params.start.as_deref(),
         params.end.as_deref(),
+        params.bucket,
     ) {
-        Ok(metrics) => Ok(Json(ApiResponse::ok(metrics))),
+        Ok(mut metrics) => {
+            if let Some(units) = params.units {
+                convert_metrics(&mut metrics, units);
+            }
+            let unit = params.units.zip(MetricId::parse(&params.metric)).map(|(units, metric)| metric_unit(metric, units));
+            Ok(Json(ApiResponse::ok(metrics).with_unit(unit)))
+        }
         Err(e) => {
             error!(error = %e, "Failed to fetch metrics");
             Ok(Json(ApiResponse::error(e.to_string())))
@@ -148,6 +695,68 @@ pub async fn get_metrics(
     }
 }
 
+/// Quote a CSV field holding a separator, a quote or a line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Render metrics as CSV: `timestamp,metric_id,avg,max,min,count`
+/// Missing values are left empty
+pub fn metrics_to_csv(metrics: &[WebMetricData]) -> String {
+    let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+    let mut csv = String::from("timestamp,metric_id,avg,max,min,count\n");
+    for metric in metrics {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            csv_field(&metric.timestamp),
+            csv_field(&metric.metric_id),
+            optional(metric.avg_value),
+            optional(metric.max_value),
+            optional(metric.min_value),
+            metric.count.map(|c| c.to_string()).unwrap_or_default()
+        ));
+    }
+    csv
+}
+
+pub async fn get_metrics_csv(
+    State(state): State<AppState>,
+    Query(params): Query<MetricsQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    info!(?params, "GET /api/metrics.csv called");
+    let mut metrics = state.database()?.fetch_metrics(
+        &params.metric,
+        params.trip_id,
+        params.start.as_deref(),
+        params.end.as_deref(),
+        params.bucket,
+    ).map_err(|e| {
+        error!(error = %e, "Failed to fetch metrics");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if let Some(units) = params.units {
+        convert_metrics(&mut metrics, units);
+    }
+
+    // The metric name ends up in a header: keep the safe characters only
+    let name: String = params.metric
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .collect();
+    let disposition = format!("attachment; filename=\"metrics-{}.csv\"", name);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        metrics_to_csv(&metrics),
+    ))
+}
+
 pub async fn update_trip_description(
     State(state): State<AppState>,
     Json(params): Json<TripDescriptionQuery>,
@@ -155,7 +764,7 @@ pub async fn update_trip_description(
 
     info!(?params, "POST /api/trip_description called");
     
-    match state.db.update_trip_description(params.id as i64, &params.description) {
+    match state.database()?.update_trip_description(params.id as i64, &params.description) {
         Ok(()) => Ok(Json(ApiResponse::ok(()))),
         Err(e) => {
             error!(error = %e, "Failed to update trip description");
@@ -164,12 +773,1038 @@ pub async fn update_trip_description(
     }
 }
 
-pub fn create_api_router(state: AppState) -> Router {
-    Router::new()
+pub async fn merge_trips(
+    State(state): State<AppState>,
+    Json(params): Json<TripMergeRequest>,
+) -> Result<Json<ApiResponse<TripSummary>>, StatusCode> {
+    info!(?params, "POST /api/trip_merge called");
+    let db = state.database()?;
+    match db.merge_trips(params.primary_id as i64, params.secondary_id as i64) {
+        Ok(true) => Ok(trip_summary_response(db.as_ref(), state.timezone, params.primary_id)),
+        Ok(false) => {
+            error!(primary_id = params.primary_id, secondary_id = params.secondary_id, "Trip not found");
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to merge trips");
+            Ok(Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+pub async fn split_trip(
+    State(state): State<AppState>,
+    Json(params): Json<TripSplitRequest>,
+) -> Result<Json<ApiResponse<TripSummary>>, StatusCode> {
+    info!(?params, "POST /api/trip_split called");
+    let at = chrono::NaiveDateTime::parse_from_str(&params.at, "%Y-%m-%d %H:%M:%S")
+        .map_err(|_| StatusCode::BAD_REQUEST)?
+        .and_utc();
+    let db = state.database()?;
+    match db.split_trip(params.id as i64, at.into()) {
+        Ok(Some(new_trip_id)) => Ok(trip_summary_response(db.as_ref(), state.timezone, new_trip_id as u32)),
+        Ok(None) => {
+            error!(trip_id = params.id, "Trip not found");
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to split trip");
+            Ok(Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+/// Summary of a trip just changed, as the response of the merge and split
+fn trip_summary_response(db: &dyn Database, timezone: Tz, trip_id: u32) -> Json<ApiResponse<TripSummary>> {
+    match db.fetch_trip(trip_id) {
+        Ok(Some(mut trip)) => {
+            trip.localize(timezone);
+            Json(ApiResponse::ok(trip))
+        }
+        Ok(None) => Json(ApiResponse::error(format!("Trip {} not found", trip_id))),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+pub async fn start_session(
+    State(state): State<AppState>,
+    params: Option<Json<SessionStartRequest>>,
+) -> Result<Json<ApiResponse<Session>>, StatusCode> {
+    let now = std::time::SystemTime::now();
+    let name = params
+        .and_then(|Json(params)| params.name)
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| format!("Session {}", chrono::DateTime::<chrono::Utc>::from(now).format("%Y-%m-%d %H:%M")));
+    info!(name, "POST /api/session/start called");
+
+    match state.database()?.start_session(&name, now) {
+        Ok(session) => Ok(Json(ApiResponse::ok(session))),
+        Err(e) => {
+            error!(error = %e, "Failed to start session");
+            Ok(Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+pub async fn stop_session(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<Session>>, StatusCode> {
+    info!("POST /api/session/stop called");
+    match state.database()?.stop_session(std::time::SystemTime::now()) {
+        Ok(Some(session)) => Ok(Json(ApiResponse::ok(session))),
+        Ok(None) => Ok(Json(ApiResponse::error("No active session".to_string()))),
+        Err(e) => {
+            error!(error = %e, "Failed to stop session");
+            Ok(Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+pub async fn get_session(
+    State(state): State<AppState>,
+    Query(params): Query<SessionIdQuery>,
+) -> Result<Json<ApiResponse<Session>>, StatusCode> {
+    info!(?params, "GET /api/session called");
+    match state.database()?.fetch_session(params.id) {
+        Ok(Some(session)) => Ok(Json(ApiResponse::ok(session))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!(error = %e, "Failed to fetch session");
+            Ok(Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+pub async fn get_session_track(
+    State(state): State<AppState>,
+    Query(params): Query<SessionIdQuery>,
+) -> Result<Json<ApiResponse<Vec<TrackPoint>>>, StatusCode> {
+    info!(?params, "GET /api/session/track called");
+    match state.database()?.fetch_session_track(params.id) {
+        Ok(mut track) => {
+            track.iter_mut().for_each(|point| point.localize(state.timezone));
+            Ok(Json(ApiResponse::ok(track)))
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to fetch session track");
+            Ok(Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+pub async fn get_session_metrics(
+    State(state): State<AppState>,
+    Query(params): Query<SessionMetricsQuery>,
+) -> Result<Json<ApiResponse<Vec<WebMetricData>>>, StatusCode> {
+    info!(?params, "GET /api/session/metrics called");
+    match state.database()?.fetch_session_metrics(&params.metric, params.id) {
+        Ok(metrics) => Ok(Json(ApiResponse::ok(metrics))),
+        Err(e) => {
+            error!(error = %e, "Failed to fetch session metrics");
+            Ok(Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+/// Whether the bearer token of the request is the expected one
+/// Compared in constant time, not to leak the matching prefix through the response time
+fn bearer_token_matches(headers: &HeaderMap, expected: &str) -> bool {
+    let Some(provided) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    provided.len() == expected.len()
+        && provided.bytes().zip(expected.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Check the bearer token of an administrative request against the configured admin token
+fn check_admin_token(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let expected = match state.admin_token.as_deref() {
+        Some(token) if !token.is_empty() => token,
+        _ => return Err(StatusCode::FORBIDDEN), // Administrative endpoints disabled
+    };
+    if bearer_token_matches(headers, expected) {
+        Ok(())
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+pub async fn flush(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<FlushReport>>, StatusCode> {
+    info!("POST /api/flush called");
+    check_admin_token(&state, &headers)?;
+
+    let requester = state.flush.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    match requester.request().await {
+        Ok(report) => {
+            info!(?report, "Flush completed");
+            Ok(Json(ApiResponse::ok(report)))
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to flush");
+            Ok(Json(ApiResponse::error(e)))
+        }
+    }
+}
+
+/// API key required on the API routes, see `require_api_key`
+#[derive(Clone)]
+struct ApiKeyAuth {
+    key: String,
+    protect_reads: bool,
+}
+
+/// Reject the requests without `Authorization: Bearer <api key>` with 401
+/// Read requests (GET) pass unless the reads are protected as well
+async fn require_api_key(State(auth): State<ApiKeyAuth>, request: Request, next: Next) -> Result<Response, StatusCode> {
+    let is_read = matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    if is_read && !auth.protect_reads {
+        return Ok(next.run(request).await);
+    }
+    if bearer_token_matches(request.headers(), &auth.key) {
+        Ok(next.run(request).await)
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// CORS layer allowing the given origins to call the API, `*` allows any origin
+/// None without origins: browsers then only allow same-origin calls
+fn cors_layer(allowed_origins: &[String]) -> Option<CorsLayer> {
+    if allowed_origins.is_empty() {
+        return None;
+    }
+    let allow_origin = if allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::from(Any)
+    } else {
+        let origins: Vec<header::HeaderValue> = allowed_origins
+            .iter()
+            .filter_map(|origin| match origin.parse() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    warn!("Ignoring invalid CORS origin '{}'", origin);
+                    None
+                }
+            })
+            .collect();
+        AllowOrigin::list(origins)
+    };
+    Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([axum::http::Method::GET, axum::http::Method::POST, axum::http::Method::DELETE])
+            .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION]),
+    )
+}
+
+pub fn create_api_router(state: AppState, config: &WebConfig) -> Router {
+    let routes = Router::new()
+        .route("/live", get(get_live))
         .route("/trip_description", post(update_trip_description))
+        .route("/trip_merge", post(merge_trips))
+        .route("/trip_split", post(split_trip))
         .route("/trips", get(get_trips))
-        .route("/trip", get(get_trip))
+        .route("/stats", get(get_stats))
+        .route("/current_status", get(get_current_status))
+        .route("/diagnostics/can_errors", get(get_can_errors))
+        .route("/pgn_rates", get(get_pgn_rates))
+        .route("/trip", get(get_trip).delete(delete_trip))
+        .route("/trip/replay.html", get(get_trip_replay))
+        .route("/trip/export", get(get_trip_export))
         .route("/track", get(get_track))
+        .route("/track.gpx", get(get_track_gpx))
+        .route("/track.geojson", get(get_track_geojson))
         .route("/metrics", get(get_metrics))
-        .with_state(state)
+        .route("/metrics.csv", get(get_metrics_csv))
+        .route("/session", get(get_session))
+        .route("/session/start", post(start_session))
+        .route("/session/stop", post(stop_session))
+        .route("/session/track", get(get_session_track))
+        .route("/session/metrics", get(get_session_metrics));
+    let routes = match config.api_key.as_deref().filter(|key| !key.is_empty()) {
+        Some(key) => {
+            let auth = ApiKeyAuth { key: key.to_string(), protect_reads: config.api_key_protects_reads };
+            routes.route_layer(middleware::from_fn_with_state(auth, require_api_key))
+        }
+        None => routes,
+    };
+
+    // The health check stays open for monitoring, the flush has its own admin token
+    let router = Router::new()
+        .route("/health", get(get_health))
+        .route("/flush", post(flush))
+        .merge(routes)
+        .with_state(state);
+    match cors_layer(&config.cors_allowed_origins) {
+        Some(cors) => router.layer(cors),
+        None => router,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+    use std::time::SystemTime;
+    use crate::db::{TripOperation, VesselStatusOperation};
+    use crate::environmental_monitor::MetricData;
+    use crate::trip::Trip;
+
+    /// Database returning a fixed trip and track, everything else is unsupported
+    struct MockDatabase {
+        trip: Option<TripSummary>,
+        track: Vec<TrackPoint>,
+        healthy: bool,
+    }
+
+    impl Database for MockDatabase {
+        fn health_check(&self) -> Result<(), Box<dyn Error>> {
+            if self.healthy {
+                Ok(())
+            } else {
+                Err("connection pool exhausted".into())
+            }
+        }
+
+        fn update_trip_description(&self, _trip_id: i64, _new_description: &str) -> Result<(), Box<dyn Error>> {
+            Err("not supported".into())
+        }
+
+        fn delete_trip(&self, trip_id: i64) -> Result<bool, Box<dyn Error>> {
+            Ok(self.trip.as_ref().is_some_and(|t| t.id as i64 == trip_id))
+        }
+
+        fn fetch_trip_descriptions(&self, _base_description: &str) -> Result<Vec<String>, Box<dyn Error>> {
+            Err("not supported".into())
+        }
+
+        fn merge_trips(&self, _primary_id: i64, _secondary_id: i64) -> Result<bool, Box<dyn Error>> {
+            Err("not supported".into())
+        }
+
+        fn split_trip(&self, _trip_id: i64, _at: SystemTime) -> Result<Option<i64>, Box<dyn Error>> {
+            Err("not supported".into())
+        }
+
+        fn insert_status_and_trip(&self, _status_op: VesselStatusOperation, _trip_operation: TripOperation) -> Result<(i64, Option<i64>), Box<dyn Error>> {
+            Err("not supported".into())
+        }
+
+        fn insert_environmental_metrics(&self, _data: &MetricData, _metric_id: MetricId, _now: SystemTime) -> Result<Option<i64>, Box<dyn Error>> {
+            Err("not supported".into())
+        }
+
+        fn get_last_trip(&self) -> Result<Option<Trip>, Box<dyn Error>> {
+            Ok(None)
+        }
+
+        fn fetch_trip(&self, trip_id: u32) -> Result<Option<TripSummary>, Box<dyn Error>> {
+            Ok(self.trip.clone().filter(|t| t.id == trip_id))
+        }
+
+        fn fetch_trips(&self, _year: Option<i32>, _last_months: Option<u32>, since: Option<&str>) -> Result<Vec<TripSummary>, Box<dyn Error>> {
+            Ok(self.trip.clone().into_iter().filter(|t| since.is_none_or(|since| t.end_date.as_str() > since)).collect())
+        }
+
+        fn fetch_track(&self, trip_id: Option<u32>, start: Option<&str>, end: Option<&str>, paging: TrackPaging) -> Result<Vec<TrackPoint>, Box<dyn Error>> {
+            if trip_id.is_none() && (start.is_none() || end.is_none()) {
+                return Err("Either trip_id or both start and end timestamps are required".into());
+            }
+            let page = self.track.iter()
+                .skip(paging.offset.unwrap_or(0) as usize)
+                .take(paging.limit.map_or(usize::MAX, |l| l as usize))
+                .cloned()
+                .collect();
+            Ok(paging.downsample(page))
+        }
+
+        fn fetch_metrics(&self, metric: &str, _trip_id: Option<u32>, _start: Option<&str>, _end: Option<&str>, _bucket: Option<MetricBucket>) -> Result<Vec<WebMetricData>, Box<dyn Error>> {
+            Ok(vec![WebMetricData {
+                timestamp: "2024-06-01 10:00:00".to_string(),
+                metric_id: metric.to_string(),
+                avg_value: Some(5.2),
+                max_value: Some(7.8),
+                min_value: None,
+                count: Some(120),
+            }])
+        }
+
+        fn fetch_latest_status(&self) -> Result<CurrentStatus, Box<dyn Error>> {
+            let mut current = CurrentStatus { status: self.track.last().cloned(), ..Default::default() };
+            if current.status.is_some() {
+                current.metrics.insert("wind_speed".to_string(), self.fetch_metrics("5", None, None, None, None)?.remove(0));
+            }
+            Ok(current)
+        }
+
+        fn fetch_stats(&self, year: Option<i32>) -> Result<VesselStats, Box<dyn Error>> {
+            match year {
+                Some(2024) | None => Ok(VesselStats {
+                    trip_count: 3,
+                    total_distance_nm: 42.5,
+                    sailing_time_ms: 36_000_000,
+                    motoring_time_ms: 7_200_000,
+                    max_speed_kn: 8.4,
+                    days_underway: 3,
+                }),
+                Some(_) => Err("no data".into()),
+            }
+        }
+
+        fn insert_can_error_bucket(&self, _bucket: &CanErrorBucket) -> Result<(), Box<dyn Error>> {
+            Err("not supported".into())
+        }
+
+        fn fetch_can_error_buckets(&self, start: Option<&str>, _end: Option<&str>) -> Result<Vec<CanErrorBucket>, Box<dyn Error>> {
+            let buckets = [("2024-06-01 10:00:00", 36000, 4), ("2024-06-01 11:00:00", 35000, 0)];
+            Ok(buckets
+                .into_iter()
+                .filter(|(hour, _, _)| start.is_none_or(|start| *hour >= start))
+                .map(|(hour, can_frames, can_errors)| CanErrorBucket { hour: hour.to_string(), can_frames, can_errors })
+                .collect())
+        }
+
+        fn start_session(&self, _name: &str, _now: SystemTime) -> Result<Session, Box<dyn Error>> {
+            Err("not supported".into())
+        }
+
+        fn stop_session(&self, _now: SystemTime) -> Result<Option<Session>, Box<dyn Error>> {
+            Ok(None)
+        }
+
+        fn fetch_session(&self, _session_id: i64) -> Result<Option<Session>, Box<dyn Error>> {
+            Ok(None)
+        }
+
+        fn fetch_session_track(&self, _session_id: i64) -> Result<Vec<TrackPoint>, Box<dyn Error>> {
+            Ok(Vec::new())
+        }
+
+        fn fetch_session_metrics(&self, _metric: &str, _session_id: i64) -> Result<Vec<WebMetricData>, Box<dyn Error>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn point(timestamp: &str, latitude: f64, longitude: f64) -> TrackPoint {
+        TrackPoint {
+            timestamp: timestamp.to_string(),
+            latitude,
+            longitude,
+            avg_speed_kn: 5.0,
+            max_speed_kn: 6.0,
+            moored: false,
+            engine_on: false,
+            timestamp_local: None,
+        }
+    }
+
+    /// Application state of the handler tests, with the optional services not running
+    fn state_with_db(db: Arc<dyn Database>) -> AppState {
+        AppState {
+            db: Arc::new(std::sync::Mutex::new(Some(db))),
+            admin_token: None,
+            flush: None,
+            live: None,
+            metrics: None,
+            pgn_rates: None,
+            timezone: Tz::UTC,
+        }
+    }
+
+    fn state_with_trip(trip: Option<TripSummary>, track: Vec<TrackPoint>) -> AppState {
+        state_with_db(Arc::new(MockDatabase { trip, track, healthy: true }))
+    }
+
+    fn state_with_track(track: Vec<TrackPoint>) -> AppState {
+        state_with_trip(None, track)
+    }
+
+    /// Three points, one minute of a trip
+    fn sample_track() -> Vec<TrackPoint> {
+        Vec::from([
+            point("2024-06-01 10:00:00", 43.5, 10.2),
+            point("2024-06-01 10:00:30", 43.501, 10.201),
+            point("2024-06-01 10:01:00", 43.502, 10.202),
+        ])
+    }
+
+    async fn preflight(router: Router, origin: &str) -> axum::response::Response {
+        use tower::ServiceExt;
+
+        let request = axum::http::Request::builder()
+            .method(axum::http::Method::OPTIONS)
+            .uri("/trips")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        router.oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight_configured_origin() {
+        let config = WebConfig { cors_allowed_origins: vec!["http://chart.local:3000".to_string()], ..Default::default() };
+        let router = create_api_router(state_with_track(Vec::new()), &config);
+        let response = preflight(router.clone(), "http://chart.local:3000").await;
+        assert!(response.status().is_success());
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "http://chart.local:3000");
+
+        // Other origins are not allowed
+        let response = preflight(router, "http://elsewhere.example").await;
+        assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn test_cors_same_origin_by_default_and_wildcard() {
+        let router = create_api_router(state_with_track(Vec::new()), &WebConfig::default());
+        let response = preflight(router, "http://chart.local:3000").await;
+        assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        let config = WebConfig { cors_allowed_origins: vec!["*".to_string()], ..Default::default() };
+        let router = create_api_router(state_with_track(Vec::new()), &config);
+        let response = preflight(router, "http://chart.local:3000").await;
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    }
+
+    async fn send(router: Router, method: Method, uri: &str, authorization: Option<&str>) -> StatusCode {
+        use tower::ServiceExt;
+
+        let mut request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(authorization) = authorization {
+            request = request.header(header::AUTHORIZATION, authorization);
+        }
+        let body = axum::body::Body::from(r#"{"id": 1, "description": "Regatta"}"#);
+        router.oneshot(request.body(body).unwrap()).await.unwrap().status()
+    }
+
+    fn api_key_config(protect_reads: bool) -> WebConfig {
+        WebConfig { api_key: Some("s3cret".to_string()), api_key_protects_reads: protect_reads, ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_api_key_required_on_mutating_routes() {
+        let router = create_api_router(state_with_track(Vec::new()), &api_key_config(false));
+        let rename = |authorization| send(router.clone(), Method::POST, "/trip_description", authorization);
+        assert_eq!(rename(None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(rename(Some("Bearer wrong")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(rename(Some("s3cret")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(rename(Some("Bearer s3cret")).await, StatusCode::OK);
+
+        // Reads and the health check stay open
+        assert_eq!(send(router.clone(), Method::GET, "/trips", None).await, StatusCode::OK);
+        assert_eq!(send(router, Method::GET, "/health", None).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_protecting_reads() {
+        let router = create_api_router(state_with_track(Vec::new()), &api_key_config(true));
+        assert_eq!(send(router.clone(), Method::GET, "/trips", None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(send(router.clone(), Method::GET, "/trips", Some("Bearer wrong")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(send(router.clone(), Method::GET, "/trips", Some("Bearer s3cret")).await, StatusCode::OK);
+        assert_eq!(send(router.clone(), Method::GET, "/health", None).await, StatusCode::OK);
+
+        // The live feed upgrade is checked like the other reads
+        assert_eq!(send(router.clone(), Method::GET, "/live", None).await, StatusCode::UNAUTHORIZED);
+        assert_ne!(send(router, Method::GET, "/live", Some("Bearer s3cret")).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_bearer_token_matches() {
+        let headers = |authorization: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, authorization.parse().unwrap());
+            headers
+        };
+        assert!(bearer_token_matches(&headers("Bearer s3cret"), "s3cret"));
+        assert!(!bearer_token_matches(&headers("Bearer s3cre"), "s3cret"));
+        assert!(!bearer_token_matches(&headers("Bearer s3cret!"), "s3cret"));
+        assert!(!bearer_token_matches(&headers("Bearer S3cret"), "s3cret"));
+        assert!(!bearer_token_matches(&headers("s3cret"), "s3cret"));
+        assert!(!bearer_token_matches(&HeaderMap::new(), "s3cret"));
+    }
+
+    #[tokio::test]
+    async fn test_no_api_key_configured() {
+        let router = create_api_router(state_with_track(Vec::new()), &WebConfig::default());
+        assert_eq!(send(router, Method::POST, "/trip_description", None).await, StatusCode::OK);
+    }
+
+    async fn body_text(response: axum::response::Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_track_gpx() {
+        let state = state_with_track(sample_track());
+        let query = TrackQuery { trip_id: Some(1), start: None, end: None, limit: None, offset: None, max_points: None, units: None };
+        let response = get_track_gpx(State(state), Query(query)).await.unwrap().into_response();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/gpx+xml");
+
+        let gpx = body_text(response).await;
+        assert_eq!(gpx.matches("<trkpt ").count(), 3);
+        assert_eq!(gpx.matches("<trk>").count(), 1);
+        assert!(gpx.contains("<trkpt lat=\"43.500000\" lon=\"10.200000\"><time>2024-06-01T10:00:00Z</time></trkpt>"));
+    }
+
+    #[tokio::test]
+    async fn test_empty_track_gpx() {
+        let query = TrackQuery { trip_id: None, start: Some("2024-06-01 00:00:00".to_string()), end: Some("2024-06-02 00:00:00".to_string()), limit: None, offset: None, max_points: None, units: None };
+        let response = get_track_gpx(State(state_with_track(Vec::new())), Query(query)).await.unwrap().into_response();
+        let gpx = body_text(response).await;
+        assert_eq!(gpx.matches("<trkpt ").count(), 0);
+        assert!(gpx.contains("<trkseg>\n</trkseg>"));
+        assert!(gpx.trim_end().ends_with("</gpx>"));
+    }
+
+    #[tokio::test]
+    async fn test_track_gpx_without_range() {
+        let query = TrackQuery { trip_id: None, start: None, end: None, limit: None, offset: None, max_points: None, units: None };
+        let result = get_track_gpx(State(state_with_track(Vec::new())), Query(query)).await;
+        assert_eq!(result.err(), Some(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+
+    #[tokio::test]
+    async fn test_track_geojson() {
+        let state = state_with_track(Vec::from([
+            point("2024-06-01 10:00:00", 43.5, 10.2),
+            point("2024-06-01 10:00:30", 43.501, 10.201),
+        ]));
+        let query = TrackQuery { trip_id: Some(1), start: None, end: None, limit: None, offset: None, max_points: None, units: None };
+        let response = get_track_geojson(State(state), Query(query)).await.unwrap().into_response();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/geo+json");
+
+        let geojson: serde_json::Value = serde_json::from_str(&body_text(response).await).unwrap();
+        assert_eq!(geojson["type"], "FeatureCollection");
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 3);
+
+        // GeoJSON positions are [lon, lat]
+        let line = &features[0]["geometry"];
+        assert_eq!(line["type"], "LineString");
+        assert_eq!(line["coordinates"][0], serde_json::json!([10.2, 43.5]));
+        assert_eq!(line["coordinates"][1], serde_json::json!([10.201, 43.501]));
+        assert_eq!(features[1]["geometry"]["coordinates"], serde_json::json!([10.2, 43.5]));
+        assert_eq!(features[1]["properties"]["avg_speed_kn"], 5.0);
+        assert_eq!(features[1]["properties"]["moored"], false);
+    }
+
+    #[test]
+    fn test_empty_track_geojson() {
+        let geojson = track_to_geojson(&[]);
+        assert_eq!(geojson["type"], "FeatureCollection");
+        assert!(geojson["features"].as_array().unwrap().is_empty());
+    }
+
+    fn trip(id: u32, description: &str) -> TripSummary {
+        TripSummary {
+            id,
+            description: description.to_string(),
+            start_date: "2024-06-01 10:00:00".to_string(),
+            end_date: "2024-06-01 10:01:00".to_string(),
+            total_distance_nm: 0.1,
+            total_time_ms: 60000,
+            sailing_time_ms: 60000,
+            motoring_time_ms: 0,
+            moored_time_ms: 0,
+            sailing_distance_nm: 0.1,
+            motoring_distance_nm: 0.0,
+            start_date_local: None,
+            end_date_local: None,
+        }
+    }
+
+    #[test]
+    fn test_trips_etag() {
+        assert!(trips_etag(&[]).starts_with("\"0--"));
+        let mut later = trip(8, "Capraia");
+        later.end_date = "2024-06-02 18:30:00".to_string();
+        let mut trips = [later, trip(7, "Elba")];
+        let etag = trips_etag(&trips);
+        assert!(etag.starts_with("\"2-20240602183000-"), "Unexpected ETag {}", etag);
+        assert_eq!(trips_etag(&trips), etag);
+
+        assert!(etag_matches(&etag, &trips_etag(&trips)));
+        assert!(etag_matches(&format!("\"1-1\", W/{}", etag), &trips_etag(&trips)));
+        assert!(etag_matches("*", &trips_etag(&trips)));
+        assert!(!etag_matches("\"1-20240602183000\"", &trips_etag(&trips)));
+
+        // Renaming a trip changes the ETag
+        trips[1].description = "Elba and back".to_string();
+        assert_ne!(trips_etag(&trips), etag);
+    }
+
+    #[tokio::test]
+    async fn test_trips_not_modified() {
+        let state = state_with_trip(Some(trip(7, "Elba")), Vec::new());
+        let query = || Query(TripsQuery { year: None, last_months: None, since: None });
+
+        let response = get_trips(State(state.clone()), query(), HeaderMap::new()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG].clone();
+        assert!(etag.to_str().unwrap().starts_with("\"1-20240601100100-"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+        let response = get_trips(State(state.clone()), query(), headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag);
+
+        // A stale ETag gets the list
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, "\"0-\"".parse().unwrap());
+        let response = get_trips(State(state), query(), headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_trips_since() {
+        let state = state_with_trip(Some(trip(7, "Elba")), Vec::new());
+        let since = |since: &str| Query(TripsQuery { year: None, last_months: None, since: Some(since.to_string()) });
+
+        let response = get_trips(State(state.clone()), since("2024-06-01 10:00:30"), HeaderMap::new()).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"][0]["id"], 7);
+
+        let response = get_trips(State(state.clone()), since("2024-06-01 10:01:00"), HeaderMap::new()).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"], serde_json::json!([]));
+
+        let response = get_trips(State(state), since("yesterday"), HeaderMap::new()).await;
+        assert_eq!(response.unwrap_err(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_trip_in_local_time() {
+        let state = AppState {
+            timezone: "Etc/GMT-2".parse().unwrap(),
+            ..state_with_trip(Some(trip(7, "Elba")), Vec::new())
+        };
+        let response = get_trip(State(state), Query(TripIdQuery { id: 7 })).await.unwrap();
+        let json = serde_json::to_value(&response.0).unwrap();
+        assert_eq!(json["data"]["start_date"], "2024-06-01 10:00:00");
+        assert_eq!(json["data"]["start_date_local"], "2024-06-01 12:00:00");
+        assert_eq!(json["data"]["end_date_local"], "2024-06-01 12:01:00");
+    }
+
+    #[tokio::test]
+    async fn test_delete_trip() {
+        let state = state_with_trip(Some(trip(7, "Test")), Vec::new());
+        let response = delete_trip(State(state.clone()), Query(TripIdQuery { id: 7 })).await.unwrap();
+        assert_eq!(response.0.status, "ok");
+
+        let not_found = delete_trip(State(state), Query(TripIdQuery { id: 8 })).await;
+        assert_eq!(not_found.unwrap_err(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_split_trip_requires_valid_time() {
+        let state = state_with_track(Vec::new());
+        let request = TripSplitRequest { id: 7, at: "yesterday".to_string() };
+        let response = split_trip(State(state), Json(request)).await;
+        assert_eq!(response.unwrap_err(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_trip_replay_html() {
+        let state = state_with_trip(Some(trip(7, "Elba </script> & back")), sample_track());
+        let query = ReplayQuery { id: 7, speedup: None };
+        let response = get_trip_replay(State(state), Query(query)).await.unwrap().into_response();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/html; charset=utf-8");
+
+        let html = body_text(response).await;
+        assert!(html.contains("<title>Elba &lt;/script&gt; &amp; back</title>"));
+
+        // The embedded JSON is the content of the data script element
+        let start = html.find("<script id=\"replay-data\" type=\"application/json\">").unwrap();
+        let json = &html[start..];
+        let json = &json[json.find('>').unwrap() + 1..json.find("</script>").unwrap()];
+        let data: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(data["track"].as_array().unwrap().len(), 3);
+        assert_eq!(data["trip"]["description"], "Elba </script> & back");
+        assert_eq!(data["speedup"], 60);
+    }
+
+    #[tokio::test]
+    async fn test_trip_replay_unknown_trip() {
+        let query = ReplayQuery { id: 3, speedup: None };
+        let result = get_trip_replay(State(state_with_track(Vec::new())), Query(query)).await;
+        assert_eq!(result.err(), Some(StatusCode::NOT_FOUND));
+    }
+
+    fn export_state() -> AppState {
+        state_with_trip(Some(trip(7, "Elba & back")), sample_track())
+    }
+
+    async fn export(format: Option<ExportFormat>) -> (String, String, String) {
+        let query = TripExportQuery { id: 7, format };
+        let response = get_trip_export(State(export_state()), Query(query)).await.unwrap().into_response();
+        let content_type = response.headers()[header::CONTENT_TYPE].to_str().unwrap().to_string();
+        let disposition = response.headers()[header::CONTENT_DISPOSITION].to_str().unwrap().to_string();
+        (content_type, disposition, body_text(response).await)
+    }
+
+    #[tokio::test]
+    async fn test_trip_export_track_gpx() {
+        let (content_type, disposition, gpx) = export(None).await;
+        assert_eq!(content_type, "application/gpx+xml");
+        assert_eq!(disposition, "attachment; filename=\"trip-7.gpx\"");
+        assert!(gpx.contains("<trk>\n<name>Elba &amp; back</name>\n<trkseg>\n"));
+        assert_eq!(gpx.matches("<trkpt ").count(), 3);
+        assert!(!gpx.contains("<rte>"));
+        assert!(gpx.trim_end().ends_with("</trkseg>\n</trk>\n</gpx>"));
+    }
+
+    #[tokio::test]
+    async fn test_trip_export_route_gpx() {
+        let (content_type, _, gpx) = export(Some(ExportFormat::RouteGpx)).await;
+        assert_eq!(content_type, "application/gpx+xml");
+        assert!(gpx.contains("<rte>\n<name>Elba &amp; back</name>\n"));
+        assert_eq!(gpx.matches("<rtept ").count(), 3);
+        assert!(gpx.contains("<rtept lat=\"43.500000\" lon=\"10.200000\"><name>WP001</name></rtept>"));
+        assert!(gpx.contains("<name>WP003</name>"));
+        assert!(!gpx.contains("<trk>"));
+        assert!(gpx.trim_end().ends_with("</rte>\n</gpx>"));
+    }
+
+    #[tokio::test]
+    async fn test_trip_export_csv() {
+        let (content_type, disposition, csv) = export(Some(ExportFormat::Csv)).await;
+        assert_eq!(content_type, "text/csv; charset=utf-8");
+        assert_eq!(disposition, "attachment; filename=\"trip-7.csv\"");
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "name,latitude,longitude,time,speed_kn");
+        assert_eq!(lines[1], "WP001,43.500000,10.200000,2024-06-01T10:00:00Z,5.0");
+        assert!(lines.iter().all(|line| line.split(',').count() == 5));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_csv() {
+        let query = MetricsQuery {
+            metric: "wind_speed".to_string(),
+            trip_id: Some(7),
+            start: None,
+            end: None,
+            units: None,
+            bucket: None,
+        };
+        let response = get_metrics_csv(State(state_with_track(Vec::new())), Query(query)).await.unwrap().into_response();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/csv; charset=utf-8");
+        assert_eq!(response.headers()[header::CONTENT_DISPOSITION], "attachment; filename=\"metrics-wind_speed.csv\"");
+        let csv = body_text(response).await;
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines, ["timestamp,metric_id,avg,max,min,count", "2024-06-01 10:00:00,wind_speed,5.2,7.8,,120"]);
+    }
+
+    #[tokio::test]
+    async fn test_current_status() {
+        let state = state_with_track(Vec::from([
+            point("2024-06-01 10:00:00", 43.5, 10.2),
+            point("2024-06-01 10:00:30", 43.501, 10.201),
+        ]));
+        let Json(response) = get_current_status(State(state)).await.unwrap();
+        let current = response.data.unwrap();
+        let status = current.status.unwrap();
+        assert_eq!(status.timestamp, "2024-06-01 10:00:30");
+        assert_eq!(status.latitude, 43.501);
+        assert_eq!(current.metrics["wind_speed"].avg_value, Some(5.2));
+    }
+
+    #[tokio::test]
+    async fn test_current_status_empty_database() {
+        let result = get_current_status(State(state_with_track(Vec::new()))).await;
+        assert_eq!(result.err(), Some(StatusCode::NO_CONTENT));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_units() {
+        let query = MetricsQuery {
+            metric: "wind_speed".to_string(),
+            trip_id: Some(7),
+            start: None,
+            end: None,
+            units: Some(UnitSystem::Metric),
+            bucket: None,
+        };
+        let Json(response) = get_metrics(State(state_with_track(Vec::new())), Query(query)).await.unwrap();
+        let metrics = response.data.unwrap();
+        assert!((metrics[0].avg_value.unwrap() - 5.2 * 0.514444).abs() < 1e-9);
+        assert!((metrics[0].max_value.unwrap() - 7.8 * 0.514444).abs() < 1e-9);
+        assert_eq!(metrics[0].count, Some(120));
+        assert_eq!(response.unit, Some("m/s"));
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("wind_speed"), "wind_speed");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[tokio::test]
+    async fn test_trip_export_unknown_trip() {
+        let query = TripExportQuery { id: 3, format: Some(ExportFormat::Csv) };
+        let result = get_trip_export(State(state_with_track(Vec::new())), Query(query)).await;
+        assert_eq!(result.err(), Some(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let state = state_with_track(Vec::new());
+        let Json(response) = get_stats(State(state.clone()), Query(StatsQuery { year: Some(2024) })).await.unwrap();
+        let stats = response.data.unwrap();
+        assert_eq!(stats.trip_count, 3);
+        assert_eq!(stats.days_underway, 3);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["total_distance_nm"], 42.5);
+        assert_eq!(json["max_speed_kn"], 8.4);
+
+        let Json(response) = get_stats(State(state), Query(StatsQuery { year: Some(1999) })).await.unwrap();
+        assert_eq!(response.status, "error");
+        assert!(response.data.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_track_paging() {
+        let track = (0..10).map(|i| point(&format!("2024-06-01 10:00:{:02}", i), 43.5, 10.2)).collect();
+        let state = state_with_track(track);
+        let query = |limit, offset, max_points| TrackQuery { trip_id: Some(1), start: None, end: None, limit, offset, max_points, units: None };
+
+        let Json(response) = get_track(State(state.clone()), Query(query(None, None, None))).await.unwrap();
+        assert_eq!(response.data.unwrap().len(), 10);
+
+        let Json(response) = get_track(State(state.clone()), Query(query(Some(3), Some(4), None))).await.unwrap();
+        let timestamps: Vec<String> = response.data.unwrap().into_iter().map(|p| p.timestamp).collect();
+        assert_eq!(timestamps, ["2024-06-01 10:00:04", "2024-06-01 10:00:05", "2024-06-01 10:00:06"]);
+
+        let Json(response) = get_track(State(state), Query(query(None, None, Some(5)))).await.unwrap();
+        let timestamps: Vec<String> = response.data.unwrap().into_iter().map(|p| p.timestamp).collect();
+        assert_eq!(timestamps, ["2024-06-01 10:00:00", "2024-06-01 10:00:02", "2024-06-01 10:00:04", "2024-06-01 10:00:06", "2024-06-01 10:00:08"]);
+    }
+
+    #[tokio::test]
+    async fn test_track_units() {
+        let state = state_with_track(Vec::from([point("2024-06-01 10:00:00", 43.5, 10.2)]));
+        let query = |units| TrackQuery { trip_id: Some(1), start: None, end: None, limit: None, offset: None, max_points: None, units };
+
+        let Json(response) = get_track(State(state.clone()), Query(query(None))).await.unwrap();
+        assert!(serde_json::to_value(&response).unwrap().get("unit").is_none());
+
+        let Json(response) = get_track(State(state), Query(query(Some(UnitSystem::Imperial)))).await.unwrap();
+        assert_eq!(response.unit, Some("mph"));
+        assert_eq!(serde_json::to_value(&response).unwrap()["unit"], "mph");
+    }
+
+    #[tokio::test]
+    async fn test_can_errors() {
+        let state = state_with_track(Vec::new());
+        let Json(response) = get_can_errors(State(state.clone()), Query(DateRangeQuery { start: None, end: None })).await.unwrap();
+        let buckets = response.data.unwrap();
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].can_errors, 4);
+
+        let Json(response) = get_can_errors(State(state), Query(DateRangeQuery { start: Some("2024-06-01 10:30:00".to_string()), end: None })).await.unwrap();
+        let json = serde_json::to_value(response.data.unwrap()).unwrap();
+        assert_eq!(json, serde_json::json!([{"hour": "2024-06-01 11:00:00", "can_frames": 35000, "can_errors": 0}]));
+    }
+
+    #[tokio::test]
+    async fn test_session_start_stop() {
+        let db = Arc::new(crate::sqlite_db::SqliteDatabase::new(":memory:").unwrap());
+        let state = state_with_db(db.clone());
+        let request = SessionStartRequest { name: Some("Race leg 1".to_string()) };
+        let Json(response) = start_session(State(state.clone()), Some(Json(request))).await.unwrap();
+        let session = response.data.unwrap();
+        assert_eq!(session.name, "Race leg 1");
+
+        let pressure = MetricData { avg: Some(101300.0), max: Some(101300.0), min: Some(101300.0), count: Some(1) };
+        db.insert_environmental_metrics(&pressure, MetricId::Pressure, SystemTime::now()).unwrap();
+
+        let Json(response) = stop_session(State(state.clone())).await.unwrap();
+        assert_eq!(response.data.unwrap().id, session.id);
+        let Json(response) = stop_session(State(state.clone())).await.unwrap();
+        assert_eq!(response.error.as_deref(), Some("No active session"));
+
+        let query = SessionMetricsQuery { id: session.id, metric: "pressure".to_string() };
+        let Json(response) = get_session_metrics(State(state.clone()), Query(query)).await.unwrap();
+        assert_eq!(response.data.unwrap().len(), 1);
+        let Json(response) = get_session(State(state.clone()), Query(SessionIdQuery { id: session.id })).await.unwrap();
+        assert!(response.data.unwrap().end_date.is_some());
+        assert_eq!(get_session(State(state.clone()), Query(SessionIdQuery { id: 99 })).await.err(), Some(StatusCode::NOT_FOUND));
+
+        // Default name
+        let Json(response) = start_session(State(state), None).await.unwrap();
+        assert!(response.data.unwrap().name.starts_with("Session "));
+    }
+
+    #[tokio::test]
+    async fn test_health() {
+        let (status, Json(response)) = get_health(State(state_with_track(Vec::new()))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.status, "ok");
+
+        let state = state_with_db(Arc::new(MockDatabase { trip: None, track: Vec::new(), healthy: false }));
+        let (status, Json(response)) = get_health(State(state)).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.status, "error");
+        assert!(response.error.unwrap().contains("connection pool exhausted"));
+
+        // Not connected, e.g. database down at startup, until the main loop reconnects
+        let state = state_with_track(Vec::new());
+        let connection = state.db.lock().unwrap().take();
+        let (status, _) = get_health(State(state.clone())).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        let result = get_trips(State(state.clone()), Query(TripsQuery { year: None, last_months: None, since: None }), HeaderMap::new()).await;
+        assert_eq!(result.err(), Some(StatusCode::SERVICE_UNAVAILABLE));
+
+        *state.db.lock().unwrap() = connection;
+        let (status, _) = get_health(State(state)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_metrics() {
+        let totals = crate::app_metrics::MetricTotals { can_frames: 5, ..Default::default() };
+        let state = AppState { metrics: Some(Arc::new(std::sync::Mutex::new(totals))), ..state_with_track(Vec::new()) };
+        let response = get_prometheus_metrics(State(state)).await.unwrap().into_response();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/plain; version=0.0.4");
+        assert!(body_text(response).await.contains("nmea_can_frames_total 5\n"));
+
+        let result = get_prometheus_metrics(State(state_with_track(Vec::new()))).await;
+        assert_eq!(result.err(), Some(StatusCode::SERVICE_UNAVAILABLE));
+    }
+
+    #[tokio::test]
+    async fn test_pgn_rates() {
+        let mut tracker = crate::pgn_stats::PgnRateTracker::default();
+        let start = std::time::Instant::now();
+        for i in 0..=4 {
+            tracker.record(127250, start + std::time::Duration::from_millis(i * 100));
+        }
+        tracker.set_bus_load_percent(12.5);
+        let state = AppState { pgn_rates: Some(Arc::new(std::sync::Mutex::new(tracker))), ..state_with_track(Vec::new()) };
+        let Json(response) = get_pgn_rates(State(state)).await.unwrap();
+        let rates = response.data.unwrap();
+        assert_eq!(rates.bus_load_percent, 12.5);
+        let rates = rates.rates;
+        assert_eq!(rates.len(), 1);
+        assert_eq!(rates[0].pgn, 127250);
+        assert!((rates[0].messages_per_second - 10.0).abs() < 1e-9);
+
+        let result = get_pgn_rates(State(state_with_track(Vec::new()))).await;
+        assert_eq!(result.err(), Some(StatusCode::SERVICE_UNAVAILABLE));
+    }
 }
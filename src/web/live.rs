@@ -0,0 +1,141 @@
+use std::time::Instant;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use nmea2k::pgns::N2kMessage;
+use nmea2k::{MessageHandler, N2kFrame};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, info, warn};
+
+use crate::udp_broadcaster::message_to_json;
+use super::api::AppState;
+
+/// Messages buffered for each WebSocket client, a client falling further behind skips messages
+pub const LIVE_FEED_CAPACITY: usize = 1024;
+
+/// Live feed of the decoded messages, published by the main loop and streamed to WebSocket clients
+///
+/// Messages are serialized once, in the same JSON format as the UDP broadcaster,
+/// and only when at least one client is connected.
+#[derive(Clone)]
+pub struct LiveFeed {
+    sender: broadcast::Sender<String>,
+}
+
+impl LiveFeed {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.sender.subscribe()
+    }
+
+    /// Number of clients currently subscribed
+    pub fn client_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Serialize a message and send it to all the subscribed clients
+    pub fn publish(&self, message: &N2kMessage, source: u8, priority: u8) {
+        if self.client_count() == 0 {
+            return;
+        }
+        match message_to_json(message, source, priority) {
+            // Sending fails only when the last client disconnected in the meantime
+            Ok(json) => {
+                let _ = self.sender.send(json);
+            }
+            Err(e) => debug!("Failed to serialize live message: {}", e),
+        }
+    }
+}
+
+impl MessageHandler for LiveFeed {
+    fn handle_message(&mut self, frame: &N2kFrame, _timestamp: Instant) {
+        self.publish(&frame.message, frame.source(), frame.priority());
+    }
+}
+
+pub async fn get_live(
+    State(state): State<AppState>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    match state.live {
+        Some(feed) => ws.on_upgrade(move |socket| stream_live(socket, feed.subscribe())).into_response(),
+        None => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+/// Forward the feed to a WebSocket client until either side closes
+async fn stream_live(mut socket: WebSocket, mut receiver: broadcast::Receiver<String>) {
+    info!("Live feed client connected");
+    loop {
+        match receiver.recv().await {
+            Ok(json) => {
+                if socket.send(Message::Text(json)).await.is_err() {
+                    break;
+                }
+            }
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Live feed client too slow, {} messages dropped", skipped);
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+    info!("Live feed client disconnected");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nmea2k::pgns::PositionRapidUpdate;
+
+    fn position() -> N2kMessage {
+        N2kMessage::PositionRapidUpdate(PositionRapidUpdate::new(43.5, 10.2))
+    }
+
+    #[tokio::test]
+    async fn test_published_message_is_received() {
+        let feed = LiveFeed::new(LIVE_FEED_CAPACITY);
+        let mut receiver = feed.subscribe();
+
+        feed.publish(&position(), 0x16, 2);
+
+        let json: serde_json::Value = serde_json::from_str(&receiver.recv().await.unwrap()).unwrap();
+        assert_eq!(json["message_type"], "PositionRapidUpdate");
+        assert_eq!(json["pgn"], 129025);
+        assert_eq!(json["source"], 0x16);
+        assert_eq!(json["data"]["latitude"], 43.5);
+    }
+
+    #[tokio::test]
+    async fn test_lagging_client_skips_messages() {
+        let feed = LiveFeed::new(2);
+        let mut receiver = feed.subscribe();
+        for _ in 0..3 {
+            feed.publish(&position(), 0x16, 2);
+        }
+
+        assert!(matches!(receiver.recv().await, Err(RecvError::Lagged(1))));
+        assert!(receiver.recv().await.is_ok());
+        assert!(receiver.recv().await.is_ok());
+    }
+
+    #[test]
+    fn test_no_clients() {
+        let feed = LiveFeed::new(LIVE_FEED_CAPACITY);
+        assert_eq!(feed.client_count(), 0);
+        // Nothing is buffered without subscribers
+        feed.publish(&position(), 0x16, 2);
+        let mut receiver = feed.subscribe();
+        assert!(matches!(receiver.try_recv(), Err(broadcast::error::TryRecvError::Empty)));
+    }
+}
@@ -1,4 +1,7 @@
 pub mod api;
+pub mod live;
 pub mod server;
+pub mod units;
 
+pub use live::{LiveFeed, LIVE_FEED_CAPACITY};
 pub use server::start_web_server;
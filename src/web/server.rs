@@ -1,36 +1,52 @@
 use axum::{
     Router,
-    routing::get_service,
+    routing::{get, get_service},
 };
-use std::net::SocketAddr;
-use std::sync::Arc;
+use chrono_tz::Tz;
 use tower_http::services::ServeDir;
-use tower_http::cors::{CorsLayer, Any};
 
-use crate::db::VesselDatabase;
-use super::api::{AppState, create_api_router};
+use crate::app_metrics::SharedMetricTotals;
+use crate::config::WebConfig;
+use crate::db::SharedDatabase;
+use crate::flush::FlushRequester;
+use crate::pgn_stats::SharedPgnRateTracker;
+use super::live::LiveFeed;
+use super::api::{AppState, create_api_router, get_prometheus_metrics};
 
 pub async fn start_web_server(
-    db: Arc<VesselDatabase>,
-    port: u16,
+    db: SharedDatabase,
+    config: WebConfig,
+    flush: FlushRequester,
+    live: LiveFeed,
+    metrics: SharedMetricTotals,
+    pgn_rates: SharedPgnRateTracker,
+    timezone: Tz,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let state = AppState { db };
+    let addr = config.socket_addr();
+    let state = AppState {
+        db,
+        admin_token: config.admin_token.clone(),
+        flush: Some(flush),
+        live: Some(live),
+        metrics: Some(metrics),
+        pgn_rates: Some(pgn_rates),
+        timezone,
+    };
+
+    // Prometheus scrapes /metrics by default
+    let metrics_router = Router::new()
+        .route("/metrics", get(get_prometheus_metrics))
+        .with_state(state.clone());
 
     // Create API router
-    let api_router = create_api_router(state);
+    let api_router = create_api_router(state, &config);
 
     // Create main app router with static file serving
     let app = Router::new()
         .nest("/api", api_router)
-        .nest_service("/", get_service(ServeDir::new("static")))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        );
+        .merge(metrics_router)
+        .nest_service("/", get_service(ServeDir::new("static")));
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
     tracing::info!("Web server starting on http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
@@ -0,0 +1,191 @@
+use serde::Deserialize;
+
+use crate::db::{TrackPoint, WebMetricData};
+use crate::environmental_monitor::MetricId;
+
+const KN_TO_MS: f64 = 0.514444;
+const KN_TO_MPH: f64 = 1.150779;
+const PA_PER_HPA: f64 = 100.0;
+const PA_PER_INHG: f64 = 3386.389;
+
+/// Unit system requested with the `units` query parameter
+/// Without it the values are returned as stored: speeds in knots, temperatures in Celsius,
+/// pressure in Pascals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitSystem {
+    /// m/s, Celsius, hPa
+    Metric,
+    /// mph, Fahrenheit, inHg
+    Imperial,
+    /// knots, Celsius, hPa
+    Nautical,
+}
+
+impl UnitSystem {
+    /// Unit of the converted speeds
+    pub fn speed_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "m/s",
+            UnitSystem::Imperial => "mph",
+            UnitSystem::Nautical => "kn",
+        }
+    }
+
+    /// Convert a speed in knots
+    pub fn speed(self, knots: f64) -> f64 {
+        match self {
+            UnitSystem::Metric => knots * KN_TO_MS,
+            UnitSystem::Imperial => knots * KN_TO_MPH,
+            UnitSystem::Nautical => knots,
+        }
+    }
+
+    /// Convert a temperature in Celsius
+    pub fn temperature(self, celsius: f64) -> f64 {
+        match self {
+            UnitSystem::Imperial => celsius * 9.0 / 5.0 + 32.0,
+            UnitSystem::Metric | UnitSystem::Nautical => celsius,
+        }
+    }
+
+    /// Convert a pressure in Pascals
+    pub fn pressure(self, pascals: f64) -> f64 {
+        match self {
+            UnitSystem::Imperial => pascals / PA_PER_INHG,
+            UnitSystem::Metric | UnitSystem::Nautical => pascals / PA_PER_HPA,
+        }
+    }
+}
+
+/// Conversion of the values of a metric, None if the metric is unit-independent (e.g. angles)
+fn metric_conversion(metric: MetricId) -> Option<fn(UnitSystem, f64) -> f64> {
+    match metric {
+        MetricId::WindSpeed | MetricId::WindGust => Some(UnitSystem::speed),
+        MetricId::CabinTemp | MetricId::WaterTemp => Some(UnitSystem::temperature),
+        MetricId::Pressure => Some(UnitSystem::pressure),
+        _ => None,
+    }
+}
+
+/// Unit of the values of a metric once converted, the stored unit if the metric is unit-independent
+pub fn metric_unit(metric: MetricId, units: UnitSystem) -> &'static str {
+    match (metric, units) {
+        (MetricId::WindSpeed | MetricId::WindGust, _) => units.speed_unit(),
+        (MetricId::CabinTemp | MetricId::WaterTemp, UnitSystem::Imperial) => "F",
+        (MetricId::Pressure, UnitSystem::Imperial) => "inHg",
+        (MetricId::Pressure, _) => "hPa",
+        _ => metric.unit(),
+    }
+}
+
+/// Convert the speeds of the track points
+/// The fields keep their `_kn` names, the unit is labeled on the response
+pub fn convert_track(track: &mut [TrackPoint], units: UnitSystem) {
+    for point in track {
+        point.avg_speed_kn = units.speed(point.avg_speed_kn);
+        point.max_speed_kn = units.speed(point.max_speed_kn);
+    }
+}
+
+/// Convert the values of the metric rows, according to the unit of their metric
+pub fn convert_metrics(metrics: &mut [WebMetricData], units: UnitSystem) {
+    for row in metrics {
+        let Some(convert) = MetricId::parse(&row.metric_id).and_then(metric_conversion) else {
+            continue;
+        };
+        row.avg_value = row.avg_value.map(|v| convert(units, v));
+        row.max_value = row.max_value.map(|v| convert(units, v));
+        row.min_value = row.min_value.map(|v| convert(units, v));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metric_row(metric: MetricId, value: f64) -> WebMetricData {
+        WebMetricData {
+            timestamp: "2024-06-01 10:00:00".to_string(),
+            metric_id: metric.as_u8().to_string(),
+            avg_value: Some(value),
+            max_value: Some(value),
+            min_value: None,
+            count: Some(10),
+        }
+    }
+
+    #[test]
+    fn test_unit_system_names() {
+        assert_eq!(serde_json::from_str::<UnitSystem>("\"imperial\"").unwrap(), UnitSystem::Imperial);
+        assert_eq!(serde_json::from_str::<UnitSystem>("\"nautical\"").unwrap(), UnitSystem::Nautical);
+        assert!(serde_json::from_str::<UnitSystem>("\"furlongs\"").is_err());
+    }
+
+    #[test]
+    fn test_speed_conversion() {
+        assert!((UnitSystem::Metric.speed(10.0) - 5.14444).abs() < 1e-9);
+        assert!((UnitSystem::Imperial.speed(10.0) - 11.50779).abs() < 1e-9);
+        assert_eq!(UnitSystem::Nautical.speed(10.0), 10.0);
+    }
+
+    #[test]
+    fn test_temperature_conversion() {
+        assert_eq!(UnitSystem::Imperial.temperature(100.0), 212.0);
+        assert_eq!(UnitSystem::Imperial.temperature(-40.0), -40.0);
+        assert_eq!(UnitSystem::Metric.temperature(20.0), 20.0);
+    }
+
+    #[test]
+    fn test_pressure_conversion() {
+        assert!((UnitSystem::Metric.pressure(101325.0) - 1013.25).abs() < 1e-9);
+        assert!((UnitSystem::Nautical.pressure(101325.0) - 1013.25).abs() < 1e-9);
+        assert!((UnitSystem::Imperial.pressure(101325.0) - 29.92).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_convert_track() {
+        let mut track = vec![TrackPoint {
+            timestamp: "2024-06-01 10:00:00".to_string(),
+            latitude: 43.5,
+            longitude: 10.2,
+            avg_speed_kn: 5.0,
+            max_speed_kn: 10.0,
+            moored: false,
+            engine_on: false,
+            timestamp_local: None,
+        }];
+        convert_track(&mut track, UnitSystem::Imperial);
+        assert!((track[0].avg_speed_kn - 5.753895).abs() < 1e-9);
+        assert!((track[0].max_speed_kn - 11.50779).abs() < 1e-9);
+        assert_eq!(track[0].latitude, 43.5);
+    }
+
+    #[test]
+    fn test_convert_metrics_by_metric() {
+        let mut metrics = vec![
+            metric_row(MetricId::CabinTemp, 0.0),
+            metric_row(MetricId::Pressure, 101325.0),
+            metric_row(MetricId::WindSpeed, 10.0),
+            metric_row(MetricId::WindDir, 270.0),
+        ];
+        convert_metrics(&mut metrics, UnitSystem::Imperial);
+        assert_eq!(metrics[0].avg_value, Some(32.0));
+        assert!((metrics[1].max_value.unwrap() - 29.92).abs() < 0.01);
+        assert!((metrics[2].avg_value.unwrap() - 11.50779).abs() < 1e-9);
+        assert_eq!(metrics[2].min_value, None);
+        // Directions don't depend on the unit system
+        assert_eq!(metrics[3].avg_value, Some(270.0));
+    }
+
+    #[test]
+    fn test_metric_unit() {
+        assert_eq!(metric_unit(MetricId::WindSpeed, UnitSystem::Imperial), "mph");
+        assert_eq!(metric_unit(MetricId::WindGust, UnitSystem::Nautical), "kn");
+        assert_eq!(metric_unit(MetricId::CabinTemp, UnitSystem::Imperial), "F");
+        assert_eq!(metric_unit(MetricId::WaterTemp, UnitSystem::Metric), "C");
+        assert_eq!(metric_unit(MetricId::Pressure, UnitSystem::Nautical), "hPa");
+        assert_eq!(metric_unit(MetricId::Pressure, UnitSystem::Imperial), "inHg");
+        assert_eq!(metric_unit(MetricId::WindDir, UnitSystem::Imperial), "deg");
+    }
+}
@@ -0,0 +1,287 @@
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::db::{Database, VesselStatusOperation};
+use crate::environmental_monitor::{MetricData, MetricId};
+
+/// A database write that could not be performed and is waiting to be replayed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BufferedWrite {
+    VesselStatus(VesselStatusOperation),
+    Metric {
+        metric_id: MetricId,
+        timestamp: SystemTime,
+        data: MetricData,
+    },
+}
+
+impl BufferedWrite {
+    pub fn timestamp(&self) -> SystemTime {
+        match self {
+            BufferedWrite::VesselStatus(op) => op.time,
+            BufferedWrite::Metric { timestamp, .. } => *timestamp,
+        }
+    }
+
+    /// Write a run of entries to the database: consecutive vessel statuses in a single batch, metrics one by one
+    /// Vessel status rows are replayed without trip operations, the trip is kept up to date in memory
+    fn replay(run: &[BufferedWrite], db: &dyn Database) -> Result<(), Box<dyn Error>> {
+        let statuses: Vec<VesselStatusOperation> = run.iter()
+            .filter_map(|write| match write {
+                BufferedWrite::VesselStatus(op) => Some(op.clone()),
+                BufferedWrite::Metric { .. } => None,
+            })
+            .collect();
+        if !statuses.is_empty() {
+            db.insert_status_batch(&statuses)?;
+        }
+        for write in run {
+            if let BufferedWrite::Metric { metric_id, timestamp, data } = write {
+                db.insert_environmental_metrics(data, *metric_id, *timestamp)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Disk buffer for database writes during outages
+///
+/// Pending writes are appended to a newline-delimited JSON file and replayed,
+/// in timestamp order, once the database connection is restored.
+#[derive(Debug, Clone)]
+pub struct WriteBuffer {
+    path: PathBuf,
+}
+
+impl WriteBuffer {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Append a pending write to the buffer file
+    pub fn append(&self, write: &BufferedWrite) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let mut line = serde_json::to_string(write)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Check if there are writes waiting to be replayed
+    pub fn has_pending(&self) -> bool {
+        fs::metadata(&self.path).map(|m| m.len() > 0).unwrap_or(false)
+    }
+
+    /// Read all the pending writes, sorted by timestamp
+    /// Lines that can't be parsed are skipped with a warning
+    pub fn read_all(&self) -> Result<Vec<BufferedWrite>, Box<dyn Error>> {
+        let file = match File::open(&self.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut writes = Vec::new();
+        for (n, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<BufferedWrite>(&line) {
+                Ok(write) => writes.push(write),
+                Err(e) => warn!("Skipping malformed write buffer entry at line {}: {}", n + 1, e),
+            }
+        }
+        writes.sort_by_key(|w| w.timestamp());
+        Ok(writes)
+    }
+
+    /// Replace the buffer content with the given writes
+    fn rewrite(&self, writes: &[BufferedWrite]) -> Result<(), Box<dyn Error>> {
+        if writes.is_empty() {
+            match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => return Ok(()),
+            }
+        }
+        let tmp_path = self.path.with_extension("tmp");
+        {
+            let mut file = File::create(&tmp_path)?;
+            for write in writes {
+                let mut line = serde_json::to_string(write)?;
+                line.push('\n');
+                file.write_all(line.as_bytes())?;
+            }
+        }
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+
+    /// Replay the pending writes to the database in timestamp order
+    /// Consecutive vessel statuses are written in batches. Returns the number of writes replayed.
+    /// On error the writes not yet replayed are kept.
+    pub fn drain(&self, db: &dyn Database) -> Result<usize, Box<dyn Error>> {
+        self.drain_with(|run| BufferedWrite::replay(run, db))
+    }
+
+    /// Replay the pending writes in runs of consecutive vessel statuses, or single metrics
+    fn drain_with<F>(&self, mut replay: F) -> Result<usize, Box<dyn Error>>
+    where
+        F: FnMut(&[BufferedWrite]) -> Result<(), Box<dyn Error>>,
+    {
+        let writes = self.read_all()?;
+        let total = writes.len();
+        let mut replayed = 0;
+        let runs = writes.chunk_by(|a, b| {
+            matches!((a, b), (BufferedWrite::VesselStatus(_), BufferedWrite::VesselStatus(_)))
+        });
+        for run in runs {
+            if let Err(e) = replay(run) {
+                warn!("Write buffer replay stopped after {} of {} entries: {}", replayed, total, e);
+                self.rewrite(&writes[replayed..])?;
+                return Err(e);
+            }
+            replayed += run.len();
+        }
+        self.rewrite(&[])?;
+        if total > 0 {
+            info!("Replayed {} buffered database writes", total);
+        }
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_buffer(name: &str) -> WriteBuffer {
+        let path = std::env::temp_dir().join(format!("nmea_router_{}_{}.jsonl", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        WriteBuffer::new(path)
+    }
+
+    fn status_at(time: SystemTime) -> BufferedWrite {
+        BufferedWrite::VesselStatus(VesselStatusOperation {
+            time,
+            latitude: 43.5,
+            longitude: 10.2,
+            average_speed_kn: 5.5,
+            max_speed_kn: 6.5,
+            is_moored: false,
+            engine_on: true,
+            total_distance_nm: 0.05,
+            total_time_ms: 30000,
+            average_wind_speed_kn: Some(12.0),
+            wind_speed_variance: None,
+            average_wind_angle_deg: None,
+            wind_angle_variance: None,
+            cog_deg: Some(270.0),
+            average_heading_deg: None,
+            quality_score: Some(0.9),
+        })
+    }
+
+    fn metric_at(time: SystemTime) -> BufferedWrite {
+        BufferedWrite::Metric {
+            metric_id: MetricId::Pressure,
+            timestamp: time,
+            data: MetricData { avg: Some(101325.0), max: Some(101400.0), min: Some(101300.0), count: Some(12) },
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let buffer = temp_buffer("round_trip");
+        assert!(!buffer.has_pending());
+
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        buffer.append(&metric_at(t0 + Duration::from_secs(60))).unwrap();
+        buffer.append(&status_at(t0)).unwrap();
+        assert!(buffer.has_pending());
+
+        let writes = buffer.read_all().unwrap();
+        assert_eq!(writes.len(), 2);
+        // Sorted by timestamp
+        match &writes[0] {
+            BufferedWrite::VesselStatus(op) => {
+                assert_eq!(op.time, t0);
+                assert_eq!(op.latitude, 43.5);
+                assert!(op.engine_on);
+                assert_eq!(op.cog_deg, Some(270.0));
+                assert!(op.average_heading_deg.is_none());
+            }
+            other => panic!("Unexpected entry {:?}", other),
+        }
+        match &writes[1] {
+            BufferedWrite::Metric { metric_id, timestamp, data } => {
+                assert_eq!(*metric_id, MetricId::Pressure);
+                assert_eq!(*timestamp, t0 + Duration::from_secs(60));
+                assert_eq!(data.avg, Some(101325.0));
+                assert_eq!(data.count, Some(12));
+            }
+            other => panic!("Unexpected entry {:?}", other),
+        }
+
+        let replayed = buffer.drain_with(|_| Ok(())).unwrap();
+        assert_eq!(replayed, 2);
+        assert!(!buffer.has_pending());
+    }
+
+    #[test]
+    fn test_partial_drain_on_error() {
+        let buffer = temp_buffer("partial_drain");
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for i in 0..5 {
+            buffer.append(&status_at(t0 + Duration::from_secs(30 * i))).unwrap();
+        }
+
+        buffer.append(&metric_at(t0 + Duration::from_secs(45))).unwrap();
+
+        // The statuses before the metric are replayed in one batch, the database fails on the metric
+        let mut replayed = Vec::new();
+        let result = buffer.drain_with(|run| {
+            if !replayed.is_empty() {
+                return Err("Database unavailable".into());
+            }
+            replayed.extend(run.iter().map(BufferedWrite::timestamp));
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(replayed, [t0, t0 + Duration::from_secs(30)]);
+
+        // The failed write and the following ones are kept, in order
+        let remaining = buffer.read_all().unwrap();
+        assert_eq!(remaining.len(), 4);
+        assert_eq!(remaining[0].timestamp(), t0 + Duration::from_secs(45));
+
+        // The remaining statuses make a single batch
+        let mut runs = Vec::new();
+        assert_eq!(buffer.drain_with(|run| { runs.push(run.len()); Ok(()) }).unwrap(), 4);
+        assert_eq!(runs, [1, 3]);
+        assert!(!buffer.has_pending());
+    }
+
+    #[test]
+    fn test_malformed_lines_are_skipped() {
+        let buffer = temp_buffer("malformed");
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        buffer.append(&metric_at(t0)).unwrap();
+        OpenOptions::new().append(true).open(&buffer.path).unwrap().write_all(b"{\"type\": \"garbage\n").unwrap();
+
+        assert_eq!(buffer.read_all().unwrap().len(), 1);
+        let _ = fs::remove_file(&buffer.path);
+    }
+}